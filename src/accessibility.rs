@@ -0,0 +1,325 @@
+// UI Automation constants are named like `UIA_NamePropertyId`, which trips the lint when matched on
+#![allow(non_upper_case_globals)]
+
+use windows::{
+    Win32::{
+        Foundation::{HWND, LPARAM, LRESULT, RECT, WPARAM},
+        System::{
+            Com::SAFEARRAY,
+            Ole::{SafeArrayCreateVector, SafeArrayPutElement},
+            Variant::{VARIANT, VT_I4},
+        },
+        UI::{
+            Accessibility::{
+                IInvokeProvider, IInvokeProvider_Impl, IRawElementProviderFragment,
+                IRawElementProviderFragment_Impl, IRawElementProviderFragmentRoot,
+                IRawElementProviderFragmentRoot_Impl, IRawElementProviderSimple,
+                IRawElementProviderSimple_Impl, IToggleProvider, IToggleProvider_Impl,
+                NavigateDirection, NavigateDirection_FirstChild, NavigateDirection_LastChild,
+                NavigateDirection_NextSibling, NavigateDirection_Parent,
+                NavigateDirection_PreviousSibling, ProviderOptions,
+                ProviderOptions_ServerSideProvider, ProviderOptions_UseComThreading, ToggleState,
+                ToggleState_Off, ToggleState_On, UIA_ButtonControlTypeId,
+                UIA_ControlTypePropertyId, UIA_E_ELEMENTNOTAVAILABLE, UIA_HelpTextPropertyId,
+                UIA_InvokePatternId, UIA_NamePropertyId, UIA_PATTERN_ID, UIA_PROPERTY_ID,
+                UIA_PaneControlTypeId, UIA_TogglePatternId, UiaAppendRuntimeId,
+                UiaHostProviderFromHwnd, UiaRect, UiaReturnRawElementProvider,
+            },
+            WindowsAndMessaging::{GetWindowRect, PostMessageA},
+        },
+    },
+    core::implement,
+};
+use windows_core::{HRESULT, IUnknown, IUnknownImpl};
+
+use crate::{WM_INVOKE_WIDGET, WM_TOGGLE_WIDGET, layout::Widget, try_wrap};
+
+/// answers WM_GETOBJECT with the root of the panel's UI Automation tree
+pub fn return_root_provider(hwnd: HWND, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+    let provider: IRawElementProviderSimple = PanelProvider { hwnd }.into();
+    unsafe { UiaReturnRawElementProvider(hwnd, wparam, lparam, &provider) }
+}
+
+/// maps failures to the error UI Automation expects from an element that has gone away
+fn unavailable(_: anyhow::Error) -> windows_core::Error {
+    HRESULT(UIA_E_ELEMENTNOTAVAILABLE as i32).into()
+}
+
+/// the widgets in the order they're laid out
+fn widgets() -> windows_core::Result<Vec<Widget>> {
+    try_wrap(|state| Ok(state.layout.widgets().collect())).map_err(unavailable)
+}
+
+fn fragment(
+    hwnd: HWND,
+    widget: Option<&Widget>,
+) -> windows_core::Result<IRawElementProviderFragment> {
+    match widget {
+        Some(&widget) => Ok(WidgetProvider { hwnd, widget }.into()),
+        None => Err(windows_core::Error::empty()),
+    }
+}
+
+/// the panel window itself, which contains one element per widget
+#[implement(
+    IRawElementProviderSimple,
+    IRawElementProviderFragment,
+    IRawElementProviderFragmentRoot
+)]
+struct PanelProvider {
+    hwnd: HWND,
+}
+
+impl IRawElementProviderSimple_Impl for PanelProvider_Impl {
+    fn ProviderOptions(&self) -> windows_core::Result<ProviderOptions> {
+        Ok(ProviderOptions_ServerSideProvider | ProviderOptions_UseComThreading)
+    }
+
+    fn GetPatternProvider(&self, _patternid: UIA_PATTERN_ID) -> windows_core::Result<IUnknown> {
+        Err(windows_core::Error::empty())
+    }
+
+    fn GetPropertyValue(&self, propertyid: UIA_PROPERTY_ID) -> windows_core::Result<VARIANT> {
+        let value = match propertyid {
+            UIA_NamePropertyId => VARIANT::from("Control panel"),
+            UIA_ControlTypePropertyId => VARIANT::from(UIA_PaneControlTypeId.0),
+            _ => VARIANT::default(),
+        };
+
+        Ok(value)
+    }
+
+    fn HostRawElementProvider(&self) -> windows_core::Result<IRawElementProviderSimple> {
+        unsafe { UiaHostProviderFromHwnd(self.hwnd) }
+    }
+}
+
+impl IRawElementProviderFragment_Impl for PanelProvider_Impl {
+    fn Navigate(
+        &self,
+        direction: NavigateDirection,
+    ) -> windows_core::Result<IRawElementProviderFragment> {
+        let widgets = widgets()?;
+
+        match direction {
+            NavigateDirection_FirstChild => fragment(self.hwnd, widgets.first()),
+            NavigateDirection_LastChild => fragment(self.hwnd, widgets.last()),
+            _ => Err(windows_core::Error::empty()),
+        }
+    }
+
+    fn GetRuntimeId(&self) -> windows_core::Result<*mut SAFEARRAY> {
+        // the host window provides the root's id
+        Ok(std::ptr::null_mut())
+    }
+
+    fn BoundingRectangle(&self) -> windows_core::Result<UiaRect> {
+        // the host window provides the root's bounds
+        Ok(UiaRect::default())
+    }
+
+    fn GetEmbeddedFragmentRoots(&self) -> windows_core::Result<*mut SAFEARRAY> {
+        Ok(std::ptr::null_mut())
+    }
+
+    fn SetFocus(&self) -> windows_core::Result<()> {
+        Ok(())
+    }
+
+    fn FragmentRoot(&self) -> windows_core::Result<IRawElementProviderFragmentRoot> {
+        Ok(self.to_interface())
+    }
+}
+
+impl IRawElementProviderFragmentRoot_Impl for PanelProvider_Impl {
+    fn ElementProviderFromPoint(
+        &self,
+        x: f64,
+        y: f64,
+    ) -> windows_core::Result<IRawElementProviderFragment> {
+        let mut window = RECT::default();
+        unsafe { GetWindowRect(self.hwnd, &mut window)? };
+
+        let x = x as i32 - window.left;
+        let y = y as i32 - window.top;
+
+        let widget = try_wrap(|state| Ok(state.layout.hit_test(x, y))).map_err(unavailable)?;
+
+        match widget {
+            Some(widget) => fragment(self.hwnd, Some(&widget)),
+            None => Ok(self.to_interface()),
+        }
+    }
+
+    fn GetFocus(&self) -> windows_core::Result<IRawElementProviderFragment> {
+        // only has focus in keyboard mode
+        let widget = try_wrap(|state| Ok(state.keyboard.as_ref().map(|mode| mode.widget)))
+            .map_err(unavailable)?;
+
+        fragment(self.hwnd, widget.as_ref())
+    }
+}
+
+/// one of the panel's widgets. it can be invoked like a click, and the device widgets can be
+/// toggled to mute or unmute their device.
+#[implement(
+    IRawElementProviderSimple,
+    IRawElementProviderFragment,
+    IInvokeProvider,
+    IToggleProvider
+)]
+struct WidgetProvider {
+    hwnd: HWND,
+    widget: Widget,
+}
+
+impl WidgetProvider {
+    /// whether the widget supports the toggle pattern
+    fn can_toggle(&self) -> bool {
+        self.widget.is_device()
+    }
+
+    fn post(&self, message: u32) -> windows_core::Result<()> {
+        // run the action from the window proc rather than from inside a UI Automation call
+        unsafe {
+            PostMessageA(
+                Some(self.hwnd),
+                message,
+                WPARAM(self.widget.index()),
+                LPARAM(0),
+            )
+        }
+    }
+}
+
+impl IRawElementProviderSimple_Impl for WidgetProvider_Impl {
+    fn ProviderOptions(&self) -> windows_core::Result<ProviderOptions> {
+        Ok(ProviderOptions_ServerSideProvider | ProviderOptions_UseComThreading)
+    }
+
+    fn GetPatternProvider(&self, patternid: UIA_PATTERN_ID) -> windows_core::Result<IUnknown> {
+        match patternid {
+            UIA_InvokePatternId => Ok(self.to_interface::<IInvokeProvider>().into()),
+            UIA_TogglePatternId if self.can_toggle() => {
+                Ok(self.to_interface::<IToggleProvider>().into())
+            }
+            _ => Err(windows_core::Error::empty()),
+        }
+    }
+
+    fn GetPropertyValue(&self, propertyid: UIA_PROPERTY_ID) -> windows_core::Result<VARIANT> {
+        let widget = self.widget;
+
+        let value = match propertyid {
+            UIA_NamePropertyId => {
+                let name = try_wrap(|state| state.accessible_name(widget)).map_err(unavailable)?;
+                VARIANT::from(name.as_str())
+            }
+            UIA_HelpTextPropertyId => match widget.flow() {
+                Some(flow) => {
+                    let name = try_wrap(|state| state.device_name(flow)).map_err(unavailable)?;
+                    VARIANT::from(name.as_str())
+                }
+                None => VARIANT::default(),
+            },
+            UIA_ControlTypePropertyId => VARIANT::from(UIA_ButtonControlTypeId.0),
+            _ => VARIANT::default(),
+        };
+
+        Ok(value)
+    }
+
+    fn HostRawElementProvider(&self) -> windows_core::Result<IRawElementProviderSimple> {
+        Err(windows_core::Error::empty())
+    }
+}
+
+impl IRawElementProviderFragment_Impl for WidgetProvider_Impl {
+    fn Navigate(
+        &self,
+        direction: NavigateDirection,
+    ) -> windows_core::Result<IRawElementProviderFragment> {
+        if direction == NavigateDirection_Parent {
+            return Ok(PanelProvider { hwnd: self.hwnd }.into());
+        }
+
+        let widgets = widgets()?;
+        let index = widgets.iter().position(|w| *w == self.widget);
+
+        let sibling = match (direction, index) {
+            (NavigateDirection_NextSibling, Some(i)) => widgets.get(i + 1),
+            (NavigateDirection_PreviousSibling, Some(i)) => i.checked_sub(1).map(|i| &widgets[i]),
+            _ => None,
+        };
+
+        fragment(self.hwnd, sibling)
+    }
+
+    fn GetRuntimeId(&self) -> windows_core::Result<*mut SAFEARRAY> {
+        let id = [UiaAppendRuntimeId as i32, self.widget.index() as i32];
+
+        unsafe {
+            let array = SafeArrayCreateVector(VT_I4, 0, id.len() as u32);
+
+            for (i, value) in id.iter().enumerate() {
+                SafeArrayPutElement(array, &(i as i32), value as *const i32 as _)?;
+            }
+
+            Ok(array)
+        }
+    }
+
+    fn BoundingRectangle(&self) -> windows_core::Result<UiaRect> {
+        let widget = self.widget;
+        let rect = try_wrap(|state| Ok(state.layout.rect(widget))).map_err(unavailable)?;
+
+        let Some(rect) = rect else {
+            return Ok(UiaRect::default());
+        };
+
+        let mut window = RECT::default();
+        unsafe { GetWindowRect(self.hwnd, &mut window)? };
+
+        Ok(UiaRect {
+            left: window.left as f64 + rect.x as f64,
+            top: window.top as f64 + rect.y as f64,
+            width: rect.width as f64,
+            height: rect.height as f64,
+        })
+    }
+
+    fn GetEmbeddedFragmentRoots(&self) -> windows_core::Result<*mut SAFEARRAY> {
+        Ok(std::ptr::null_mut())
+    }
+
+    fn SetFocus(&self) -> windows_core::Result<()> {
+        Ok(())
+    }
+
+    fn FragmentRoot(&self) -> windows_core::Result<IRawElementProviderFragmentRoot> {
+        Ok(PanelProvider { hwnd: self.hwnd }.into())
+    }
+}
+
+impl IInvokeProvider_Impl for WidgetProvider_Impl {
+    fn Invoke(&self) -> windows_core::Result<()> {
+        self.post(WM_INVOKE_WIDGET)
+    }
+}
+
+impl IToggleProvider_Impl for WidgetProvider_Impl {
+    fn Toggle(&self) -> windows_core::Result<()> {
+        self.post(WM_TOGGLE_WIDGET)
+    }
+
+    fn ToggleState(&self) -> windows_core::Result<ToggleState> {
+        let flow = self.widget.flow().ok_or_else(windows_core::Error::empty)?;
+        let muted = try_wrap(|state| state.is_muted(flow)).map_err(unavailable)?;
+
+        Ok(if muted {
+            ToggleState_On
+        } else {
+            ToggleState_Off
+        })
+    }
+}
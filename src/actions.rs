@@ -0,0 +1,104 @@
+use serde::Deserialize;
+
+use crate::{layout::Widget, widgets::focus::FocusMode};
+
+/// a mouse input on one of the panel's widgets
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Gesture {
+    Click,
+    DoubleClick,
+    MiddleClick,
+    RightClick,
+    Wheel,
+}
+
+impl Gesture {
+    /// the action used when the config has no binding for a gesture on `widget`
+    pub fn default_action(self, widget: Option<Widget>) -> Action {
+        match self {
+            Gesture::Click => match widget {
+                Some(Widget::Date) => Action::OpenCalendar,
+                Some(Widget::Wifi) => Action::OpenNetworks,
+                Some(Widget::Vpn) => Action::ToggleVpn,
+                Some(Widget::NowPlaying | Widget::MediaPlayPause) => Action::PlayPause,
+                Some(Widget::MediaNext) => Action::NextTrack,
+                Some(Widget::MediaPrevious) => Action::PreviousTrack,
+                Some(Widget::FocusAssist) => Action::CycleFocusAssist,
+                Some(Widget::NightLight) => Action::ToggleNightLight,
+                Some(Widget::InputSource) => Action::CycleInputSource,
+                Some(Widget::RefreshRate) => Action::CycleRefreshRate,
+                Some(Widget::Bluetooth) => Action::BluetoothMenu,
+                Some(Widget::Pomodoro) => Action::ToggleTimer,
+                Some(Widget::Button(_)) => Action::RunButton,
+                _ => Action::ConnectBluetooth,
+            },
+            Gesture::RightClick => Action::ContextMenu,
+            Gesture::Wheel => match widget {
+                Some(Widget::Brightness) => Action::StepBrightness,
+                _ => Action::StepVolume,
+            },
+            Gesture::MiddleClick => match widget {
+                Some(Widget::Pomodoro) => Action::ResetTimer,
+                _ => Action::None,
+            },
+            Gesture::DoubleClick => Action::None,
+        }
+    }
+}
+
+/// something a gesture can be bound to. actions that act on a device use the device shown by the
+/// widget the gesture happened on, or the output device if there is no widget under the cursor.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum Action {
+    None,
+    ToggleMute,
+    /// switches the default device to the next active endpoint
+    CycleDevice,
+    /// steps the volume in the direction of the wheel, or up for anything else
+    StepVolume,
+    ConnectBluetooth,
+    ContextMenu,
+    /// lists the bluetooth widget's devices, to connect one or open the bluetooth settings
+    BluetoothMenu,
+    /// opens the calendar flyout, or the date widget's configured url
+    OpenCalendar,
+    /// opens the flyout listing the available wireless networks
+    OpenNetworks,
+    /// runs the vpn widget's connect or disconnect command, whichever applies
+    ToggleVpn,
+    /// sent to whatever app is playing media
+    PlayPause,
+    NextTrack,
+    PreviousTrack,
+    /// switches focus assist from off to priority only to alarms only, and back to off
+    CycleFocusAssist,
+    SetFocusAssist {
+        mode: FocusMode,
+    },
+    ToggleNightLight,
+    /// starts or pauses the pomodoro timer
+    ToggleTimer,
+    /// stops the pomodoro timer and goes back to the start of a work phase
+    ResetTimer,
+    /// steps the brightness of the monitor under the cursor in the direction of the wheel, or of
+    /// every monitor when not on the brightness widget
+    StepBrightness,
+    /// switches the input source widget's monitor to the next of its configured inputs
+    CycleInputSource,
+    /// switches the refresh rate widget's display to the next of its configured rates
+    CycleRefreshRate,
+    /// switches the input source widget's monitor to `input`, an MCCS input source value
+    SwitchInputSource {
+        input: u32,
+    },
+    RunCommand {
+        command: String,
+    },
+    /// runs the command configured for the button widget the gesture happened on
+    RunButton,
+    /// shows or hides the diagnostics overlay, with how long painting takes, how busy the window
+    /// is, how many devices are tracked and the last error
+    ToggleDiagnostics,
+}
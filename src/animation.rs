@@ -0,0 +1,101 @@
+use std::{
+    collections::HashMap,
+    f32::consts::PI,
+    time::{Duration, Instant},
+};
+
+use crate::layout::Widget;
+
+/// how long the mute cross takes to draw in or out
+const MUTE_DURATION: Duration = Duration::from_millis(150);
+
+/// how much the icon grows at the peak of the pulse
+const PULSE_SCALE: f32 = 0.15;
+
+/// how long the mic live warning stays at each brightness. it steps between the two rather than
+/// fading, so it only needs a repaint each step for as long as the mic is live
+const LIVE_STEP_MS: u64 = 600;
+
+/// interval of the timer that repaints while an animation is running
+pub const FRAME_INTERVAL_MS: u32 = 16;
+
+struct MuteState {
+    muted: bool,
+    changed: Option<Instant>,
+}
+
+/// what to draw for a device widget on the current frame
+pub struct MuteFrame {
+    /// how much of the mute cross is drawn, from 0 (none) to 1 (all of it)
+    pub cross: f32,
+    /// scale factor for the icon
+    pub scale: f32,
+}
+
+/// tracks mute state changes per widget so they can be animated instead of flipping instantly
+#[derive(Default)]
+pub struct MuteAnimations {
+    widgets: HashMap<Widget, MuteState>,
+    live_since: Option<Instant>,
+}
+
+impl MuteAnimations {
+    /// records the current mute state of a widget and returns how it should be drawn
+    pub fn update(&mut self, widget: Widget, muted: bool, now: Instant) -> MuteFrame {
+        let state = self.widgets.entry(widget).or_insert(MuteState {
+            muted,
+            changed: None,
+        });
+
+        if state.muted != muted {
+            state.muted = muted;
+            state.changed = Some(now);
+        }
+
+        let t = match state.changed {
+            Some(changed) => {
+                let t = (now - changed).as_secs_f32() / MUTE_DURATION.as_secs_f32();
+                t.min(1.0)
+            }
+            None => 1.0,
+        };
+
+        if t >= 1.0 {
+            state.changed = None;
+        }
+
+        // ease out, so the change registers immediately and then settles
+        let eased = 1.0 - (1.0 - t) * (1.0 - t);
+
+        MuteFrame {
+            cross: if muted { eased } else { 1.0 - eased },
+            scale: 1.0 + PULSE_SCALE * (t * PI).sin(),
+        }
+    }
+
+    /// records whether the mic is live and returns the opacity of the warning ring, from 0 to 1,
+    /// or `None` if there's nothing to draw
+    pub fn update_live(&mut self, live: bool, now: Instant) -> Option<f32> {
+        if !live {
+            self.live_since = None;
+            return None;
+        }
+
+        let since = *self.live_since.get_or_insert(now);
+        let step = (now - since).as_millis() as u64 / LIVE_STEP_MS;
+
+        // starts fully lit so it shows up as soon as the mic goes live
+        Some(if step.is_multiple_of(2) { 1.0 } else { 0.4 })
+    }
+
+    /// milliseconds until the live warning's next step, if it's shown
+    pub fn next_live_step(&self, now: Instant) -> Option<u32> {
+        let since = self.live_since?;
+        let elapsed = (now - since).as_millis() as u64;
+        Some((LIVE_STEP_MS - elapsed % LIVE_STEP_MS) as u32)
+    }
+
+    pub fn is_animating(&self) -> bool {
+        self.widgets.values().any(|state| state.changed.is_some())
+    }
+}
@@ -0,0 +1,202 @@
+use std::{net::ToSocketAddrs, sync::mpsc::Sender, thread::JoinHandle};
+
+use anyhow::{Context, Result, anyhow};
+use rouille::{Request, Response, websocket};
+use serde::Deserialize;
+use windows::Win32::Foundation::HWND;
+
+use crate::{
+    actions::Action,
+    events,
+    ipc::{self, Command, Target, X},
+    metrics,
+};
+
+/// an http server on localhost for things that can't open a pipe, like browser extensions or
+/// phone shortcuts, with a websocket at `/events` for changes as they happen. read at startup
+/// only.
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct ApiConfig {
+    pub port: u16,
+    /// sent as `Authorization: Bearer <token>` or `?token=<token>`. the server only starts
+    /// with one set, since any web page can make requests to localhost.
+    pub token: Option<String>,
+    /// serves counters for prometheus at `/metrics`, which needs the token too
+    pub metrics: bool,
+}
+
+impl Default for ApiConfig {
+    fn default() -> Self {
+        Self {
+            port: 25563,
+            token: None,
+            metrics: false,
+        }
+    }
+}
+
+/// a rouille server on a thread of its own, which stops when this is dropped. requests being
+/// answered are finished first.
+pub struct HttpServer {
+    stop: Sender<()>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl HttpServer {
+    pub fn start<A, F>(addr: A, handler: F) -> Result<Self>
+    where
+        A: ToSocketAddrs,
+        F: Fn(&Request) -> Response + Send + Sync + 'static,
+    {
+        let (thread, stop) = rouille::Server::new(addr, handler)
+            .map_err(|e| anyhow!(e))?
+            .stoppable();
+
+        Ok(Self {
+            stop,
+            thread: Some(thread),
+        })
+    }
+}
+
+impl Drop for HttpServer {
+    fn drop(&mut self) {
+        let _ = self.stop.send(());
+
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// upgrades to a websocket that's sent each published event as json
+fn subscribe(request: &Request) -> Response {
+    let Ok((response, websocket)) = websocket::start(request, None::<&str>) else {
+        return Response::empty_400();
+    };
+
+    std::thread::spawn(move || {
+        // arrives once the response has been sent
+        let Ok(mut websocket) = websocket.recv() else {
+            return;
+        };
+
+        for event in events::subscribe() {
+            if websocket.send_text(&event).is_err() {
+                break;
+            }
+        }
+    });
+
+    response
+}
+
+#[derive(Deserialize)]
+struct VolumeBody {
+    volume: f32,
+}
+
+#[derive(Deserialize)]
+struct DefaultBody {
+    name: String,
+}
+
+fn authorized(request: &Request, token: &str) -> bool {
+    let header = request
+        .header("Authorization")
+        .and_then(|header| header.strip_prefix("Bearer "));
+
+    let given = header.map(str::to_string).or(request.get_param("token"));
+    given.as_deref() == Some(token)
+}
+
+fn body<T: serde::de::DeserializeOwned>(request: &Request) -> Result<T> {
+    let body = request.data().context("missing body")?;
+    serde_json::from_reader(body).context("invalid body")
+}
+
+/// the command for a request, e.g. `POST /input/toggle_mute`, or `POST /command` with a pipe
+/// command as the body
+fn command(request: &Request) -> Result<Option<Command>> {
+    let url = request.url();
+    let parts: Vec<_> = url.trim_matches('/').split('/').collect();
+
+    let command = match (request.method(), parts.as_slice()) {
+        ("GET", ["status"]) => Command::Status {
+            full: request.get_param("full").is_some(),
+        },
+        ("POST", ["command"]) => body(request)?,
+        ("POST", ["bluetooth", "reconnect"]) => Command::Action {
+            target: Target::Output,
+            action: Action::ConnectBluetooth,
+        },
+        (method, [target, action]) => {
+            let Ok(target) = target.parse() else {
+                return Ok(None);
+            };
+
+            match (method, *action) {
+                ("POST", "toggle_mute") => Command::ToggleMute { target },
+                ("POST", "mute") => Command::SetMute {
+                    target,
+                    muted: true,
+                },
+                ("POST", "unmute") => Command::SetMute {
+                    target,
+                    muted: false,
+                },
+                ("PUT", "volume") => Command::SetVolume {
+                    target,
+                    volume: body::<VolumeBody>(request)?.volume,
+                },
+                ("PUT", "default") => Command::SetDefault {
+                    target,
+                    name: body::<DefaultBody>(request)?.name,
+                },
+                _ => return Ok(None),
+            }
+        }
+        _ => return Ok(None),
+    };
+
+    Ok(Some(command))
+}
+
+fn respond(hwnd: X, request: &Request, token: &str, metrics: bool) -> Response {
+    if !authorized(request, token) {
+        return Response::text("unauthorized").with_status_code(401);
+    }
+
+    if metrics && request.method() == "GET" && request.url() == "/metrics" {
+        return Response::text(metrics::render());
+    }
+
+    // browsers can't set headers on websockets, so this needs `?token=`
+    if request.method() == "GET" && request.url() == "/events" {
+        return subscribe(request);
+    }
+
+    let (status, response) = match command(request) {
+        Ok(Some(command)) => match ipc::send(hwnd.0, command) {
+            Ok(result) => (200, Ok(result)),
+            Err(e) => (500, Err(e)),
+        },
+        Ok(None) => return Response::empty_404(),
+        Err(e) => (400, Err(e)),
+    };
+
+    Response::json(&ipc::reply(response)).with_status_code(status)
+}
+
+/// `None` without a token, or if the port's taken
+pub fn spawn(hwnd: HWND, config: &ApiConfig) -> Option<HttpServer> {
+    let token = config.token.clone()?;
+    let hwnd = X(hwnd);
+    let metrics = config.metrics;
+
+    HttpServer::start(("127.0.0.1", config.port), move |request| {
+        respond(hwnd, request, &token, metrics)
+    })
+    .ok()
+}
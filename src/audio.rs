@@ -0,0 +1,268 @@
+use std::ptr::null_mut;
+
+use anyhow::{Context, Result};
+use windows::Win32::{
+    Devices::FunctionDiscovery::PKEY_Device_FriendlyName,
+    Foundation::ERROR_NOT_FOUND,
+    Media::{
+        Audio::{
+            DEVICE_STATE, DEVICE_STATE_ACTIVE, DEVICE_STATEMASK_ALL, EDataFlow, ERole,
+            Endpoints::IAudioEndpointVolume, IDeviceTopology, IMMDevice, IMMDeviceEnumerator,
+            MMDeviceEnumerator, eCommunications, eConsole, eMultimedia, eRender,
+        },
+        KernelStreaming::{
+            IKsControl, KSIDENTIFIER, KSIDENTIFIER_0, KSIDENTIFIER_0_0,
+            KSPROPERTY_ONESHOT_RECONNECT, KSPROPERTY_TYPE_GET, KSPROPSETID_BtAudio,
+        },
+    },
+    System::Com::{CLSCTX_ALL, CoCreateInstance, STGM_READ},
+};
+use windows_core::HSTRING;
+
+use policy_config::{CLSID_PolicyConfigClient, IPolicyConfig};
+
+#[allow(non_snake_case, non_upper_case_globals)]
+mod policy_config {
+    use windows_core::{GUID, HRESULT, IUnknown, IUnknown_Vtbl, interface};
+
+    pub const CLSID_PolicyConfigClient: GUID =
+        GUID::from_u128(0x870af99c_171d_4f9e_af0d_e63df40c2bc9);
+
+    /// the undocumented interface the sound settings use to change the default devices. only
+    /// `SetDefaultEndpoint` is called, the rest are declared to get the vtable right.
+    #[interface("F8679F50-850A-41CF-9C72-430F290290C8")]
+    pub unsafe trait IPolicyConfig: IUnknown {
+        pub fn GetMixFormat(&self) -> HRESULT;
+        pub fn GetDeviceFormat(&self) -> HRESULT;
+        pub fn ResetDeviceFormat(&self) -> HRESULT;
+        pub fn SetDeviceFormat(&self) -> HRESULT;
+        pub fn GetProcessingPeriod(&self) -> HRESULT;
+        pub fn SetProcessingPeriod(&self) -> HRESULT;
+        pub fn GetShareMode(&self) -> HRESULT;
+        pub fn SetShareMode(&self) -> HRESULT;
+        pub fn GetPropertyValue(&self) -> HRESULT;
+        pub fn SetPropertyValue(&self) -> HRESULT;
+        pub fn SetDefaultEndpoint(&self, deviceID: *const u16, role: u32) -> HRESULT;
+        pub fn SetEndpointVisibility(&self) -> HRESULT;
+    }
+}
+
+/// what came of asking for a bluetooth output to reconnect
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Reconnect {
+    /// one took the request, which doesn't mean it's connected yet
+    Taken,
+    /// some matched, but none of them took it
+    Refused,
+    /// no output's name matched
+    NoSuchDevice,
+}
+
+/// the system's audio endpoints, for finding devices and changing which are the defaults
+pub struct Endpoints {
+    enumerator: IMMDeviceEnumerator,
+    policy_config: IPolicyConfig,
+}
+
+impl Endpoints {
+    pub fn new() -> Result<Self> {
+        unsafe {
+            Ok(Self {
+                enumerator: CoCreateInstance(&MMDeviceEnumerator, None, CLSCTX_ALL)?,
+                policy_config: CoCreateInstance(&CLSID_PolicyConfigClient, None, CLSCTX_ALL)?,
+            })
+        }
+    }
+
+    /// for registering for notifications about the endpoints
+    pub fn enumerator(&self) -> &IMMDeviceEnumerator {
+        &self.enumerator
+    }
+
+    /// the default device for media, which is what the panel shows
+    pub fn default_device(&self, flow: EDataFlow) -> Result<Option<IMMDevice>> {
+        self.default_device_for(flow, eMultimedia)
+    }
+
+    /// `None` when there isn't one, e.g. on a headless machine or with every device disabled
+    pub fn default_device_for(&self, flow: EDataFlow, role: ERole) -> Result<Option<IMMDevice>> {
+        match unsafe { self.enumerator.GetDefaultAudioEndpoint(flow, role) } {
+            Ok(device) => Ok(Some(device)),
+            Err(e) if e.code() == ERROR_NOT_FOUND.to_hresult() => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn active_devices(&self, flow: EDataFlow) -> Result<Vec<IMMDevice>> {
+        self.devices(flow, DEVICE_STATE_ACTIVE)
+    }
+
+    /// including the disabled, unplugged and disconnected ones
+    pub fn all_devices(&self, flow: EDataFlow) -> Result<Vec<IMMDevice>> {
+        self.devices(flow, DEVICE_STATE(DEVICE_STATEMASK_ALL))
+    }
+
+    fn devices(&self, flow: EDataFlow, state: DEVICE_STATE) -> Result<Vec<IMMDevice>> {
+        unsafe {
+            let devices = self.enumerator.EnumAudioEndpoints(flow, state)?;
+
+            let mut list = vec![];
+            for i in 0..devices.GetCount()? {
+                list.push(devices.Item(i)?);
+            }
+
+            Ok(list)
+        }
+    }
+
+    pub fn device(&self, id: &str) -> Result<IMMDevice> {
+        Ok(unsafe { self.enumerator.GetDevice(&HSTRING::from(id))? })
+    }
+
+    /// makes the device with `id` the default for every role
+    pub fn set_default(&self, id: &str) -> Result<()> {
+        let id = HSTRING::from(id);
+
+        unsafe {
+            for role in [eConsole, eMultimedia, eCommunications] {
+                self.policy_config
+                    .SetDefaultEndpoint(id.as_ptr(), role.0 as _)
+                    .ok()?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// asks the first bluetooth audio output whose name contains `name` to reconnect
+    pub fn reconnect_bluetooth(&self, name: &str) -> Result<Reconnect> {
+        let name = name.to_lowercase();
+        let mut result = Reconnect::NoSuchDevice;
+
+        for device in self.all_devices(eRender)? {
+            if !self::name(&device).is_ok_and(|n| n.to_lowercase().contains(&name)) {
+                continue;
+            }
+
+            if self.reconnect(&device).is_ok() {
+                return Ok(Reconnect::Taken);
+            }
+
+            result = Reconnect::Refused;
+        }
+
+        Ok(result)
+    }
+
+    /// asks the bluetooth device behind an audio endpoint to reconnect, like the connect button
+    /// in the sound settings
+    pub fn reconnect(&self, device: &IMMDevice) -> Result<()> {
+        unsafe {
+            let topology: IDeviceTopology = device.Activate(CLSCTX_ALL, None)?;
+            if topology.GetConnectorCount()? != 1 {
+                anyhow::bail!("not a bluetooth device");
+            }
+
+            let connector = topology.GetConnector(0)?;
+
+            let bluetooth_device = connector.GetDeviceIdConnectedTo()?;
+            let bluetooth_device = self.enumerator.GetDevice(bluetooth_device)?;
+
+            let control: IKsControl = bluetooth_device.Activate(CLSCTX_ALL, None)?;
+
+            let property = KSIDENTIFIER {
+                Anonymous: KSIDENTIFIER_0 {
+                    Anonymous: KSIDENTIFIER_0_0 {
+                        Set: KSPROPSETID_BtAudio,
+                        Id: KSPROPERTY_ONESHOT_RECONNECT.0 as u32,
+                        Flags: KSPROPERTY_TYPE_GET,
+                    },
+                },
+            };
+
+            let mut out = 0;
+            control.KsProperty(
+                &property,
+                size_of_val(&property) as u32,
+                null_mut(),
+                0,
+                &mut out,
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+pub fn id(device: &IMMDevice) -> Result<String> {
+    Ok(unsafe { device.GetId()?.to_string()? })
+}
+
+pub fn name(device: &IMMDevice) -> Result<String> {
+    unsafe {
+        let props = device.OpenPropertyStore(STGM_READ)?;
+        let name = props.GetValue(&PKEY_Device_FriendlyName)?.to_string();
+
+        Ok(name)
+    }
+}
+
+/// a device's volume and mute state
+pub struct Volume {
+    controls: IAudioEndpointVolume,
+}
+
+impl Volume {
+    pub fn new(device: &IMMDevice) -> Result<Self> {
+        let controls = unsafe { device.Activate(CLSCTX_ALL, None) }
+            .context("failed to activate volume controls")?;
+
+        Ok(Self { controls })
+    }
+
+    /// for controls activated on another thread, e.g. resolved from an `AgileReference`
+    pub fn from_controls(controls: IAudioEndpointVolume) -> Self {
+        Self { controls }
+    }
+
+    /// for registering for changes
+    pub fn controls(&self) -> &IAudioEndpointVolume {
+        &self.controls
+    }
+
+    /// from 0 to 1
+    pub fn volume(&self) -> Result<f32> {
+        Ok(unsafe { self.controls.GetMasterVolumeLevelScalar()? })
+    }
+
+    pub fn set_volume(&self, volume: f32) -> Result<()> {
+        let volume = volume.clamp(0.0, 1.0);
+        unsafe {
+            self.controls
+                .SetMasterVolumeLevelScalar(volume, null_mut())?
+        };
+
+        Ok(())
+    }
+
+    /// by the device's own step size, like the volume keys
+    pub fn step(&self, up: bool) -> Result<()> {
+        unsafe {
+            match up {
+                true => self.controls.VolumeStepUp(null_mut())?,
+                false => self.controls.VolumeStepDown(null_mut())?,
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn is_mute(&self) -> Result<bool> {
+        Ok(unsafe { self.controls.GetMute()?.as_bool() })
+    }
+
+    pub fn set_mute(&self, value: bool) -> Result<()> {
+        unsafe { self.controls.SetMute(value, null_mut())? };
+        Ok(())
+    }
+}
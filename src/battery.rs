@@ -0,0 +1,108 @@
+use std::mem::size_of;
+
+use anyhow::Result;
+use windows::Win32::{
+    Devices::{
+        DeviceAndDriverInstallation::{
+            DIGCF_ALLCLASSES, DIGCF_PRESENT, HDEVINFO, SP_DEVINFO_DATA,
+            SetupDiDestroyDeviceInfoList, SetupDiEnumDeviceInfo, SetupDiGetClassDevsW,
+            SetupDiGetDevicePropertyW,
+        },
+        Properties::{DEVPKEY_Device_FriendlyName, DEVPROP_TYPE_BYTE, DEVPROPTYPE},
+    },
+    Foundation::DEVPROPKEY,
+};
+use windows_core::{GUID, PCWSTR};
+
+use crate::default;
+
+/// `DEVPKEY_Bluetooth_Battery`, which isn't in the windows metadata
+const DEVPKEY_BLUETOOTH_BATTERY: DEVPROPKEY = DEVPROPKEY {
+    fmtid: GUID::from_u128(0x104ea319_6ee2_4701_bd47_8ddbf425bbe5),
+    pid: 2,
+};
+
+/// the battery percentage of the first present device whose name contains `name`
+/// (case-insensitively) and reports one
+pub fn battery_level(name: &str) -> Result<Option<u8>> {
+    let name = name.to_lowercase();
+
+    unsafe {
+        let devices =
+            SetupDiGetClassDevsW(None, PCWSTR::null(), None, DIGCF_PRESENT | DIGCF_ALLCLASSES)?;
+
+        let level = find_level(devices, &name);
+        SetupDiDestroyDeviceInfoList(devices)?;
+
+        Ok(level)
+    }
+}
+
+fn find_level(devices: HDEVINFO, name: &str) -> Option<u8> {
+    for index in 0.. {
+        let mut info = SP_DEVINFO_DATA {
+            cbSize: size_of::<SP_DEVINFO_DATA>() as u32,
+            ..default()
+        };
+
+        // fails with ERROR_NO_MORE_ITEMS after the last device
+        unsafe { SetupDiEnumDeviceInfo(devices, index, &mut info).ok()? };
+
+        let Some(friendly_name) = string_property(devices, &info, &DEVPKEY_Device_FriendlyName)
+        else {
+            continue;
+        };
+
+        if !friendly_name.to_lowercase().contains(name) {
+            continue;
+        }
+
+        let mut kind = DEVPROPTYPE::default();
+        let mut level = [0u8];
+
+        let found = unsafe {
+            SetupDiGetDevicePropertyW(
+                devices,
+                &info,
+                &DEVPKEY_BLUETOOTH_BATTERY,
+                &mut kind,
+                Some(&mut level),
+                None,
+                0,
+            )
+        };
+
+        if found.is_ok() && kind == DEVPROP_TYPE_BYTE {
+            return Some(level[0]);
+        }
+    }
+
+    None
+}
+
+fn string_property(devices: HDEVINFO, info: &SP_DEVINFO_DATA, key: &DEVPROPKEY) -> Option<String> {
+    let mut kind = DEVPROPTYPE::default();
+    let mut buffer = [0u8; 512];
+    let mut size = 0;
+
+    unsafe {
+        SetupDiGetDevicePropertyW(
+            devices,
+            info,
+            key,
+            &mut kind,
+            Some(&mut buffer),
+            Some(&mut size),
+            0,
+        )
+        .ok()?;
+    }
+
+    let wide: Vec<u16> = buffer[..size as usize]
+        .chunks_exact(2)
+        .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+        .take_while(|c| *c != 0)
+        .collect();
+
+    Some(String::from_utf16_lossy(&wide))
+}
@@ -1,7 +1,8 @@
 use std::io::Read;
 
 use anyhow::Result;
-use rouille::{Response, ResponseBody};
+use rouille::{Request, Response, ResponseBody};
+use serde_json::json;
 use windows::Win32::{
     Foundation::{HANDLE, HWND},
     System::{
@@ -9,11 +10,43 @@ use windows::Win32::{
         Memory::{GMEM_MOVEABLE, GlobalAlloc, GlobalLock, GlobalUnlock},
         Ole::CF_TEXT,
     },
+    UI::WindowsAndMessaging::{
+        CreateWindowExA, DestroyWindow, HWND_MESSAGE, WINDOW_EX_STYLE, WINDOW_STYLE,
+    },
 };
+use windows_core::s;
+
+use crate::{api::HttpServer, history};
+
+/// the clipboard needs a window to own what's put on it. rouille answers on threads of its own
+/// and the panel's window belongs to the main thread, so each request makes a message-only
+/// window for the job. what's copied stays on the clipboard once it's gone.
+fn set_clipboard(content: &[u8]) -> Result<()> {
+    unsafe {
+        let owner = CreateWindowExA(
+            WINDOW_EX_STYLE::default(),
+            s!("STATIC"),
+            None,
+            WINDOW_STYLE::default(),
+            0,
+            0,
+            0,
+            0,
+            Some(HWND_MESSAGE),
+            None,
+            None,
+            None,
+        )?;
+
+        let result = fill_clipboard(owner, content);
+        let _ = DestroyWindow(owner);
+        result
+    }
+}
 
-fn set_clipboard(hwnd: X, content: &[u8]) -> Result<()> {
+fn fill_clipboard(owner: HWND, content: &[u8]) -> Result<()> {
     unsafe {
-        OpenClipboard(Some(hwnd.0))?;
+        OpenClipboard(Some(owner))?;
         EmptyClipboard()?;
 
         let memory = GlobalAlloc(GMEM_MOVEABLE, content.len() + 1)?;
@@ -32,59 +65,84 @@ fn set_clipboard(hwnd: X, content: &[u8]) -> Result<()> {
     Ok(())
 }
 
-#[derive(Clone, Copy)]
-struct X(HWND);
-unsafe impl Send for X {}
-unsafe impl Sync for X {}
-
-fn run(hwnd: X) {
-    let addr = ("0.0.0.0", 25562);
-    rouille::start_server(addr, move |request| {
-        if request.url() == "/clip" {
-            if let Some(mut body) = request.data() {
-                let len = match request.header("content-length").map(str::parse).transpose() {
-                    Ok(v) => v.unwrap_or_default(),
-                    Err(e) => {
-                        eprintln!("{e:?}");
-                        return Response::empty_400();
-                    }
-                };
-
-                let mut content = Vec::with_capacity(len);
-                if let Err(e) = body.read_to_end(&mut content) {
+fn respond(request: &Request) -> Response {
+    if request.url() == "/clip" {
+        if let Some(mut body) = request.data() {
+            let len = match request.header("content-length").map(str::parse).transpose() {
+                Ok(v) => v.unwrap_or_default(),
+                Err(e) => {
                     eprintln!("{e:?}");
                     return Response::empty_400();
                 }
+            };
 
-                if let Err(e) = set_clipboard(hwnd, &content) {
-                    eprintln!("{e:?}");
-                    return Response {
-                        status_code: 500,
-                        headers: vec![],
-                        data: ResponseBody::empty(),
-                        upgrade: None,
-                    };
-                }
+            let mut content = Vec::with_capacity(len);
+            if let Err(e) = body.read_to_end(&mut content) {
+                eprintln!("{e:?}");
+                return Response::empty_400();
+            }
 
-                Response {
-                    status_code: 200,
+            if let Err(e) = set_clipboard(&content) {
+                eprintln!("{e:?}");
+                return Response {
+                    status_code: 500,
                     headers: vec![],
                     data: ResponseBody::empty(),
                     upgrade: None,
-                }
-            } else {
-                Response::empty_400()
+                };
+            }
+
+            Response {
+                status_code: 200,
+                headers: vec![],
+                data: ResponseBody::empty(),
+                upgrade: None,
             }
         } else {
-            Response::empty_404()
+            Response::empty_400()
         }
-    });
+    } else if request.url() == "/history" {
+        history(request)
+    } else {
+        Response::empty_404()
+    }
 }
 
-pub fn spawn(hwnd: HWND) {
-    let hwnd = X(hwnd);
+/// the lock history as json, with how long each lock lasted. only answered locally, since
+/// it's a record of when someone was at their desk.
+fn history(request: &Request) -> Response {
+    if !request.remote_addr().ip().is_loopback() {
+        return Response::empty_404();
+    }
+
+    let entries = match history::load() {
+        Ok(entries) => entries,
+        Err(e) => {
+            eprintln!("{e:?}");
+            return Response {
+                status_code: 500,
+                headers: vec![],
+                data: ResponseBody::empty(),
+                upgrade: None,
+            };
+        }
+    };
+
+    let entries: Vec<_> = entries
+        .iter()
+        .map(|away| {
+            json!({
+                "locked": away.locked,
+                "unlocked": away.unlocked,
+                "away_minutes": away.duration().as_secs() / 60,
+            })
+        })
+        .collect();
+
+    Response::json(&entries)
+}
 
-    std::thread::spawn(move || {
-        run(hwnd);
-    });
+/// `None` if the port's taken
+pub fn spawn() -> Option<HttpServer> {
+    HttpServer::start(("0.0.0.0", 25562), respond).ok()
 }
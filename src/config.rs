@@ -0,0 +1,290 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    actions::{Action, Gesture},
+    api::ApiConfig,
+    discord::DiscordConfig,
+    headset::HeadsetButtonsConfig,
+    history::HistoryConfig,
+    keyboard::{Hotkey, HotkeyBinding},
+    layout::{Widget, WidgetOffset},
+    notifications::NotificationConfig,
+    obs::ObsConfig,
+    osd::OsdConfig,
+    placement::{Position, Preset},
+    render::Backend,
+    session::{IdleConfig, LockConfig, SessionCommands},
+    tray::TrayDevice,
+    webhook::WebhookConfig,
+    widgets::{
+        bluetooth::BluetoothConfig, brightness::BrightnessConfig, button::ButtonConfig,
+        clock::ClockConfig, command::CommandConfig, cpu::CpuConfig, date::DateConfig,
+        disk::DiskConfig, locks::LockKeysConfig, meeting::MeetingConfig, memory::MemoryConfig,
+        network::NetworkConfig, peripherals::PeripheralsConfig, pomodoro::PomodoroConfig,
+        refresh::RefreshRateConfig, source::InputSourceConfig, vpn::VpnConfig,
+        weather::WeatherConfig,
+    },
+    zorder::ZOrder,
+};
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// substring matched (case-insensitively) against device names to find the bluetooth headset.
+    /// without one, nothing looks for a headset and clicks don't try to reconnect one.
+    pub bluetooth_device: Option<String>,
+
+    /// per-widget overrides of what each gesture does
+    pub bindings: HashMap<Widget, HashMap<Gesture, Action>>,
+
+    /// how the panel is drawn, falling back to gdi+ if direct2d can't be used; only read at
+    /// startup
+    pub renderer: Backend,
+
+    /// the widgets from left to right, rearranged in layout edit mode
+    pub widgets: Vec<Widget>,
+
+    /// extra space before some of the widgets, set in layout edit mode
+    pub widget_offsets: Vec<WidgetOffset>,
+
+    pub clock: ClockConfig,
+
+    pub date: DateConfig,
+
+    pub cpu: CpuConfig,
+
+    pub memory: MemoryConfig,
+
+    pub network: NetworkConfig,
+
+    pub vpn: VpnConfig,
+
+    pub disk: DiskConfig,
+
+    pub lock_keys: LockKeysConfig,
+
+    pub brightness: BrightnessConfig,
+
+    pub input_source: InputSourceConfig,
+
+    pub refresh_rate: RefreshRateConfig,
+
+    pub bluetooth: BluetoothConfig,
+
+    pub peripherals: PeripheralsConfig,
+
+    pub meeting: MeetingConfig,
+
+    pub pomodoro: PomodoroConfig,
+
+    pub command: CommandConfig,
+
+    pub weather: WeatherConfig,
+
+    /// widgets that run a command when clicked
+    pub buttons: Vec<ButtonConfig>,
+
+    /// where the panel sits on screen, updated by shift-dragging it
+    pub position: Position,
+
+    /// a named spot to put the panel in instead of `position`
+    pub placement: Option<Preset>,
+
+    /// stops the panel from being dragged around
+    pub lock_layout: bool,
+
+    /// whether the panel stays above other windows, below them, or neither
+    pub z_order: ZOrder,
+
+    /// which devices are muted while the session is locked
+    pub lock: LockConfig,
+
+    /// used instead of `lock` for sessions connected over remote desktop
+    pub remote_lock: Option<LockConfig>,
+
+    /// muting after a while without input, even if the session isn't locked
+    pub idle: IdleConfig,
+
+    /// commands run on lock, unlock, suspend and resume
+    pub session_commands: SessionCommands,
+
+    /// a log of locks and how long they lasted
+    pub lock_history: HistoryConfig,
+
+    /// pulse a ring around the input widget while the mic is unmuted and being recorded from
+    pub mic_live_warning: bool,
+
+    /// how long a device that isn't a default stays tracked after it was last needed, e.g. by
+    /// the device menu or a full status, before it's let go
+    pub device_cache_minutes: u64,
+
+    /// keeps outputs about as loud as each other when the default output changes, by setting
+    /// the new one's volume to the old one's plus the difference in their offsets. keyed by a
+    /// substring of the device's name, matched case-insensitively, with offsets in percentage
+    /// points, e.g. `{"headphones": 0, "speakers": -20}` switches from headphones at 30% to
+    /// speakers at 10%. switches to or from a device that isn't listed are left alone.
+    pub volume_offsets: HashMap<String, f32>,
+
+    /// global hotkey that gives the panel keyboard focus, e.g. "ctrl+alt+k"
+    pub keyboard_hotkey: Option<Hotkey>,
+
+    /// global hotkeys bound to actions
+    pub hotkeys: Vec<HotkeyBinding>,
+
+    /// actions for the buttons on a headset
+    pub headset_buttons: HeadsetButtonsConfig,
+
+    /// the popup shown when a hotkey changes the volume or mute state
+    pub osd: OsdConfig,
+
+    /// default devices to show in the notification area, crossed out while muted
+    pub tray_icons: Vec<TrayDevice>,
+
+    /// which events are announced with a toast
+    pub notifications: NotificationConfig,
+
+    /// also writes errors, failed reconnects and restarts to the Application event log
+    pub event_log: bool,
+
+    /// urls posted to on lock, unlock and mute changes
+    pub webhooks: WebhookConfig,
+
+    /// an http server on localhost taking the same commands as the pipe
+    pub api: ApiConfig,
+
+    /// keeps discord's mute in step with the input device's
+    pub discord: DiscordConfig,
+
+    /// mutes an obs source along with the input device, and feeds the obs widget
+    pub obs: ObsConfig,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            bluetooth_device: None,
+            bindings: HashMap::new(),
+            renderer: Backend::default(),
+            widgets: Widget::DEFAULT.to_vec(),
+            widget_offsets: vec![],
+            clock: ClockConfig::default(),
+            date: DateConfig::default(),
+            cpu: CpuConfig::default(),
+            memory: MemoryConfig::default(),
+            network: NetworkConfig::default(),
+            vpn: VpnConfig::default(),
+            disk: DiskConfig::default(),
+            lock_keys: LockKeysConfig::default(),
+            brightness: BrightnessConfig::default(),
+            input_source: InputSourceConfig::default(),
+            refresh_rate: RefreshRateConfig::default(),
+            bluetooth: BluetoothConfig::default(),
+            peripherals: PeripheralsConfig::default(),
+            meeting: MeetingConfig::default(),
+            pomodoro: PomodoroConfig::default(),
+            command: CommandConfig::default(),
+            weather: WeatherConfig::default(),
+            buttons: vec![],
+            position: Position::default(),
+            placement: None,
+            lock_layout: false,
+            z_order: ZOrder::default(),
+            lock: LockConfig::default(),
+            remote_lock: None,
+            idle: IdleConfig::default(),
+            session_commands: SessionCommands::default(),
+            lock_history: HistoryConfig::default(),
+            mic_live_warning: false,
+            device_cache_minutes: 30,
+            volume_offsets: HashMap::new(),
+            keyboard_hotkey: None,
+            hotkeys: vec![],
+            headset_buttons: HeadsetButtonsConfig::default(),
+            osd: OsdConfig::default(),
+            tray_icons: vec![TrayDevice::Input],
+            notifications: NotificationConfig::default(),
+            event_log: false,
+            webhooks: WebhookConfig::default(),
+            api: ApiConfig::default(),
+            discord: DiscordConfig::default(),
+            obs: ObsConfig::default(),
+        }
+    }
+}
+
+impl Config {
+    pub fn path() -> PathBuf {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+        root.join("config.json")
+    }
+
+    pub fn load() -> Result<Self> {
+        let path = Self::path();
+
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let text = std::fs::read_to_string(&path).context("failed to read config")?;
+        let config = serde_json::from_str(&text).context("failed to parse config")?;
+
+        Ok(config)
+    }
+
+    /// writes one top-level setting to the config file, leaving the rest of the file as it is
+    pub fn set(key: &str, value: impl Serialize) -> Result<()> {
+        let path = Self::path();
+
+        let mut config = if path.exists() {
+            let text = std::fs::read_to_string(&path).context("failed to read config")?;
+            serde_json::from_str(&text).context("failed to parse config")?
+        } else {
+            serde_json::Value::Object(serde_json::Map::new())
+        };
+
+        let settings = config.as_object_mut().context("config is not an object")?;
+        settings.insert(key.to_string(), serde_json::to_value(value)?);
+
+        let text = serde_json::to_string_pretty(&config)? + "\n";
+        std::fs::write(&path, text).context("failed to write config")?;
+
+        Ok(())
+    }
+
+    /// the audio devices the bluetooth widget keeps track of
+    pub fn bluetooth_devices(&self) -> Vec<String> {
+        match self.bluetooth.devices.is_empty() {
+            true => self.bluetooth_device.iter().cloned().collect(),
+            false => self.bluetooth.devices.clone(),
+        }
+    }
+
+    /// the offset from `volume_offsets` for the device called `name`, if it's listed
+    pub fn volume_offset(&self, name: &str) -> Option<f32> {
+        let name = name.to_lowercase();
+
+        // the most specific key wins, e.g. `Headphones (Yeti)` over `Headphones`
+        self.volume_offsets
+            .iter()
+            .filter(|(key, _)| name.contains(&key.to_lowercase()))
+            .max_by_key(|(key, _)| key.len())
+            .map(|(_, offset)| *offset)
+    }
+
+    pub fn action_for(&self, widget: Option<Widget>, gesture: Gesture) -> Action {
+        widget
+            .and_then(|widget| self.bindings.get(&widget))
+            .and_then(|bindings| bindings.get(&gesture))
+            .cloned()
+            .unwrap_or_else(|| match gesture.default_action(widget) {
+                Action::ConnectBluetooth if self.bluetooth_device.is_none() => Action::None,
+                action => action,
+            })
+    }
+}
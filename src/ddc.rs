@@ -0,0 +1,134 @@
+use std::sync::{
+    OnceLock,
+    mpsc::{self, Sender},
+};
+
+use anyhow::{Result, bail};
+use windows::Win32::{
+    Devices::Display::{
+        DestroyPhysicalMonitors, GetNumberOfPhysicalMonitorsFromHMONITOR,
+        GetPhysicalMonitorsFromHMONITOR, GetVCPFeatureAndVCPFeatureReply, PHYSICAL_MONITOR,
+        SetVCPFeature,
+    },
+    Foundation::{HANDLE, LPARAM, RECT},
+    Graphics::Gdi::{EnumDisplayMonitors, HDC, HMONITOR},
+};
+use windows_core::BOOL;
+
+type Job = Box<dyn FnOnce() + Send>;
+
+static WORKER: OnceLock<Sender<Job>> = OnceLock::new();
+
+/// runs `job` on the thread all DDC/CI goes through, started on first use. a monitor can take a
+/// good part of a second to answer, and doesn't cope with two conversations at once, so jobs wait
+/// their turn there rather than on the ui thread.
+pub fn queue(job: impl FnOnce() + Send + 'static) {
+    let worker = WORKER.get_or_init(|| {
+        let (sender, jobs) = mpsc::channel::<Job>();
+        std::thread::spawn(move || jobs.into_iter().for_each(|job| job()));
+
+        sender
+    });
+
+    let _ = worker.send(Box::new(job));
+}
+
+/// a monitor that can be talked to over DDC/CI, which is closed when dropped
+pub struct PhysicalMonitor(PHYSICAL_MONITOR);
+
+impl PhysicalMonitor {
+    pub fn handle(&self) -> HANDLE {
+        self.0.hPhysicalMonitor
+    }
+
+    pub fn name(&self) -> String {
+        // copied out, since the struct is packed
+        let name = self.0.szPhysicalMonitorDescription;
+        let len = name.iter().position(|&c| c == 0).unwrap_or(name.len());
+
+        String::from_utf16_lossy(&name[..len])
+    }
+
+    /// the current value of a VCP feature, as defined by the MCCS standard
+    pub fn vcp(&self, code: u8) -> Result<u32> {
+        let mut value = 0;
+        let ok =
+            unsafe { GetVCPFeatureAndVCPFeatureReply(self.handle(), code, None, &mut value, None) };
+
+        if ok == 0 {
+            bail!("failed to read VCP {:#04x} from {}", code, self.name());
+        }
+
+        Ok(value)
+    }
+
+    pub fn set_vcp(&self, code: u8, value: u32) -> Result<()> {
+        if unsafe { SetVCPFeature(self.handle(), code, value) } == 0 {
+            bail!("failed to set VCP {:#04x} on {}", code, self.name());
+        }
+
+        Ok(())
+    }
+}
+
+impl Drop for PhysicalMonitor {
+    fn drop(&mut self) {
+        let _ = unsafe { DestroyPhysicalMonitors(&[self.0]) };
+    }
+}
+
+/// the physical monitors behind every display, in the order windows enumerates them. monitors
+/// that don't support DDC/CI are included, but fail the calls made on them.
+pub fn monitors() -> Result<Vec<PhysicalMonitor>> {
+    unsafe extern "system" fn callback(
+        monitor: HMONITOR,
+        _: HDC,
+        _: *mut RECT,
+        data: LPARAM,
+    ) -> BOOL {
+        let displays = unsafe { &mut *(data.0 as *mut Vec<HMONITOR>) };
+        displays.push(monitor);
+
+        true.into()
+    }
+
+    let mut displays: Vec<HMONITOR> = vec![];
+    unsafe {
+        EnumDisplayMonitors(
+            None,
+            None,
+            Some(callback),
+            LPARAM(&mut displays as *mut _ as _),
+        )
+        .ok()?;
+    }
+
+    let mut monitors = vec![];
+    for display in displays {
+        let mut count = 0;
+        unsafe { GetNumberOfPhysicalMonitorsFromHMONITOR(display, &mut count)? };
+
+        let mut physical = vec![PHYSICAL_MONITOR::default(); count as usize];
+        unsafe { GetPhysicalMonitorsFromHMONITOR(display, &mut physical)? };
+
+        monitors.extend(physical.into_iter().map(PhysicalMonitor));
+    }
+
+    Ok(monitors)
+}
+
+/// the first physical monitor with `name` (lowercase) in its name, or the first one at all
+pub fn find(name: Option<&str>) -> Result<PhysicalMonitor> {
+    let monitors = monitors()?;
+
+    let monitor = monitors.into_iter().find(|monitor| match name {
+        Some(name) => monitor.name().to_lowercase().contains(name),
+        None => true,
+    });
+
+    match (monitor, name) {
+        (Some(monitor), _) => Ok(monitor),
+        (None, Some(name)) => bail!("no monitor named {}", name),
+        (None, None) => bail!("no monitors found"),
+    }
+}
@@ -0,0 +1,320 @@
+use std::{
+    fs::File,
+    io::{Read, Write},
+    os::windows::io::AsRawHandle,
+    path::PathBuf,
+    sync::mpsc::{self, Receiver, RecvTimeoutError, Sender, TryRecvError},
+    time::Duration,
+};
+
+use anyhow::{Context, Result, bail};
+use serde::Deserialize;
+use serde_json::{Value, json};
+use windows::{
+    Foundation::Uri,
+    Storage::Streams::UnicodeEncoding,
+    Web::Http::{HttpClient, HttpStringContent},
+    Win32::{
+        Foundation::{HANDLE, HWND, LPARAM, WPARAM},
+        System::Pipes::PeekNamedPipe,
+        UI::WindowsAndMessaging::PostMessageA,
+    },
+};
+use windows_core::{HSTRING, h};
+
+use crate::{WM_DISCORD_MUTE, config::Config, ipc::X, throttle::Throttle};
+
+const OP_HANDSHAKE: u32 = 0;
+const OP_FRAME: u32 = 1;
+
+/// how long to wait before looking for discord again after it closes or isn't running
+const RETRY: Duration = Duration::from_secs(15);
+
+/// the most failures logged a minute, on top of repeats being collapsed
+const FAILURES_PER_MINUTE: u32 = 2;
+
+/// keeps discord's mute in step with the input device's, both ways, using discord's local rpc.
+/// needs an application from the discord developer portal, which discord asks to approve the
+/// first time. read at startup only.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct DiscordConfig {
+    /// the application's id. nothing connects to discord without one
+    pub client_id: Option<String>,
+    pub client_secret: String,
+}
+
+/// where the access token is kept once discord's approved the app, so it's only asked once
+fn token_path() -> PathBuf {
+    Config::path().with_file_name("discord_token")
+}
+
+/// the panel's side of the connection, which lives on its own thread
+pub struct Discord {
+    mute: Sender<bool>,
+    /// the input's mute state as last sent to or heard from discord
+    last_muted: Option<bool>,
+}
+
+impl Discord {
+    pub fn spawn(hwnd: HWND, config: &DiscordConfig) -> Option<Self> {
+        let client_id = config.client_id.clone()?;
+        let client_secret = config.client_secret.clone();
+        let hwnd = X(hwnd);
+        let (mute, commands) = mpsc::channel();
+
+        std::thread::spawn(move || run(hwnd, &client_id, &client_secret, commands));
+
+        Some(Self {
+            mute,
+            last_muted: None,
+        })
+    }
+
+    /// tells discord the input's mute state, if it's changed since discord last heard about it
+    pub fn sync(&mut self, muted: bool) {
+        if self.last_muted != Some(muted) {
+            self.last_muted = Some(muted);
+            let _ = self.mute.send(muted);
+        }
+    }
+
+    /// records a mute change made in discord, so it isn't sent straight back
+    pub fn on_mute(&mut self, muted: bool) {
+        self.last_muted = Some(muted);
+    }
+}
+
+struct Connection {
+    pipe: File,
+    nonce: u64,
+}
+
+impl Connection {
+    /// discord listens on the first free one of ten pipes
+    fn open() -> Result<Self> {
+        for i in 0..10 {
+            let pipe = std::fs::OpenOptions::new()
+                .read(true)
+                .write(true)
+                .open(format!(r"\\.\pipe\discord-ipc-{}", i));
+
+            if let Ok(pipe) = pipe {
+                return Ok(Self { pipe, nonce: 0 });
+            }
+        }
+
+        bail!("discord isn't running")
+    }
+
+    fn write(&mut self, op: u32, payload: &Value) -> Result<()> {
+        let payload = payload.to_string();
+
+        let mut frame = Vec::with_capacity(8 + payload.len());
+        frame.extend_from_slice(&op.to_le_bytes());
+        frame.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+        frame.extend_from_slice(payload.as_bytes());
+
+        self.pipe
+            .write_all(&frame)
+            .context("failed to write to discord")
+    }
+
+    fn read(&mut self) -> Result<Value> {
+        let mut header = [0u8; 8];
+        self.pipe
+            .read_exact(&mut header)
+            .context("failed to read from discord")?;
+
+        let op = u32::from_le_bytes(header[..4].try_into()?);
+        let length = u32::from_le_bytes(header[4..].try_into()?);
+
+        let mut payload = vec![0u8; length as usize];
+        self.pipe.read_exact(&mut payload)?;
+        let payload: Value = serde_json::from_slice(&payload).context("invalid message")?;
+
+        if op != OP_FRAME {
+            bail!("discord closed the connection: {}", payload["message"]);
+        }
+
+        Ok(payload)
+    }
+
+    /// whether there's anything to read, since reading blocks writes on the same pipe
+    fn has_data(&self) -> Result<bool> {
+        let mut available = 0;
+
+        unsafe {
+            PeekNamedPipe(
+                HANDLE(self.pipe.as_raw_handle()),
+                None,
+                0,
+                None,
+                Some(&mut available),
+                None,
+            )?
+        };
+
+        Ok(available > 0)
+    }
+
+    fn send(&mut self, cmd: &str, args: Value) -> Result<()> {
+        self.nonce += 1;
+        let message = json!({ "cmd": cmd, "args": args, "nonce": self.nonce.to_string() });
+        self.write(OP_FRAME, &message)
+    }
+
+    fn subscribe(&mut self, evt: &str) -> Result<()> {
+        self.nonce += 1;
+        let message = json!({ "cmd": "SUBSCRIBE", "evt": evt, "nonce": self.nonce.to_string() });
+        self.write(OP_FRAME, &message)?;
+        self.reply().map(|_| ())
+    }
+
+    /// sends `cmd` and waits for its answer, for the setup before anything's subscribed to
+    fn call(&mut self, cmd: &str, args: Value) -> Result<Value> {
+        self.send(cmd, args)?;
+        self.reply()
+    }
+
+    fn reply(&mut self) -> Result<Value> {
+        let nonce = self.nonce.to_string();
+
+        loop {
+            let message = self.read()?;
+            if message["nonce"].as_str() != Some(&nonce) {
+                continue;
+            }
+
+            if message["evt"] == "ERROR" {
+                bail!("discord: {}", message["data"]["message"]);
+            }
+
+            return Ok(message["data"].clone());
+        }
+    }
+}
+
+/// asks discord to approve the app, which shows a prompt in discord and waits for an answer,
+/// and trades the code it gives back for an access token
+fn authorize(connection: &mut Connection, client_id: &str, client_secret: &str) -> Result<String> {
+    let args = json!({
+        "client_id": client_id,
+        "scopes": ["rpc", "rpc.voice.read", "rpc.voice.write"],
+    });
+    let data = connection.call("AUTHORIZE", args)?;
+    let code = data["code"].as_str().context("no authorization code")?;
+
+    let body = format!(
+        "client_id={}&client_secret={}&grant_type=authorization_code&code={}",
+        client_id, client_secret, code
+    );
+
+    let client = HttpClient::new()?;
+    let uri = Uri::CreateUri(h!("https://discord.com/api/oauth2/token"))?;
+    let content = HttpStringContent::CreateFromStringWithEncodingAndMediaType(
+        &HSTRING::from(body),
+        UnicodeEncoding::Utf8,
+        h!("application/x-www-form-urlencoded"),
+    )?;
+
+    let response = client.PostAsync(&uri, &content)?.get()?;
+    response.EnsureSuccessStatusCode()?;
+    let text = response.Content()?.ReadAsStringAsync()?.get()?;
+
+    let json: Value = serde_json::from_str(&text.to_string_lossy())?;
+    let token = json["access_token"].as_str().context("no access token")?;
+
+    Ok(token.to_string())
+}
+
+fn authenticate(connection: &mut Connection, client_id: &str, client_secret: &str) -> Result<()> {
+    if let Ok(token) = std::fs::read_to_string(token_path()) {
+        let args = json!({ "access_token": token.trim() });
+        if connection.call("AUTHENTICATE", args).is_ok() {
+            return Ok(());
+        }
+    }
+
+    // never approved, or the token has expired or been revoked
+    let token = authorize(connection, client_id, client_secret)?;
+    std::fs::write(token_path(), &token).context("failed to save discord token")?;
+
+    connection.call("AUTHENTICATE", json!({ "access_token": token }))?;
+    Ok(())
+}
+
+/// one connection to discord, until either side closes it. `latest` is the last state the panel
+/// asked for, sent again on connecting so discord picks up changes made while it was closed
+fn session(
+    hwnd: X,
+    client_id: &str,
+    client_secret: &str,
+    commands: &Receiver<bool>,
+    latest: &mut Option<bool>,
+) -> Result<()> {
+    let mut connection = Connection::open()?;
+
+    connection.write(OP_HANDSHAKE, &json!({ "v": 1, "client_id": client_id }))?;
+    // READY
+    connection.read()?;
+
+    authenticate(&mut connection, client_id, client_secret)?;
+    connection.subscribe("VOICE_SETTINGS_UPDATE")?;
+
+    if let Some(muted) = *latest {
+        connection.send("SET_VOICE_SETTINGS", json!({ "mute": muted }))?;
+    }
+
+    loop {
+        match commands.recv_timeout(Duration::from_millis(100)) {
+            Ok(muted) => {
+                *latest = Some(muted);
+                connection.send("SET_VOICE_SETTINGS", json!({ "mute": muted }))?;
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => return Ok(()),
+        }
+
+        while connection.has_data()? {
+            let message = connection.read()?;
+
+            if message["evt"] != "VOICE_SETTINGS_UPDATE" {
+                continue;
+            }
+
+            if let Some(muted) = message["data"]["mute"].as_bool() {
+                unsafe {
+                    PostMessageA(
+                        Some(hwnd.0),
+                        WM_DISCORD_MUTE,
+                        WPARAM(muted as usize),
+                        LPARAM(0),
+                    )?
+                };
+            }
+        }
+    }
+}
+
+fn run(hwnd: X, client_id: &str, client_secret: &str, commands: Receiver<bool>) {
+    let mut latest = None;
+    let mut failures = Throttle::new(FAILURES_PER_MINUTE);
+
+    loop {
+        if let Err(e) = session(hwnd, client_id, client_secret, &commands, &mut latest) {
+            failures.pass(&format!("discord: {:#}", e), crate::log_background);
+        }
+
+        std::thread::sleep(RETRY);
+
+        // keeps whatever the panel asked for while discord was closed for when it's back
+        loop {
+            match commands.try_recv() {
+                Ok(muted) => latest = Some(muted),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => return,
+            }
+        }
+    }
+}
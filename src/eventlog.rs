@@ -0,0 +1,89 @@
+use std::sync::{
+    Mutex,
+    atomic::{AtomicBool, Ordering},
+};
+
+use anyhow::Result;
+use windows::Win32::System::{
+    EventLog::{
+        DeregisterEventSource, EVENTLOG_ERROR_TYPE, EVENTLOG_WARNING_TYPE, REPORT_EVENT_TYPE,
+        RegisterEventSourceW, ReportEventW,
+    },
+    Registry::HKEY_LOCAL_MACHINE,
+};
+use windows_core::{HSTRING, PCWSTR, h};
+
+use crate::{registry, throttle::Throttle};
+
+const SOURCE_KEY: &str = r"SYSTEM\CurrentControlSet\Services\EventLog\Application\control-panel";
+
+/// the .NET framework's message file, whose every event id is just "%1", so the event viewer
+/// shows the messages as they are without a message file of our own
+const MESSAGE_FILE: &str =
+    r"%SystemRoot%\Microsoft.NET\Framework64\v4.0.30319\EventLogMessages.dll";
+
+const EVENT_ID: u32 = 1000;
+
+/// from the config's `event_log`, kept here so restarts after errors can still be reported
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// far fewer than the log file, since the event log is shared with everything else
+const EVENTS_PER_MINUTE: u32 = 10;
+
+static THROTTLE: Mutex<Throttle> = Mutex::new(Throttle::new(EVENTS_PER_MINUTE));
+
+/// starts or stops reporting, registering the source with the event log when it can. that takes
+/// administrator rights, so it only happens the first time the panel runs elevated. until then
+/// the events still show up, with a note about the missing description in front of them.
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+
+    if enabled {
+        let _ = register();
+    }
+}
+
+fn register() -> Result<()> {
+    registry::set_expand_string(
+        HKEY_LOCAL_MACHINE,
+        SOURCE_KEY,
+        "EventMessageFile",
+        MESSAGE_FILE,
+    )?;
+
+    // errors, warnings and information
+    registry::set_dword(HKEY_LOCAL_MACHINE, SOURCE_KEY, "TypesSupported", 7)
+}
+
+pub fn warning(message: &str) {
+    report(EVENTLOG_WARNING_TYPE, message);
+}
+
+pub fn error(message: &str) {
+    report(EVENTLOG_ERROR_TYPE, message);
+}
+
+/// writes `message` to the Application log through a `Throttle`, if that's turned on
+fn report(kind: REPORT_EVENT_TYPE, message: &str) {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return;
+    }
+
+    let mut throttle = THROTTLE.lock().unwrap_or_else(|e| e.into_inner());
+    throttle.pass(message, |message| write(kind, message));
+}
+
+/// failures are ignored, since there's nowhere left to report them
+fn write(kind: REPORT_EVENT_TYPE, message: &str) {
+    unsafe {
+        let Ok(source) = RegisterEventSourceW(PCWSTR::null(), h!("control-panel")) else {
+            return;
+        };
+
+        let message = HSTRING::from(message);
+        let strings = [PCWSTR(message.as_ptr())];
+        let _ = ReportEventW(source, kind, 0, EVENT_ID, None, 0, Some(&strings), None);
+
+        let _ = DeregisterEventSource(source);
+    }
+}
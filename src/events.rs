@@ -0,0 +1,77 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        Mutex,
+        mpsc::{self, Receiver, Sender},
+    },
+};
+
+use serde_json::{Value, json};
+use windows::Win32::Media::Audio::{EDataFlow, eRender};
+
+/// one for each open `/events` websocket or subscribed pipe, see `publish`
+static SUBSCRIBERS: Mutex<Vec<Sender<String>>> = Mutex::new(vec![]);
+
+/// whether anything is listening for events, so the state only has to be watched while something
+/// is
+pub fn has_subscribers() -> bool {
+    !SUBSCRIBERS.lock().unwrap().is_empty()
+}
+
+/// sends `event` to every subscriber, forgetting the ones that have gone
+pub fn publish(event: Value) {
+    let text = event.to_string();
+    let mut subscribers = SUBSCRIBERS.lock().unwrap();
+    subscribers.retain(|subscriber| subscriber.send(text.clone()).is_ok());
+}
+
+/// each event published from now on, as a line of json
+pub fn subscribe() -> Receiver<String> {
+    let (sender, events) = mpsc::channel();
+    SUBSCRIBERS.lock().unwrap().push(sender);
+    events
+}
+
+/// a default device as last seen, to notice what changes
+#[derive(Clone, Debug, PartialEq)]
+pub struct DeviceState {
+    pub id: String,
+    pub name: String,
+    /// from 0 to 100
+    pub volume: u32,
+    pub muted: bool,
+}
+
+/// the last seen state of each default device, so only changes are published
+#[derive(Default)]
+pub struct StateWatch {
+    devices: HashMap<i32, DeviceState>,
+}
+
+impl StateWatch {
+    /// records `flow`'s default device, returning an event for each thing that changed. the
+    /// first state seen isn't a change.
+    pub fn update(&mut self, flow: EDataFlow, state: DeviceState) -> Vec<Value> {
+        let device = if flow == eRender { "output" } else { "input" };
+
+        let Some(previous) = self.devices.insert(flow.0, state.clone()) else {
+            return vec![];
+        };
+
+        let mut events = vec![];
+
+        if previous.id != state.id {
+            events.push(json!({ "event": "default_device", "device": device, "name": state.name }));
+        }
+
+        if previous.muted != state.muted {
+            events.push(json!({ "event": "mute", "device": device, "muted": state.muted }));
+        }
+
+        if previous.volume != state.volume {
+            events.push(json!({ "event": "volume", "device": device, "volume": state.volume }));
+        }
+
+        events
+    }
+}
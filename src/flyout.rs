@@ -0,0 +1,156 @@
+use anyhow::Result;
+use windows::Win32::{
+    Foundation::{HWND, POINT, RECT},
+    Graphics::Gdi::{GetMonitorInfoW, MONITOR_DEFAULTTONEAREST, MONITORINFO, MonitorFromPoint},
+    System::LibraryLoader::GetModuleHandleA,
+    UI::{
+        HiDpi::{GetDpiForMonitor, MDT_EFFECTIVE_DPI},
+        WindowsAndMessaging::{
+            DestroyWindow, HWND_TOPMOST, SW_HIDE, SW_SHOWNOACTIVATE, SWP_NOACTIVATE, SetWindowPos,
+            ShowWindow, UnregisterClassA,
+        },
+    },
+};
+use windows_core::{PCSTR, s};
+
+use crate::{
+    default,
+    layout::Rect,
+    osd,
+    render::{Backend, Renderer},
+};
+
+const WINDOW_CLASS: PCSTR = s!("mfro flyout class");
+
+// in pixels at 96 dpi
+const WIDTH: f32 = 280.0;
+const PADDING: f32 = 10.0;
+const TEXT_SIZE: f32 = 12.0;
+const LINE_HEIGHT: f32 = 18.0;
+
+/// gap between the flyout and the tray icon
+const MARGIN: f32 = 8.0;
+
+/// popup listing the devices' status, shown in place of the tooltip while a tray icon is
+/// hovered
+pub struct Flyout {
+    hwnd: HWND,
+    renderer: Box<dyn Renderer>,
+}
+
+impl Flyout {
+    pub fn new(backend: Backend) -> Result<Self> {
+        // the renderer first, so failing to make it doesn't leave the window behind
+        let renderer = backend.create()?;
+        let hwnd = osd::create_popup(WINDOW_CLASS, s!("mfro flyout"), backend)?;
+
+        Ok(Self { hwnd, renderer })
+    }
+
+    /// shows `lines` next to the tray icon at `anchor`, in screen coordinates, until `hide` is
+    /// called. it goes above the icon, or below it when there's no room, e.g. with the taskbar
+    /// at the top.
+    pub fn show(&mut self, anchor: POINT, lines: &[String]) -> Result<()> {
+        let (work, dpi) = monitor(anchor)?;
+        let scale = dpi as f32 / 96.0;
+        let px = |value: f32| (value * scale).round() as i32;
+
+        let width = px(WIDTH);
+        let height = px(PADDING * 2.0 + LINE_HEIGHT * lines.len() as f32);
+        let margin = px(MARGIN);
+
+        let x = (anchor.x - width / 2)
+            .min(work.right - width)
+            .max(work.left);
+        let y = if anchor.y - margin - height >= work.top {
+            anchor.y - margin - height
+        } else {
+            anchor.y + margin
+        };
+
+        unsafe {
+            SetWindowPos(
+                self.hwnd,
+                Some(HWND_TOPMOST),
+                x,
+                y,
+                width,
+                height,
+                SWP_NOACTIVATE,
+            )?;
+        }
+
+        // drawn before it's shown so the previous frame doesn't flash up
+        self.renderer.render(self.hwnd, &mut |canvas| {
+            let (width, height) = (width as f32, height as f32);
+            let padding = PADDING * scale;
+
+            let background = Rect {
+                x: 0.0,
+                y: 0.0,
+                width,
+                height,
+            };
+            canvas.fill_rect(background, 0xe0202020)?;
+
+            // long device names are cut off rather than wrapped
+            let text = Rect {
+                x: padding,
+                y: 0.0,
+                width: width - padding * 2.0,
+                height,
+            };
+            canvas.push_clip(text)?;
+
+            for (i, line) in lines.iter().enumerate() {
+                let y = padding + LINE_HEIGHT * scale * i as f32;
+                canvas.draw_text(line, TEXT_SIZE * scale, padding, y, 0xffffffff)?;
+            }
+
+            canvas.pop_clip()
+        })?;
+
+        unsafe {
+            let _ = ShowWindow(self.hwnd, SW_SHOWNOACTIVATE);
+        }
+
+        Ok(())
+    }
+
+    pub fn hide(&self) {
+        unsafe {
+            let _ = ShowWindow(self.hwnd, SW_HIDE);
+        }
+    }
+}
+
+/// the window and its class, so another `Flyout` can be made in the same process
+impl Drop for Flyout {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = DestroyWindow(self.hwnd);
+
+            if let Ok(module) = GetModuleHandleA(None) {
+                let _ = UnregisterClassA(WINDOW_CLASS, Some(module.into()));
+            }
+        }
+    }
+}
+
+/// the work area and dpi of the monitor `point` is on
+fn monitor(point: POINT) -> Result<(RECT, u32)> {
+    unsafe {
+        let monitor = MonitorFromPoint(point, MONITOR_DEFAULTTONEAREST);
+
+        let mut info = MONITORINFO {
+            cbSize: size_of::<MONITORINFO>() as u32,
+            ..default()
+        };
+        GetMonitorInfoW(monitor, &mut info).ok()?;
+
+        let (mut dpi, mut dpi_y) = (0, 0);
+        GetDpiForMonitor(monitor, MDT_EFFECTIVE_DPI, &mut dpi, &mut dpi_y)?;
+
+        Ok((info.rcWork, dpi))
+    }
+}
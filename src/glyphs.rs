@@ -0,0 +1,124 @@
+use anyhow::Result;
+use windows::Win32::{
+    Graphics::Gdi::{CreateBitmap, DeleteObject},
+    UI::WindowsAndMessaging::{CreateIconIndirect, HICON, ICONINFO},
+};
+
+use crate::default;
+
+/// glyphs are drawn on a 32 pixel grid
+const SIZE: i32 = 32;
+
+/// samples per pixel along each axis, for antialiasing
+const SAMPLES: i32 = 4;
+
+const COLOR: u32 = 0xc0c0c0;
+
+/// built-in speaker icon, for output devices whose own icon can't be loaded
+pub fn speaker() -> Result<HICON> {
+    icon(is_speaker)
+}
+
+/// built-in microphone icon, for input devices whose own icon can't be loaded
+pub fn microphone() -> Result<HICON> {
+    icon(is_microphone)
+}
+
+/// the speaker struck through, for when there's no output device at all
+pub fn no_speaker() -> Result<HICON> {
+    icon(|x, y| struck_through(is_speaker, x, y))
+}
+
+/// the microphone struck through, for when there's no input device at all
+pub fn no_microphone() -> Result<HICON> {
+    icon(|x, y| struck_through(is_microphone, x, y))
+}
+
+fn is_speaker(x: f32, y: f32) -> bool {
+    let body = (5.0..11.0).contains(&x) && (12.0..20.0).contains(&y);
+
+    // widens from the body to the front of the speaker
+    let spread = 4.0 + (x - 11.0) * 6.0 / 7.0;
+    let cone = (11.0..18.0).contains(&x) && (y - 16.0).abs() <= spread;
+
+    let (dx, dy) = (x - 18.0, y - 16.0);
+    let radius = (dx * dx + dy * dy).sqrt();
+    let facing = dx > 0.0 && dy.abs() < dx * 1.2;
+    let waves = facing && ((radius - 6.0).abs() <= 1.0 || (radius - 11.0).abs() <= 1.0);
+
+    body || cone || waves
+}
+
+fn is_microphone(x: f32, y: f32) -> bool {
+    // a rounded capsule around the segment from (16, 8) to (16, 16)
+    let nearest = y.clamp(8.0, 16.0);
+    let capsule = (x - 16.0).powi(2) + (y - nearest).powi(2) <= 16.0;
+
+    let (dx, dy) = (x - 16.0, y - 16.0);
+    let radius = (dx * dx + dy * dy).sqrt();
+    let holder = dy >= 0.0 && (radius - 8.0).abs() <= 1.0;
+
+    let stem = (15.0..17.0).contains(&x) && (24.0..28.0).contains(&y);
+    let base = (11.0..21.0).contains(&x) && (27.0..29.0).contains(&y);
+
+    capsule || holder || stem || base
+}
+
+/// `glyph` with a line from the top left to the bottom right, cut out a little wider than the
+/// line so it stands apart from what's behind
+fn struck_through(glyph: impl Fn(f32, f32) -> bool, x: f32, y: f32) -> bool {
+    let distance = (x - y).abs() / 2f32.sqrt();
+    let along = (4.0..28.0).contains(&((x + y) / 2.0));
+
+    match along {
+        true if distance <= 1.5 => true,
+        true if distance <= 3.0 => false,
+        _ => glyph(x, y),
+    }
+}
+
+/// makes a `SIZE` pixel icon covering the points where `inside` is true
+fn icon(inside: impl Fn(f32, f32) -> bool) -> Result<HICON> {
+    let mut pixels = vec![0u32; (SIZE * SIZE) as usize];
+
+    for y in 0..SIZE {
+        for x in 0..SIZE {
+            let mut hits = 0;
+
+            for sy in 0..SAMPLES {
+                for sx in 0..SAMPLES {
+                    let fx = x as f32 + (sx as f32 + 0.5) / SAMPLES as f32;
+                    let fy = y as f32 + (sy as f32 + 0.5) / SAMPLES as f32;
+
+                    if inside(fx, fy) {
+                        hits += 1;
+                    }
+                }
+            }
+
+            let alpha = hits * 255 / (SAMPLES * SAMPLES);
+            pixels[(y * SIZE + x) as usize] = (alpha as u32) << 24 | COLOR;
+        }
+    }
+
+    unsafe {
+        let color = CreateBitmap(SIZE, SIZE, 1, 32, Some(pixels.as_ptr() as _));
+
+        // the mask is ignored for 32bpp color bitmaps, but has to be there
+        let mask = CreateBitmap(SIZE, SIZE, 1, 1, None);
+
+        let info = ICONINFO {
+            fIcon: true.into(),
+            hbmMask: mask,
+            hbmColor: color,
+            ..default()
+        };
+
+        let icon = CreateIconIndirect(&info);
+
+        let _ = DeleteObject(color.into());
+        let _ = DeleteObject(mask.into());
+
+        Ok(icon?)
+    }
+}
@@ -0,0 +1,208 @@
+use std::collections::HashMap;
+
+use anyhow::{Result, bail};
+use serde::Deserialize;
+use windows::Win32::{
+    Devices::HumanInterfaceDevice::{
+        HIDP_STATUS_SUCCESS, HidP_GetUsages, HidP_Input, PHIDP_PREPARSED_DATA,
+    },
+    Foundation::{HANDLE, HWND, LPARAM},
+    UI::Input::{
+        GetRawInputData, GetRawInputDeviceInfoW, HRAWINPUT, RAWINPUT, RAWINPUTDEVICE,
+        RAWINPUTHEADER, RID_INPUT, RIDEV_INPUTSINK, RIDEV_REMOVE, RIDI_PREPARSEDDATA, RIM_TYPEHID,
+        RegisterRawInputDevices,
+    },
+};
+
+use crate::actions::Action;
+
+/// the HID usage page for phones and headsets
+const USAGE_PAGE_TELEPHONY: u16 = 0x0b;
+const USAGE_HEADSET: u16 = 0x05;
+const USAGE_HOOK_SWITCH: u16 = 0x20;
+const USAGE_PHONE_MUTE: u16 = 0x2f;
+
+/// the buttons on a headset's cable or earcup, read as raw input so they work whichever app is in
+/// front. actions apply to the input device.
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct HeadsetButtonsConfig {
+    pub enabled: bool,
+    pub mute: Action,
+    /// the answer or hang up button
+    pub hook_switch: Action,
+}
+
+impl Default for HeadsetButtonsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            mute: Action::ToggleMute,
+            hook_switch: Action::None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Button {
+    Mute,
+    HookSwitch,
+}
+
+impl Button {
+    fn from_usage(usage: u16) -> Option<Self> {
+        match usage {
+            USAGE_PHONE_MUTE => Some(Button::Mute),
+            USAGE_HOOK_SWITCH => Some(Button::HookSwitch),
+            _ => None,
+        }
+    }
+
+    pub fn action(self, config: &HeadsetButtonsConfig) -> Action {
+        match self {
+            Button::Mute => config.mute.clone(),
+            Button::HookSwitch => config.hook_switch.clone(),
+        }
+    }
+}
+
+/// starts or stops `hwnd` getting `WM_INPUT` for headsets, even while it isn't in front
+pub fn register(hwnd: HWND, enabled: bool) -> Result<()> {
+    let device = RAWINPUTDEVICE {
+        usUsagePage: USAGE_PAGE_TELEPHONY,
+        usUsage: USAGE_HEADSET,
+        dwFlags: if enabled {
+            RIDEV_INPUTSINK
+        } else {
+            RIDEV_REMOVE
+        },
+        hwndTarget: if enabled { hwnd } else { HWND::default() },
+    };
+
+    let result = unsafe { RegisterRawInputDevices(&[device], size_of::<RAWINPUTDEVICE>() as u32) };
+
+    match enabled {
+        true => Ok(result?),
+        // fails harmlessly if it was never registered
+        false => Ok(()),
+    }
+}
+
+/// the telephony usages each headset last reported as on, so only presses are acted on rather
+/// than every report while a button is held or a switch stays on
+#[derive(Default)]
+pub struct HeadsetButtons {
+    pressed: HashMap<isize, Vec<u16>>,
+}
+
+impl HeadsetButtons {
+    /// reads the `WM_INPUT` with `lparam`, returning the buttons that were just pressed
+    pub fn on_input(&mut self, lparam: LPARAM) -> Result<Vec<Button>> {
+        let data = raw_input(lparam)?;
+        let input = unsafe { &*(data.as_ptr() as *const RAWINPUT) };
+
+        if input.header.dwType != RIM_TYPEHID.0 {
+            return Ok(vec![]);
+        }
+
+        let preparsed = preparsed_data(input.header.hDevice)?;
+
+        // `dwCount` reports of `dwSizeHid` bytes each, running on past the end of the struct
+        let hid = unsafe { &input.data.hid };
+        let size = hid.dwSizeHid as usize;
+        let reports = unsafe {
+            std::slice::from_raw_parts(hid.bRawData.as_ptr(), size * hid.dwCount as usize)
+        };
+
+        let mut buttons = vec![];
+
+        for report in reports.chunks(size) {
+            // reports without any telephony usages don't say anything about the buttons
+            let Some(usages) = usages(&preparsed, &mut report.to_vec()) else {
+                continue;
+            };
+
+            let previous = self
+                .pressed
+                .insert(input.header.hDevice.0 as isize, usages.clone())
+                .unwrap_or_default();
+
+            let pressed = usages.iter().filter(|usage| !previous.contains(usage));
+            buttons.extend(pressed.filter_map(|&usage| Button::from_usage(usage)));
+        }
+
+        Ok(buttons)
+    }
+}
+
+/// the input, in a buffer aligned for reading it as a `RAWINPUT`
+fn raw_input(lparam: LPARAM) -> Result<Vec<u64>> {
+    let handle = HRAWINPUT(lparam.0 as _);
+    let header = size_of::<RAWINPUTHEADER>() as u32;
+
+    unsafe {
+        let mut size = 0;
+        GetRawInputData(handle, RID_INPUT, None, &mut size, header);
+
+        let mut data = vec![0u64; (size as usize).div_ceil(8)];
+        let read = GetRawInputData(
+            handle,
+            RID_INPUT,
+            Some(data.as_mut_ptr() as _),
+            &mut size,
+            header,
+        );
+
+        if read != size {
+            bail!("failed to read raw input");
+        }
+
+        Ok(data)
+    }
+}
+
+/// the device's report descriptor, parsed, for making sense of its reports
+fn preparsed_data(device: HANDLE) -> Result<Vec<u64>> {
+    unsafe {
+        let mut size = 0;
+        GetRawInputDeviceInfoW(Some(device), RIDI_PREPARSEDDATA, None, &mut size);
+
+        let mut data = vec![0u64; (size as usize).div_ceil(8)];
+        let read = GetRawInputDeviceInfoW(
+            Some(device),
+            RIDI_PREPARSEDDATA,
+            Some(data.as_mut_ptr() as _),
+            &mut size,
+        );
+
+        if read != size {
+            bail!("failed to read preparsed data");
+        }
+
+        Ok(data)
+    }
+}
+
+/// the telephony usages that are on in `report`, or `None` if it doesn't have any
+fn usages(preparsed: &[u64], report: &mut [u8]) -> Option<Vec<u16>> {
+    let mut usages = [0u16; 32];
+    let mut count = usages.len() as u32;
+
+    let status = unsafe {
+        HidP_GetUsages(
+            HidP_Input,
+            USAGE_PAGE_TELEPHONY,
+            None,
+            usages.as_mut_ptr(),
+            &mut count,
+            PHIDP_PREPARSED_DATA(preparsed.as_ptr() as isize),
+            report,
+        )
+    };
+
+    if status != HIDP_STATUS_SUCCESS {
+        return None;
+    }
+
+    Some(usages[..count as usize].to_vec())
+}
@@ -0,0 +1,103 @@
+use std::{
+    io::Write,
+    path::PathBuf,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::config::Config;
+
+/// a log of when the session was locked, e.g. for keeping track of time away from the desk
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct HistoryConfig {
+    /// append each lock and unlock to `history.jsonl` next to the config file
+    pub record: bool,
+    /// a toast saying how long the session was locked for, on unlock
+    pub show_away: bool,
+    /// locks shorter than this aren't worth a toast
+    pub show_after_minutes: u32,
+}
+
+impl Default for HistoryConfig {
+    fn default() -> Self {
+        Self {
+            record: false,
+            show_away: false,
+            show_after_minutes: 5,
+        }
+    }
+}
+
+/// one lock and the unlock after it, in seconds since the unix epoch
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+pub struct Away {
+    pub locked: u64,
+    pub unlocked: u64,
+}
+
+impl Away {
+    pub fn new(locked: SystemTime, unlocked: SystemTime) -> Self {
+        let seconds = |time: SystemTime| {
+            time.duration_since(UNIX_EPOCH)
+                .map(|since| since.as_secs())
+                .unwrap_or_default()
+        };
+
+        Self {
+            locked: seconds(locked),
+            unlocked: seconds(unlocked),
+        }
+    }
+
+    pub fn duration(&self) -> Duration {
+        Duration::from_secs(self.unlocked.saturating_sub(self.locked))
+    }
+}
+
+pub fn path() -> PathBuf {
+    Config::path().with_file_name("history.jsonl")
+}
+
+/// appends `away` to the log, one json object per line
+pub fn record(away: Away) -> Result<()> {
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path())
+        .context("failed to open history")?;
+
+    let line = serde_json::to_string(&away)? + "\n";
+    file.write_all(line.as_bytes())
+        .context("failed to write history")?;
+
+    Ok(())
+}
+
+/// everything recorded so far, oldest first
+pub fn load() -> Result<Vec<Away>> {
+    let path = path();
+
+    if !path.exists() {
+        return Ok(vec![]);
+    }
+
+    let text = std::fs::read_to_string(&path).context("failed to read history")?;
+
+    text.lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| serde_json::from_str(line).context("failed to parse history"))
+        .collect()
+}
+
+/// e.g. "35 min" or "2 h 5 min"
+pub fn describe(duration: Duration) -> String {
+    let minutes = duration.as_secs() / 60;
+
+    match minutes / 60 {
+        0 => format!("{} min", minutes),
+        hours => format!("{} h {} min", hours, minutes % 60),
+    }
+}
@@ -1,7 +1,7 @@
 #![allow(non_snake_case, non_camel_case_types)]
 
 use windows::Win32::{
-    Foundation::{HINSTANCE, HWND},
+    Foundation::{HINSTANCE, HWND, NTSTATUS},
     UI::WindowsAndMessaging::{HMENU, WINDOW_EX_STYLE, WINDOW_STYLE},
 };
 use windows_core::{GUID, HRESULT, IUnknown, IUnknown_Vtbl, interface};
@@ -23,22 +23,23 @@ windows_link::link!(
         lpparam: *const core::ffi::c_void) -> HWND
 );
 
-#[allow(non_upper_case_globals)]
+windows_link::link!(
+    "ntdll.dll" "system"
+    fn NtQueryWnfStateData(
+        statename: *const u64,
+        typeid: *const GUID,
+        explicitscope: *const core::ffi::c_void,
+        changestamp: *mut u32,
+        buffer: *mut core::ffi::c_void,
+        buffersize: *mut u32) -> NTSTATUS
+);
 
-pub const CLSID_PolicyConfigClient: GUID = GUID::from_u128(0x870af99c_171d_4f9e_af0d_e63df40c2bc9);
+#[allow(non_upper_case_globals)]
+pub const CLSID_QuietHoursSettings: GUID = GUID::from_u128(0xf53321fa_34f8_4b7f_b9a3_361877cb94cf);
 
-#[interface("F8679F50-850A-41CF-9C72-430F290290C8")]
-pub unsafe trait IPolicyConfig: IUnknown {
-    pub fn GetMixFormat(&self) -> HRESULT;
-    pub fn GetDeviceFormat(&self) -> HRESULT;
-    pub fn ResetDeviceFormat(&self) -> HRESULT;
-    pub fn SetDeviceFormat(&self) -> HRESULT;
-    pub fn GetProcessingPeriod(&self) -> HRESULT;
-    pub fn SetProcessingPeriod(&self) -> HRESULT;
-    pub fn GetShareMode(&self) -> HRESULT;
-    pub fn SetShareMode(&self) -> HRESULT;
-    pub fn GetPropertyValue(&self) -> HRESULT;
-    pub fn SetPropertyValue(&self) -> HRESULT;
-    pub fn SetDefaultEndpoint(&self, deviceID: *const u16, role: u32) -> HRESULT;
-    pub fn SetEndpointVisibility(&self) -> HRESULT;
+/// the shell's focus assist settings. the methods after these aren't declared.
+#[interface("6BFF4732-81EC-4FFB-AE67-B6C1BC29631F")]
+pub unsafe trait IQuietHoursSettings: IUnknown {
+    pub fn get_UserSelectedProfile(&self, profileId: *mut windows_core::PWSTR) -> HRESULT;
+    pub fn put_UserSelectedProfile(&self, profileId: windows_core::PCWSTR) -> HRESULT;
 }
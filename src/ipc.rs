@@ -0,0 +1,399 @@
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, Write},
+    os::windows::io::FromRawHandle,
+    process::ExitCode,
+    str::FromStr,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread::JoinHandle,
+    time::Duration,
+};
+
+use anyhow::{Context, Result, bail};
+use serde::Deserialize;
+use serde_json::{Value, json};
+use windows::Win32::{
+    Foundation::{CloseHandle, ERROR_PIPE_CONNECTED, HWND, LPARAM, WPARAM},
+    Media::Audio::{EDataFlow, eCapture, eRender},
+    Storage::FileSystem::PIPE_ACCESS_DUPLEX,
+    System::{
+        Console::{ATTACH_PARENT_PROCESS, AttachConsole},
+        Pipes::{
+            ConnectNamedPipe, CreateNamedPipeW, PIPE_READMODE_BYTE, PIPE_REJECT_REMOTE_CLIENTS,
+            PIPE_TYPE_BYTE, PIPE_UNLIMITED_INSTANCES, PIPE_WAIT,
+        },
+    },
+    UI::WindowsAndMessaging::SendMessageA,
+};
+use windows_core::HSTRING;
+
+use crate::{WM_IPC_COMMAND, actions::Action, events, layout::Widget};
+
+pub const PIPE_NAME: &str = r"\\.\pipe\control-panel";
+
+/// what `dwData` has to be set to for a `WM_COPYDATA` to be taken as a command, so other programs'
+/// messages to the window aren't
+pub const COPYDATA_COMMAND: usize = 0x6d66_726f;
+
+/// which default device a command applies to
+#[derive(Clone, Copy, Debug, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Target {
+    #[default]
+    Output,
+    Input,
+}
+
+impl FromStr for Target {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "output" => Ok(Target::Output),
+            "input" => Ok(Target::Input),
+            _ => bail!("unknown target {:?}", s),
+        }
+    }
+}
+
+impl Target {
+    pub fn flow(self) -> EDataFlow {
+        match self {
+            Target::Output => eRender,
+            Target::Input => eCapture,
+        }
+    }
+
+    pub fn widget(self) -> Widget {
+        match self {
+            Target::Output => Widget::Output,
+            Target::Input => Widget::Input,
+        }
+    }
+}
+
+/// one line sent to the pipe, e.g. `{"cmd": "toggle_mute", "target": "input"}`. these names,
+/// and the action names from the config, don't change, so things like stream deck buttons can
+/// be bound to them. buttons showing state can ask for `status` once and then `subscribe`.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "cmd", rename_all = "snake_case")]
+pub enum Command {
+    /// the default devices' names, volumes and mute states, whether the session is locked,
+    /// focus assist's mode and whether media is playing. with `full`, every active device and
+    /// the defaults for each role too, bluetooth, and the paint and error counters, e.g. for
+    /// attaching to bug reports.
+    Status {
+        #[serde(default)]
+        full: bool,
+    },
+    /// answers once, and then sends each change to the default devices or the lock state as a
+    /// line of json until the pipe is closed, e.g. `{"event": "mute", "device": "input",
+    /// "muted": true}`. only on the pipe.
+    Subscribe,
+    /// queued with the panel's own actions, like `action`, so it's answered once it's queued and
+    /// anything going wrong after that is only logged
+    ToggleMute {
+        #[serde(default)]
+        target: Target,
+    },
+    SetMute {
+        #[serde(default)]
+        target: Target,
+        muted: bool,
+    },
+    /// from 0 to 100
+    SetVolume {
+        #[serde(default)]
+        target: Target,
+        volume: f32,
+    },
+    /// makes the first active device whose name contains `name` the default
+    SetDefault {
+        #[serde(default)]
+        target: Target,
+        name: String,
+    },
+    /// anything a gesture can be bound to, e.g. `{"cmd": "action", "action": "play_pause"}`.
+    /// answered once it's queued to run, see `ActionQueue`.
+    Action {
+        #[serde(default)]
+        target: Target,
+        #[serde(flatten)]
+        action: Action,
+    },
+}
+
+/// either json, as sent to the pipe, or words separated by spaces for tools where that's easier,
+/// e.g. `toggle_mute input`, `mute output`, `set_volume output 40`, `set_default input Yeti` or
+/// the name of an action like `play_pause`. the target can be left out where it comes last.
+impl FromStr for Command {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim();
+        if s.starts_with('{') {
+            return serde_json::from_str(s).context("invalid command");
+        }
+
+        let mut words = s.split_whitespace();
+        let name = words.next().context("empty command")?;
+        let mut target = || words.next().map(Target::from_str).transpose();
+
+        let command = match name {
+            "status" => Command::Status {
+                full: words.any(|word| matches!(word, "full" | "--json" | "--full")),
+            },
+            "subscribe" => Command::Subscribe,
+            "toggle_mute" => Command::ToggleMute {
+                target: target()?.unwrap_or_default(),
+            },
+            "mute" | "unmute" => Command::SetMute {
+                target: target()?.unwrap_or_default(),
+                muted: name == "mute",
+            },
+            "set_volume" | "set_default" => {
+                let target = target()?.context("missing target")?;
+                let rest: Vec<_> = words.collect();
+                let rest = rest.join(" ");
+
+                match name {
+                    "set_volume" => Command::SetVolume {
+                        target,
+                        volume: rest.parse().context("invalid volume")?,
+                    },
+                    _ => Command::SetDefault { target, name: rest },
+                }
+            }
+            action => Command::Action {
+                target: target()?.unwrap_or_default(),
+                action: serde_json::from_value(json!({ "action": action }))
+                    .with_context(|| format!("unknown command {:?}", action))?,
+            },
+        };
+
+        Ok(command)
+    }
+}
+
+/// sent to the window with `WM_IPC_COMMAND`, which fills in `response`
+pub struct Call {
+    pub command: Command,
+    pub response: Option<Result<Value>>,
+}
+
+/// runs `command` on the window's thread, waiting for the result
+pub fn send(hwnd: HWND, command: Command) -> Result<Value> {
+    let mut call = Call {
+        command,
+        response: None,
+    };
+
+    unsafe {
+        SendMessageA(
+            hwnd,
+            WM_IPC_COMMAND,
+            WPARAM(0),
+            LPARAM(&mut call as *mut Call as isize),
+        )
+    };
+
+    call.response.context("the panel didn't answer")?
+}
+
+/// `{"ok": true, "result": ...}` or `{"ok": false, "error": "..."}`
+pub fn reply(response: Result<Value>) -> Value {
+    match response {
+        Ok(result) => json!({ "ok": true, "result": result }),
+        Err(e) => json!({ "ok": false, "error": format!("{e:#}") }),
+    }
+}
+
+/// the window, for the server threads to send commands to
+#[derive(Clone, Copy)]
+pub struct X(pub HWND);
+unsafe impl Send for X {}
+unsafe impl Sync for X {}
+
+/// answers each line from one client until it disconnects
+fn handle(hwnd: X, pipe: File) -> Result<()> {
+    let mut writer = pipe.try_clone()?;
+
+    for line in BufReader::new(pipe).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let response = match line.parse() {
+            Ok(Command::Subscribe) => return stream_events(writer),
+            Ok(command) => send(hwnd.0, command),
+            Err(e) => Err(e),
+        };
+
+        writeln!(writer, "{}", reply(response))?;
+    }
+
+    Ok(())
+}
+
+/// answers `subscribe`, and passes on events until the client goes away
+fn stream_events(mut writer: File) -> Result<()> {
+    let events = events::subscribe();
+    writeln!(writer, "{}", reply(Ok(Value::Null)))?;
+
+    for event in events {
+        writeln!(writer, "{}", event)?;
+    }
+
+    Ok(())
+}
+
+fn run(hwnd: X, stop: &AtomicBool) -> Result<()> {
+    while !stop.load(Ordering::Relaxed) {
+        let pipe = unsafe {
+            CreateNamedPipeW(
+                &HSTRING::from(PIPE_NAME),
+                PIPE_ACCESS_DUPLEX,
+                PIPE_TYPE_BYTE | PIPE_READMODE_BYTE | PIPE_WAIT | PIPE_REJECT_REMOTE_CLIENTS,
+                PIPE_UNLIMITED_INSTANCES,
+                4096,
+                4096,
+                0,
+                None,
+            )
+        };
+
+        if pipe.is_invalid() {
+            return Err(windows_core::Error::from_win32()).context("failed to create pipe");
+        }
+
+        // a client that connected before this started waiting is already connected
+        if let Err(e) = unsafe { ConnectNamedPipe(pipe, None) }
+            && e.code() != ERROR_PIPE_CONNECTED.to_hresult()
+        {
+            unsafe { CloseHandle(pipe)? };
+            continue;
+        }
+
+        if stop.load(Ordering::Relaxed) {
+            unsafe { CloseHandle(pipe)? };
+            break;
+        }
+
+        // closing the file disconnects the client
+        let pipe = unsafe { File::from_raw_handle(pipe.0) };
+
+        std::thread::spawn(move || {
+            let _ = handle(hwnd, pipe);
+        });
+    }
+
+    Ok(())
+}
+
+/// the arguments as a command, with the target allowed on the end of the name, so
+/// `set-default-output AirPods` is `set_default output AirPods`
+fn command_line(args: &[String]) -> String {
+    let name = args[0].replace('-', "_");
+
+    let name = ["output", "input"]
+        .iter()
+        .find_map(|target| {
+            let name = name.strip_suffix(&format!("_{}", target))?;
+            Some(format!("{} {}", name, target))
+        })
+        .unwrap_or(name);
+
+    std::iter::once(name)
+        .chain(args[1..].iter().cloned())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// sends the command line to the running panel and prints what it answers
+fn request(args: &[String]) -> Result<Value> {
+    let mut pipe = std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(PIPE_NAME)
+        .context("the panel isn't running")?;
+
+    // checked here so mistakes don't need the panel to point them out
+    let line = command_line(args);
+    line.parse::<Command>()?;
+    writeln!(pipe, "{}", line)?;
+
+    let mut reply = String::new();
+    BufReader::new(pipe).read_line(&mut reply)?;
+    let reply: Value = serde_json::from_str(&reply).context("invalid reply")?;
+
+    match reply["ok"].as_bool() {
+        Some(true) => Ok(reply["result"].clone()),
+        _ => bail!("{}", reply["error"].as_str().unwrap_or("failed")),
+    }
+}
+
+/// runs e.g. `control-panel status` against the instance that's already running
+pub fn run_cli(args: &[String]) -> ExitCode {
+    // release builds have no console of their own, so borrow the one they were started from
+    unsafe {
+        let _ = AttachConsole(ATTACH_PARENT_PROCESS);
+    }
+
+    match request(args) {
+        Ok(Value::Null) => ExitCode::SUCCESS,
+        Ok(result) => {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&result).unwrap_or_default()
+            );
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("{e:#}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// the thread listening on the pipe, which stops when this is dropped. connections that are
+/// already open carry on until their clients close them.
+pub struct PipeServer {
+    stop: Arc<AtomicBool>,
+    thread: JoinHandle<()>,
+}
+
+impl Drop for PipeServer {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+
+        // the thread's waiting for a client, so be one. the pipe can be busy for a moment
+        // between clients, so keep trying for a bit.
+        for _ in 0..100 {
+            if self.thread.is_finished() {
+                return;
+            }
+
+            let _ = File::open(PIPE_NAME);
+            std::thread::sleep(Duration::from_millis(10));
+        }
+    }
+}
+
+/// listens on `PIPE_NAME` for line-delimited json commands, answering each with a line of json
+pub fn spawn(hwnd: HWND) -> PipeServer {
+    let hwnd = X(hwnd);
+    let stop = Arc::new(AtomicBool::new(false));
+
+    let thread = std::thread::spawn({
+        let stop = stop.clone();
+        move || {
+            let _ = run(hwnd, &stop);
+        }
+    });
+
+    PipeServer { stop, thread }
+}
@@ -0,0 +1,226 @@
+use std::{fmt, str::FromStr};
+
+use anyhow::{Context, Result, bail};
+use serde::Deserialize;
+use windows::Win32::{
+    Foundation::{ERROR_HOTKEY_ALREADY_REGISTERED, HWND},
+    UI::Input::KeyboardAndMouse::{
+        GetAsyncKeyState, HOT_KEY_MODIFIERS, INPUT, INPUT_0, INPUT_KEYBOARD, KEYBD_EVENT_FLAGS,
+        KEYBDINPUT, KEYEVENTF_KEYUP, MOD_ALT, MOD_CONTROL, MOD_NOREPEAT, MOD_SHIFT, MOD_WIN,
+        RegisterHotKey, SendInput, UnregisterHotKey, VIRTUAL_KEY, VK_CONTROL, VK_DOWN, VK_F1,
+        VK_LEFT, VK_LWIN, VK_MENU, VK_RIGHT, VK_SHIFT, VK_SPACE, VK_UP, VK_VOLUME_DOWN,
+        VK_VOLUME_MUTE, VK_VOLUME_UP,
+    },
+};
+
+use crate::{actions::Action, layout::Widget};
+
+/// a global hotkey from the config, written like "ctrl+alt+k" or "win+shift+f9"
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Hotkey {
+    modifiers: HOT_KEY_MODIFIERS,
+    key: u32,
+}
+
+impl FromStr for Hotkey {
+    type Err = anyhow::Error;
+
+    fn from_str(text: &str) -> Result<Self> {
+        let mut modifiers = HOT_KEY_MODIFIERS(0);
+        let mut key = None;
+
+        for part in text.split('+').map(|part| part.trim().to_lowercase()) {
+            match part.as_str() {
+                "ctrl" | "control" => modifiers |= MOD_CONTROL,
+                "alt" => modifiers |= MOD_ALT,
+                "shift" => modifiers |= MOD_SHIFT,
+                "win" => modifiers |= MOD_WIN,
+                _ if key.is_some() => bail!("hotkey has more than one key: {}", text),
+                _ => key = Some(virtual_key(&part).context(format!("unknown key: {}", part))?),
+            }
+        }
+
+        let key = key.context(format!("hotkey has no key: {}", text))?;
+        Ok(Self { modifiers, key })
+    }
+}
+
+/// written the way the config does, e.g. "ctrl+shift+m"
+impl fmt::Display for Hotkey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (modifier, name) in [
+            (MOD_CONTROL, "ctrl"),
+            (MOD_ALT, "alt"),
+            (MOD_SHIFT, "shift"),
+            (MOD_WIN, "win"),
+        ] {
+            if self.modifiers.contains(modifier) {
+                write!(f, "{}+", name)?;
+            }
+        }
+
+        match key_name(self.key) {
+            Some(name) => write!(f, "{}", name),
+            None => write!(f, "0x{:02x}", self.key),
+        }
+    }
+}
+
+impl TryFrom<String> for Hotkey {
+    type Error = anyhow::Error;
+
+    fn try_from(text: String) -> Result<Self> {
+        text.parse()
+    }
+}
+
+/// maps a letter, digit, function key or named key to its virtual key code
+fn virtual_key(name: &str) -> Option<u32> {
+    let named = match name {
+        "up" => Some(VK_UP),
+        "down" => Some(VK_DOWN),
+        "left" => Some(VK_LEFT),
+        "right" => Some(VK_RIGHT),
+        "space" => Some(VK_SPACE),
+        "volume_up" => Some(VK_VOLUME_UP),
+        "volume_down" => Some(VK_VOLUME_DOWN),
+        "volume_mute" => Some(VK_VOLUME_MUTE),
+        _ => None,
+    };
+
+    if let Some(key) = named {
+        return Some(key.0 as u32);
+    }
+
+    let mut chars = name.chars();
+
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        // letters and digits share their virtual key codes with their uppercase ascii
+        if c.is_ascii_alphanumeric() {
+            return Some(c.to_ascii_uppercase() as u32);
+        }
+    }
+
+    match name.strip_prefix('f')?.parse::<u32>() {
+        Ok(n @ 1..=24) => Some(VK_F1.0 as u32 + n - 1),
+        _ => None,
+    }
+}
+
+/// the reverse of `virtual_key`
+fn key_name(key: u32) -> Option<String> {
+    let named = [
+        (VK_UP, "up"),
+        (VK_DOWN, "down"),
+        (VK_LEFT, "left"),
+        (VK_RIGHT, "right"),
+        (VK_SPACE, "space"),
+        (VK_VOLUME_UP, "volume_up"),
+        (VK_VOLUME_DOWN, "volume_down"),
+        (VK_VOLUME_MUTE, "volume_mute"),
+    ];
+
+    if let Some((_, name)) = named.iter().find(|(vk, _)| vk.0 as u32 == key) {
+        return Some(name.to_string());
+    }
+
+    match key {
+        0x30..=0x39 | 0x41..=0x5a => Some((key as u8 as char).to_ascii_lowercase().to_string()),
+        _ if (VK_F1.0 as u32..VK_F1.0 as u32 + 24).contains(&key) => {
+            Some(format!("f{}", key - VK_F1.0 as u32 + 1))
+        }
+        _ => None,
+    }
+}
+
+impl Hotkey {
+    pub fn register(self, hwnd: HWND, id: i32) -> Result<()> {
+        let result =
+            unsafe { RegisterHotKey(Some(hwnd), id, self.modifiers | MOD_NOREPEAT, self.key) };
+
+        match result {
+            Err(e) if e.code() == ERROR_HOTKEY_ALREADY_REGISTERED.to_hresult() => {
+                bail!("{} is already taken by another app", self)
+            }
+            result => Ok(result?),
+        }
+    }
+
+    pub fn unregister(hwnd: HWND, id: i32) {
+        // fails harmlessly if the hotkey was never registered
+        let _ = unsafe { UnregisterHotKey(Some(hwnd), id) };
+    }
+
+    /// types the hotkey for whichever app gets it, e.g. another app's own shortcut. modifiers
+    /// still held down from the hotkey that led to this are let go of first, so they don't end
+    /// up in it.
+    pub fn press(self) -> Result<()> {
+        let modifiers = [
+            (MOD_CONTROL, VK_CONTROL),
+            (MOD_ALT, VK_MENU),
+            (MOD_SHIFT, VK_SHIFT),
+            (MOD_WIN, VK_LWIN),
+        ];
+
+        let input = |key: VIRTUAL_KEY, up: bool| INPUT {
+            r#type: INPUT_KEYBOARD,
+            Anonymous: INPUT_0 {
+                ki: KEYBDINPUT {
+                    wVk: key,
+                    dwFlags: if up {
+                        KEYEVENTF_KEYUP
+                    } else {
+                        KEYBD_EVENT_FLAGS(0)
+                    },
+                    ..Default::default()
+                },
+            },
+        };
+
+        let mut inputs = vec![];
+
+        for (modifier, key) in modifiers {
+            let held = unsafe { GetAsyncKeyState(key.0 as i32) } < 0;
+            if held && !self.modifiers.contains(modifier) {
+                inputs.push(input(key, true));
+            }
+        }
+
+        let pressed: Vec<_> = modifiers
+            .iter()
+            .filter(|(modifier, _)| self.modifiers.contains(*modifier))
+            .map(|&(_, key)| key)
+            .collect();
+
+        inputs.extend(pressed.iter().map(|&key| input(key, false)));
+        inputs.push(input(VIRTUAL_KEY(self.key as u16), false));
+        inputs.push(input(VIRTUAL_KEY(self.key as u16), true));
+        inputs.extend(pressed.iter().rev().map(|&key| input(key, true)));
+
+        let sent = unsafe { SendInput(&inputs, size_of::<INPUT>() as i32) };
+        if sent as usize != inputs.len() {
+            bail!("failed to press {}", self);
+        }
+
+        Ok(())
+    }
+}
+
+/// a global hotkey that runs an action, e.g.
+/// `{ "keys": "ctrl+alt+down", "action": "step_volume", "down": true }`
+#[derive(Clone, Debug, Deserialize)]
+pub struct HotkeyBinding {
+    pub keys: Hotkey,
+
+    /// the widget whose device the action applies to, the output device if not set
+    #[serde(default)]
+    pub widget: Option<Widget>,
+
+    /// makes `step_volume` step down instead of up
+    #[serde(default)]
+    pub down: bool,
+
+    #[serde(flatten)]
+    pub action: Action,
+}
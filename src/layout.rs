@@ -0,0 +1,387 @@
+use serde::{Deserialize, Serialize};
+use windows::Win32::Media::Audio::{EDataFlow, eCapture, eRender};
+
+use crate::widgets::media::MediaCommand;
+
+/// space before a widget's content, in device-independent pixels
+pub const PADDING: f32 = 8.0;
+
+/// size of the device icons, in device-independent pixels
+pub const ICON_SIZE: f32 = 32.0;
+
+/// spacing of the grid shown in layout edit mode, which widget offsets snap to, in
+/// device-independent pixels
+pub const GRID_SPACING: f32 = 8.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Widget {
+    Volume,
+    Output,
+    Input,
+    Clock,
+    Date,
+    Cpu,
+    Memory,
+    Network,
+    Wifi,
+    Vpn,
+    Disk,
+    LockKeys,
+    NowPlaying,
+    MediaPrevious,
+    MediaPlayPause,
+    MediaNext,
+    Camera,
+    ScreenCapture,
+    FocusAssist,
+    NightLight,
+    Brightness,
+    InputSource,
+    RefreshRate,
+    Bluetooth,
+    Controllers,
+    Peripherals,
+    Meeting,
+    Pomodoro,
+    Uptime,
+    Command,
+    Weather,
+    /// whether obs is recording or streaming, see `obs`
+    Obs,
+    /// the button at this index of the config's `buttons`
+    Button(u8),
+}
+
+impl Widget {
+    /// every widget but the buttons, in declaration order
+    pub const ALL: [Widget; 32] = [
+        Widget::Volume,
+        Widget::Output,
+        Widget::Input,
+        Widget::Clock,
+        Widget::Date,
+        Widget::Cpu,
+        Widget::Memory,
+        Widget::Network,
+        Widget::Wifi,
+        Widget::Vpn,
+        Widget::Disk,
+        Widget::LockKeys,
+        Widget::NowPlaying,
+        Widget::MediaPrevious,
+        Widget::MediaPlayPause,
+        Widget::MediaNext,
+        Widget::Camera,
+        Widget::ScreenCapture,
+        Widget::FocusAssist,
+        Widget::NightLight,
+        Widget::Brightness,
+        Widget::InputSource,
+        Widget::RefreshRate,
+        Widget::Bluetooth,
+        Widget::Controllers,
+        Widget::Peripherals,
+        Widget::Meeting,
+        Widget::Pomodoro,
+        Widget::Uptime,
+        Widget::Command,
+        Widget::Weather,
+        Widget::Obs,
+    ];
+
+    /// identifies the widget to tooltips and in posted messages: its index in `ALL`, or for
+    /// buttons the index after the end of it
+    pub fn index(self) -> usize {
+        match self {
+            Widget::Button(index) => Self::ALL.len() + index as usize,
+            widget => Self::ALL
+                .iter()
+                .position(|w| *w == widget)
+                .unwrap_or_default(),
+        }
+    }
+
+    pub fn from_index(index: usize) -> Option<Widget> {
+        match index.checked_sub(Self::ALL.len()) {
+            Some(button) => u8::try_from(button).ok().map(Widget::Button),
+            None => Self::ALL.get(index).copied(),
+        }
+    }
+
+    /// the widgets shown when the config doesn't list any
+    pub const DEFAULT: [Widget; 3] = [Widget::Volume, Widget::Output, Widget::Input];
+
+    /// the audio flow the widget controls, if it's an audio widget
+    pub fn flow(self) -> Option<EDataFlow> {
+        match self {
+            Widget::Volume | Widget::Output => Some(eRender),
+            Widget::Input => Some(eCapture),
+            _ => None,
+        }
+    }
+
+    /// the transport button the widget is, if it's one
+    pub fn media_command(self) -> Option<MediaCommand> {
+        match self {
+            Widget::MediaPrevious => Some(MediaCommand::Previous),
+            Widget::MediaPlayPause => Some(MediaCommand::PlayPause),
+            Widget::MediaNext => Some(MediaCommand::Next),
+            _ => None,
+        }
+    }
+
+    /// whether the widget shows a device that can be muted
+    pub fn is_device(self) -> bool {
+        matches!(self, Widget::Output | Widget::Input)
+    }
+
+    /// in device-independent pixels
+    fn width(self) -> f32 {
+        match self {
+            Widget::Volume => 105.0,
+            Widget::Output | Widget::Input => PADDING + ICON_SIZE,
+            Widget::Clock | Widget::Meeting => 90.0,
+            Widget::Date => 100.0,
+            Widget::Cpu => 80.0,
+            Widget::Memory => 100.0,
+            Widget::Network => 100.0,
+            Widget::Wifi => 130.0,
+            Widget::Vpn | Widget::InputSource | Widget::RefreshRate | Widget::Controllers => 70.0,
+            Widget::Obs => 70.0,
+            Widget::Bluetooth => 50.0,
+            Widget::Pomodoro | Widget::Uptime => 70.0,
+            Widget::Peripherals => 80.0,
+            Widget::Disk => 90.0,
+            Widget::LockKeys => 80.0,
+            Widget::NowPlaying => 180.0,
+            Widget::Command => 160.0,
+            Widget::Weather => 90.0,
+            Widget::MediaPrevious | Widget::MediaPlayPause | Widget::MediaNext => 28.0,
+            Widget::Camera | Widget::ScreenCapture | Widget::NightLight | Widget::Button(_) => 40.0,
+            Widget::FocusAssist | Widget::Brightness => 100.0,
+        }
+    }
+}
+
+/// extra space before a widget, set by nudging it along the grid in layout edit mode
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+pub struct WidgetOffset {
+    pub widget: Widget,
+    /// in device-independent pixels
+    pub offset: f32,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Rect {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Rect {
+    pub fn right(&self) -> f32 {
+        self.x + self.width
+    }
+
+    pub fn bottom(&self) -> f32 {
+        self.y + self.height
+    }
+
+    pub fn contains(&self, x: f32, y: f32) -> bool {
+        x >= self.x && x < self.right() && y >= self.y && y < self.bottom()
+    }
+}
+
+/// positions of the widgets within the window in physical pixels, shared by painting and
+/// hit-testing
+pub struct Layout {
+    /// every configured widget, including hidden ones
+    widgets: Vec<Widget>,
+    /// widgets with nothing to show right now, which take up no space
+    hidden: Vec<Widget>,
+    /// only the widgets that have one
+    offsets: Vec<WidgetOffset>,
+    slots: Vec<(Widget, Rect)>,
+    scale: f32,
+}
+
+impl Layout {
+    /// in device-independent pixels
+    pub const HEIGHT: f32 = 48.0;
+
+    /// lays `widgets` out left to right, each after its offset if it has one, with `scale`
+    /// physical pixels per device-independent one
+    pub fn new(widgets: &[Widget], offsets: &[WidgetOffset], scale: f32) -> Self {
+        Self::arrange(widgets.to_vec(), vec![], offsets.to_vec(), scale)
+    }
+
+    fn arrange(
+        widgets: Vec<Widget>,
+        hidden: Vec<Widget>,
+        offsets: Vec<WidgetOffset>,
+        scale: f32,
+    ) -> Self {
+        let mut x = 0.0;
+        let mut slots = vec![];
+
+        for &widget in widgets.iter().filter(|widget| !hidden.contains(widget)) {
+            if let Some(offset) = offsets.iter().find(|offset| offset.widget == widget) {
+                x += offset.offset * scale;
+            }
+
+            let rect = Rect {
+                x,
+                y: 0.0,
+                width: widget.width() * scale,
+                height: Self::HEIGHT * scale,
+            };
+
+            slots.push((widget, rect));
+            x += rect.width;
+        }
+
+        Self {
+            widgets,
+            hidden,
+            offsets,
+            slots,
+            scale,
+        }
+    }
+
+    /// the same widgets laid out at a different scale
+    pub fn rescaled(&self, scale: f32) -> Self {
+        Self::arrange(
+            self.widgets.clone(),
+            self.hidden.clone(),
+            self.offsets.clone(),
+            scale,
+        )
+    }
+
+    /// lays the widgets out again without `hidden`. returns whether anything changed.
+    pub fn set_hidden(&mut self, hidden: Vec<Widget>) -> bool {
+        if hidden == self.hidden {
+            return false;
+        }
+
+        *self = Self::arrange(
+            self.widgets.clone(),
+            hidden,
+            self.offsets.clone(),
+            self.scale,
+        );
+        true
+    }
+
+    /// converts device-independent pixels to physical ones
+    pub fn px(&self, dip: f32) -> f32 {
+        dip * self.scale
+    }
+
+    /// the size of the window that fits every widget, in physical pixels
+    pub fn size(&self) -> (i32, i32) {
+        let width = self.slots.last().map_or(0.0, |(_, rect)| rect.right());
+        let height = Self::HEIGHT * self.scale;
+
+        (width.ceil() as i32, height.ceil() as i32)
+    }
+
+    /// the widgets shown, from left to right
+    pub fn widgets(&self) -> impl Iterator<Item = Widget> + '_ {
+        self.slots.iter().map(|(widget, _)| *widget)
+    }
+
+    /// every configured widget from left to right, including hidden ones
+    pub fn configured(&self) -> impl Iterator<Item = Widget> + '_ {
+        self.widgets.iter().copied()
+    }
+
+    pub fn offsets(&self) -> &[WidgetOffset] {
+        &self.offsets
+    }
+
+    pub fn rect(&self, widget: Widget) -> Option<Rect> {
+        self.slots
+            .iter()
+            .find(|(w, _)| *w == widget)
+            .map(|(_, rect)| *rect)
+    }
+
+    /// moves `widget` into the slot under `x`, shifting the widgets in between over
+    pub fn move_widget(&mut self, widget: Widget, x: f32) {
+        let mut widgets = self.widgets.clone();
+
+        let target = self
+            .slots
+            .iter()
+            .find(|(_, rect)| x >= rect.x && x < rect.right())
+            .and_then(|(target, _)| widgets.iter().position(|w| w == target));
+
+        let from = widgets.iter().position(|w| *w == widget);
+
+        if let (Some(from), Some(target)) = (from, target)
+            && from != target
+        {
+            let widget = widgets.remove(from);
+            widgets.insert(target, widget);
+            *self = Self::arrange(
+                widgets,
+                self.hidden.clone(),
+                self.offsets.clone(),
+                self.scale,
+            );
+        }
+    }
+
+    /// moves `widget`'s left edge to the grid line nearest `x`, by changing the space before it.
+    /// returns whether it moved, which changes the size of the layout.
+    pub fn nudge_widget(&mut self, widget: Widget, x: f32) -> bool {
+        let Some(rect) = self.rect(widget) else {
+            return false;
+        };
+
+        let mut offsets = self.offsets.clone();
+        let current = offsets
+            .iter()
+            .find(|offset| offset.widget == widget)
+            .map_or(0.0, |offset| offset.offset);
+
+        // the widget can't move back past where it would be without an offset
+        let offset = current + (x - rect.x) / self.scale;
+        let offset = ((offset / GRID_SPACING).round() * GRID_SPACING).max(0.0);
+
+        if offset == current {
+            return false;
+        }
+
+        offsets.retain(|offset| offset.widget != widget);
+        if offset > 0.0 {
+            offsets.push(WidgetOffset { widget, offset });
+        }
+
+        *self = Self::arrange(
+            self.widgets.clone(),
+            self.hidden.clone(),
+            offsets,
+            self.scale,
+        );
+        true
+    }
+
+    /// finds the widget under a point in window coordinates
+    pub fn hit_test(&self, x: i32, y: i32) -> Option<Widget> {
+        self.slots
+            .iter()
+            .find(|(_, rect)| rect.contains(x as f32, y as f32))
+            .map(|(widget, _)| *widget)
+    }
+}
+
+impl Default for Layout {
+    fn default() -> Self {
+        Self::new(&Widget::DEFAULT, &[], 1.0)
+    }
+}
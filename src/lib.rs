@@ -0,0 +1,7 @@
+//! the audio side of the control panel, for other tools that want to find devices, change their
+//! volume or mute state, switch the defaults or reconnect bluetooth headsets without going
+//! through the panel. everything here is plain COM, so the thread using it has to have called
+//! `CoInitialize` or `CoInitializeEx` first. the panel's cache of tracked devices isn't part of
+//! it, since that's tied to the panel's window, icons and notifications.
+
+pub mod audio;
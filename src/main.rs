@@ -2,13 +2,16 @@
 
 use std::{
     cell::RefCell,
-    collections::HashMap,
-    ffi::{CString, OsString},
-    os::windows::ffi::OsStrExt,
-    ptr::null_mut,
+    collections::{HashMap, HashSet},
+    ffi::CString,
+    hash::{DefaultHasher, Hash, Hasher},
+    process::ExitCode,
     str::FromStr,
-    sync::Mutex,
-    time::Duration,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, AtomicU32, Ordering},
+    },
+    time::{Duration, Instant, SystemTime},
 };
 
 use anyhow::{Context, Result, bail};
@@ -16,612 +19,3802 @@ use windows::{
     self,
     Win32::{
         Devices::FunctionDiscovery::{PKEY_Device_FriendlyName, PKEY_DeviceClass_IconPath},
-        Foundation::{HINSTANCE, HWND, LPARAM, LRESULT, PROPERTYKEY, RECT, SIZE, WPARAM},
+        Foundation::{HINSTANCE, HWND, LPARAM, LRESULT, POINT, PROPERTYKEY, RECT, WPARAM},
         Graphics::{
-            Gdi::{
-                AC_SRC_ALPHA, AC_SRC_OVER, BLENDFUNCTION, CreateCompatibleBitmap,
-                CreateCompatibleDC, DeleteDC, DeleteObject, GetDC, InvalidateRect, SelectObject,
-            },
-            GdiPlus::{
-                FontStyleRegular, GdipCreateFont, GdipCreateFontFamilyFromName, GdipCreateFromHDC,
-                GdipCreatePen1, GdipCreateSolidFill, GdipCreateStringFormat, GdipDrawLine,
-                GdipDrawString, GdipFillRectangle, GdipMeasureString, GdipSetPenEndCap,
-                GdipSetPenStartCap, GdipSetTextRenderingHint, GdiplusStartup, GdiplusStartupInput,
-                LineCapSquare, LineCapTriangle, RectF, TextRenderingHintAntiAlias, UnitPixel,
-            },
+            Gdi::{InvalidateRect, ScreenToClient},
+            GdiPlus::{GdiplusShutdown, GdiplusStartup, GdiplusStartupInput},
         },
-        Media::{
-            Audio::{
-                DEVICE_STATE, DEVICE_STATEMASK_ALL, EDataFlow, ERole,
-                Endpoints::{
-                    IAudioEndpointVolume, IAudioEndpointVolumeCallback,
-                    IAudioEndpointVolumeCallback_Impl,
-                },
-                IDeviceTopology, IMMDevice, IMMDeviceEnumerator, IMMNotificationClient,
-                IMMNotificationClient_Impl, MMDeviceEnumerator, eCapture, eCommunications,
-                eConsole, eMultimedia, eRender,
-            },
-            KernelStreaming::{
-                IKsControl, KSIDENTIFIER, KSIDENTIFIER_0, KSPROPERTY_ONESHOT_RECONNECT,
-                KSPROPERTY_TYPE_GET, KSPROPSETID_BtAudio,
-            },
+        Media::Audio::{
+            AudioSessionDisconnectReason, AudioSessionState, AudioSessionStateActive, DEVICE_STATE,
+            EDataFlow, ERole,
+            Endpoints::{IAudioEndpointVolumeCallback, IAudioEndpointVolumeCallback_Impl},
+            IAudioSessionControl, IAudioSessionControl2, IAudioSessionEvents,
+            IAudioSessionEvents_Impl, IAudioSessionManager2, IAudioSessionNotification,
+            IAudioSessionNotification_Impl, IMMDevice, IMMNotificationClient,
+            IMMNotificationClient_Impl, eCapture, eCommunications, eConsole, eMultimedia, eRender,
         },
         System::{
-            Com::{CLSCTX_ALL, CoCreateInstance, CoInitialize, STGM_READ},
+            Com::{CLSCTX_ALL, CoInitialize, CoUninitialize},
+            DataExchange::COPYDATASTRUCT,
             LibraryLoader::GetModuleHandleA,
-            RemoteDesktop::{NOTIFY_FOR_ALL_SESSIONS, WTSRegisterSessionNotification},
+            RemoteDesktop::{
+                NOTIFY_FOR_ALL_SESSIONS, WTSRegisterSessionNotification,
+                WTSUnRegisterSessionNotification,
+            },
+            SystemServices::{
+                APPCOMMAND_VOLUME_DOWN, APPCOMMAND_VOLUME_MUTE, APPCOMMAND_VOLUME_UP,
+            },
+            Threading::CREATE_NO_WINDOW,
         },
         UI::{
-            Shell::ExtractIconExA,
+            Accessibility::UiaRootObjectId,
+            Controls::{NMHDR, NMTTDISPINFOW, TTN_GETDISPINFOW},
+            HiDpi::{
+                DPI_AWARENESS_CONTEXT_PER_MONITOR_AWARE_V2, GetDpiForWindow,
+                SetProcessDpiAwarenessContext,
+            },
+            Input::KeyboardAndMouse::{
+                GetKeyState, ReleaseCapture, SetCapture, VIRTUAL_KEY, VK_CONTROL, VK_DOWN,
+                VK_ESCAPE, VK_LEFT, VK_RETURN, VK_RIGHT, VK_SHIFT, VK_SPACE, VK_TAB, VK_UP,
+            },
+            Shell::{ExtractIconExA, NIN_POPUPCLOSE, NIN_POPUPOPEN, ShellExecuteW},
             WindowsAndMessaging::{
-                CloseWindow, DefWindowProcA, DestroyIcon, DestroyWindow, DispatchMessageA,
-                DrawIcon, GetMessageA, GetWindowRect, HICON, HWND_DESKTOP, HWND_TOPMOST, IDC_ARROW,
-                LoadCursorW, MSG, PostQuitMessage, RegisterClassA, SWP_NOMOVE, SWP_NOSIZE,
-                SendMessageA, SetWindowPos, ULW_ALPHA, UpdateLayeredWindow, WM_DESTROY,
-                WM_DEVICECHANGE, WM_KILLFOCUS, WM_LBUTTONDOWN, WM_MOUSEWHEEL, WM_PAINT, WM_QUIT,
-                WM_RBUTTONDOWN, WM_WINDOWPOSCHANGING, WM_WTSSESSION_CHANGE, WNDCLASSA,
-                WS_EX_LAYERED, WS_EX_NOACTIVATE, WS_EX_TOPMOST, WS_POPUP, WS_VISIBLE,
-                WTS_SESSION_LOCK, WTS_SESSION_UNLOCK,
+                CS_DBLCLKS, DefWindowProcA, DestroyIcon, DestroyWindow, DispatchMessageA,
+                FAPPCOMMAND_MASK, GetCursorPos, GetForegroundWindow, GetMessageA, HICON,
+                HSHELL_APPCOMMAND, HWND_DESKTOP, IDC_ARROW, IsWindow, KillTimer, LoadCursorW, MSG,
+                NFR_UNICODE, PBT_APMRESUMEAUTOMATIC, PBT_APMSUSPEND, PM_REMOVE, PeekMessageA,
+                PostMessageA, PostQuitMessage, RegisterClassA, RegisterShellHookWindow,
+                RegisterWindowMessageA, SC_MONITORPOWER, SW_SHOWNORMAL, SWP_NOACTIVATE, SWP_NOMOVE,
+                SWP_NOZORDER, SetForegroundWindow, SetTimer, SetWindowPos, UnregisterClassA,
+                WM_APP, WM_CAPTURECHANGED, WM_CLOSE, WM_CONTEXTMENU, WM_COPYDATA, WM_DESTROY,
+                WM_DEVICECHANGE, WM_DPICHANGED, WM_GETOBJECT, WM_HOTKEY, WM_INPUT, WM_KEYDOWN,
+                WM_KILLFOCUS, WM_LBUTTONDBLCLK, WM_LBUTTONDOWN, WM_LBUTTONUP, WM_MBUTTONDOWN,
+                WM_MOUSEMOVE, WM_MOUSEWHEEL, WM_NOTIFY, WM_NOTIFYFORMAT, WM_PAINT,
+                WM_POWERBROADCAST, WM_QUIT, WM_RBUTTONDOWN, WM_SYSCOMMAND, WM_TIMER,
+                WM_WINDOWPOSCHANGING, WM_WTSSESSION_CHANGE, WNDCLASSA, WS_EX_NOACTIVATE, WS_POPUP,
+                WS_VISIBLE, WTS_SESSION_LOCK, WTS_SESSION_UNLOCK,
             },
         },
     },
     core::implement,
 };
-use windows_core::{PCSTR, PCWSTR, s, w};
+use windows_core::{BOOL, GUID, HSTRING, Interface, PCSTR, PCWSTR, s, w};
+
+use control_panel::audio::{self, Endpoints, Reconnect, Volume};
 
 mod interop;
 use interop::*;
 
+mod api;
 mod clip;
+mod discord;
+mod eventlog;
+mod events;
+mod ipc;
+mod metrics;
+mod obs;
+mod shared;
+mod throttle;
+use throttle::Throttle;
+mod watchdog;
+mod worker;
+use worker::{Done, Job, Prepared, Worker};
+
+mod actions;
+use actions::{Action, Gesture};
+
+mod config;
+use config::Config;
+
+mod menu;
+use menu::{Menu, MenuCommand};
+
+mod layout;
+use layout::{Layout, Widget};
+
+mod render;
+use render::{Canvas, LineCap, Renderer, SurfaceLost};
+
+mod animation;
+use animation::{MuteAnimations, MuteFrame};
+
+mod accessibility;
+
+mod keyboard;
+use keyboard::Hotkey;
+
+mod tray;
+use tray::TrayIcon;
+
+mod battery;
+mod glyphs;
+mod headset;
+mod history;
+mod notifications;
+use notifications::Conditions;
+
+mod flyout;
+use flyout::Flyout;
+
+mod osd;
+use osd::{Osd, OsdState};
+
+mod widgets;
+use widgets::{Monitors, TEXT_COLOR, focus::FocusMode, media::MediaCommand, pomodoro::Phase};
+
+mod zorder;
+use zorder::ZOrder;
+
+mod placement;
+use placement::{Drag, Position, Preset};
+
+mod ddc;
+mod registry;
+mod session;
+use session::{FocusOverride, FocusReason, LockConfig, LockMute};
+
+mod tooltip;
+use tooltip::Tooltips;
+
+mod webhook;
+use webhook::{MuteSource, MuteWatch};
+
+mod queue;
+use queue::{ActionQueue, Queued};
 
 fn default<T: Default>() -> T {
     Default::default()
 }
 
-#[cfg(not(debug_assertions))]
-fn log_to_file(str: &str) {
-    use std::{fs::File, io::Write, path::Path};
+/// the most lines written a minute, so something failing in a loop can't fill the disk
+const LOG_LINES_PER_MINUTE: u32 = 120;
+
+/// the log file, written through a `Throttle`
+struct Log {
+    #[cfg(not(debug_assertions))]
+    file: Option<std::fs::File>,
+    throttle: Throttle,
+}
+
+static LOG: Mutex<Log> = Mutex::new(Log {
+    #[cfg(not(debug_assertions))]
+    file: None,
+    throttle: Throttle::new(LOG_LINES_PER_MINUTE),
+});
+
+impl Log {
+    #[cfg(not(debug_assertions))]
+    fn log(&mut self, line: &str) {
+        use std::{fs::File, io::Write, path::Path};
+
+        let Log { file, throttle } = self;
+
+        throttle.pass(line, |line| {
+            // opened on first use and kept open, trying again next time if it can't be
+            if file.is_none() {
+                let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+
+                *file = File::options()
+                    .append(true)
+                    .create(true)
+                    .open(root.join("log.txt"))
+                    .ok();
+            }
 
-    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+            if let Some(open) = file
+                && writeln!(open, "{}", line).is_err()
+            {
+                *file = None;
+            }
+        });
+    }
 
-    let mut file = File::options()
-        .write(true)
-        .append(true)
-        .create(true)
-        .open(root.join("log.txt"))
-        .unwrap();
+    #[cfg(debug_assertions)]
+    fn log(&mut self, line: &str) {
+        self.throttle.pass(line, |line| println!("{}", line));
+    }
+}
 
-    file.write_all(str.as_bytes()).unwrap();
-    file.write_all(b"\n").unwrap();
+fn log_line(line: &str) {
+    // a panic while logging shouldn't stop everything after it from being logged
+    let mut log = LOG.lock().unwrap_or_else(|e| e.into_inner());
+    log.log(line);
 }
 
-#[cfg(not(debug_assertions))]
 macro_rules! log {
     ($expression:literal $(, $arg:expr)*) => {
-        crate::log_to_file(&format!($expression $(, $arg )*))
+        crate::log_line(&format!($expression $(, $arg )*))
     };
 }
 
-#[cfg(debug_assertions)]
-macro_rules! log {
-    ($expression:literal $(, $arg:expr)*) => {
-        println!($expression $(, $arg )*)
-    };
+/// logs a failure on one of the threads that keep other programs in step with the panel, e.g.
+/// discord, and reports it to the event log. they're passed on through a `Throttle` of their own,
+/// since they tend to fail the same way on every retry.
+fn log_background(line: &str) {
+    log!("{}", line);
+    eventlog::warning(line);
 }
 
-unsafe impl Sync for RedrawHandle {}
-unsafe impl Send for RedrawHandle {}
+/// posted by `Notifier::changed` so the coalescing timer is started on the window's thread
+const WM_REDRAW: u32 = WM_APP + 1;
+
+const REDRAW_TIMER: usize = 2;
+const REDRAW_DELAY_MS: u32 = 40;
+
+/// posted by UI Automation providers to invoke or toggle the widget with `Widget::index`
+/// `wparam` from the window proc
+const WM_INVOKE_WIDGET: u32 = WM_APP + 2;
+const WM_TOGGLE_WIDGET: u32 = WM_APP + 3;
+
+/// sent by a tray icon when it's clicked, with its id in `wparam` and the mouse message in
+/// `lparam`
+const WM_TRAY: u32 = WM_APP + 4;
+
+/// posted by the device callback when the default device for the `EDataFlow` in `wparam`
+/// changes
+const WM_DEFAULT_DEVICE_CHANGED: u32 = WM_APP + 5;
+
+/// posted by the device callback when a device's icon path changes
+const WM_DEVICE_ICON_CHANGED: u32 = WM_APP + 6;
+
+/// sent by the ipc server with a pointer to an `ipc::Call` in `lparam`, to be answered before
+/// returning
+const WM_IPC_COMMAND: u32 = WM_APP + 7;
+
+/// posted by the discord connection when discord's mute changes, with the new state in `wparam`
+const WM_DISCORD_MUTE: u32 = WM_APP + 8;
+
+/// posted by the audio worker when it's finished a job
+const WM_WORKER_DONE: u32 = WM_APP + 9;
+
+/// posted by the watchdog with a number in `wparam` to hand back to `watchdog::pong`
+const WM_WATCHDOG_PING: u32 = WM_APP + 10;
+
+/// posted when an action's queued on an empty `ActionQueue`, to run everything queued by then
+const WM_RUN_ACTIONS: u32 = WM_APP + 11;
+
+/// posted by the device callback when a device is renamed
+const WM_DEVICE_NAME_CHANGED: u32 = WM_APP + 12;
+
+/// broadcast when explorer restarts, after which the tray icons have to be added again
+static TASKBAR_CREATED: AtomicU32 = AtomicU32::new(0);
+
+/// sent to shell hook windows, including app commands like the keyboard's volume keys once the
+/// foreground window has passed on them
+static SHELL_HOOK: AtomicU32 = AtomicU32::new(0);
+
+/// id of the hotkey that enters keyboard mode
+const KEYBOARD_HOTKEY: i32 = 1;
+
+/// id of the first hotkey from `Config::hotkeys`, the rest follow in order
+const BINDING_HOTKEYS: i32 = 100;
+
+/// id of the timer that repaints the panel while a transition is animating
+const ANIMATION_TIMER: usize = 3;
+
+/// id of the timer that hides the osd
+const OSD_TIMER: usize = 4;
+
+/// id of the timer that repaints the clock and date widgets when the text they show changes
+const CLOCK_TIMER: usize = 5;
+
+/// id of the timer that polls the widgets that show system readings, see `Monitors`
+const POLL_TIMER: usize = 6;
+
+/// id of the timer that retries reconnecting bluetooth devices after resuming from sleep
+const RECONNECT_TIMER: usize = 7;
+
+/// the bluetooth stack takes a moment to come back after resuming
+const RECONNECT_DELAY_MS: u32 = 5000;
+const RECONNECT_ATTEMPTS: u32 = 3;
+
+/// id of the timer that checks for idleness and the screensaver when either is configured to
+/// mute, often enough that the mic comes back as soon as there's input
+const ACTIVITY_TIMER: usize = 8;
+const ACTIVITY_CHECK_MS: u32 = 1000;
+
+/// id of the timer that shows the osd after a volume key, once the shell has changed the volume
+const MEDIA_KEY_TIMER: usize = 9;
+const MEDIA_KEY_DELAY_MS: u32 = 100;
 
+/// id of the timer that paints again after the window's surface was lost, see `SurfaceLost`
+const SURFACE_RETRY_TIMER: usize = 10;
+const SURFACE_RETRY_MS: u32 = 500;
+
+/// id of the timer that repaints the diagnostics overlay while it's shown, so its numbers move
+const DIAGNOSTICS_TIMER: usize = 11;
+const DIAGNOSTICS_INTERVAL_MS: u32 = 1000;
+
+/// id of the timer that steps the mic live warning, see `MuteAnimations::next_live_step`
+const LIVE_TIMER: usize = 12;
+
+/// id of the timer that repaints the now playing widget while its title is too long to fit
+const SCROLL_TIMER: usize = 13;
+
+static REDRAW_PENDING: AtomicBool = AtomicBool::new(false);
+
+/// how the audio callbacks, which are called on whichever threads the audio service likes, tell
+/// the window what's happened. everything's posted as a message to be handled on the window's
+/// thread, which is safe from any thread, so the handle is kept as a plain number to be `Send`
+/// and `Sync` without having to promise anything.
 #[derive(Clone, Copy)]
-struct RedrawHandle {
-    hwnd: HWND,
+struct Notifier {
+    hwnd: isize,
 }
 
-impl RedrawHandle {
+impl Notifier {
     fn new(hwnd: HWND) -> Self {
-        Self { hwnd }
+        Self {
+            hwnd: hwnd.0 as isize,
+        }
+    }
+
+    fn hwnd(&self) -> HWND {
+        HWND(self.hwnd as _)
     }
 
-    fn redraw(&self) {
+    fn post(&self, message: u32, wparam: usize) {
         unsafe {
-            SendMessageA(self.hwnd, WM_KILLFOCUS, default(), default());
-            let _ = InvalidateRect(Some(self.hwnd), None, true);
+            let _ = PostMessageA(Some(self.hwnd()), message, WPARAM(wparam), default());
+        }
+    }
+
+    /// something that may be shown has changed, e.g. a volume or a session starting. bursts
+    /// (e.g. while a volume slider is being dragged) are coalesced into one check per
+    /// `REDRAW_DELAY_MS`, which repaints if anything shown is different.
+    fn changed(&self) {
+        if !REDRAW_PENDING.swap(true, Ordering::AcqRel) {
+            self.post(WM_REDRAW, 0);
         }
     }
+
+    /// `flow`'s default device for media has changed
+    fn default_device_changed(&self, flow: EDataFlow) {
+        self.post(WM_DEFAULT_DEVICE_CHANGED, flow.0 as usize);
+    }
+
+    /// a device's icon path has changed
+    fn icon_changed(&self) {
+        self.post(WM_DEVICE_ICON_CHANGED, 0);
+    }
+
+    /// a device's friendly name has changed
+    fn name_changed(&self) {
+        self.post(WM_DEVICE_NAME_CHANGED, 0);
+    }
+}
+
+/// a device's volume and mute state as of its last notification, which is what's shown and
+/// compared to notice changes, so neither asks the device anything
+#[derive(Clone, Copy, Default)]
+struct Levels {
+    volume: f32,
+    muted: bool,
 }
 
 struct AudioDevice {
-    controls: IAudioEndpointVolume,
-    icon: HICON,
+    volume: Volume,
+    /// registered for this device only, and keeping `levels` up to date
+    callback: IAudioEndpointVolumeCallback,
+    levels: Arc<Mutex<Levels>>,
+    /// as read by the worker when it was prepared, and again when it's renamed, so painting
+    /// doesn't open the property store
+    name: String,
+    /// the built-in glyph for the device's kind, shown until its own icon has loaded or if it
+    /// can't be. shared between devices, so it isn't theirs to destroy
+    placeholder: HICON,
+    icon: Option<HICON>,
+    /// where `icon` was loaded from, to notice when the driver changes it
+    icon_path: Option<String>,
+    /// when it was last asked for, see `AudioManager::evict`
+    used: Instant,
 }
 
 impl AudioDevice {
-    pub fn new(controls: IAudioEndpointVolume, icon: HICON) -> Self {
-        Self { controls, icon }
+    pub fn new(
+        volume: Volume,
+        callback: IAudioEndpointVolumeCallback,
+        levels: Arc<Mutex<Levels>>,
+        name: String,
+        placeholder: HICON,
+    ) -> Self {
+        Self {
+            volume,
+            callback,
+            levels,
+            name,
+            placeholder,
+            icon: None,
+            icon_path: None,
+            used: Instant::now(),
+        }
+    }
+
+    pub fn icon(&self) -> HICON {
+        self.icon.unwrap_or(self.placeholder)
+    }
+
+    /// the volume and mute state last heard of. actions read the device itself instead, since
+    /// their own changes only show up here once the notification has
+    pub fn levels(&self) -> Levels {
+        *self.levels.lock().unwrap()
     }
 
     pub fn volume(&self) -> Result<f32> {
-        unsafe {
-            let volume = self.controls.GetMasterVolumeLevelScalar()?;
-            Ok(volume)
-        }
+        self.volume.volume()
     }
 
     pub fn is_mute(&self) -> Result<bool> {
-        let value = unsafe { self.controls.GetMute() }?;
-        Ok(value.as_bool())
+        self.volume.is_mute()
     }
 
     pub fn set_mute(&self, value: bool) -> Result<()> {
-        unsafe {
-            self.controls.SetMute(value, null_mut())?;
-        }
+        self.volume.set_mute(value)?;
+        metrics::MUTE_TOGGLES.add();
         Ok(())
     }
 }
 
 struct AudioManager {
-    policy_config: IPolicyConfig,
-    device_enumerator: IMMDeviceEnumerator,
+    endpoints: Endpoints,
 
     device_callback: IMMNotificationClient,
-    controls_callback: IAudioEndpointVolumeCallback,
+    notifier: Notifier,
 
     devices: HashMap<String, AudioDevice>,
+    /// the default device for each flow and its id, until `forget_default` hears it's changed
+    defaults: HashMap<i32, (String, IMMDevice)>,
+    /// devices the worker is getting ready to track
+    preparing: HashSet<String>,
+    pub worker: Worker,
+    /// the placeholder icons, see `AudioDevice::placeholder`
+    speaker: HICON,
+    microphone: HICON,
+    /// shown in place of a flow's device when it has none, see `has_default`
+    no_speaker: HICON,
+    no_microphone: HICON,
+    capture_sessions: HashMap<String, CaptureSessions>,
+}
+
+/// the audio sessions on a capture device, each registered for state change events
+struct CaptureSessions {
+    manager: IAudioSessionManager2,
+    /// registered with the manager and each session, and only for this device
+    callback: IAudioSessionNotification,
+    events: IAudioSessionEvents,
+    sessions: HashMap<String, IAudioSessionControl>,
+    /// whether a session was active when they were last looked at
+    active: bool,
+    /// set by the callbacks when a session's been created or changed state since
+    stale: Arc<AtomicBool>,
 }
 
 impl AudioManager {
-    fn new(redraw_handle: RedrawHandle) -> Result<Self> {
+    fn new(notifier: Notifier) -> Result<Self> {
         unsafe {
-            let policy_config: IPolicyConfig =
-                CoCreateInstance(&CLSID_PolicyConfigClient, None, CLSCTX_ALL)?;
-
-            let device_enumerator: IMMDeviceEnumerator =
-                CoCreateInstance(&MMDeviceEnumerator, None, CLSCTX_ALL)?;
+            let endpoints = Endpoints::new()?;
 
-            let callback = DeviceCallback { redraw_handle };
+            let callback = DeviceCallback { notifier };
             let device_callback = callback.into();
-            device_enumerator.RegisterEndpointNotificationCallback(&device_callback)?;
-
-            let controls_callback = VolumeCallback { redraw_handle };
-            let controls_callback = controls_callback.into();
+            endpoints
+                .enumerator()
+                .RegisterEndpointNotificationCallback(&device_callback)?;
 
             Ok(Self {
-                device_enumerator,
-                policy_config,
-                controls_callback,
+                endpoints,
                 device_callback,
+                notifier,
                 devices: HashMap::new(),
+                defaults: HashMap::new(),
+                preparing: HashSet::new(),
+                worker: Worker::spawn(notifier.hwnd()),
+                speaker: glyphs::speaker()?,
+                microphone: glyphs::microphone()?,
+                no_speaker: glyphs::no_speaker()?,
+                no_microphone: glyphs::no_microphone()?,
+                capture_sessions: HashMap::new(),
             })
         }
     }
 
-    pub fn get_default_device(&self, flow: EDataFlow) -> Result<IMMDevice> {
-        unsafe {
-            let device = self
-                .device_enumerator
-                .GetDefaultAudioEndpoint(flow, eMultimedia)?;
+    pub fn get_default_device(&mut self, flow: EDataFlow) -> Result<IMMDevice> {
+        Ok(self.default_entry(flow)?.1.clone())
+    }
+
+    pub fn default_id(&mut self, flow: EDataFlow) -> Result<String> {
+        Ok(self.default_entry(flow)?.0.clone())
+    }
+
+    /// the default device's tracked state, without asking the endpoint anything once it's known
+    pub fn default_device(&mut self, flow: EDataFlow) -> Result<&AudioDevice> {
+        let (id, endpoint) = self.default_entry(flow)?.clone();
+        self.track(&endpoint, id)
+    }
 
-            Ok(device)
+    fn default_entry(&mut self, flow: EDataFlow) -> Result<&(String, IMMDevice)> {
+        if !self.has_default(flow)? {
+            bail!("there's no default device for {:?}", flow);
         }
+
+        Ok(&self.defaults[&flow.0])
     }
 
-    pub fn set_default_device(&mut self, device_id: PCWSTR) -> Result<()> {
-        let device_id = device_id.as_ptr();
+    /// whether `flow` has a default device at all, which it doesn't on a headless machine or
+    /// with every device disabled. not having one isn't remembered, so a device turning up is
+    /// noticed straight away
+    pub fn has_default(&mut self, flow: EDataFlow) -> Result<bool> {
+        if !self.defaults.contains_key(&flow.0) {
+            let Some(endpoint) = self.endpoints.default_device(flow)? else {
+                return Ok(false);
+            };
 
-        unsafe {
-            self.policy_config
-                .SetDefaultEndpoint(device_id, eConsole.0 as _)
-                .ok()?;
-            self.policy_config
-                .SetDefaultEndpoint(device_id, eMultimedia.0 as _)
-                .ok()?;
-            self.policy_config
-                .SetDefaultEndpoint(device_id, eCommunications.0 as _)
-                .ok()?;
+            let id = audio::id(&endpoint)?;
+            self.defaults.insert(flow.0, (id, endpoint));
+        }
 
-            Ok(())
+        Ok(true)
+    }
+
+    /// the glyph shown for `flow` while it has no device
+    pub fn no_device_icon(&self, flow: EDataFlow) -> HICON {
+        match flow {
+            flow if flow == eCapture => self.no_microphone,
+            _ => self.no_speaker,
         }
     }
 
+    /// called when `flow`'s default device changes, so it's looked up again
+    pub fn forget_default(&mut self, flow: EDataFlow) {
+        self.defaults.remove(&flow.0);
+    }
+
+    pub fn set_default_device(&mut self, device_id: &str) -> Result<()> {
+        self.endpoints.set_default(device_id)?;
+
+        // so whatever's shown straight after doesn't wait for the notification
+        self.defaults.clear();
+        Ok(())
+    }
+
+    pub fn active_devices(&self, flow: EDataFlow) -> Result<Vec<IMMDevice>> {
+        self.endpoints.active_devices(flow)
+    }
+
     pub fn get_device(&mut self, device: &IMMDevice) -> Result<&AudioDevice> {
-        unsafe {
-            let props = device.OpenPropertyStore(STGM_READ)?;
+        let id = audio::id(device)?;
+        self.track(device, id)
+    }
 
-            let id = device.GetId()?.to_string()?;
+    /// starts tracking the device if it isn't already, waiting for it if it's slow. painting
+    /// uses `ready_default` instead
+    fn track(&mut self, device: &IMMDevice, id: String) -> Result<&AudioDevice> {
+        if !self.devices.contains_key(&id) {
+            let prepared = worker::prepare(device)?;
+            self.add(id.clone(), prepared)?;
+        }
 
-            if !self.devices.contains_key(&id) {
-                let name = props.GetValue(&PKEY_Device_FriendlyName)?.to_string();
-                let icon_path = props.GetValue(&PKEY_DeviceClass_IconPath)?.to_string();
-                let icon = load_icon(&icon_path).context(icon_path)?;
+        let device = self.devices.get_mut(&id).unwrap();
+        device.used = Instant::now();
+        Ok(device)
+    }
 
-                log!("start tracking device: {} {}", id, name);
+    /// the default device if it's tracked. otherwise the worker is asked to get it ready, and
+    /// it's `None` until it is. it's also `None` if there's no default device at all
+    pub fn ready_default(&mut self, flow: EDataFlow) -> Result<Option<&AudioDevice>> {
+        if !self.has_default(flow)? {
+            return Ok(None);
+        }
 
-                let controls: IAudioEndpointVolume = device.Activate(CLSCTX_ALL, None)?;
-                controls.RegisterControlChangeNotify(&self.controls_callback)?;
+        let id = self.default_id(flow)?;
 
-                let info = AudioDevice::new(controls, icon);
-                self.devices.insert(id.clone(), info);
+        if !self.devices.contains_key(&id) {
+            if self.preparing.insert(id.clone()) {
+                self.worker.send(Job::Prepare(id));
             }
 
-            Ok(&self.devices[&id])
+            return Ok(None);
         }
-    }
 
-    pub fn destroy(self) -> Result<()> {
-        unsafe {
-            self.device_enumerator
-                .UnregisterEndpointNotificationCallback(&self.device_callback)?;
+        let device = self.devices.get_mut(&id).unwrap();
+        device.used = Instant::now();
+        Ok(Some(device))
+    }
 
-            for (_, device) in self.devices {
-                device
-                    .controls
-                    .UnregisterControlChangeNotify(&self.controls_callback)?;
+    /// starts tracking a device the worker has got ready
+    pub fn add_prepared(&mut self, id: String, prepared: Result<Prepared>) -> Result<()> {
+        self.preparing.remove(&id);
+        let prepared = prepared?;
 
-                DestroyIcon(device.icon)?;
-            }
+        // it may have been needed before the worker was done
+        if self.devices.contains_key(&id) {
+            return Ok(());
         }
 
-        Ok(())
+        self.add(id, prepared)
     }
-}
 
-fn load_icon(icon_path: &str) -> Result<HICON> {
-    unsafe {
-        let mut parts = icon_path.split(",");
-        let path = parts.next().context("invalid icon path")?;
+    /// starts tracking with the placeholder icon, leaving the worker to load the device's own
+    fn add(&mut self, id: String, prepared: Prepared) -> Result<()> {
+        log!("start tracking device: {} {}", id, prepared.name);
 
-        let index = match parts.next() {
-            Some(i) => i.parse()?,
-            None => 0,
+        let volume = Volume::from_controls(prepared.controls.resolve()?);
+        let levels = Arc::new(Mutex::new(Levels::default()));
+        let callback: IAudioEndpointVolumeCallback = VolumeCallback {
+            notifier: self.notifier,
+            levels: levels.clone(),
+        }
+        .into();
+
+        // read after registering, so nothing that changes in between is missed
+        unsafe { volume.controls().RegisterControlChangeNotify(&callback)? };
+        *levels.lock().unwrap() = Levels {
+            volume: volume.volume()?,
+            muted: volume.is_mute()?,
         };
 
-        let mut icon = default();
-        ExtractIconExA(
-            PCSTR(CString::from_str(path)?.as_ptr() as *const u8),
-            index,
-            Some(&mut icon),
-            None,
-            1,
-        );
+        let placeholder = match prepared.flow {
+            flow if flow == eCapture => self.microphone,
+            _ => self.speaker,
+        };
 
-        Ok(icon)
+        let device = AudioDevice::new(volume, callback, levels, prepared.name, placeholder);
+        self.devices.insert(id.clone(), device);
+        self.worker.send(Job::LoadIcon(id));
+
+        Ok(())
     }
-}
 
-struct WindowHelper {
-    audio: AudioManager,
+    /// asks the worker to load every tracked device's icon again, after one has changed
+    pub fn reload_icons(&self) {
+        for id in self.devices.keys() {
+            self.worker.send(Job::LoadIcon(id.clone()));
+        }
+    }
 
-    airpods_available: bool,
-    unlock_mute_output: bool,
-    unlock_mute_input: bool,
-}
+    /// asks the worker to read every tracked device's name again, after one has been renamed
+    pub fn reload_names(&self) {
+        for id in self.devices.keys() {
+            self.worker.send(Job::ReadName(id.clone()));
+        }
+    }
 
-fn get_name(device: &IMMDevice) -> Result<String> {
-    unsafe {
-        let props = device.OpenPropertyStore(STGM_READ)?;
-        let name = props.GetValue(&PKEY_Device_FriendlyName)?.to_string();
+    /// takes a name the worker has read
+    pub fn set_name(&mut self, id: &str, name: Result<String>) -> Result<()> {
+        let name = name?;
 
-        Ok(name)
+        if let Some(device) = self.devices.get_mut(id) {
+            device.name = name;
+        }
+
+        Ok(())
     }
-}
 
-impl WindowHelper {
-    fn on_paint(&mut self, hwnd: HWND) -> Result<()> {
-        unsafe {
-            let mut window_rect = RECT::default();
-            GetWindowRect(hwnd, &mut window_rect)?;
+    /// takes an icon the worker has loaded, returning the one it replaces to be destroyed once
+    /// nothing refers to it. a device whose icon can't be loaded keeps the one it has.
+    pub fn set_icon(&mut self, id: &str, loaded: Result<(String, HICON)>) -> Result<Option<HICON>> {
+        let (icon_path, icon) = match loaded {
+            Ok(loaded) => loaded,
+            Err(e) => {
+                log!("no icon for {}: {:?}", id, e);
+                return Ok(None);
+            }
+        };
 
-            let size = SIZE {
-                cx: window_rect.right - window_rect.left,
-                cy: window_rect.bottom - window_rect.top,
-            };
+        let Some(device) = self.devices.get_mut(id) else {
+            unsafe { DestroyIcon(icon)? };
+            return Ok(None);
+        };
 
-            let screen = GetDC(None);
-            let dc = CreateCompatibleDC(Some(screen));
-            let bitmap = CreateCompatibleBitmap(screen, size.cx, size.cy);
-            let _ = DeleteObject(SelectObject(dc, bitmap.into()));
+        if device.icon_path.as_ref() == Some(&icon_path) {
+            unsafe { DestroyIcon(icon)? };
+            return Ok(None);
+        }
 
-            let mut graphics = default();
-            GdipCreateFromHDC(dc, &mut graphics);
+        log!("icon loaded: {} {}", id, icon_path);
 
-            {
-                let mut brush = default();
-                GdipCreateSolidFill(0x01000000, &mut brush);
-                GdipFillRectangle(graphics, brush as _, 0.0, 0.0, size.cx as _, size.cy as _);
-            }
+        device.icon_path = Some(icon_path);
+        Ok(device.icon.replace(icon))
+    }
 
-            let mut pen = default();
-            GdipCreatePen1(0xffff0000, 8.0, UnitPixel, &mut pen);
-            GdipSetPenEndCap(pen, LineCapTriangle);
-            GdipSetPenStartCap(pen, LineCapTriangle);
+    /// stops tracking devices that aren't a default and haven't been asked for in `max_age`, so
+    /// ones that were only looked at once, or have since gone away, aren't held onto forever.
+    /// returns their icons, to be destroyed once nothing refers to them.
+    pub fn evict(&mut self, max_age: Duration) -> Vec<HICON> {
+        let defaults: HashSet<&String> = self.defaults.values().map(|(id, _)| id).collect();
 
-            let output = self.audio.get_default_device(eRender)?;
-            let output = self.audio.get_device(&output)?;
+        let stale: Vec<String> = self
+            .devices
+            .iter()
+            .filter(|(id, device)| device.used.elapsed() >= max_age && !defaults.contains(id))
+            .map(|(id, _)| id.clone())
+            .collect();
 
-            let x = 100.0;
+        let mut icons = vec![];
 
-            let string = format!("{:.0}%", output.volume()? * 100.0);
-            let string: Vec<_> = OsString::from(string).encode_wide().collect();
+        for id in stale {
+            let Some(device) = self.devices.remove(&id) else {
+                continue;
+            };
 
-            {
-                let mut brush = default();
-                let mut font_family = default();
-                let mut font = default();
-                let mut format = default();
-
-                GdipCreateSolidFill(0xff202020, &mut brush);
-                GdipCreateFontFamilyFromName(w!("Segoe UI"), default(), &mut font_family);
-                GdipCreateFont(font_family, 24.0, FontStyleRegular.0, UnitPixel, &mut font);
-                GdipCreateStringFormat(0, 0, &mut format);
-
-                GdipSetTextRenderingHint(graphics, TextRenderingHintAntiAlias);
-
-                let rect = RectF {
-                    X: x,
-                    Y: 8.0,
-                    Width: 1000.0,
-                    Height: 1000.0,
-                };
+            log!("stop tracking device: {}", id);
+            icons.extend(device.icon);
+
+            // the device may already be gone, which is often why it's stale, so failing to
+            // unregister isn't worth keeping it for
+            unsafe {
+                let _ = device
+                    .volume
+                    .controls()
+                    .UnregisterControlChangeNotify(&device.callback);
+
+                if let Some(tracked) = self.capture_sessions.remove(&id) {
+                    let _ = tracked
+                        .manager
+                        .UnregisterSessionNotification(&tracked.callback);
+
+                    for (_, session) in tracked.sessions {
+                        let _ = session.UnregisterAudioSessionNotification(&tracked.events);
+                    }
+                }
+            }
+        }
 
-                let mut bounds = default();
-                let mut chars = 0;
-                let mut lines = 0;
-                GdipMeasureString(
-                    graphics,
-                    PCWSTR(&string[0]),
-                    string.len() as _,
-                    font,
-                    &rect,
-                    format,
-                    &mut bounds,
-                    &mut chars,
-                    &mut lines,
-                );
+        icons
+    }
 
-                let rect = RectF {
-                    X: x - bounds.Width,
-                    Y: 24.0 - bounds.Height / 2.0,
-                    ..rect
+    /// whether any application is currently recording from `flow`'s default device. the
+    /// sessions are only looked at again once their callbacks say something's changed
+    pub fn is_capturing(&mut self, flow: EDataFlow) -> Result<bool> {
+        let (id, device) = self.default_entry(flow)?.clone();
+
+        unsafe {
+            if !self.capture_sessions.contains_key(&id) {
+                let manager: IAudioSessionManager2 = device.Activate(CLSCTX_ALL, None)?;
+
+                let stale = Arc::new(AtomicBool::new(true));
+                let callback: IAudioSessionNotification = CaptureCallback {
+                    notifier: self.notifier,
+                    stale: stale.clone(),
+                }
+                .into();
+                let events = callback.cast()?;
+
+                // session notifications only start once the sessions have been enumerated
+                manager.GetSessionEnumerator()?;
+                manager.RegisterSessionNotification(&callback)?;
+
+                let sessions = CaptureSessions {
+                    manager,
+                    callback,
+                    events,
+                    sessions: HashMap::new(),
+                    active: false,
+                    stale,
                 };
 
-                GdipDrawString(
-                    graphics,
-                    PCWSTR(&string[0]),
-                    string.len() as _,
-                    font,
-                    &rect,
-                    format,
-                    brush as _,
-                );
+                self.capture_sessions.insert(id.clone(), sessions);
             }
 
-            {
-                let mut pen = default();
-                GdipCreatePen1(0xffc0c0c0, 8.0, UnitPixel, &mut pen);
-                GdipSetPenEndCap(pen, LineCapSquare);
-                GdipSetPenStartCap(pen, LineCapSquare);
-                GdipDrawLine(graphics, pen, x + 4.0, 10.0, x + 4.0, 39.0);
+            let tracked = self.capture_sessions.get_mut(&id).unwrap();
+
+            // cleared first, so a change while they're being looked at is picked up next time
+            if !tracked.stale.swap(false, Ordering::AcqRel) {
+                return Ok(tracked.active);
+            }
 
-                let volume = output.volume()?;
-                let start = 39.0 - (29.0 * volume);
+            let sessions = tracked.manager.GetSessionEnumerator()?;
+
+            let mut current = HashMap::new();
+            let mut active = false;
+
+            for i in 0..sessions.GetCount()? {
+                let session = sessions.GetSession(i)?;
+                let key = session
+                    .cast::<IAudioSessionControl2>()?
+                    .GetSessionInstanceIdentifier()?
+                    .to_string()?;
+
+                // watch new sessions so we hear about them starting and stopping
+                match tracked.sessions.remove(&key) {
+                    Some(session) => {
+                        current.insert(key, session);
+                    }
+                    None => {
+                        session.RegisterAudioSessionNotification(&tracked.events)?;
+                        current.insert(key, session.clone());
+                    }
+                }
 
-                let mut pen = default();
-                GdipCreatePen1(0xff404040, 8.0, UnitPixel, &mut pen);
-                GdipSetPenEndCap(pen, LineCapSquare);
-                GdipSetPenStartCap(pen, LineCapSquare);
-                GdipDrawLine(graphics, pen, x + 4.0, start, x + 4.0, 39.0);
+                if session.GetState()? == AudioSessionStateActive {
+                    active = true;
+                }
             }
 
-            let x = x + 5.0;
-            DrawIcon(dc, x as i32 + 8, 8, output.icon)?;
-            if output.is_mute()? {
-                GdipDrawLine(graphics, pen, x + 8.0, 8.0, x + 40.0, 40.0);
-                GdipDrawLine(graphics, pen, x + 40.0, 8.0, x + 8.0, 40.0);
+            // whatever is left has expired
+            for (_, session) in tracked.sessions.drain() {
+                session.UnregisterAudioSessionNotification(&tracked.events)?;
             }
 
-            let input = self.audio.get_default_device(eCapture)?;
-            let input = self.audio.get_device(&input)?;
+            tracked.sessions = current;
+            tracked.active = active;
+
+            Ok(active)
+        }
+    }
+
+    /// carries on past anything that fails, so one dead device doesn't leave the rest registered,
+    /// and returns the first failure
+    pub fn destroy(self) -> Result<()> {
+        let mut result = Ok(());
 
-            let x = x + 40.0;
-            DrawIcon(dc, x as i32 + 8, 8, input.icon)?;
-            if input.is_mute()? {
-                GdipDrawLine(graphics, pen, x + 8.0, 8.0, x + 40.0, 40.0);
-                GdipDrawLine(graphics, pen, x + 40.0, 8.0, x + 8.0, 40.0);
+        unsafe {
+            result = result.and(
+                self.endpoints
+                    .enumerator()
+                    .UnregisterEndpointNotificationCallback(&self.device_callback),
+            );
+
+            for (_, tracked) in self.capture_sessions {
+                result = result.and(
+                    tracked
+                        .manager
+                        .UnregisterSessionNotification(&tracked.callback),
+                );
+
+                for (_, session) in tracked.sessions {
+                    result =
+                        result.and(session.UnregisterAudioSessionNotification(&tracked.events));
+                }
             }
 
-            let blend = BLENDFUNCTION {
-                BlendOp: AC_SRC_OVER as _,
-                BlendFlags: 0,
-                SourceConstantAlpha: 0xff,
-                AlphaFormat: AC_SRC_ALPHA as _,
-            };
+            for (_, device) in self.devices {
+                result = result.and(
+                    device
+                        .volume
+                        .controls()
+                        .UnregisterControlChangeNotify(&device.callback),
+                );
 
-            UpdateLayeredWindow(
-                hwnd,
-                Some(screen),
-                None,
-                Some(&size),
-                Some(dc),
-                Some(&default()),
-                default(),
-                Some(&blend),
-                ULW_ALPHA,
-            )?;
+                if let Some(icon) = device.icon {
+                    result = result.and(DestroyIcon(icon));
+                }
+            }
 
-            let _ = DeleteObject(bitmap.into());
-            let _ = DeleteDC(dc);
+            result = result.and(DestroyIcon(self.speaker));
+            result = result.and(DestroyIcon(self.microphone));
+            result = result.and(DestroyIcon(self.no_speaker));
+            result = result.and(DestroyIcon(self.no_microphone));
         }
 
-        Ok(())
+        Ok(result?)
     }
+}
 
-    fn on_lock(&mut self) -> Result<()> {
-        let output = self.audio.get_default_device(eRender)?;
-        let device = self.audio.get_device(&output)?;
+fn load_icon(icon_path: &str) -> Result<HICON> {
+    unsafe {
+        let mut parts = icon_path.split(",");
+        let path = parts.next().context("invalid icon path")?;
 
-        if !device.is_mute()? {
-            device.set_mute(true)?;
-            self.unlock_mute_output = true;
-        }
+        let index = match parts.next() {
+            Some(i) => i.parse()?,
+            None => 0,
+        };
 
-        let input = self.audio.get_default_device(eCapture)?;
-        let device = self.audio.get_device(&input)?;
+        let mut icon = HICON::default();
+        let count = ExtractIconExA(
+            PCSTR(CString::from_str(path)?.as_ptr() as *const u8),
+            index,
+            Some(&mut icon),
+            None,
+            1,
+        );
 
-        if !device.is_mute()? {
-            device.set_mute(true)?;
-            self.unlock_mute_input = true;
+        if count == 0 || icon.is_invalid() {
+            bail!("no icon in {}", icon_path);
         }
 
-        Ok(())
+        Ok(icon)
     }
+}
 
-    fn on_unlock(&mut self) -> Result<()> {
-        if self.unlock_mute_output {
-            let output = self.audio.get_default_device(eRender)?;
-            let device = self.audio.get_device(&output)?;
-
-            if device.is_mute()? {
-                device.set_mute(false)?;
-            }
+fn shell_open(file: &str, parameters: Option<&str>) -> Result<()> {
+    let parameters = parameters.map(HSTRING::from);
 
-            self.unlock_mute_output = false;
-        }
+    let result = unsafe {
+        ShellExecuteW(
+            None,
+            w!("open"),
+            &HSTRING::from(file),
+            parameters
+                .as_ref()
+                .map_or(PCWSTR::null(), |p| PCWSTR(p.as_ptr())),
+            PCWSTR::null(),
+            SW_SHOWNORMAL,
+        )
+    };
 
-        if self.unlock_mute_input {
-            let input = self.audio.get_default_device(eCapture)?;
-            let device = self.audio.get_device(&input)?;
+    // ShellExecute returns a value greater than 32 on success
+    if result.0 as usize <= 32 {
+        bail!("failed to open {}: {}", file, result.0 as usize);
+    }
 
-            if device.is_mute()? {
-                device.set_mute(false)?;
-            }
+    Ok(())
+}
 
-            self.unlock_mute_input = false;
-        }
+fn run_command(command: &str) -> Result<()> {
+    use std::os::windows::process::CommandExt;
 
-        Ok(())
-    }
+    log!("run command: {}", command);
 
-    fn step_volume(&mut self, up: bool) -> Result<()> {
-        let output = self.audio.get_default_device(eRender)?;
-        let device = self.audio.get_device(&output)?;
+    std::process::Command::new("cmd")
+        .arg("/C")
+        .raw_arg(command)
+        .creation_flags(CREATE_NO_WINDOW.0)
+        .spawn()?;
 
-        if up {
-            (unsafe { device.controls.VolumeStepUp(default()) })?;
-        } else {
-            (unsafe { device.controls.VolumeStepDown(default()) })?;
-        }
+    Ok(())
+}
 
-        Ok(())
-    }
+/// runs each of `commands` for `event` without waiting for them, logging how they exit. a
+/// command failing to start doesn't stop the others, or whatever else handles the event.
+fn run_session_commands(event: &'static str, commands: &[String]) {
+    use std::os::windows::process::CommandExt;
 
-    fn find_connected_airpods(&mut self) -> Result<Option<IMMDevice>> {
-        unsafe {
-            let devices = self
-                .audio
-                .device_enumerator
-                .EnumAudioEndpoints(eRender, DEVICE_STATE(DEVICE_STATEMASK_ALL))?;
+    for command in commands {
+        log!("{}: run {}", event, command);
 
-            for i in 0..devices.GetCount()? {
-                let device = devices.Item(i)?;
+        let spawned = std::process::Command::new("cmd")
+            .arg("/C")
+            .raw_arg(command)
+            .creation_flags(CREATE_NO_WINDOW.0)
+            .spawn();
 
-                if let Ok(name) = get_name(&device)
-                    && name.to_lowercase().contains("airpods")
-                {
-                    return Ok(Some(device));
-                }
+        let mut child = match spawned {
+            Ok(child) => child,
+            Err(e) => {
+                log!("{}: failed to run {}: {:?}", event, command, e);
+                continue;
             }
+        };
+
+        let command = command.clone();
+        std::thread::spawn(move || match child.wait() {
+            Ok(status) => log!("{}: {} exited with {}", event, command, status),
+            Err(e) => log!("{}: failed to wait for {}: {:?}", event, command, e),
+        });
+    }
+}
+
+struct WindowHelper {
+    audio: AudioManager,
+    config: Config,
+    layout: Layout,
+    renderer: Box<dyn Renderer>,
+    animations: MuteAnimations,
+    keyboard: Option<KeyboardMode>,
+    trays: Vec<TrayIcon>,
+    conditions: Conditions,
+    osd: Osd,
+    flyout: Flyout,
+    tooltips: Tooltips,
+    monitors: Monitors,
+    drag: Option<Drag>,
+    edit: Option<LayoutEdit>,
+    /// how many of `config.hotkeys` were registered, to unregister on reload
+    bound_hotkeys: usize,
+
+    airpods_available: bool,
+    /// the bluetooth headset as the worker last found it
+    headset: Option<worker::Headset>,
+    /// the devices muted when the session was locked, to unmute on unlock
+    lock_muted: Vec<IMMDevice>,
+    /// whether media was paused when the session was locked, to resume on unlock. set by the
+    /// thread that pauses it
+    lock_paused_media: Arc<AtomicBool>,
+    /// reconnects left to try after resuming, see `reconnect_bluetooth`
+    reconnect_attempts: u32,
+    mute_watch: MuteWatch,
+    headset_buttons: headset::HeadsetButtons,
+    state_watch: events::StateWatch,
+    /// the devices muted for being idle, or `None` while there's been recent input
+    idle_muted: Option<Vec<IMMDevice>>,
+    /// the devices muted when the screensaver started, or `None` while it isn't running
+    screensaver_muted: Option<Vec<IMMDevice>>,
+    focus_override: FocusOverride,
+    lock_mute_paused: bool,
+    /// when the session was locked, for the lock history
+    locked_at: Option<SystemTime>,
+    discord: Option<discord::Discord>,
+    obs: Option<obs::Obs>,
+    /// `None` if the section couldn't be created, which only costs the tools reading it
+    status_block: Option<shared::StatusBlock>,
+    /// why each widget that failed to draw or to read on the last paint did, for its tooltip
+    widget_errors: HashMap<Widget, String>,
+    /// `audio_snapshot` as of the last paint
+    painted_audio: Option<u64>,
+    /// the last paint lost the window's surface, and painting's being retried
+    surface_lost: bool,
+    /// `Some` while the diagnostics overlay is shown
+    diagnostics: Option<metrics::EventRate>,
+    actions: ActionQueue,
+    /// the default output's id as of the last change, for `match_loudness`
+    output_id: Option<String>,
+    /// the old output's volume and offset, waiting for the new output to be ready to match them
+    loudness: Option<(f32, f32)>,
+}
+
+/// in layout edit mode the panel shows a grid and widget bounds, and widgets can be dragged to
+/// reorder them, or dragged with ctrl held to nudge them along the grid
+struct LayoutEdit {
+    dragging: Option<Widget>,
+    /// while nudging, how far into the widget it was picked up
+    nudging: Option<f32>,
+}
+
+/// the panel takes focus in keyboard mode so the arrow keys can move between widgets
+struct KeyboardMode {
+    widget: Widget,
+    /// the window that had focus before, which gets it back when the mode is left with Esc
+    previous: HWND,
+}
+
+#[allow(clippy::too_many_arguments)]
+fn paint(
+    canvas: &mut dyn Canvas,
+    audio: &mut AudioManager,
+    config: &Config,
+    layout: &Layout,
+    animations: &mut MuteAnimations,
+    monitors: &Monitors,
+    focus: Option<Widget>,
+    edit: Option<&LayoutEdit>,
+    errors: &mut HashMap<Widget, String>,
+) -> Result<()> {
+    let now = Instant::now();
+
+    // widgets taken out of the layout don't get the chance to clear theirs
+    errors.retain(|widget, _| layout.rect(*widget).is_some());
+
+    if let Some(edit) = edit {
+        draw_edit_grid(canvas, layout, edit)?;
+    }
+
+    if let Some(rect) = focus.and_then(|widget| layout.rect(widget)) {
+        draw_outline(canvas, layout, rect, FOCUS_COLOR)?;
+    }
+
+    for widget in layout.widgets() {
+        let Some(rect) = layout.rect(widget) else {
+            continue;
+        };
+
+        // a widget whose reading failed has nothing to draw. the poll already logged why
+        if let Some(error) = monitors.errors.get(&widget) {
+            errors.insert(widget, error.clone());
+            let _ = draw_warning(canvas, layout, rect);
+            continue;
+        }
+
+        let drawn = draw_widget(
+            canvas, audio, config, layout, animations, monitors, widget, rect, now,
+        );
+
+        match drawn {
+            Ok(()) => {
+                errors.remove(&widget);
+            }
+            Err(e) => {
+                let error = format!("{:#}", e);
+
+                // logged once rather than on every paint while it keeps failing
+                if errors.get(&widget) != Some(&error) {
+                    metrics::record_error(&e);
+                    log!("error drawing {:?}: {:?}", widget, e);
+                }
+
+                errors.insert(widget, error);
+
+                // the rest of the panel still gets drawn if even this fails
+                let _ = draw_warning(canvas, layout, rect);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// draws one widget. a widget that fails is left with a warning by `paint` rather than stopping
+/// the whole panel from being drawn
+#[allow(clippy::too_many_arguments)]
+fn draw_widget(
+    canvas: &mut dyn Canvas,
+    audio: &mut AudioManager,
+    config: &Config,
+    layout: &Layout,
+    animations: &mut MuteAnimations,
+    monitors: &Monitors,
+    widget: Widget,
+    rect: layout::Rect,
+    now: Instant,
+) -> Result<()> {
+    match widget {
+        Widget::Volume => draw_volume(canvas, layout, rect, audio)?,
+        Widget::Output | Widget::Input => {
+            draw_device_widget(canvas, audio, config, layout, rect, animations, widget, now)?
+        }
+        Widget::Clock => widgets::clock::draw(canvas, layout, rect, &config.clock)?,
+        Widget::Date => widgets::date::draw(canvas, layout, rect, &config.date)?,
+        Widget::Cpu => widgets::cpu::draw(canvas, layout, rect, &config.cpu, &monitors.cpu)?,
+        Widget::Memory => {
+            widgets::memory::draw(canvas, layout, rect, &config.memory, &monitors.memory)?
+        }
+        Widget::Network => {
+            widgets::network::draw(canvas, layout, rect, &config.network, &monitors.network)?
+        }
+        Widget::Wifi => widgets::wifi::draw(canvas, layout, rect, &monitors.wifi)?,
+        Widget::Vpn => widgets::vpn::draw(canvas, layout, rect, &monitors.vpn)?,
+        Widget::Disk => widgets::disk::draw(canvas, layout, rect, &config.disk, &monitors.disk)?,
+        Widget::LockKeys => widgets::locks::draw(canvas, layout, rect, &monitors.locks)?,
+        Widget::NowPlaying => widgets::media::draw(canvas, layout, rect, &monitors.media)?,
+        Widget::Camera => widgets::camera::draw(canvas, layout, rect, &monitors.camera)?,
+        Widget::ScreenCapture => widgets::capture::draw(canvas, layout, rect, &monitors.capture)?,
+        Widget::FocusAssist => widgets::focus::draw(canvas, layout, rect, &monitors.focus)?,
+        Widget::NightLight => {
+            widgets::nightlight::draw(canvas, layout, rect, &monitors.night_light)?
+        }
+        Widget::Brightness => {
+            widgets::brightness::draw(canvas, layout, rect, &monitors.brightness)?
+        }
+        Widget::InputSource => widgets::source::draw(canvas, layout, rect, &monitors.input_source)?,
+        Widget::RefreshRate => {
+            widgets::refresh::draw(canvas, layout, rect, &monitors.refresh_rate)?
+        }
+        Widget::Bluetooth => widgets::bluetooth::draw(canvas, layout, rect, &monitors.bluetooth)?,
+        Widget::Controllers => {
+            widgets::controllers::draw(canvas, layout, rect, &monitors.controllers)?
+        }
+        Widget::Peripherals => {
+            widgets::peripherals::draw(canvas, layout, rect, &monitors.peripherals)?
+        }
+        Widget::Meeting => widgets::meeting::draw(canvas, layout, rect, &monitors.meeting)?,
+        Widget::Uptime => widgets::uptime::draw(canvas, layout, rect)?,
+        Widget::Weather => widgets::weather::draw(canvas, layout, rect, &monitors.weather)?,
+        Widget::Obs => widgets::obs::draw(canvas, layout, rect, &monitors.obs)?,
+        Widget::Command => {
+            widgets::command::draw(canvas, layout, rect, &config.command, &monitors.command)?
+        }
+        Widget::Button(index) => {
+            if let Some(button) = config.buttons.get(index as usize) {
+                let icon = monitors.buttons.get(index as usize).copied().flatten();
+                widgets::button::draw(canvas, layout, rect, button, icon)?
+            }
+        }
+        Widget::Pomodoro => {
+            widgets::pomodoro::draw(canvas, layout, rect, &config.pomodoro, &monitors.pomodoro)?
+        }
+        Widget::MediaPrevious | Widget::MediaPlayPause | Widget::MediaNext => {
+            if let Some(command) = widget.media_command() {
+                widgets::media::draw_button(canvas, layout, rect, command, &monitors.media)?
+            }
+        }
+    }
+
+    Ok(())
+}
+
+const WARNING_COLOR: u32 = 0xffffb900;
+
+/// dark enough to read the diagnostics over whatever's under them
+const DIAGNOSTICS_BACKGROUND: u32 = 0xd0000000;
+
+/// a small triangle with an exclamation mark in the top right corner of a widget that couldn't
+/// be drawn
+fn draw_warning(canvas: &mut dyn Canvas, layout: &Layout, rect: layout::Rect) -> Result<()> {
+    let size = layout.px(14.0);
+    let right = rect.right() - layout.px(3.0);
+    let left = right - size;
+    let top = rect.y + layout.px(3.0);
+    let bottom = top + size;
+    let middle = left + size / 2.0;
+
+    let corners = [(middle, top), (right, bottom), (left, bottom)];
+    for i in 0..corners.len() {
+        let next = corners[(i + 1) % corners.len()];
+        canvas.draw_line(
+            corners[i],
+            next,
+            layout.px(2.0),
+            LineCap::Square,
+            WARNING_COLOR,
+        )?;
+    }
+
+    let text_size = layout.px(10.0);
+    let (width, height) = canvas.measure_text("!", text_size)?;
+    let y = top + (size - height) / 2.0 + layout.px(1.5);
+    canvas.draw_text("!", text_size, middle - width / 2.0, y, WARNING_COLOR)
+}
+
+/// a line of numbers along the top of the panel, over the widgets
+fn draw_diagnostics(canvas: &mut dyn Canvas, layout: &Layout, text: &str) -> Result<()> {
+    let (width, _) = layout.size();
+    let text_size = layout.px(9.0);
+    let (_, height) = canvas.measure_text(text, text_size)?;
+
+    let band = layout::Rect {
+        x: 0.0,
+        y: 0.0,
+        width: width as f32,
+        height: height + layout.px(2.0),
+    };
+
+    canvas.push_clip(band)?;
+    canvas.fill_rect(band, DIAGNOSTICS_BACKGROUND)?;
+    let drawn = canvas.draw_text(text, text_size, layout.px(2.0), layout.px(1.0), 0xffffffff);
+    canvas.pop_clip()?;
+
+    drawn
+}
+
+fn draw_volume(
+    canvas: &mut dyn Canvas,
+    layout: &Layout,
+    rect: layout::Rect,
+    audio: &mut AudioManager,
+) -> Result<()> {
+    // left empty until the worker has the device ready, or if there's no output at all
+    let Some(output) = audio.ready_default(eRender)? else {
+        return Ok(());
+    };
+
+    {
+        let x = rect.right() - layout.px(5.0);
+        let middle = rect.y + rect.height / 2.0;
+
+        let volume = output.levels().volume;
+        let string = format!("{:.0}%", volume * 100.0);
+        let size = layout.px(24.0);
+        let (width, height) = canvas.measure_text(&string, size)?;
+        canvas.draw_text(&string, size, x - width, middle - height / 2.0, TEXT_COLOR)?;
+
+        // the bar runs from 10 to 39 of the 48 high widget
+        let top = rect.y + layout.px(10.0);
+        let bottom = rect.y + layout.px(39.0);
+        let start = bottom - (bottom - top) * volume;
+
+        let bar_x = x + layout.px(4.0);
+        let bar_width = layout.px(8.0);
+        canvas.draw_line(
+            (bar_x, top),
+            (bar_x, bottom),
+            bar_width,
+            LineCap::Square,
+            0xffc0c0c0,
+        )?;
+        canvas.draw_line(
+            (bar_x, start),
+            (bar_x, bottom),
+            bar_width,
+            LineCap::Square,
+            0xff404040,
+        )?;
+    }
+
+    Ok(())
+}
+
+/// draws the output or input device, with the live ring around the input while it's recording
+#[allow(clippy::too_many_arguments)]
+fn draw_device_widget(
+    canvas: &mut dyn Canvas,
+    audio: &mut AudioManager,
+    config: &Config,
+    layout: &Layout,
+    rect: layout::Rect,
+    animations: &mut MuteAnimations,
+    widget: Widget,
+    now: Instant,
+) -> Result<()> {
+    let flow = widget.flow().context("not a device widget")?;
+
+    if !audio.has_default(flow)? {
+        let size = layout.px(layout::ICON_SIZE);
+        let left = rect.x + layout.px(layout::PADDING);
+        let top = rect.y + layout.px(layout::PADDING);
+        return canvas.draw_icon(audio.no_device_icon(flow), left, top, size);
+    }
+
+    // left empty until the worker has the device ready
+    let Some(device) = audio.ready_default(flow)? else {
+        return Ok(());
+    };
+    let muted = device.levels().muted;
+
+    if widget == Widget::Input {
+        let live = config.mic_live_warning && !muted && audio.is_capturing(flow)?;
+
+        if let Some(opacity) = animations.update_live(live, now) {
+            let alpha = (opacity * 255.0) as u32;
+            let ring = layout::Rect {
+                x: rect.x + layout.px(6.0),
+                y: rect.y + layout.px(2.0),
+                width: layout.px(36.0),
+                height: layout.px(44.0),
+            };
+
+            canvas.draw_ellipse(ring, layout.px(3.0), alpha << 24 | 0xff0000)?;
+        }
+    }
+
+    let device = audio.default_device(flow)?;
+    let frame = animations.update(widget, muted, now);
+    draw_device(canvas, layout, rect, device, frame)
+}
+
+const FOCUS_COLOR: u32 = 0xff0078d7;
+
+/// shows a grid behind the widgets and outlines each of them, highlighting the one being moved
+fn draw_edit_grid(canvas: &mut dyn Canvas, layout: &Layout, edit: &LayoutEdit) -> Result<()> {
+    let (width, height) = layout.size();
+    let (width, height) = (width as f32, height as f32);
+
+    let background = layout::Rect {
+        x: 0.0,
+        y: 0.0,
+        width,
+        height,
+    };
+    canvas.fill_rect(background, 0xc0ffffff)?;
+
+    let spacing = layout.px(layout::GRID_SPACING);
+    let grid = 0x40000000;
+
+    let mut x = spacing;
+    while x < width {
+        canvas.draw_line((x, 0.0), (x, height), 1.0, LineCap::Square, grid)?;
+        x += spacing;
+    }
+
+    let mut y = spacing;
+    while y < height {
+        canvas.draw_line((0.0, y), (width, y), 1.0, LineCap::Square, grid)?;
+        y += spacing;
+    }
+
+    for widget in layout.widgets() {
+        if let Some(rect) = layout.rect(widget) {
+            let color = if edit.dragging == Some(widget) {
+                FOCUS_COLOR
+            } else {
+                0xff808080
+            };
+
+            draw_outline(canvas, layout, rect, color)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// outlines a widget just inside its bounds
+fn draw_outline(
+    canvas: &mut dyn Canvas,
+    layout: &Layout,
+    rect: layout::Rect,
+    color: u32,
+) -> Result<()> {
+    let inset = layout.px(2.0);
+
+    let left = rect.x + inset;
+    let top = rect.y + inset;
+    let right = rect.right() - inset;
+    let bottom = rect.bottom() - inset;
+
+    let corners = [(left, top), (right, top), (right, bottom), (left, bottom)];
+    for i in 0..corners.len() {
+        let next = corners[(i + 1) % corners.len()];
+        canvas.draw_line(corners[i], next, layout.px(2.0), LineCap::Square, color)?;
+    }
+
+    Ok(())
+}
+
+/// draws a device's icon, crossed out when it's muted. the cross is drawn in stroke by stroke
+/// as `frame.cross` goes from 0 to 1.
+fn draw_device(
+    canvas: &mut dyn Canvas,
+    layout: &Layout,
+    rect: layout::Rect,
+    device: &AudioDevice,
+    frame: MuteFrame,
+) -> Result<()> {
+    let icon_size = layout.px(layout::ICON_SIZE);
+    let left = rect.x + layout.px(layout::PADDING);
+    let top = rect.y + layout.px(layout::PADDING);
+    let right = left + icon_size;
+    let bottom = top + icon_size;
+
+    let size = icon_size * frame.scale;
+    let offset = (size - icon_size) / 2.0;
+    canvas.draw_icon(device.icon(), left - offset, top - offset, size)?;
+
+    let red = 0xffff0000;
+    let strokes = [
+        ((left, top), (right, bottom)),
+        ((right, top), (left, bottom)),
+    ];
+
+    for (i, (from, to)) in strokes.into_iter().enumerate() {
+        let progress = (frame.cross * 2.0 - i as f32).clamp(0.0, 1.0);
+        if progress <= 0.0 {
+            continue;
+        }
+
+        let end = (
+            from.0 + (to.0 - from.0) * progress,
+            from.1 + (to.1 - from.1) * progress,
+        );
+
+        canvas.draw_line(from, end, layout.px(8.0), LineCap::Triangle, red)?;
+    }
+
+    Ok(())
+}
+
+impl WindowHelper {
+    fn on_paint(&mut self, hwnd: HWND) -> Result<()> {
+        let focus = self.keyboard.as_ref().map(|mode| mode.widget);
+        let overlay = self.diagnostics_text();
+
+        let WindowHelper {
+            audio,
+            config,
+            layout,
+            renderer,
+            animations,
+            monitors,
+            edit,
+            widget_errors,
+            ..
+        } = self;
+
+        let rendered = renderer.render(hwnd, &mut |canvas| {
+            paint(
+                canvas,
+                audio,
+                config,
+                layout,
+                animations,
+                monitors,
+                focus,
+                edit.as_ref(),
+                widget_errors,
+            )?;
+
+            match &overlay {
+                Some(text) => draw_diagnostics(canvas, layout, text),
+                None => Ok(()),
+            }
+        });
+
+        match rendered {
+            // tried again until it's back, rather than leaving whatever was last shown
+            Err(e) if e.is::<SurfaceLost>() => {
+                if !std::mem::replace(&mut self.surface_lost, true) {
+                    log!("painting: {:?}", e);
+                }
+
+                self.painted_audio = None;
+                unsafe { SetTimer(Some(hwnd), SURFACE_RETRY_TIMER, SURFACE_RETRY_MS, None) };
+                return Ok(());
+            }
+            rendered => rendered?,
+        }
+
+        if std::mem::replace(&mut self.surface_lost, false) {
+            log!("painting again after the surface was lost");
+        }
+
+        // keep repainting until every transition has finished, and while a title scrolls
+        unsafe {
+            if self.animations.is_animating() {
+                SetTimer(
+                    Some(hwnd),
+                    ANIMATION_TIMER,
+                    animation::FRAME_INTERVAL_MS,
+                    None,
+                );
+            } else {
+                let _ = KillTimer(Some(hwnd), ANIMATION_TIMER);
+            }
+
+            let scrolling = self.monitors.media.is_scrolling()
+                && self
+                    .layout
+                    .widgets()
+                    .any(|widget| widget == Widget::NowPlaying);
+
+            if scrolling {
+                let interval = widgets::media::SCROLL_INTERVAL_MS;
+                SetTimer(Some(hwnd), SCROLL_TIMER, interval, None);
+            } else {
+                let _ = KillTimer(Some(hwnd), SCROLL_TIMER);
+            }
+
+            if let Some(step) = self.animations.next_live_step(Instant::now()) {
+                SetTimer(Some(hwnd), LIVE_TIMER, step, None);
+            } else {
+                let _ = KillTimer(Some(hwnd), LIVE_TIMER);
+            }
+
+            if let Some(tick) = self.next_clock_tick() {
+                SetTimer(Some(hwnd), CLOCK_TIMER, tick, None);
+            } else {
+                let _ = KillTimer(Some(hwnd), CLOCK_TIMER);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// passes the default devices' state on to everything outside the panel that follows it,
+    /// then repaints if anything shown has changed. run for each burst of notifications rather
+    /// than from painting, so webhooks and the rest don't depend on when the panel repaints.
+    fn on_audio_changed(&mut self, hwnd: HWND) -> Result<()> {
+        let trays = self.update_trays();
+        let webhooks = self.post_mute_changes();
+        let events = self.publish_changes();
+        let discord = self.sync_discord();
+        let obs = self.sync_obs();
+        let status_block = self.publish_status_block();
+        self.repaint_if_changed(hwnd);
+
+        trays
+            .and(webhooks)
+            .and(events)
+            .and(discord)
+            .and(obs)
+            .and(status_block)
+    }
+
+    /// passes the input's mute state on to the obs source, which only hears about changes
+    fn sync_obs(&mut self) -> Result<()> {
+        let Some(obs) = &mut self.obs else {
+            return Ok(());
+        };
+
+        if let Some(device) = self.audio.ready_default(eCapture)? {
+            obs.sync(device.levels().muted);
+        }
+
+        Ok(())
+    }
+
+    /// passes the input's mute state on to discord, which only hears about changes
+    fn sync_discord(&mut self) -> Result<()> {
+        let Some(discord) = &mut self.discord else {
+            return Ok(());
+        };
+
+        if let Some(device) = self.audio.ready_default(eCapture)? {
+            discord.sync(device.levels().muted);
+        }
+
+        Ok(())
+    }
+
+    /// matches the input's mute state to discord's after it's changed in discord
+    fn on_discord_mute(&mut self, muted: bool) -> Result<()> {
+        let Some(discord) = &mut self.discord else {
+            return Ok(());
+        };
+
+        discord.on_mute(muted);
+
+        let device = self.audio.default_device(eCapture)?;
+
+        if device.is_mute()? != muted {
+            self.mute_watch.expect(MuteSource::Discord);
+            device.set_mute(muted)?;
+        }
+
+        Ok(())
+    }
+
+    /// posts the mute webhooks for default devices whose mute state changed since the last
+    /// check, which every volume notification leads to, and passes the app's own changes to the
+    /// input on to conferencing apps' mute hotkeys
+    fn post_mute_changes(&mut self) -> Result<()> {
+        let source = self.mute_watch.take_source();
+
+        if self.config.webhooks.mute.is_empty() && self.config.meeting.mute_keys.is_empty() {
+            return Ok(());
+        }
+
+        for flow in [eRender, eCapture] {
+            let Some(device) = self.audio.ready_default(flow)? else {
+                continue;
+            };
+            let muted = device.levels().muted;
+
+            if self.mute_watch.update(flow, muted) {
+                let event = webhook::Event::Mute {
+                    flow,
+                    muted,
+                    source,
+                };
+                webhook::fire(&self.config.webhooks, event);
+
+                // changes from outside, which could be the conferencing app itself, are left
+                // alone
+                if flow == eCapture && source != MuteSource::External {
+                    widgets::meeting::press_mute_keys(&self.config.meeting)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// sends the default devices' changes to whatever's subscribed to events
+    fn publish_changes(&mut self) -> Result<()> {
+        if !events::has_subscribers() {
+            // so nothing's published for what changed while nobody was listening
+            self.state_watch = events::StateWatch::default();
+            return Ok(());
+        }
+
+        for flow in [eRender, eCapture] {
+            // nothing's published for a flow without a device until one turns up
+            if !self.audio.has_default(flow)? {
+                continue;
+            }
+
+            let id = self.audio.default_id(flow)?;
+            let Some(device) = self.audio.ready_default(flow)? else {
+                continue;
+            };
+
+            let levels = device.levels();
+            let state = events::DeviceState {
+                id,
+                name: device.name.clone(),
+                volume: (levels.volume * 100.0).round() as u32,
+                muted: levels.muted,
+            };
+
+            for event in self.state_watch.update(flow, state) {
+                events::publish(event);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// a hash of everything about the default devices that's shown or published: which
+    /// they are, their names, icons and mute states, their volumes to the percent, and whether
+    /// the input's recording
+    fn audio_snapshot(&mut self) -> Result<u64> {
+        let mut hasher = DefaultHasher::new();
+
+        for flow in [eRender, eCapture] {
+            let has_default = self.audio.has_default(flow)?;
+            has_default.hash(&mut hasher);
+
+            if !has_default {
+                continue;
+            }
+
+            self.audio.default_id(flow)?.hash(&mut hasher);
+
+            if flow == eCapture && self.config.mic_live_warning {
+                self.audio.is_capturing(flow)?.hash(&mut hasher);
+            }
+
+            if let Some(device) = self.audio.ready_default(flow)? {
+                device.name.hash(&mut hasher);
+                (device.icon().0 as isize).hash(&mut hasher);
+                let levels = device.levels();
+                levels.muted.hash(&mut hasher);
+                ((levels.volume * 100.0).round() as u32).hash(&mut hasher);
+            }
+        }
+
+        Ok(hasher.finish())
+    }
+
+    /// repaints if the audio devices look any different from when this last repainted. most
+    /// notifications change nothing that's shown, e.g. another app's volume or a session that
+    /// isn't capturing. the snapshot is made from what the notifications left cached, so this
+    /// asks the devices nothing.
+    fn repaint_if_changed(&mut self, hwnd: HWND) {
+        // if it can't be told, the paint will say why
+        let snapshot = self.audio_snapshot().ok();
+
+        if snapshot.is_none() || snapshot != self.painted_audio {
+            self.painted_audio = snapshot;
+
+            unsafe {
+                let _ = InvalidateRect(Some(hwnd), None, true);
+            }
+        }
+    }
+
+    /// writes the default devices to the shared status block for the tools reading it
+    fn publish_status_block(&mut self) -> Result<()> {
+        if self.status_block.is_none() {
+            return Ok(());
+        }
+
+        let mut devices = vec![];
+        for flow in [eRender, eCapture] {
+            // published as an empty name at zero volume, so readers can tell
+            if !self.audio.has_default(flow)? {
+                devices.push(shared::Device::new("", 0.0, false));
+                continue;
+            }
+
+            let Some(device) = self.audio.ready_default(flow)? else {
+                return Ok(());
+            };
+            let levels = device.levels();
+            devices.push(shared::Device::new(
+                &device.name,
+                levels.volume,
+                levels.muted,
+            ));
+        }
+
+        let status = shared::Status {
+            flags: match self.locked_at {
+                Some(_) => shared::FLAG_LOCKED,
+                None => 0,
+            },
+            output: devices[0],
+            input: devices[1],
+        };
+
+        match &mut self.status_block {
+            Some(block) => block.publish(status),
+            None => Ok(()),
+        }
+    }
+
+    /// takes the readings that are due and repaints if there were any
+    fn poll(&mut self, hwnd: HWND) -> Result<()> {
+        let polled = self.monitors.poll(self.layout.configured(), &self.config);
+
+        for (widget, e) in &polled.failed {
+            metrics::record_error(e);
+            log!("error reading {:?}: {:?}", widget, e);
+        }
+
+        if self.config.notifications.battery_low {
+            for (device, level) in &self.monitors.peripherals.levels {
+                if self.conditions.peripheral_battery(device, *level)
+                    && let Some(level) = level
+                {
+                    let body = format!("{} is at {}%", device, level);
+                    notifications::show("Device battery low", &body)?;
+                }
+            }
+        }
+
+        if let Some(started) = self.monitors.meeting.transition.take() {
+            let config = &self.config.meeting;
+            let actions = if started {
+                &config.on_start
+            } else {
+                &config.on_end
+            };
+
+            for action in actions.clone() {
+                self.queue(hwnd, Queued::new(Some(Widget::Meeting), action, true));
+            }
+
+            match (started, self.config.meeting.focus_assist) {
+                (true, Some(mode)) => self.focus_override.start(FocusReason::Call, mode)?,
+                (false, _) => self.focus_override.end(FocusReason::Call)?,
+                _ => {}
+            }
+        }
+
+        if self.layout.set_hidden(self.monitors.hidden()) {
+            self.resize(hwnd, None)?;
+
+            // keeps a preset placement lined up with its edge
+            self.apply_position(hwnd)?;
+        }
+
+        unsafe {
+            match polled.next {
+                Some(next) => {
+                    SetTimer(Some(hwnd), POLL_TIMER, next.as_millis() as u32, None);
+                }
+                None => {
+                    let _ = KillTimer(Some(hwnd), POLL_TIMER);
+                }
+            }
+
+            if polled.changed {
+                let _ = InvalidateRect(Some(hwnd), None, true);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// milliseconds until one of the widgets that keep time next changes, if any are shown
+    fn next_clock_tick(&self) -> Option<u32> {
+        self.layout
+            .widgets()
+            .filter_map(|widget| match widget {
+                Widget::Clock => Some(self.config.clock.next_tick()),
+                Widget::Date => Some(self.config.date.next_tick()),
+                Widget::Uptime => Some(widgets::uptime::next_tick()),
+                Widget::Pomodoro => {
+                    let config = &self.config.pomodoro;
+                    self.monitors.pomodoro.next_tick(config, Instant::now())
+                }
+                _ => None,
+            })
+            .min()
+    }
+
+    /// announces the end of a pomodoro phase, once it's over
+    fn advance_pomodoro(&mut self) -> Result<()> {
+        let config = &self.config.pomodoro;
+        let Some(ended) = self.monitors.pomodoro.advance(config, Instant::now()) else {
+            return Ok(());
+        };
+
+        config.play_sound();
+
+        let (title, minutes) = match ended {
+            Phase::Work => ("Time for a break", config.work_minutes),
+            Phase::Break => ("Back to work", config.break_minutes),
+        };
+        let body = format!(
+            "{} minutes of {} done",
+            minutes,
+            ended.label().to_lowercase()
+        );
+        notifications::show(title, &body)
+    }
+
+    /// refreshes the tray icons, which also runs on every audio change so they follow default
+    /// device changes along with the panel
+    fn update_trays(&mut self) -> Result<()> {
+        for tray in &mut self.trays {
+            let flow = tray.device().flow();
+
+            if !self.audio.has_default(flow)? {
+                tray.update(self.audio.no_device_icon(flow), false)?;
+                continue;
+            }
+
+            let Some(device) = self.audio.ready_default(flow)? else {
+                continue;
+            };
+
+            tray.update(device.icon(), device.levels().muted)?;
+        }
+
+        Ok(())
+    }
+
+    /// shows the status flyout by a tray icon and refreshes the tooltips screen readers use;
+    /// only done when an icon is hovered, since building the text touches every device
+    fn show_tray_flyout(&mut self, anchor: POINT) -> Result<()> {
+        let lines = self.status_lines()?;
+        let tip = lines.join("\n");
+
+        for tray in &mut self.trays {
+            tray.set_tip(&tip)?;
+        }
+
+        self.flyout.show(anchor, &lines)
+    }
+
+    /// a summary of both default devices and the bluetooth headset, one line each
+    fn status_lines(&mut self) -> Result<Vec<String>> {
+        let mut lines = vec![];
+
+        for (label, flow) in [("Output", eRender), ("Input", eCapture)] {
+            if !self.audio.has_default(flow)? {
+                lines.push(format!("{}: no device", label));
+                continue;
+            }
+
+            let Some(device) = self.audio.ready_default(flow)? else {
+                continue;
+            };
+
+            let levels = device.levels();
+            let mut line = format!("{}: {} {:.0}%", label, device.name, levels.volume * 100.0);
+            if levels.muted {
+                line += ", muted";
+            }
+
+            lines.push(line);
+        }
+
+        if let Some(headset) = &self.headset {
+            let status = match headset.connected {
+                true => "connected",
+                false => "disconnected",
+            };
+
+            lines.push(format!("{}: {}", headset.name, status));
+        }
+
+        Ok(lines)
+    }
+
+    /// adds and removes tray icons to match the config
+    fn apply_tray_config(&mut self, hwnd: HWND) -> Result<()> {
+        let wanted = &self.config.tray_icons;
+        self.trays.retain(|tray| wanted.contains(&tray.device()));
+
+        for &device in wanted {
+            if !self.trays.iter().any(|tray| tray.device() == device) {
+                self.trays.push(TrayIcon::new(hwnd, device, WM_TRAY));
+            }
+        }
+
+        self.update_trays()
+    }
+
+    fn register_hotkeys(&mut self, hwnd: HWND) -> Result<()> {
+        Hotkey::unregister(hwnd, KEYBOARD_HOTKEY);
+        for i in 0..self.bound_hotkeys {
+            Hotkey::unregister(hwnd, BINDING_HOTKEYS + i as i32);
+        }
+
+        // one hotkey that can't be registered shouldn't stop the rest from working
+        let mut conflicts = vec![];
+        let mut seen = vec![];
+
+        let bindings = self.config.hotkeys.iter().enumerate();
+        let bindings = bindings.map(|(i, binding)| (BINDING_HOTKEYS + i as i32, binding.keys));
+        let keyboard = self
+            .config
+            .keyboard_hotkey
+            .map(|keys| (KEYBOARD_HOTKEY, keys));
+
+        for (id, keys) in bindings.chain(keyboard) {
+            if seen.contains(&keys) {
+                conflicts.push(format!("{} is bound more than once", keys));
+                continue;
+            }
+
+            seen.push(keys);
+
+            if let Err(e) = keys.register(hwnd, id) {
+                log!("failed to register hotkey {}: {:?}", keys, e);
+                conflicts.push(e.to_string());
+            }
+        }
+
+        self.bound_hotkeys = self.config.hotkeys.len();
+
+        if !conflicts.is_empty() && self.config.notifications.hotkey_conflict {
+            notifications::show("Hotkey conflict", &conflicts.join("\n"))?;
+        }
+
+        Ok(())
+    }
+
+    /// shows the default `flow` device's volume and mute state in the osd
+    fn show_osd(&mut self, hwnd: HWND, flow: EDataFlow) -> Result<()> {
+        if !self.config.osd.enabled {
+            return Ok(());
+        }
+
+        let device = self.audio.default_device(flow)?;
+
+        let state = OsdState {
+            icon: device.icon(),
+            volume: device.volume()?,
+            muted: device.is_mute()?,
+        };
+
+        self.osd.show(hwnd, self.config.osd.position, &state)?;
+
+        // restarted by every change, so holding a hotkey keeps the osd up
+        unsafe {
+            SetTimer(Some(hwnd), OSD_TIMER, self.config.osd.timeout_ms, None);
+        }
+
+        Ok(())
+    }
+
+    /// lays the widgets out for `dpi` and resizes the window to fit them. `suggested` is the rect
+    /// that comes with WM_DPICHANGED, which keeps the window where it was relative to the cursor
+    /// when it's dragged to another monitor.
+    fn apply_dpi(&mut self, hwnd: HWND, dpi: u32, suggested: Option<&RECT>) -> Result<()> {
+        self.layout = self.layout.rescaled(dpi as f32 / 96.0);
+
+        let at = suggested.map(|rect| POINT {
+            x: rect.left,
+            y: rect.top,
+        });
+
+        self.resize(hwnd, at)
+    }
+
+    /// fits the window to the layout, moving its top-left corner to `at` if given. the layout
+    /// decides the size, since it's rounded differently from a rect scaled by windows.
+    fn resize(&mut self, hwnd: HWND, at: Option<POINT>) -> Result<()> {
+        self.tooltips.update(hwnd, &self.layout);
+        let (width, height) = self.layout.size();
+
+        let mut flags = SWP_NOZORDER | SWP_NOACTIVATE;
+        if at.is_none() {
+            flags |= SWP_NOMOVE;
+        }
+
+        let at = at.unwrap_or_default();
+
+        unsafe {
+            SetWindowPos(hwnd, None, at.x, at.y, width, height, flags)?;
+
+            let _ = InvalidateRect(Some(hwnd), None, true);
+        }
+
+        Ok(())
+    }
+
+    /// what the diagnostics overlay shows, if it's shown
+    fn diagnostics_text(&mut self) -> Option<String> {
+        let per_second = self.diagnostics.as_mut()?.per_second();
+        let metrics = metrics::snapshot();
+
+        Some(format!(
+            "{:.1} ms  {:.0} events/s  {} devices  {}",
+            metrics.last_paint_time.as_secs_f64() * 1000.0,
+            per_second,
+            self.audio.devices.len(),
+            metrics.last_error.as_deref().unwrap_or("no errors"),
+        ))
+    }
+
+    fn toggle_diagnostics(&mut self, hwnd: HWND) {
+        unsafe {
+            if self.diagnostics.take().is_none() {
+                self.diagnostics = Some(metrics::EventRate::new());
+                SetTimer(Some(hwnd), DIAGNOSTICS_TIMER, DIAGNOSTICS_INTERVAL_MS, None);
+            } else {
+                let _ = KillTimer(Some(hwnd), DIAGNOSTICS_TIMER);
+            }
+
+            let _ = InvalidateRect(Some(hwnd), None, true);
+        }
+    }
+
+    /// enters layout edit mode, or leaves it and saves the new widget order and offsets
+    fn toggle_edit_layout(&mut self, hwnd: HWND) -> Result<()> {
+        if self.edit.take().is_some() {
+            self.config.widgets = self.layout.configured().collect();
+            self.config.widget_offsets = self.layout.offsets().to_vec();
+            Config::set("widgets", &self.config.widgets)?;
+            Config::set("widget_offsets", &self.config.widget_offsets)?;
+        } else {
+            self.edit = Some(LayoutEdit {
+                dragging: None,
+                nudging: None,
+            });
+        }
+
+        unsafe {
+            let _ = InvalidateRect(Some(hwnd), None, true);
+        }
+
+        Ok(())
+    }
+
+    /// picks up the widget under `point` in edit mode, to nudge it rather than reorder it if
+    /// `nudge`. returns false if not in edit mode.
+    fn start_edit_drag(&mut self, hwnd: HWND, point: POINT, nudge: bool) -> Result<bool> {
+        let Some(edit) = &mut self.edit else {
+            return Ok(false);
+        };
+
+        edit.dragging = self.layout.hit_test(point.x, point.y);
+        edit.nudging = match (edit.dragging.and_then(|w| self.layout.rect(w)), nudge) {
+            (Some(rect), true) => Some(point.x as f32 - rect.x),
+            _ => None,
+        };
+
+        unsafe {
+            SetCapture(hwnd);
+            let _ = InvalidateRect(Some(hwnd), None, true);
+        }
+
+        Ok(true)
+    }
+
+    fn on_mouse_move(&mut self, hwnd: HWND, point: POINT) -> Result<()> {
+        if let Some(drag) = &self.drag {
+            drag.update(hwnd)?;
+        }
+
+        if let Some(LayoutEdit {
+            dragging: Some(widget),
+            nudging,
+        }) = self.edit
+        {
+            match nudging {
+                Some(grab) => {
+                    if self.layout.nudge_widget(widget, point.x as f32 - grab) {
+                        self.resize(hwnd, None)?;
+                    }
+                }
+                None => {
+                    self.layout.move_widget(widget, point.x as f32);
+                    self.tooltips.update(hwnd, &self.layout);
+                }
+            }
+
+            unsafe {
+                let _ = InvalidateRect(Some(hwnd), None, true);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn on_button_up(&mut self, hwnd: HWND) -> Result<()> {
+        self.finish_drag(hwnd)?;
+
+        if let Some(edit) = &mut self.edit
+            && edit.dragging.take().is_some()
+        {
+            unsafe {
+                ReleaseCapture()?;
+                let _ = InvalidateRect(Some(hwnd), None, true);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// starts moving the panel with the mouse, returning false if the layout is locked
+    fn start_drag(&mut self, hwnd: HWND) -> Result<bool> {
+        if self.config.lock_layout {
+            return Ok(false);
+        }
+
+        self.drag = Some(Drag::start(hwnd)?);
+        Ok(true)
+    }
+
+    fn finish_drag(&mut self, hwnd: HWND) -> Result<()> {
+        if let Some(drag) = self.drag.take() {
+            let position = drag.finish(hwnd)?;
+            self.save_position(position)?;
+        }
+
+        Ok(())
+    }
+
+    /// saves where the panel was dragged to, which replaces any preset
+    fn save_position(&mut self, position: Position) -> Result<()> {
+        self.config.position = position;
+        self.config.placement = None;
+
+        Config::set("position", position)?;
+        Config::set("placement", None::<Preset>)
+    }
+
+    /// moves the panel to its preset if it has one, or its configured position
+    fn apply_position(&self, hwnd: HWND) -> Result<()> {
+        let position = match self.config.placement {
+            Some(preset) => preset.position(hwnd)?,
+            None => self.config.position,
+        };
+
+        placement::move_to(hwnd, position)
+    }
+
+    fn toggle_keyboard_mode(&mut self, hwnd: HWND) -> Result<()> {
+        if self.keyboard.is_some() {
+            return self.leave_keyboard_mode(hwnd);
+        }
+
+        let widget = self.layout.widgets().next().context("no widgets")?;
+
+        unsafe {
+            let previous = GetForegroundWindow();
+
+            // allowed because we just received the hotkey
+            if !SetForegroundWindow(hwnd).as_bool() {
+                bail!("failed to take focus");
+            }
+
+            self.keyboard = Some(KeyboardMode { widget, previous });
+            let _ = InvalidateRect(Some(hwnd), None, true);
+        }
+
+        Ok(())
+    }
+
+    /// gives focus back to whichever window had it before keyboard mode was entered
+    fn leave_keyboard_mode(&mut self, hwnd: HWND) -> Result<()> {
+        if let Some(mode) = self.keyboard.take() {
+            unsafe {
+                let _ = SetForegroundWindow(mode.previous);
+                let _ = InvalidateRect(Some(hwnd), None, true);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// handles a key press in keyboard mode, returning the widget to invoke if there is one
+    fn on_key(&mut self, hwnd: HWND, key: VIRTUAL_KEY) -> Result<Option<Widget>> {
+        let Some(mode) = &mut self.keyboard else {
+            return Ok(None);
+        };
+
+        let widgets: Vec<_> = self.layout.widgets().collect();
+        let index = widgets.iter().position(|w| *w == mode.widget).unwrap_or(0);
+
+        match key {
+            VK_LEFT | VK_UP => {
+                mode.widget = widgets[(index + widgets.len() - 1) % widgets.len()];
+            }
+            VK_RIGHT | VK_DOWN | VK_TAB => {
+                mode.widget = widgets[(index + 1) % widgets.len()];
+            }
+            VK_RETURN | VK_SPACE => return Ok(Some(mode.widget)),
+            VK_ESCAPE => self.leave_keyboard_mode(hwnd)?,
+            _ => return Ok(None),
+        }
+
+        unsafe {
+            let _ = InvalidateRect(Some(hwnd), None, true);
+        }
+
+        Ok(None)
+    }
+
+    /// the lock policy for `session`, which may be a remote desktop one with its own
+    fn lock_config(&self, session: u32) -> LockConfig {
+        let remote = match &self.config.remote_lock {
+            Some(remote) => remote,
+            None => return self.config.lock.clone(),
+        };
+
+        match session::is_remote(session) {
+            Ok(true) => remote.clone(),
+            Ok(false) => self.config.lock.clone(),
+            Err(e) => {
+                log!(
+                    "couldn't tell whether session {} is remote: {:?}",
+                    session,
+                    e
+                );
+                self.config.lock.clone()
+            }
+        }
+    }
+
+    fn on_lock(&mut self, hwnd: HWND, session: u32) -> Result<()> {
+        let lock = self.lock_config(session);
+        if !lock.other_sessions && !session::is_own(session)? {
+            return Ok(());
+        }
+
+        webhook::fire(&self.config.webhooks, webhook::Event::Lock);
+        events::publish(serde_json::json!({ "event": "lock" }));
+        run_session_commands("lock", &self.config.session_commands.lock);
+        self.locked_at = Some(SystemTime::now());
+        // nothing's repainted for the lock itself
+        self.publish_status_block()?;
+
+        if lock.displays_off {
+            // DefWindowProc powers the displays down, 2 meaning off rather than low power
+            unsafe {
+                PostMessageA(
+                    Some(hwnd),
+                    WM_SYSCOMMAND,
+                    WPARAM(SC_MONITORPOWER as usize),
+                    LPARAM(2),
+                )?
+            };
+        }
+
+        if let Some(mode) = lock.focus_assist {
+            self.focus_override.start(FocusReason::Lock, mode)?;
+        }
+
+        if self.lock_mute_paused {
+            return Ok(());
+        }
+
+        let muted = self.mute_devices(MuteSource::Lock, lock.mute, lock.all_devices);
+        self.lock_muted.extend(muted);
+
+        if lock.pause_media {
+            self.monitors
+                .media
+                .pause_if_playing(&self.lock_paused_media);
+        }
+
+        Ok(())
+    }
+
+    fn on_unlock(&mut self, hwnd: HWND, session: u32) -> Result<()> {
+        let lock = self.lock_config(session);
+        if !lock.other_sessions && !session::is_own(session)? {
+            return Ok(());
+        }
+
+        webhook::fire(&self.config.webhooks, webhook::Event::Unlock);
+        events::publish(serde_json::json!({ "event": "unlock" }));
+        run_session_commands("unlock", &self.config.session_commands.unlock);
+        self.record_away()?;
+        self.publish_status_block()?;
+
+        // the ones that can't be unmuted, e.g. because they've gone while locked, are tried again
+        // on the next unlock
+        let muted = std::mem::take(&mut self.lock_muted);
+        self.lock_muted = self.unmute_devices(MuteSource::Lock, muted);
+        self.focus_override.end(FocusReason::Lock)?;
+
+        if let Some(name) = &lock.output_device {
+            self.restore_default_device(eRender, name)?;
+        }
+
+        if let Some(name) = &lock.input_device {
+            self.restore_default_device(eCapture, name)?;
+        }
+
+        if self.lock_paused_media.swap(false, Ordering::AcqRel) && lock.resume_media {
+            self.monitors.media.send(hwnd, MediaCommand::Play);
+        }
+
+        Ok(())
+    }
+
+    /// logs how long the session was locked for, and says so if it was a while
+    fn record_away(&mut self) -> Result<()> {
+        // e.g. the first unlock after starting while locked
+        let Some(locked_at) = self.locked_at.take() else {
+            return Ok(());
+        };
+
+        let away = history::Away::new(locked_at, SystemTime::now());
+        let config = &self.config.lock_history;
+
+        if config.record {
+            history::record(away)?;
+        }
+
+        let minimum = Duration::from_secs(config.show_after_minutes as u64 * 60);
+        if config.show_away && away.duration() >= minimum {
+            let body = format!("Away {}", history::describe(away.duration()));
+            notifications::show("Welcome back", &body)?;
+        }
+
+        Ok(())
+    }
+
+    /// mutes the devices `mute` covers, returning the ones that weren't muted already so they
+    /// can be unmuted later. one that fails is logged and skipped, so it doesn't stop the rest
+    fn mute_devices(
+        &mut self,
+        source: MuteSource,
+        mute: LockMute,
+        all_devices: bool,
+    ) -> Vec<IMMDevice> {
+        self.mute_watch.expect(source);
+        let mut muted = vec![];
+
+        for &flow in mute.flows() {
+            let endpoints = match all_devices {
+                true => self.audio.active_devices(flow),
+                false => self
+                    .audio
+                    .get_default_device(flow)
+                    .map(|device| vec![device]),
+            };
+
+            let endpoints = match endpoints {
+                Ok(endpoints) => endpoints,
+                Err(e) => {
+                    log!("couldn't find the devices to mute: {:?}", e);
+                    continue;
+                }
+            };
+
+            for endpoint in endpoints {
+                match self.set_device_mute(&endpoint, true) {
+                    Ok(true) => muted.push(endpoint),
+                    Ok(false) => {}
+                    Err(e) => log!("couldn't mute a device: {:?}", e),
+                }
+            }
+        }
+
+        muted
+    }
+
+    /// unmutes what `mute_devices` muted, carrying on past any that fail, and returns those
+    fn unmute_devices(&mut self, source: MuteSource, devices: Vec<IMMDevice>) -> Vec<IMMDevice> {
+        self.mute_watch.expect(source);
+
+        devices
+            .into_iter()
+            .filter(|endpoint| match self.set_device_mute(endpoint, false) {
+                Ok(_) => false,
+                Err(e) => {
+                    log!("couldn't unmute a device: {:?}", e);
+                    true
+                }
+            })
+            .collect()
+    }
+
+    /// returns whether the device's mute state had to be changed
+    fn set_device_mute(&mut self, endpoint: &IMMDevice, muted: bool) -> Result<bool> {
+        let id = audio::id(endpoint)?;
+        let device = self.audio.get_device(endpoint).context(id.clone())?;
+
+        if device.is_mute().context(id.clone())? == muted {
+            return Ok(false);
+        }
+
+        device.set_mute(muted).context(id)?;
+        Ok(true)
+    }
+
+    fn apply_activity_config(&mut self, hwnd: HWND) {
+        unsafe {
+            if self.config.idle.timeout().is_some() || self.config.lock.screensaver {
+                SetTimer(Some(hwnd), ACTIVITY_TIMER, ACTIVITY_CHECK_MS, None);
+            } else {
+                let _ = KillTimer(Some(hwnd), ACTIVITY_TIMER);
+            }
+        }
+    }
+
+    /// mutes once there's been no input for the configured time, and unmutes on the next input
+    fn check_idle(&mut self) -> Result<()> {
+        let Some(timeout) = self.config.idle.timeout() else {
+            return Ok(());
+        };
+
+        let idle = session::idle_time()? >= timeout;
+
+        if idle && self.idle_muted.is_none() {
+            log!("idle, muting");
+            let muted = self.mute_devices(MuteSource::Idle, self.config.idle.mute, false);
+            self.idle_muted = Some(muted);
+        } else if !idle && let Some(muted) = self.idle_muted.take() {
+            log!("input after idle, unmuting");
+            self.unmute_devices(MuteSource::Idle, muted);
+        }
+
+        Ok(())
+    }
+
+    /// mutes like locking does while the screensaver runs
+    fn check_screensaver(&mut self) -> Result<()> {
+        if !self.config.lock.screensaver || self.lock_mute_paused {
+            return Ok(());
+        }
+
+        let running = session::is_screensaver_running()?;
+
+        if running && self.screensaver_muted.is_none() {
+            log!("screensaver started, muting");
+            let lock = &self.config.lock;
+            let muted = self.mute_devices(MuteSource::Screensaver, lock.mute, lock.all_devices);
+            self.screensaver_muted = Some(muted);
+        } else if !running && let Some(muted) = self.screensaver_muted.take() {
+            log!("screensaver stopped, unmuting");
+            self.unmute_devices(MuteSource::Screensaver, muted);
+        }
+
+        Ok(())
+    }
+
+    fn on_suspend(&mut self) -> Result<()> {
+        log!("suspending");
+        run_session_commands("suspend", &self.config.session_commands.suspend);
+
+        // the headset drops off while asleep, so it's made the default again when it reconnects
+        self.airpods_available = false;
+
+        Ok(())
+    }
+
+    /// endpoints and bluetooth state are often out of date after sleep, so everything that's
+    /// tracked is looked at again
+    fn on_resume(&mut self, hwnd: HWND) -> Result<()> {
+        log!("resumed");
+        run_session_commands("resume", &self.config.session_commands.resume);
+
+        self.update_devices()?;
+        self.poll(hwnd)?;
+
+        if self.config.bluetooth.reconnect_on_resume {
+            self.reconnect_attempts = RECONNECT_ATTEMPTS;
+            unsafe { SetTimer(Some(hwnd), RECONNECT_TIMER, RECONNECT_DELAY_MS, None) };
+        }
+
+        for tray in &mut self.trays {
+            tray.invalidate();
+        }
+
+        unsafe {
+            let _ = InvalidateRect(Some(hwnd), None, true);
+        }
+
+        Ok(())
+    }
+
+    fn step_volume(&mut self, flow: EDataFlow, up: bool) -> Result<()> {
+        let device = self.audio.default_device(flow)?;
+
+        device.volume.step(up)
+    }
+
+    fn toggle_mute(&mut self, flow: EDataFlow) -> Result<()> {
+        self.mute_watch.expect(MuteSource::Panel);
+        let device = self.audio.default_device(flow)?;
+        device.set_mute(!device.is_mute()?)?;
+
+        Ok(())
+    }
+
+    fn cycle_device(&mut self, flow: EDataFlow) -> Result<()> {
+        let current = self.audio.default_id(flow)?;
+
+        let mut ids = vec![];
+        for device in self.audio.active_devices(flow)? {
+            ids.push(unsafe { device.GetId()?.to_string()? });
+        }
+
+        let next = match ids.iter().position(|id| *id == current) {
+            Some(i) => ids.get(i + 1).or(ids.first()),
+            None => ids.first(),
+        };
+
+        if let Some(next) = next {
+            self.audio.set_default_device(next)?;
+        }
+
+        Ok(())
+    }
+
+    /// makes the first active device whose name contains `name` the default, if it isn't already.
+    /// returns whether there was one.
+    fn restore_default_device(&mut self, flow: EDataFlow, name: &str) -> Result<bool> {
+        let name = name.to_lowercase();
+        let current = self.audio.default_id(flow)?;
+
+        for device in self.audio.active_devices(flow)? {
+            if !audio::name(&device)?.to_lowercase().contains(&name) {
+                continue;
+            }
+
+            let id = audio::id(&device)?;
+            if id != current {
+                log!("restoring default device {}", name);
+                self.audio.set_default_device(&id)?;
+            }
+
+            return Ok(true);
+        }
+
+        Ok(false)
+    }
+
+    fn run_ipc(&mut self, hwnd: HWND, command: ipc::Command) -> Result<serde_json::Value> {
+        self.mute_watch.expect(MuteSource::Command);
+
+        match command {
+            ipc::Command::Status { full: false } => return self.status(),
+            ipc::Command::Status { full: true } => return self.full_status(),
+            ipc::Command::Subscribe => bail!("subscribe only works on the pipe"),
+            ipc::Command::ToggleMute { target } => {
+                let queued = Queued::new(Some(target.widget()), Action::ToggleMute, true);
+                self.run_now(hwnd, queued.from(MuteSource::Command))?;
+            }
+            ipc::Command::SetMute { target, muted } => {
+                self.audio.default_device(target.flow())?.set_mute(muted)?;
+            }
+            ipc::Command::SetVolume { target, volume } => {
+                let device = self.audio.default_device(target.flow())?;
+                device.volume.set_volume(volume / 100.0)?;
+            }
+            ipc::Command::SetDefault { target, name } => {
+                if !self.restore_default_device(target.flow(), &name)? {
+                    bail!("no active device matching {:?}", name);
+                }
+            }
+            ipc::Command::Action { target, action } => {
+                let queued = Queued::new(Some(target.widget()), action, true);
+                self.run_now(hwnd, queued.from(MuteSource::Command))?;
+            }
+        }
+
+        Ok(serde_json::Value::Null)
+    }
+
+    /// the default devices, as answered to the `status` ipc command
+    fn status(&mut self) -> Result<serde_json::Value> {
+        let mut status = serde_json::Map::new();
+
+        for (key, flow) in [("output", eRender), ("input", eCapture)] {
+            if !self.audio.has_default(flow)? {
+                status.insert(key.to_string(), serde_json::Value::Null);
+                continue;
+            }
+
+            let endpoint = self.audio.get_default_device(flow)?;
+            let name = audio::name(&endpoint)?;
+            let device = self.audio.get_device(&endpoint)?;
+
+            let value = serde_json::json!({
+                "name": name,
+                "volume": (device.volume()? * 100.0).round(),
+                "muted": device.is_mute()?,
+            });
+            status.insert(key.to_string(), value);
+        }
+
+        status.insert("locked".to_string(), self.locked_at.is_some().into());
+        let focus_assist = serde_json::to_value(FocusMode::current()?)?;
+        status.insert("focus_assist".to_string(), focus_assist);
+        let playing = self.monitors.media.is_playing()?;
+        status.insert("playing".to_string(), playing.into());
+
+        Ok(serde_json::Value::Object(status))
+    }
+
+    /// `status`, plus every active device, the default for each role and bluetooth
+    fn full_status(&mut self) -> Result<serde_json::Value> {
+        let mut status = self.status()?;
+
+        let mut devices = vec![];
+        let mut defaults = serde_json::Map::new();
+
+        for (key, flow) in [("output", eRender), ("input", eCapture)] {
+            for endpoint in self.audio.active_devices(flow)? {
+                let id = unsafe { endpoint.GetId()?.to_string()? };
+                let name = audio::name(&endpoint)?;
+                let device = self.audio.get_device(&endpoint)?;
+
+                devices.push(serde_json::json!({
+                    "id": id,
+                    "name": name,
+                    "flow": key,
+                    "volume": (device.volume()? * 100.0).round(),
+                    "muted": device.is_mute()?,
+                }));
+            }
+
+            let mut roles = serde_json::Map::new();
+            for (role_key, role) in [
+                ("console", eConsole),
+                ("multimedia", eMultimedia),
+                ("communications", eCommunications),
+            ] {
+                let id = match self.audio.endpoints.default_device_for(flow, role)? {
+                    Some(endpoint) => audio::id(&endpoint)?.into(),
+                    None => serde_json::Value::Null,
+                };
+                roles.insert(role_key.to_string(), id);
+            }
+            defaults.insert(key.to_string(), roles.into());
+        }
+
+        let devices_config = self.config.bluetooth_devices();
+        self.monitors.bluetooth.sample(&devices_config)?;
+        let bluetooth = serde_json::json!({
+            "on": self.monitors.bluetooth.on,
+            "connected": self.monitors.bluetooth.connected,
+            "airpods_available": self.airpods_available,
+        });
+
+        let metrics = metrics::snapshot();
+        let diagnostics = serde_json::json!({
+            "paints": metrics.paints,
+            "paint_seconds": metrics.paint_time.as_secs_f64(),
+            "last_paint_seconds": metrics.last_paint_time.as_secs_f64(),
+            "events": metrics.events,
+            "errors": metrics.errors,
+            "last_error": metrics.last_error,
+            "tracked_devices": self.audio.devices.len(),
+        });
+
+        let object = status.as_object_mut().context("status is not an object")?;
+        object.insert("devices".to_string(), devices.into());
+        object.insert("defaults".to_string(), defaults.into());
+        object.insert("bluetooth".to_string(), bluetooth);
+        object.insert("diagnostics".to_string(), diagnostics);
+        object.insert("lock_mute_paused".to_string(), self.lock_mute_paused.into());
+
+        Ok(status)
+    }
+
+    fn is_muted(&mut self, flow: EDataFlow) -> Result<bool> {
+        self.audio.default_device(flow)?.is_mute()
+    }
+
+    fn device_name(&mut self, flow: EDataFlow) -> Result<String> {
+        let device = self.audio.get_default_device(flow)?;
+        audio::name(&device)
+    }
+
+    /// what screen readers announce for a widget, e.g. "Microphone, muted"
+    fn accessible_name(&mut self, widget: Widget) -> Result<String> {
+        let name = match widget {
+            Widget::Volume => {
+                let volume = self.audio.default_device(eRender)?.volume()?;
+                return Ok(format!("Volume, {:.0}%", volume * 100.0));
+            }
+            Widget::Output => "Speakers",
+            Widget::Input => "Microphone",
+            Widget::Clock => return Ok(format!("Clock, {}", self.config.clock.text())),
+            Widget::Date => {
+                let (_, week) = widgets::date::iso_week(&widgets::clock::now());
+                let date = self.config.date.text();
+                return Ok(format!("Date, {}, week {}", date, week));
+            }
+            Widget::Cpu => {
+                return Ok(match self.monitors.cpu.usage() {
+                    Some(usage) => format!("CPU, {:.0}%", usage * 100.0),
+                    None => "CPU".to_string(),
+                });
+            }
+            Widget::Memory => {
+                return Ok(match self.monitors.memory.usage() {
+                    Some(usage) => format!("Memory, {:.0}% used", usage * 100.0),
+                    None => "Memory".to_string(),
+                });
+            }
+            Widget::Network => {
+                return Ok(match self.monitors.network.rates() {
+                    Some((down, up)) => format!(
+                        "Network, {:.0} kilobytes per second down, {:.0} up",
+                        down / 1e3,
+                        up / 1e3
+                    ),
+                    None => "Network".to_string(),
+                });
+            }
+            Widget::Wifi => {
+                return Ok(match &self.monitors.wifi.connection {
+                    Some(connection) => format!(
+                        "Wi-Fi, {}, {} of 4 bars",
+                        connection.ssid,
+                        connection.bars()
+                    ),
+                    None => "Wi-Fi, disconnected".to_string(),
+                });
+            }
+            Widget::Vpn => {
+                return Ok(match self.monitors.vpn.connected {
+                    true => "VPN, connected".to_string(),
+                    false => "VPN, disconnected".to_string(),
+                });
+            }
+            Widget::Disk => {
+                let drives: Vec<_> = self
+                    .monitors
+                    .disk
+                    .drives
+                    .iter()
+                    .map(|(drive, free)| match free {
+                        Some(free) => format!("{} {:.0} gigabytes free", drive, *free as f64 / 1e9),
+                        None => format!("{} unavailable", drive),
+                    })
+                    .collect();
+                return Ok(format!("Disk space, {}", drives.join(", ")));
+            }
+            Widget::LockKeys => {
+                let keys: Vec<_> = self
+                    .monitors
+                    .locks
+                    .states
+                    .iter()
+                    .map(|(key, on)| format!("{} {}", key.label(), if *on { "on" } else { "off" }))
+                    .collect();
+                return Ok(format!("Lock keys, {}", keys.join(", ")));
+            }
+            Widget::NowPlaying => {
+                return Ok(match &self.monitors.media.track {
+                    Some(track) => format!(
+                        "Now playing, {} by {}, {}",
+                        track.title,
+                        track.artist,
+                        if track.playing { "playing" } else { "paused" }
+                    ),
+                    None => "Now playing, nothing".to_string(),
+                });
+            }
+            Widget::MediaPrevious => return Ok("Previous track".to_string()),
+            Widget::MediaPlayPause => return Ok("Play or pause".to_string()),
+            Widget::MediaNext => return Ok("Next track".to_string()),
+            Widget::Camera => {
+                let apps = &self.monitors.camera.apps;
+                return Ok(match apps.is_empty() {
+                    true => "Camera, not in use".to_string(),
+                    false => format!("Camera, in use by {}", apps.join(", ")),
+                });
+            }
+            Widget::ScreenCapture => {
+                let apps = &self.monitors.capture.apps;
+                return Ok(match apps.is_empty() {
+                    true => "Screen capture, not in use".to_string(),
+                    false => format!("Screen capture, in use by {}", apps.join(", ")),
+                });
+            }
+            Widget::FocusAssist => {
+                let mode = self.monitors.focus.mode;
+                return Ok(format!("Focus assist, {}", mode.label().to_lowercase()));
+            }
+            Widget::NightLight => {
+                return Ok(match self.monitors.night_light.on {
+                    true => "Night light, on".to_string(),
+                    false => "Night light, off".to_string(),
+                });
+            }
+            Widget::Brightness => {
+                let displays = &self.monitors.brightness.displays;
+                if displays.is_empty() {
+                    return Ok("Brightness, no DDC/CI monitors".to_string());
+                }
+
+                let levels: Vec<_> = displays
+                    .iter()
+                    .map(|(name, percent)| format!("{} {}%", name, percent))
+                    .collect();
+                return Ok(format!("Brightness, {}", levels.join(", ")));
+            }
+            Widget::InputSource => {
+                return Ok(match self.monitors.input_source.input {
+                    Some(input) => format!("Monitor input, {}", widgets::source::label(input)),
+                    None => "Monitor input, unknown".to_string(),
+                });
+            }
+            Widget::RefreshRate => {
+                return Ok(match self.monitors.refresh_rate.rate {
+                    Some(rate) => format!("Refresh rate, {} hertz", rate),
+                    None => "Refresh rate, unknown".to_string(),
+                });
+            }
+            Widget::Controllers => {
+                let controllers: Vec<_> = self
+                    .monitors
+                    .controllers
+                    .controllers
+                    .iter()
+                    .map(|(player, battery)| {
+                        format!("Controller {}, {}", player, battery.describe())
+                    })
+                    .collect();
+                return Ok(controllers.join("; "));
+            }
+            Widget::Uptime => return Ok(format!("Uptime, {}", widgets::uptime::text())),
+            Widget::Command => return Ok(self.monitors.command.text(&self.config.command)),
+            Widget::Weather => return Ok(self.monitors.weather.describe()),
+            Widget::Obs => return Ok(self.monitors.obs.describe()),
+            Widget::Button(index) => {
+                let button = self.config.buttons.get(index as usize);
+                return Ok(button.map_or("Button", |button| button.name()).to_string());
+            }
+            Widget::Pomodoro => {
+                let pomodoro = &self.monitors.pomodoro;
+                let remaining = pomodoro.remaining(&self.config.pomodoro, Instant::now());
+                let state = if pomodoro.is_running() {
+                    "running"
+                } else {
+                    "paused"
+                };
+
+                return Ok(format!(
+                    "Pomodoro, {}, {} left, {}",
+                    pomodoro.phase.label().to_lowercase(),
+                    widgets::pomodoro::format_remaining(remaining),
+                    state
+                ));
+            }
+            Widget::Meeting => {
+                let apps = &self.monitors.meeting.apps;
+                return Ok(match apps.is_empty() {
+                    true => "Meeting, not in a call".to_string(),
+                    false => format!("Meeting, in a call with {}", apps.join(", ")),
+                });
+            }
+            Widget::Peripherals => {
+                let levels: Vec<_> = self
+                    .monitors
+                    .peripherals
+                    .levels
+                    .iter()
+                    .map(|(device, level)| match level {
+                        Some(level) => format!("{} {}%", device, level),
+                        None => format!("{} unknown", device),
+                    })
+                    .collect();
+                return Ok(format!("Device batteries, {}", levels.join(", ")));
+            }
+            Widget::Bluetooth => {
+                let bluetooth = &self.monitors.bluetooth;
+                return Ok(match (bluetooth.on, bluetooth.connected.len()) {
+                    (None, _) => "Bluetooth, no adapter".to_string(),
+                    (Some(false), _) => "Bluetooth, off".to_string(),
+                    (Some(true), 0) => "Bluetooth, on".to_string(),
+                    (Some(true), _) => {
+                        format!(
+                            "Bluetooth, on, {} connected",
+                            bluetooth.connected.join(", ")
+                        )
+                    }
+                });
+            }
+        };
+
+        let flow = widget.flow().context("not a device widget")?;
+        let device = self.audio.default_device(flow)?;
+
+        if device.is_mute()? {
+            Ok(format!("{}, muted", name))
+        } else {
+            Ok(name.to_string())
+        }
+    }
+
+    /// runs `queued` along with whatever else is queued, once the window's thread gets to it
+    fn queue(&mut self, hwnd: HWND, queued: Queued) {
+        if self.actions.push(queued) {
+            unsafe {
+                let _ = PostMessageA(Some(hwnd), WM_RUN_ACTIONS, WPARAM(0), LPARAM(0));
+            }
+        }
+    }
+
+    /// queues `queued` and runs the queue straight away, for a caller waiting to hear whether it
+    /// worked. it's still coalesced with whatever was queued before it.
+    fn run_now(&mut self, hwnd: HWND, queued: Queued) -> Result<()> {
+        self.actions.push(queued);
+        self.run_queued(hwnd)
+    }
+
+    /// runs everything queued, carrying on past any that fail
+    fn run_queued(&mut self, hwnd: HWND) -> Result<()> {
+        let mut result = Ok(());
+
+        for queued in self.actions.take() {
+            if let Some(source) = queued.source {
+                self.mute_watch.expect(source);
+            }
+
+            for _ in 0..queued.times {
+                let ran = self.run_action(hwnd, queued.widget, queued.action.clone(), queued.up);
+                result = result.and(ran);
+            }
+
+            if queued.osd {
+                result = result.and(self.show_osd(hwnd, queued.flow()));
+            }
+        }
+
+        result
+    }
+
+    fn run_action(
+        &mut self,
+        hwnd: HWND,
+        widget: Option<Widget>,
+        action: Action,
+        up: bool,
+    ) -> Result<()> {
+        let flow = widget.and_then(Widget::flow).unwrap_or(eRender);
+
+        match action {
+            Action::None => {}
+            Action::ToggleMute => self.toggle_mute(flow)?,
+            Action::CycleDevice => self.cycle_device(flow)?,
+            Action::StepVolume => self.step_volume(flow, up)?,
+            Action::ConnectBluetooth => self.connect_headset()?,
+            Action::ToggleDiagnostics => self.toggle_diagnostics(hwnd),
+            Action::RunCommand { command } => run_command(&command)?,
+            Action::RunButton => {
+                if let Some(Widget::Button(index)) = widget
+                    && let Some(button) = self.config.buttons.get(index as usize)
+                {
+                    run_command(&button.command)?;
+                }
+            }
+            Action::OpenCalendar => shell_open(self.config.date.target(), None)?,
+            Action::OpenNetworks => shell_open(widgets::wifi::NETWORK_FLYOUT, None)?,
+            Action::ToggleVpn => {
+                let connected = self.monitors.vpn.connected;
+                if let Some(command) = self.config.vpn.toggle_command(connected) {
+                    run_command(command)?;
+                }
+            }
+            Action::PlayPause => self.monitors.media.send(hwnd, MediaCommand::PlayPause),
+            Action::NextTrack => self.monitors.media.send(hwnd, MediaCommand::Next),
+            Action::PreviousTrack => self.monitors.media.send(hwnd, MediaCommand::Previous),
+            Action::CycleFocusAssist => {
+                // the widget catches up on its next poll
+                self.monitors.focus.mode.next().select()?;
+            }
+            Action::SetFocusAssist { mode } => mode.select()?,
+            Action::ToggleTimer | Action::ResetTimer => {
+                let pomodoro = &mut self.monitors.pomodoro;
+                match action {
+                    Action::ToggleTimer => pomodoro.toggle(&self.config.pomodoro, Instant::now()),
+                    _ => pomodoro.reset(),
+                }
+
+                // repainting also schedules the next tick
+                unsafe {
+                    let _ = InvalidateRect(Some(hwnd), None, true);
+                }
+            }
+            Action::ToggleNightLight => widgets::nightlight::set(!self.monitors.night_light.on)?,
+            Action::StepBrightness => {
+                // on the brightness widget, only the monitor under the cursor changes
+                let index = match widget.and_then(|widget| self.layout.rect(widget)) {
+                    Some(rect) if widget == Some(Widget::Brightness) => {
+                        let mut point = POINT::default();
+                        unsafe {
+                            GetCursorPos(&mut point)?;
+                            let _ = ScreenToClient(hwnd, &mut point);
+                        }
+
+                        let count = self.monitors.brightness.displays.len();
+                        widgets::brightness::segment(rect, count, point.x as f32)
+                    }
+                    _ => None,
+                };
+
+                let config = &self.config.brightness;
+                self.monitors.brightness.step(index, up, config);
+                unsafe {
+                    let _ = InvalidateRect(Some(hwnd), None, true);
+                }
+            }
+            Action::CycleInputSource => {
+                let config = &self.config.input_source;
+                if let Some(input) = config.next(self.monitors.input_source.input) {
+                    self.monitors.input_source.switch(config, input);
+                    unsafe {
+                        let _ = InvalidateRect(Some(hwnd), None, true);
+                    }
+                }
+            }
+            Action::CycleRefreshRate => {
+                let config = &self.config.refresh_rate;
+                if let Some(rate) = config.next(self.monitors.refresh_rate.rate) {
+                    // the widget catches up on its next poll
+                    widgets::refresh::set(config, rate)?;
+                }
+            }
+            Action::SwitchInputSource { input } => {
+                let config = &self.config.input_source;
+                self.monitors.input_source.switch(config, input);
+                unsafe {
+                    let _ = InvalidateRect(Some(hwnd), None, true);
+                }
+            }
+
+            // needs its own message loop, see `on_gesture`
+            Action::ContextMenu | Action::BluetoothMenu => {
+                bail!("menus must be shown from the window proc")
+            }
+        }
+
+        Ok(())
+    }
+
+    fn connect_headset(&mut self) -> Result<()> {
+        let Some(name) = self.config.bluetooth_device.clone() else {
+            bail!("there's no bluetooth_device in the config to connect");
+        };
+
+        self.connect_bluetooth(&name)
+    }
+
+    /// reconnects the first bluetooth audio device whose name contains `name`
+    fn connect_bluetooth(&mut self, name: &str) -> Result<()> {
+        self.request_reconnect(name, true);
+        Ok(())
+    }
+
+    /// asks the first bluetooth audio device whose name contains `name` to reconnect, on the
+    /// worker since the request can take a while. `report` says whether to tell the user if
+    /// nothing took it.
+    fn request_reconnect(&mut self, name: &str, report: bool) {
+        let name = name.to_string();
+        self.audio.worker.send(Job::Reconnect { name, report });
+    }
+
+    /// a reconnect request has been taken or not, which doesn't mean the device is connected
+    /// yet
+    fn on_reconnected(&mut self, name: &str, report: bool, result: Reconnect) -> Result<()> {
+        if !report || !self.config.notifications.reconnect_failed {
+            return Ok(());
+        }
+
+        let body = match result {
+            Reconnect::Taken => return Ok(()),
+            Reconnect::Refused => format!("Couldn't reconnect {}", name),
+            Reconnect::NoSuchDevice => format!("Couldn't reconnect {}: no such device", name),
+        };
+
+        notifications::show("Reconnect failed", &body)
+    }
+
+    /// reconnects whichever of the bluetooth widget's devices aren't connected after resuming,
+    /// checking again after a delay until they all are or the attempts run out
+    fn reconnect_bluetooth(&mut self, hwnd: HWND) -> Result<()> {
+        let devices = self.config.bluetooth_devices();
+        self.monitors.bluetooth.sample(&devices)?;
+
+        let missing: Vec<String> = devices
+            .into_iter()
+            .filter(|device| !self.monitors.bluetooth.connected.contains(device))
+            .collect();
+
+        if missing.is_empty() {
+            self.reconnect_attempts = 0;
+            return Ok(());
+        }
+
+        if self.reconnect_attempts == 0 {
+            let body = format!("Couldn't reconnect {} after sleep", missing.join(", "));
+            eventlog::warning(&body);
+
+            if self.config.notifications.reconnect_failed {
+                notifications::show("Reconnect failed", &body)?;
+            }
+
+            return Ok(());
+        }
+
+        self.reconnect_attempts -= 1;
+        for device in &missing {
+            log!("reconnecting {} after resume", device);
+            self.request_reconnect(device, false);
+        }
+
+        unsafe { SetTimer(Some(hwnd), RECONNECT_TIMER, RECONNECT_DELAY_MS, None) };
+
+        Ok(())
+    }
+
+    /// replaces the button widgets' icons with the ones in the current config. every old icon is
+    /// destroyed even if one of them fails to be.
+    fn load_button_icons(&mut self) -> Result<()> {
+        let mut result = Ok(());
+
+        for icon in self.monitors.buttons.drain(..).flatten() {
+            self.renderer.forget_icon(icon);
+            result = result.and(unsafe { DestroyIcon(icon) });
+        }
+
+        for button in &self.config.buttons {
+            let icon = button
+                .icon
+                .as_deref()
+                .and_then(|path| match load_icon(path) {
+                    Ok(icon) => Some(icon),
+                    Err(e) => {
+                        log!("no icon for button {}: {:?}", button.name(), e);
+                        None
+                    }
+                });
+
+            self.monitors.buttons.push(icon);
+        }
+
+        Ok(result?)
+    }
+
+    /// shows a device's newly loaded icon, destroying the one it replaces
+    fn on_icon(&mut self, id: &str, loaded: Result<(String, HICON)>) -> Result<()> {
+        let Some(replaced) = self.audio.set_icon(id, loaded)? else {
+            return Ok(());
+        };
+
+        self.renderer.forget_icon(replaced);
+        self.osd.forget_icon(replaced);
+        unsafe { DestroyIcon(replaced)? };
+
+        for tray in &mut self.trays {
+            tray.invalidate();
+        }
+
+        Ok(())
+    }
+
+    /// lets go of the devices nothing's needed for `device_cache_minutes`, on the refresh timer.
+    /// every evicted device's icon is destroyed even if one of them fails to be.
+    fn evict_devices(&mut self) -> Result<()> {
+        let max_age = Duration::from_secs(self.config.device_cache_minutes * 60);
+        let mut result = Ok(());
+
+        for icon in self.audio.evict(max_age) {
+            self.renderer.forget_icon(icon);
+            self.osd.forget_icon(icon);
+            result = result.and(unsafe { DestroyIcon(icon) });
+        }
+
+        Ok(result?)
+    }
+
+    fn on_default_device_changed(&mut self, flow: EDataFlow) -> Result<()> {
+        self.audio.forget_default(flow);
+
+        if flow == eRender {
+            self.match_loudness()?;
+        }
+
+        // the last device going away changes the default to nothing, which isn't worth a toast
+        if !self.config.notifications.default_device_changed || !self.audio.has_default(flow)? {
+            return Ok(());
+        }
+
+        let device = self.audio.get_default_device(flow)?;
+        let name = audio::name(&device)?;
+
+        let title = if flow == eCapture {
+            "Input device changed"
+        } else {
+            "Output device changed"
+        };
+
+        notifications::show(title, &name)
+    }
+
+    /// sets the new default output's volume from the old one's, see `Config::volume_offsets`
+    fn match_loudness(&mut self) -> Result<()> {
+        let current = match self.audio.has_default(eRender)? {
+            true => Some(self.audio.default_id(eRender)?),
+            false => None,
+        };
+
+        let previous = std::mem::replace(&mut self.output_id, current.clone());
+        self.loudness = None;
+
+        let (Some(previous), Some(current)) = (previous, current) else {
+            return Ok(());
+        };
+
+        if previous == current || self.config.volume_offsets.is_empty() {
+            return Ok(());
+        }
+
+        // only from what's already tracked, since the old output is often gone by now, e.g.
+        // unplugged, and asking it anything could wait on the audio service
+        let Some(device) = self.audio.devices.get(&previous) else {
+            return Ok(());
+        };
+        let Some(from) = self.config.volume_offset(&device.name) else {
+            return Ok(());
+        };
+        let Ok(volume) = device.volume() else {
+            return Ok(());
+        };
+
+        self.loudness = Some((volume, from));
+        self.apply_loudness()
+    }
+
+    /// sets the new output's volume for `match_loudness` once it's ready, which may be after the
+    /// worker's prepared it
+    fn apply_loudness(&mut self) -> Result<()> {
+        if self.loudness.is_none() {
+            return Ok(());
+        }
+
+        let Some(device) = self.audio.ready_default(eRender)? else {
+            return Ok(());
+        };
+
+        let (volume, from) = self.loudness.take().unwrap();
+        let Some(to) = self.config.volume_offset(&device.name) else {
+            return Ok(());
+        };
+
+        let target = (volume + (to - from) / 100.0).clamp(0.0, 1.0);
+        log!("matching loudness, output volume {:.0}%", target * 100.0);
+        device.volume.set_volume(target)
+    }
+
+    /// checks for slow-moving conditions worth a toast, on the refresh timer
+    fn check_conditions(&mut self) -> Result<()> {
+        if self.config.notifications.battery_low
+            && let Some(name) = &self.config.bluetooth_device
+        {
+            let level = battery::battery_level(name)?;
+
+            if self.conditions.battery(level)
+                && let Some(level) = level
+            {
+                let body = format!("{} is at {}%", name, level);
+                notifications::show("Headset battery low", &body)?;
+            }
+        }
+
+        if self.config.notifications.mic_left_hot && self.audio.has_default(eCapture)? {
+            let input = self.audio.get_default_device(eCapture)?;
+            let hot =
+                !self.audio.get_device(&input)?.is_mute()? && self.audio.is_capturing(eCapture)?;
+
+            if self.conditions.mic(hot, Instant::now()) {
+                let body = "Your microphone has been live for over 10 minutes";
+                notifications::show("Microphone still live", body)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn context_menu(&mut self, widget: Option<Widget>) -> Result<Menu> {
+        let mut menu = Menu::new()?;
+        let root = menu.root();
+
+        if let Some(flow) = widget.and_then(Widget::flow) {
+            let current_id = self.audio.default_id(flow)?;
+
+            let devices = menu.submenu(root, "Default device")?;
+            for device in self.audio.active_devices(flow)? {
+                let id = unsafe { device.GetId()?.to_string()? };
+                let name = audio::name(&device)?;
+                let checked = id == current_id;
+
+                menu.item(devices, &name, MenuCommand::SetDefault(id), checked)?;
+            }
+
+            let muted = self.audio.default_device(flow)?.is_mute()?;
+            menu.item(root, "Mute", MenuCommand::ToggleMute(flow), muted)?;
+            menu.item(
+                root,
+                "Device properties...",
+                MenuCommand::DeviceProperties(flow),
+                false,
+            )?;
+            menu.separator(root)?;
+        }
+
+        menu.item(root, "Settings...", MenuCommand::Settings, false)?;
+        menu.item(root, "Reload config", MenuCommand::ReloadConfig, false)?;
+        menu.item(
+            root,
+            "Pause mute on lock",
+            MenuCommand::ToggleLockMute,
+            self.lock_mute_paused,
+        )?;
+        let lock_mute = menu.submenu(root, "Mute on lock")?;
+        for mode in LockMute::ALL {
+            let checked = self.config.lock.mute == mode;
+            menu.item(
+                lock_mute,
+                mode.label(),
+                MenuCommand::SetLockMute(mode),
+                checked,
+            )?;
+        }
+        let positions = menu.submenu(root, "Position")?;
+        for preset in Preset::ALL {
+            let checked = self.config.placement == Some(preset);
+            menu.item(
+                positions,
+                preset.label(),
+                MenuCommand::Place(preset),
+                checked,
+            )?;
+        }
+
+        menu.item(
+            root,
+            "Edit layout",
+            MenuCommand::ToggleEditLayout,
+            self.edit.is_some(),
+        )?;
+        menu.item(
+            root,
+            "Lock layout",
+            MenuCommand::ToggleLockLayout,
+            self.config.lock_layout,
+        )?;
+        menu.item(
+            root,
+            "Diagnostics",
+            MenuCommand::ToggleDiagnostics,
+            self.diagnostics.is_some(),
+        )?;
+        menu.separator(root)?;
+        menu.item(root, "Quit", MenuCommand::Quit, false)?;
+
+        Ok(menu)
+    }
+
+    /// the bluetooth widget's devices, checked while connected
+    fn bluetooth_menu(&mut self) -> Result<Menu> {
+        let mut menu = Menu::new()?;
+        let root = menu.root();
+
+        for device in self.config.bluetooth_devices() {
+            let connected = self.monitors.bluetooth.connected.contains(&device);
+            let command = MenuCommand::ConnectBluetooth(device.clone());
+            menu.item(root, &device, command, connected)?;
+        }
+
+        menu.separator(root)?;
+        menu.item(
+            root,
+            "Bluetooth settings...",
+            MenuCommand::BluetoothSettings,
+            false,
+        )?;
+
+        Ok(menu)
+    }
+
+    fn on_menu_command(&mut self, hwnd: HWND, command: MenuCommand) -> Result<()> {
+        log!("menu command: {:?}", command);
+
+        match command {
+            MenuCommand::SetDefault(id) => self.audio.set_default_device(&id)?,
+
+            MenuCommand::ToggleMute(flow) => self.toggle_mute(flow)?,
+
+            MenuCommand::DeviceProperties(flow) => {
+                // opens the playback or recording tab of the classic sound control panel
+                let tab = if flow == eCapture {
+                    "mmsys.cpl,,1"
+                } else {
+                    "mmsys.cpl,,0"
+                };
+                shell_open("control.exe", Some(tab))?;
+            }
+
+            MenuCommand::Settings => {
+                let path = Config::path();
+                if !path.exists() {
+                    std::fs::write(&path, "{}\n")?;
+                }
+
+                shell_open(&path.to_string_lossy(), None)?;
+            }
+
+            MenuCommand::ReloadConfig => {
+                self.config = Config::load()?;
+                eventlog::set_enabled(self.config.event_log);
+                self.layout = Layout::new(&self.config.widgets, &self.config.widget_offsets, 1.0);
+                self.load_button_icons()?;
+                self.apply_dpi(hwnd, unsafe { GetDpiForWindow(hwnd) }, None)?;
+                self.config.z_order.apply(hwnd)?;
+                self.apply_position(hwnd)?;
+                self.apply_tray_config(hwnd)?;
+                self.register_hotkeys(hwnd)?;
+                headset::register(hwnd, self.config.headset_buttons.enabled)?;
+                self.apply_activity_config(hwnd);
+                self.update_devices()?;
+                self.poll(hwnd)?;
+            }
+
+            MenuCommand::ToggleLockMute => {
+                self.lock_mute_paused = !self.lock_mute_paused;
+            }
+
+            MenuCommand::SetLockMute(mode) => {
+                self.config.lock.mute = mode;
+                Config::set("lock", &self.config.lock)?;
+            }
+
+            MenuCommand::Place(preset) => {
+                self.config.placement = Some(preset);
+                Config::set("placement", preset)?;
+                self.apply_position(hwnd)?;
+            }
+
+            MenuCommand::ToggleEditLayout => self.toggle_edit_layout(hwnd)?,
+
+            MenuCommand::ToggleDiagnostics => self.toggle_diagnostics(hwnd),
+
+            MenuCommand::ToggleLockLayout => {
+                self.config.lock_layout = !self.config.lock_layout;
+                Config::set("lock_layout", self.config.lock_layout)?;
+            }
+
+            MenuCommand::ConnectBluetooth(name) => self.connect_bluetooth(&name)?,
+
+            MenuCommand::BluetoothSettings => {
+                shell_open(widgets::bluetooth::BLUETOOTH_SETTINGS, None)?
+            }
+
+            MenuCommand::Quit => unsafe {
+                PostMessageA(Some(hwnd), WM_CLOSE, default(), default())?;
+            },
+        }
+
+        Ok(())
+    }
+
+    /// looks for the headset again, on the worker since it reads every device's name
+    fn update_devices(&mut self) -> Result<()> {
+        match self.config.bluetooth_device.clone() {
+            Some(name) => self.audio.worker.send(Job::FindHeadset(name)),
+            None => {
+                self.airpods_available = false;
+                self.headset = None;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// makes the headset the default when it turns up
+    fn on_headset(&mut self, headset: Option<worker::Headset>) -> Result<()> {
+        let previous = std::mem::replace(&mut self.airpods_available, headset.is_some());
+
+        if let Some(headset) = &headset
+            && !previous
+        {
+            self.audio.set_default_device(&headset.id)?;
+        }
+
+        self.headset = headset;
+        Ok(())
+    }
+
+    /// picks up whatever the worker has finished
+    fn on_worker_done(&mut self) -> Result<()> {
+        let mut result = Ok(());
+
+        for done in self.audio.worker.finished() {
+            let handled = match done {
+                Done::Prepared { id, device } => self
+                    .audio
+                    .add_prepared(id, device)
+                    .and_then(|()| self.apply_loudness()),
+                Done::Reconnected {
+                    name,
+                    report,
+                    result,
+                } => result.and_then(|result| self.on_reconnected(&name, report, result)),
+                Done::Headset(headset) => headset.and_then(|headset| self.on_headset(headset)),
+                Done::Name { id, name } => self.audio.set_name(&id, name),
+                Done::Icon { id, icon } => {
+                    let loaded = icon.map(|(path, handle)| (path, HICON(handle as _)));
+                    self.on_icon(&id, loaded)
+                }
+            };
+
+            result = result.and(handled);
+        }
+
+        result
+    }
+}
+
+fn wrap(function: impl FnOnce(&mut WindowHelper) -> Result<()>) {
+    WINDOW_HELPER.with(|state| {
+        if let Some(state) = state.borrow_mut().as_mut() {
+            let mut state = state.lock().unwrap();
+
+            if let Err(e) = (function)(&mut *state) {
+                metrics::record_error(&e);
+                log!("error: {:?}", e);
+            }
+        } else {
+            log!("no window state");
+        }
+    });
+}
+
+/// like `wrap`, but also reports failures to the event log. for handling what the audio callbacks
+/// posted, where a failure leaves the panel out of step with the devices
+fn wrap_audio(function: impl FnOnce(&mut WindowHelper) -> Result<()>) {
+    wrap(|state| {
+        let result = function(state);
+
+        if let Err(e) = &result {
+            eventlog::error(&format!("handling an audio notification failed: {:?}", e));
+        }
+
+        result
+    });
+}
+
+/// like `wrap`, but returns the result and fails instead of panicking if the state is already
+/// borrowed, since UI Automation can call in while a message is being handled
+fn try_wrap<T>(function: impl FnOnce(&mut WindowHelper) -> Result<T>) -> Result<T> {
+    WINDOW_HELPER.with(|state| {
+        let mut state = state.try_borrow_mut().context("window state is busy")?;
+        let state = state.as_mut().context("no window state")?;
+
+        (function)(state.get_mut().unwrap())
+    })
+}
+
+fn show_context_menu(hwnd: HWND, widget: Option<Widget>) {
+    show_menu(hwnd, |state| state.context_menu(widget));
+}
+
+fn show_menu(hwnd: HWND, build: impl FnOnce(&mut WindowHelper) -> Result<Menu>) {
+    // the menu runs its own message loop, so it must be tracked outside of `wrap`
+    let mut menu = None;
+    wrap(|state| {
+        menu = Some(build(state)?);
+        Ok(())
+    });
+
+    if let Some(menu) = menu {
+        match menu.track(hwnd) {
+            Ok(Some(command)) => wrap(|state| state.on_menu_command(hwnd, command)),
+            Ok(None) => {}
+            Err(e) => log!("error: {:?}", e),
+        }
+    }
+}
+
+/// looks up the action bound to a gesture on the widget at `point` (in window coordinates)
+fn on_gesture(hwnd: HWND, point: POINT, gesture: Gesture, up: bool) {
+    let mut widget = None;
+    wrap(|state| {
+        widget = state.layout.hit_test(point.x, point.y);
+        Ok(())
+    });
+
+    on_widget_gesture(hwnd, widget, gesture, up);
+}
+
+fn on_widget_gesture(hwnd: HWND, widget: Option<Widget>, gesture: Gesture, up: bool) {
+    let mut action = Action::None;
+    wrap(|state| {
+        action = state.config.action_for(widget, gesture);
+        Ok(())
+    });
+
+    match action {
+        Action::ContextMenu => show_context_menu(hwnd, widget),
+        Action::BluetoothMenu => show_menu(hwnd, |state| state.bluetooth_menu()),
+        action => queue_action(hwnd, Queued::new(widget, action, up)),
+    }
+}
+
+fn queue_action(hwnd: HWND, queued: Queued) {
+    wrap(|state| {
+        state.queue(hwnd, queued);
+        Ok(())
+    });
+}
+
+/// clicking a tray icon toggles its device's mute, and right-clicking opens its widget's menu
+/// `anchor` is the icon's position on screen, or the cursor's for mouse messages
+fn on_tray(hwnd: HWND, id: u32, message: u32, anchor: POINT) {
+    let mut device = None;
+    wrap(|state| {
+        let tray = state.trays.iter().find(|tray| tray.id() == id);
+        device = tray.map(|tray| tray.device());
+        Ok(())
+    });
+
+    let Some(device) = device else {
+        return;
+    };
 
-            Ok(None)
+    match message {
+        NIN_POPUPOPEN => wrap(|state| state.show_tray_flyout(anchor)),
+        NIN_POPUPCLOSE => wrap(|state| {
+            state.flyout.hide();
+            Ok(())
+        }),
+        WM_LBUTTONUP => {
+            let queued = Queued::new(Some(device.widget()), Action::ToggleMute, true);
+            queue_action(hwnd, queued.from(MuteSource::Panel));
         }
+        // sent for right clicks as well as the menu key, with version 4 icons
+        WM_CONTEXTMENU => show_context_menu(hwnd, Some(device.widget())),
+
+        _ => {}
     }
+}
 
-    fn connect_airpods(&mut self) -> Result<()> {
-        unsafe {
-            let devices = self
-                .audio
-                .device_enumerator
-                .EnumAudioEndpoints(eRender, DEVICE_STATE(DEVICE_STATEMASK_ALL))?;
+/// shows the osd for the keyboard's volume keys, if configured. they can't be remapped from here
+/// since the shell has already acted on them, but a hotkey bound to e.g. "volume_mute" takes the
+/// key over entirely.
+fn on_app_command(hwnd: HWND, lparam: LPARAM) {
+    // GET_APPCOMMAND_LPARAM
+    let command = ((lparam.0 >> 16) as u32 & 0xffff) & !FAPPCOMMAND_MASK;
+
+    let volume_key = [
+        APPCOMMAND_VOLUME_UP,
+        APPCOMMAND_VOLUME_DOWN,
+        APPCOMMAND_VOLUME_MUTE,
+    ]
+    .iter()
+    .any(|key| key.0 == command);
+
+    wrap(|state| {
+        if volume_key && state.config.osd.media_keys {
+            unsafe { SetTimer(Some(hwnd), MEDIA_KEY_TIMER, MEDIA_KEY_DELAY_MS, None) };
+        }
 
-            for i in 0..devices.GetCount()? {
-                let device = devices.Item(i)?;
+        Ok(())
+    });
+}
 
-                if let Ok(name) = get_name(&device)
-                    && name.to_lowercase().contains("airpods")
-                    && self.connect_airpods_device(device).is_ok()
-                {
-                    break;
-                }
-            }
+/// runs the actions for the headset buttons pressed in the `WM_INPUT` with `lparam`
+fn on_headset_input(hwnd: HWND, lparam: LPARAM) {
+    let mut actions = vec![];
+    wrap(|state| {
+        for button in state.headset_buttons.on_input(lparam)? {
+            actions.push(button.action(&state.config.headset_buttons));
         }
 
         Ok(())
-    }
-
-    fn connect_airpods_device(&mut self, device: IMMDevice) -> Result<()> {
-        unsafe {
-            let topology: IDeviceTopology = device.Activate(CLSCTX_ALL, None)?;
-            assert_eq!(1, topology.GetConnectorCount()?);
+    });
 
-            let connector = topology.GetConnector(0)?;
+    for action in actions {
+        match action {
+            Action::ContextMenu => show_context_menu(hwnd, Some(Widget::Input)),
+            Action::BluetoothMenu => show_menu(hwnd, |state| state.bluetooth_menu()),
+            action => {
+                let queued = Queued::new(Some(Widget::Input), action, true);
+                queue_action(hwnd, queued.from(MuteSource::Headset));
+            }
+        }
+    }
+}
 
-            let bluetooth_device = connector.GetDeviceIdConnectedTo()?;
-            let bluetooth_device = self.audio.device_enumerator.GetDevice(bluetooth_device)?;
+/// runs the action bound to the hotkey at `index` of `Config::hotkeys`
+fn on_hotkey_binding(hwnd: HWND, index: usize) {
+    let mut binding = None;
+    wrap(|state| {
+        binding = state.config.hotkeys.get(index).cloned();
+        Ok(())
+    });
 
-            let control: IKsControl = bluetooth_device.Activate(CLSCTX_ALL, None)?;
+    let Some(binding) = binding else {
+        return;
+    };
 
-            let property = KSIDENTIFIER {
-                Anonymous: KSIDENTIFIER_0 {
-                    Anonymous: windows::Win32::Media::KernelStreaming::KSIDENTIFIER_0_0 {
-                        Set: KSPROPSETID_BtAudio,
-                        Id: KSPROPERTY_ONESHOT_RECONNECT.0 as u32,
-                        Flags: KSPROPERTY_TYPE_GET,
-                    },
-                },
-            };
+    match binding.action {
+        Action::ContextMenu => show_context_menu(hwnd, binding.widget),
+        Action::BluetoothMenu => show_menu(hwnd, |state| state.bluetooth_menu()),
 
-            let mut out = 0;
-            control.KsProperty(
-                &property,
-                size_of_val(&property) as u32,
-                null_mut(),
-                0,
-                &mut out,
-            )?;
+        action @ (Action::ToggleMute | Action::StepVolume) => {
+            let queued = Queued::new(binding.widget, action, !binding.down);
+            queue_action(hwnd, queued.from(MuteSource::Hotkey).with_osd());
         }
 
-        Ok(())
+        action => queue_action(hwnd, Queued::new(binding.widget, action, !binding.down)),
     }
+}
 
-    fn update_devices(&mut self) -> Result<()> {
-        unsafe {
-            let airpods = self.find_connected_airpods()?;
-            let previous = std::mem::replace(&mut self.airpods_available, airpods.is_some());
-
-            if let Some(airpods) = airpods
-                && !previous
-            {
-                let str = airpods.GetId()?;
-                self.audio.set_default_device(PCWSTR(str.0))?;
-            }
-        }
-
+fn on_key(hwnd: HWND, key: VIRTUAL_KEY) {
+    let mut invoke = None;
+    wrap(|state| {
+        invoke = state.on_key(hwnd, key)?;
         Ok(())
+    });
+
+    // Enter does whatever a click would
+    if let Some(widget) = invoke {
+        on_widget_gesture(hwnd, Some(widget), Gesture::Click, true);
     }
 }
 
-fn wrap(function: impl FnOnce(&mut WindowHelper) -> Result<()>) {
-    WINDOW_HELPER.with(|state| {
-        if let Some(state) = state.borrow_mut().as_mut() {
-            let mut state = state.lock().unwrap();
-
-            if let Err(e) = (function)(&mut *state) {
-                log!("error: {:?}", e);
-            }
-        } else {
-            log!("no window state");
-        }
-    });
+fn lparam_point(lparam: LPARAM) -> POINT {
+    POINT {
+        x: (lparam.0 & 0xffff) as i16 as i32,
+        y: ((lparam.0 >> 16) & 0xffff) as i16 as i32,
+    }
 }
 
 fn message_name(event: u32) -> &'static str {
@@ -831,6 +4024,25 @@ fn message_name(event: u32) -> &'static str {
     }
 }
 
+/// id of the timer that periodically repaints the panel, in case a notification was missed
+const REFRESH_TIMER: usize = 1;
+const REFRESH_INTERVAL_MS: u32 = 30_000;
+
+/// puts the panel back where the z-order policy wants it, after other windows may have moved
+/// above or below it
+fn keep_z_order(hwnd: HWND) {
+    // the state is busy if we're giving focus away ourselves; the refresh timer catches up
+    let Ok(z_order) = try_wrap(|state| Ok(state.config.z_order)) else {
+        return;
+    };
+
+    if z_order.needs_reassert()
+        && let Err(e) = z_order.apply(hwnd)
+    {
+        log!("failed to keep window z-order: {:?}", e);
+    }
+}
+
 thread_local! {
     static WINDOW_HELPER: RefCell<Option<Mutex<WindowHelper>>> = RefCell::new(None);
 }
@@ -841,55 +4053,335 @@ unsafe extern "system" fn window_proc(
     wparam: WPARAM,
     lparam: LPARAM,
 ) -> LRESULT {
+    metrics::EVENTS.add();
+
     unsafe {
         match event {
             WM_WINDOWPOSCHANGING => {}
 
             WM_DESTROY => {
+                let _ = WTSUnRegisterSessionNotification(hwnd);
                 PostQuitMessage(WM_QUIT as _);
             }
 
             WM_KILLFOCUS => {
-                SetWindowPos(
-                    hwnd,
-                    Some(HWND_TOPMOST),
-                    0,
-                    0,
-                    0,
-                    0,
-                    SWP_NOMOVE | SWP_NOSIZE,
-                )
-                .unwrap();
+                keep_z_order(hwnd);
+
+                // the state is busy if we're giving focus away ourselves
+                let _ = try_wrap(|state| {
+                    if state.keyboard.take().is_some() {
+                        let _ = InvalidateRect(Some(hwnd), None, true);
+                    }
+                    Ok(())
+                });
+            }
+
+            WM_HOTKEY if wparam.0 == KEYBOARD_HOTKEY as usize => {
+                wrap(|state| state.toggle_keyboard_mode(hwnd));
             }
 
-            WM_PAINT => wrap(|state| state.on_paint(hwnd)),
+            WM_HOTKEY if wparam.0 >= BINDING_HOTKEYS as usize => {
+                on_hotkey_binding(hwnd, wparam.0 - BINDING_HOTKEYS as usize);
+            }
 
-            WM_WTSSESSION_CHANGE => match wparam.0 as _ {
-                WTS_SESSION_LOCK => wrap(|state| state.on_lock()),
-                WTS_SESSION_UNLOCK => wrap(|state| state.on_unlock()),
+            WM_KEYDOWN => on_key(hwnd, VIRTUAL_KEY(wparam.0 as u16)),
 
-                _ => {}
-            },
+            // has to reach DefWindowProc too, which frees the input
+            WM_INPUT => on_headset_input(hwnd, lparam),
+
+            WM_TRAY => {
+                let id = (lparam.0 >> 16) as u32 & 0xffff;
+                let message = (lparam.0 & 0xffff) as u32;
+                // packed the same way as a mouse message's lparam
+                let anchor = lparam_point(LPARAM(wparam.0 as isize));
+
+                on_tray(hwnd, id, message, anchor);
+            }
+
+            _ if event == SHELL_HOOK.load(Ordering::Relaxed)
+                && wparam.0 as u32 == HSHELL_APPCOMMAND =>
+            {
+                on_app_command(hwnd, lparam);
+            }
+
+            _ if event == TASKBAR_CREATED.load(Ordering::Relaxed) => {
+                wrap(|state| {
+                    for tray in &mut state.trays {
+                        tray.reset();
+                    }
+
+                    state.update_trays()
+                });
+            }
+
+            WM_REDRAW => {
+                SetTimer(Some(hwnd), REDRAW_TIMER, REDRAW_DELAY_MS, None);
+            }
+
+            WM_TIMER if wparam.0 == REDRAW_TIMER => {
+                let _ = KillTimer(Some(hwnd), REDRAW_TIMER);
+                REDRAW_PENDING.store(false, Ordering::Release);
+                wrap(|state| state.on_audio_changed(hwnd));
+            }
+
+            WM_TIMER if wparam.0 == ANIMATION_TIMER || wparam.0 == SCROLL_TIMER => {
+                let _ = InvalidateRect(Some(hwnd), None, true);
+            }
+
+            WM_TIMER if wparam.0 == LIVE_TIMER => {
+                let _ = KillTimer(Some(hwnd), LIVE_TIMER);
+                let _ = InvalidateRect(Some(hwnd), None, true);
+            }
+
+            WM_TIMER if wparam.0 == DIAGNOSTICS_TIMER => {
+                let _ = InvalidateRect(Some(hwnd), None, true);
+            }
+
+            WM_TIMER if wparam.0 == SURFACE_RETRY_TIMER => {
+                let _ = KillTimer(Some(hwnd), SURFACE_RETRY_TIMER);
+                let _ = InvalidateRect(Some(hwnd), None, true);
+            }
+
+            WM_TIMER if wparam.0 == CLOCK_TIMER => {
+                let _ = KillTimer(Some(hwnd), CLOCK_TIMER);
+                wrap(|state| state.advance_pomodoro());
+                let _ = InvalidateRect(Some(hwnd), None, true);
+            }
+
+            WM_TIMER if wparam.0 == POLL_TIMER => {
+                wrap(|state| state.poll(hwnd));
+            }
+
+            WM_TIMER if wparam.0 == RECONNECT_TIMER => {
+                let _ = KillTimer(Some(hwnd), RECONNECT_TIMER);
+                wrap(|state| state.reconnect_bluetooth(hwnd));
+            }
+
+            WM_TIMER if wparam.0 == ACTIVITY_TIMER => {
+                wrap(|state| state.check_idle());
+                wrap(|state| state.check_screensaver());
+            }
+
+            WM_TIMER if wparam.0 == MEDIA_KEY_TIMER => {
+                let _ = KillTimer(Some(hwnd), MEDIA_KEY_TIMER);
+                wrap(|state| state.show_osd(hwnd, eRender));
+            }
+
+            WM_TIMER if wparam.0 == OSD_TIMER => {
+                let _ = KillTimer(Some(hwnd), OSD_TIMER);
+                wrap(|state| {
+                    state.osd.hide();
+                    Ok(())
+                });
+            }
+
+            WM_TIMER if wparam.0 == REFRESH_TIMER => {
+                // other topmost windows can end up above ours without us losing focus
+                keep_z_order(hwnd);
+                wrap(|state| state.on_audio_changed(hwnd));
+
+                wrap(|state| state.check_conditions());
+                wrap(|state| state.evict_devices());
+            }
+
+            WM_DEFAULT_DEVICE_CHANGED => {
+                let flow = EDataFlow(wparam.0 as i32);
+                metrics::DEVICE_SWITCHES.add();
+                wrap_audio(|state| state.on_default_device_changed(flow));
+            }
+
+            WM_DEVICE_ICON_CHANGED => wrap(|state| {
+                state.audio.reload_icons();
+                Ok(())
+            }),
+
+            WM_DEVICE_NAME_CHANGED => wrap(|state| {
+                state.audio.reload_names();
+                Ok(())
+            }),
+
+            WM_DPICHANGED => {
+                let dpi = (wparam.0 & 0xffff) as u32;
+                let suggested = &*(lparam.0 as *const RECT);
+                wrap(|state| state.apply_dpi(hwnd, dpi, Some(suggested)));
+            }
+
+            WM_PAINT => {
+                let start = Instant::now();
+                wrap(|state| state.on_paint(hwnd));
+                metrics::record_paint(start.elapsed());
+            }
+
+            // the panel is an ansi window, but the tooltips are set up with wide strings
+            WM_NOTIFYFORMAT => return LRESULT(NFR_UNICODE as isize),
+
+            WM_NOTIFY => {
+                let header = &*(lparam.0 as *const NMHDR);
+
+                if header.code == TTN_GETDISPINFOW
+                    && let Some(widget) = Widget::from_index(header.idFrom)
+                {
+                    let info = &mut *(lparam.0 as *mut NMTTDISPINFOW);
+                    let result = try_wrap(|state| {
+                        let text = match state.widget_errors.get(&widget) {
+                            Some(error) => format!("Couldn't show this: {}", error),
+                            None => state.accessible_name(widget)?,
+                        };
+                        state.tooltips.fill(info, &text);
+                        Ok(())
+                    });
+
+                    if let Err(e) = result {
+                        log!("error: {:?}", e);
+                    }
+                }
+            }
+
+            WM_WTSSESSION_CHANGE => {
+                let session = lparam.0 as u32;
+
+                match wparam.0 as _ {
+                    WTS_SESSION_LOCK => wrap(|state| state.on_lock(hwnd, session)),
+                    WTS_SESSION_UNLOCK => wrap(|state| state.on_unlock(hwnd, session)),
+
+                    _ => {}
+                }
+            }
+
+            // top-level windows get these without registering
+            WM_POWERBROADCAST => {
+                match wparam.0 as u32 {
+                    PBT_APMSUSPEND => wrap(|state| state.on_suspend()),
+                    PBT_APMRESUMEAUTOMATIC => wrap(|state| state.on_resume(hwnd)),
+                    _ => {}
+                }
+
+                return LRESULT(1);
+            }
 
             WM_LBUTTONDOWN => {
-                wrap(|state| state.connect_airpods());
+                let point = lparam_point(lparam);
+                let mut dragging = false;
+
+                // shift-drag moves the panel, plain dragging in edit mode moves a widget and
+                // ctrl-drag nudges it
+                wrap(|state| {
+                    dragging = if GetKeyState(VK_SHIFT.0 as i32) < 0 {
+                        state.start_drag(hwnd)?
+                    } else {
+                        let nudge = GetKeyState(VK_CONTROL.0 as i32) < 0;
+                        state.start_edit_drag(hwnd, point, nudge)?
+                    };
+                    Ok(())
+                });
+
+                if !dragging {
+                    on_gesture(hwnd, point, Gesture::Click, true);
+                }
             }
 
-            WM_RBUTTONDOWN => {
-                CloseWindow(hwnd).unwrap();
-                DestroyWindow(hwnd).unwrap();
+            WM_MOUSEMOVE => {
+                let point = lparam_point(lparam);
+                let _ = try_wrap(|state| state.on_mouse_move(hwnd, point));
             }
 
+            WM_LBUTTONUP => wrap(|state| state.on_button_up(hwnd)),
+
+            WM_CAPTURECHANGED => {
+                // the state is busy if a drag is finishing normally
+                let _ = try_wrap(|state| {
+                    state.drag = None;
+                    if let Some(edit) = &mut state.edit {
+                        edit.dragging = None;
+                    }
+                    Ok(())
+                });
+            }
+
+            WM_LBUTTONDBLCLK => on_gesture(hwnd, lparam_point(lparam), Gesture::DoubleClick, true),
+            WM_MBUTTONDOWN => on_gesture(hwnd, lparam_point(lparam), Gesture::MiddleClick, true),
+            WM_RBUTTONDOWN => on_gesture(hwnd, lparam_point(lparam), Gesture::RightClick, true),
+
             WM_MOUSEWHEEL => {
                 let value = ((wparam.0 >> 16) & 0xffff) as i16;
                 let up = value > 0;
-                wrap(|state| state.step_volume(up));
+
+                // wheel messages carry screen coordinates
+                let mut point = lparam_point(lparam);
+                let _ = ScreenToClient(hwnd, &mut point);
+
+                on_gesture(hwnd, point, Gesture::Wheel, up);
             }
 
             WM_DEVICECHANGE => {
                 wrap(|state| state.update_devices());
             }
 
+            WM_GETOBJECT if lparam.0 as i32 == UiaRootObjectId => {
+                return accessibility::return_root_provider(hwnd, wparam, lparam);
+            }
+
+            WM_INVOKE_WIDGET => {
+                if let Some(widget) = Widget::from_index(wparam.0) {
+                    on_widget_gesture(hwnd, Some(widget), Gesture::Click, true);
+                }
+            }
+
+            WM_TOGGLE_WIDGET => {
+                if let Some(widget) = Widget::from_index(wparam.0)
+                    && widget.flow().is_some()
+                {
+                    let queued = Queued::new(Some(widget), Action::ToggleMute, true);
+                    queue_action(hwnd, queued.from(MuteSource::Panel));
+                }
+            }
+
+            WM_RUN_ACTIONS => wrap(|state| state.run_queued(hwnd)),
+
+            // a command from another program, as text in the same form as from the pipe
+            WM_COPYDATA => {
+                let data = &*(lparam.0 as *const COPYDATASTRUCT);
+                if data.dwData != ipc::COPYDATA_COMMAND || data.lpData.is_null() || data.cbData == 0
+                {
+                    return LRESULT(0);
+                }
+
+                let text = std::slice::from_raw_parts(data.lpData as *const u8, data.cbData as _);
+                let text = String::from_utf8_lossy(text);
+
+                let result = text
+                    .parse()
+                    .and_then(|command| try_wrap(|state| state.run_ipc(hwnd, command)));
+
+                return match result {
+                    Ok(_) => LRESULT(1),
+                    Err(e) => {
+                        log!("WM_COPYDATA {:?} failed: {:?}", text, e);
+                        LRESULT(0)
+                    }
+                };
+            }
+
+            WM_DISCORD_MUTE => wrap(|state| state.on_discord_mute(wparam.0 != 0)),
+
+            WM_WORKER_DONE => {
+                wrap_audio(|state| state.on_worker_done());
+                wrap(|state| state.on_audio_changed(hwnd));
+                let _ = InvalidateRect(Some(hwnd), None, true);
+            }
+
+            WM_WATCHDOG_PING => watchdog::pong(wparam.0 as u32),
+
+            WM_IPC_COMMAND => {
+                // the sender is blocked until this returns, so the call outlives this
+                let call = &mut *(lparam.0 as *mut ipc::Call);
+                let command =
+                    std::mem::replace(&mut call.command, ipc::Command::Status { full: false });
+                call.response = Some(try_wrap(|state| state.run_ipc(hwnd, command)));
+
+                return LRESULT(0);
+            }
+
             _ => {
                 #[cfg(debug_assertions)]
                 println!("event: {:x} {}", event, message_name(event));
@@ -902,7 +4394,7 @@ unsafe extern "system" fn window_proc(
 
 #[implement(IMMNotificationClient)]
 struct DeviceCallback {
-    redraw_handle: RedrawHandle,
+    notifier: Notifier,
 }
 
 impl IMMNotificationClient_Impl for DeviceCallback_Impl {
@@ -924,11 +4416,17 @@ impl IMMNotificationClient_Impl for DeviceCallback_Impl {
 
     fn OnDefaultDeviceChanged(
         &self,
-        _flow: EDataFlow,
-        _role: ERole,
+        flow: EDataFlow,
+        role: ERole,
         _pwstrdefaultdeviceid: &PCWSTR,
     ) -> windows_core::Result<()> {
-        self.redraw_handle.redraw();
+        // called once per role, only the one we read matters. posted before the redraw so the
+        // cached default is forgotten before the change is looked at
+        if role == eMultimedia {
+            self.notifier.default_device_changed(flow);
+        }
+
+        self.notifier.changed();
 
         Ok(())
     }
@@ -936,43 +4434,155 @@ impl IMMNotificationClient_Impl for DeviceCallback_Impl {
     fn OnPropertyValueChanged(
         &self,
         _pwstrdeviceid: &PCWSTR,
-        _key: &PROPERTYKEY,
+        key: &PROPERTYKEY,
     ) -> windows_core::Result<()> {
+        if *key == PKEY_DeviceClass_IconPath {
+            self.notifier.icon_changed();
+        } else if *key == PKEY_Device_FriendlyName {
+            // the cached names are read again on the worker, which repaints once it's done
+            self.notifier.name_changed();
+        }
+
         Ok(())
     }
 }
 
+/// one for each tracked device, see `AudioDevice::levels`
 #[implement(IAudioEndpointVolumeCallback)]
 struct VolumeCallback {
-    redraw_handle: RedrawHandle,
+    notifier: Notifier,
+    levels: Arc<Mutex<Levels>>,
 }
 
 impl IAudioEndpointVolumeCallback_Impl for VolumeCallback_Impl {
     fn OnNotify(
         &self,
-        _event: *mut windows::Win32::Media::Audio::AUDIO_VOLUME_NOTIFICATION_DATA,
+        event: *mut windows::Win32::Media::Audio::AUDIO_VOLUME_NOTIFICATION_DATA,
+    ) -> windows_core::Result<()> {
+        if let Some(event) = unsafe { event.as_ref() } {
+            *self.levels.lock().unwrap() = Levels {
+                volume: event.fMasterVolume,
+                muted: event.bMuted.as_bool(),
+            };
+        }
+
+        self.notifier.changed();
+
+        Ok(())
+    }
+}
+
+/// one for each capture device whose sessions are watched, see `AudioManager::is_capturing`
+#[implement(IAudioSessionNotification, IAudioSessionEvents)]
+struct CaptureCallback {
+    notifier: Notifier,
+    stale: Arc<AtomicBool>,
+}
+
+impl CaptureCallback {
+    fn changed(&self) {
+        self.stale.store(true, Ordering::Release);
+        self.notifier.changed();
+    }
+}
+
+impl IAudioSessionNotification_Impl for CaptureCallback_Impl {
+    fn OnSessionCreated(
+        &self,
+        _newsession: windows_core::Ref<'_, IAudioSessionControl>,
+    ) -> windows_core::Result<()> {
+        // the next look at the sessions registers for the new one's events
+        self.changed();
+
+        Ok(())
+    }
+}
+
+impl IAudioSessionEvents_Impl for CaptureCallback_Impl {
+    fn OnDisplayNameChanged(
+        &self,
+        _newdisplayname: &PCWSTR,
+        _eventcontext: *const GUID,
+    ) -> windows_core::Result<()> {
+        Ok(())
+    }
+
+    fn OnIconPathChanged(
+        &self,
+        _newiconpath: &PCWSTR,
+        _eventcontext: *const GUID,
+    ) -> windows_core::Result<()> {
+        Ok(())
+    }
+
+    fn OnSimpleVolumeChanged(
+        &self,
+        _newvolume: f32,
+        _newmute: BOOL,
+        _eventcontext: *const GUID,
+    ) -> windows_core::Result<()> {
+        Ok(())
+    }
+
+    fn OnChannelVolumeChanged(
+        &self,
+        _channelcount: u32,
+        _newchannelvolumearray: *const f32,
+        _changedchannel: u32,
+        _eventcontext: *const GUID,
+    ) -> windows_core::Result<()> {
+        Ok(())
+    }
+
+    fn OnGroupingParamChanged(
+        &self,
+        _newgroupingparam: *const GUID,
+        _eventcontext: *const GUID,
+    ) -> windows_core::Result<()> {
+        Ok(())
+    }
+
+    fn OnStateChanged(&self, _newstate: AudioSessionState) -> windows_core::Result<()> {
+        self.changed();
+
+        Ok(())
+    }
+
+    fn OnSessionDisconnected(
+        &self,
+        _disconnectreason: AudioSessionDisconnectReason,
     ) -> windows_core::Result<()> {
-        self.redraw_handle.redraw();
+        self.changed();
 
         Ok(())
     }
 }
 
-fn initialize_gdip() {
+/// the token for `GdiplusShutdown`
+fn initialize_gdip() -> usize {
     let mut token = 0;
     let mut input = GdiplusStartupInput::default();
     input.GdiplusVersion = 1;
     let mut output = default();
     unsafe { GdiplusStartup(&mut token, &input, &mut output) };
+    token
 }
 
-fn create_window() -> Result<HWND> {
+/// for other programs to find the window by, e.g. to send it `WM_COPYDATA` with `dwData` set to
+/// `ipc::COPYDATA_COMMAND`
+const WINDOW_CLASS: PCSTR = s!("mfro window class");
+
+fn create_window(backend: render::Backend, z_order: ZOrder, position: Position) -> Result<HWND> {
     unsafe {
         let hinstance: HINSTANCE = GetModuleHandleA(None)?.into();
 
-        let window_class_name = s!("mfro window class");
+        let window_class_name = WINDOW_CLASS;
+
+        // resized for the monitor's dpi once the state exists
+        let (width, height) = Layout::default().size();
 
         let mut wc = WNDCLASSA::default();
+        wc.style = CS_DBLCLKS;
         wc.hInstance = hinstance;
         wc.lpfnWndProc = Some(window_proc);
         wc.lpszClassName = window_class_name;
@@ -983,14 +4593,14 @@ fn create_window() -> Result<HWND> {
         }
 
         let hwnd = CreateWindowExA(
-            WS_EX_LAYERED | WS_EX_NOACTIVATE | WS_EX_TOPMOST,
+            WS_EX_NOACTIVATE | z_order.window_style() | backend.window_style(),
             window_class_name,
             s!("mfro window name"),
             WS_POPUP | WS_VISIBLE,
-            -800,
-            1440 - 48,
-            400,
-            48,
+            position.x,
+            position.y,
+            width,
+            height,
             HWND_DESKTOP,
             default(),
             hinstance,
@@ -1001,29 +4611,188 @@ fn create_window() -> Result<HWND> {
     }
 }
 
+/// undoes what `run` set up as it returns, however it returns, so it can be run again in the
+/// same process without the last run's window class, threads or registrations in the way. made
+/// first so it's dropped last, once everything else from the run is gone.
+#[derive(Default)]
+struct Teardown {
+    com: bool,
+    gdip: Option<usize>,
+    /// the window, which also means its class is registered
+    hwnd: Option<HWND>,
+    /// dropped after the body, once the window they send messages to is gone
+    clip: Option<api::HttpServer>,
+    pipe: Option<ipc::PipeServer>,
+    api: Option<api::HttpServer>,
+    watchdog: Option<watchdog::Watchdog>,
+}
+
+impl Drop for Teardown {
+    fn drop(&mut self) {
+        unsafe {
+            // left behind when `run` failed before taking it back
+            if let Some(state) = WINDOW_HELPER.take()
+                && let Ok(state) = state.into_inner()
+            {
+                let _ = state.audio.destroy();
+            }
+
+            if let Some(hwnd) = self.hwnd {
+                // already gone if the message loop ended normally
+                if IsWindow(Some(hwnd)).as_bool() {
+                    let _ = DestroyWindow(hwnd);
+                }
+
+                // which posted `WM_QUIT`, and it mustn't end the next run's loop
+                let mut message = MSG::default();
+                while PeekMessageA(&mut message, None, WM_QUIT, WM_QUIT, PM_REMOVE).into() {}
+
+                if let Ok(module) = GetModuleHandleA(None) {
+                    let _ = UnregisterClassA(WINDOW_CLASS, Some(module.into()));
+                }
+            }
+
+            if let Some(token) = self.gdip {
+                GdiplusShutdown(token);
+            }
+
+            if self.com {
+                CoUninitialize();
+            }
+
+            // so the next run doesn't think a redraw's already on its way
+            REDRAW_PENDING.store(false, Ordering::Release);
+            TASKBAR_CREATED.store(0, Ordering::Relaxed);
+            SHELL_HOOK.store(0, Ordering::Relaxed);
+        }
+    }
+}
+
 fn run() -> Result<()> {
     unsafe {
+        let mut teardown = Teardown::default();
+
         log!("launch attempt");
+        // the window's thread has to be single-threaded for the accessibility provider and the
+        // shell. audio objects made on the worker's multithreaded apartment only reach it
+        // through agile references
         CoInitialize(None).ok()?;
-        initialize_gdip();
+        teardown.com = true;
+
+        // toasts don't show up without it, but nothing else depends on it
+        if let Err(e) = notifications::register() {
+            log!("failed to register for notifications: {:?}", e);
+        }
+        teardown.gdip = Some(initialize_gdip());
+
+        // fails if it's already been set, e.g. when `run` is retried
+        let _ = SetProcessDpiAwarenessContext(DPI_AWARENESS_CONTEXT_PER_MONITOR_AWARE_V2);
+
+        let config = Config::load().unwrap_or_else(|e| {
+            log!("config error: {:?}", e);
+            default()
+        });
+        eventlog::set_enabled(config.event_log);
 
-        let hwnd = create_window()?;
-        clip::spawn(hwnd);
+        // the window's style depends on the renderer, so it has to be settled first
+        let backend = match config.renderer.check() {
+            Ok(()) => config.renderer,
+            Err(e) => {
+                log!("falling back to gdi+: {:?}", e);
+                render::Backend::GdiPlus
+            }
+        };
+
+        let hwnd = create_window(backend, config.z_order, config.position)?;
+        teardown.hwnd = Some(hwnd);
 
-        // register for WM_WTSSESSION_CHANGE events
+        let renderer = backend.create()?;
+        let layout = Layout::new(&config.widgets, &config.widget_offsets, 1.0);
+        let osd = Osd::new(backend)?;
+        let flyout = Flyout::new(backend)?;
+        let tooltips = Tooltips::new(hwnd)?;
+        teardown.clip = clip::spawn();
+        teardown.pipe = Some(ipc::spawn(hwnd));
+        teardown.api = api::spawn(hwnd, &config.api);
+
+        // register for WM_WTSSESSION_CHANGE events. other sessions' are only acted on if
+        // `lock.other_sessions` is set
         WTSRegisterSessionNotification(hwnd, NOTIFY_FOR_ALL_SESSIONS)?;
 
-        let redraw_handle = RedrawHandle::new(hwnd);
-        let audio_manager = AudioManager::new(redraw_handle)?;
+        let notifier = Notifier::new(hwnd);
+        let audio_manager = AudioManager::new(notifier)?;
+        let discord = discord::Discord::spawn(hwnd, &config.discord);
+        let obs = obs::Obs::spawn(&config.obs);
 
         WINDOW_HELPER.set(Some(Mutex::new(WindowHelper {
             audio: audio_manager,
+            config,
+            layout,
+            renderer,
+            animations: MuteAnimations::default(),
+            keyboard: None,
+            trays: vec![],
+            conditions: Conditions::default(),
+            osd,
+            flyout,
+            tooltips,
+            monitors: Monitors::default(),
+            drag: None,
+            edit: None,
+            bound_hotkeys: 0,
             airpods_available: false,
-            unlock_mute_input: false,
-            unlock_mute_output: false,
+            headset: None,
+            lock_muted: vec![],
+            lock_paused_media: Arc::new(AtomicBool::new(false)),
+            reconnect_attempts: 0,
+            mute_watch: MuteWatch::default(),
+            headset_buttons: headset::HeadsetButtons::default(),
+            state_watch: events::StateWatch::default(),
+            idle_muted: None,
+            screensaver_muted: None,
+            focus_override: FocusOverride::default(),
+            lock_mute_paused: false,
+            locked_at: None,
+            discord,
+            obs,
+            status_block: shared::StatusBlock::create().ok(),
+            widget_errors: HashMap::new(),
+            painted_audio: None,
+            surface_lost: false,
+            diagnostics: None,
+            actions: ActionQueue::default(),
+            output_id: None,
+            loudness: None,
         })));
 
-        redraw_handle.redraw();
+        TASKBAR_CREATED.store(
+            RegisterWindowMessageA(s!("TaskbarCreated")),
+            Ordering::Relaxed,
+        );
+
+        SHELL_HOOK.store(RegisterWindowMessageA(s!("SHELLHOOK")), Ordering::Relaxed);
+        // only needed for the osd on volume keys, so not worth failing over
+        let _ = RegisterShellHookWindow(hwnd);
+
+        wrap(|state| {
+            state.apply_tray_config(hwnd)?;
+            state.load_button_icons()?;
+            state.apply_dpi(hwnd, GetDpiForWindow(hwnd), None)?;
+            state.register_hotkeys(hwnd)?;
+            headset::register(hwnd, state.config.headset_buttons.enabled)?;
+            state.apply_activity_config(hwnd);
+            state.apply_position(hwnd)?;
+            state.output_id = state.audio.default_id(eRender).ok();
+            state.poll(hwnd)
+        });
+
+        match watchdog::spawn(ipc::X(hwnd), on_hang) {
+            Ok(watchdog) => teardown.watchdog = Some(watchdog),
+            Err(e) => log!("failed to start the watchdog: {:?}", e),
+        }
+
+        notifier.changed();
+        SetTimer(Some(hwnd), REFRESH_TIMER, REFRESH_INTERVAL_MS, None);
 
         let mut message = MSG::default();
 
@@ -1040,14 +4809,57 @@ fn run() -> Result<()> {
     Ok(())
 }
 
-fn main() {
-    loop {
+/// called on the watchdog's thread once the window's thread has stopped answering, e.g. stuck in
+/// a COM call that'll never return. nothing can get it out of that, so rather than `run` being
+/// retried the whole process starts over.
+fn on_hang(report: String) {
+    log!("hung: {}", report);
+    eventlog::error(&format!("restarting after hanging: {}", report));
+
+    // the new process waits for this one to be gone before taking the window and hotkeys
+    if let Ok(exe) = std::env::current_exe() {
+        let _ = std::process::Command::new(exe)
+            .arg(watchdog::AFTER_HANG)
+            .arg(std::process::id().to_string())
+            .spawn();
+    }
+
+    std::process::exit(1);
+}
+
+fn main() -> ExitCode {
+    // with arguments, this is a command for the instance that's already running
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().is_some_and(|arg| arg == watchdog::AFTER_HANG) {
+        if let Some(pid) = args.get(1).and_then(|pid| pid.parse().ok()) {
+            watchdog::wait_for_exit(pid);
+        }
+
+        args.clear();
+    }
+
+    if !args.is_empty() {
+        return ipc::run_cli(&args);
+    }
+
+    metrics::start();
+
+    for restarts in 1.. {
         match run() {
             Ok(()) => break,
             Err(e) => {
                 log!("main error: {:?}", e);
+                eventlog::warning(&format!("restarting after an error: {:?}", e));
+
+                // each restart is worth a warning, but one that keeps happening is a problem
+                if restarts % 10 == 0 {
+                    eventlog::error(&format!("restarted {} times after errors", restarts));
+                }
+
                 std::thread::sleep(Duration::from_millis(500));
             }
         }
     }
+
+    ExitCode::SUCCESS
 }
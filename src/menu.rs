@@ -0,0 +1,126 @@
+use anyhow::Result;
+use windows::Win32::{
+    Foundation::{HWND, POINT},
+    Media::Audio::EDataFlow,
+    UI::WindowsAndMessaging::{
+        AppendMenuW, CreatePopupMenu, DestroyMenu, GetCursorPos, HMENU, MF_CHECKED, MF_POPUP,
+        MF_SEPARATOR, MF_STRING, PostMessageA, SetForegroundWindow, TPM_NONOTIFY, TPM_RETURNCMD,
+        TPM_RIGHTBUTTON, TrackPopupMenu, WM_NULL,
+    },
+};
+use windows_core::{HSTRING, PCWSTR};
+
+use crate::{default, placement::Preset, session::LockMute};
+
+#[derive(Clone, Debug)]
+pub enum MenuCommand {
+    SetDefault(String),
+    ToggleMute(EDataFlow),
+    DeviceProperties(EDataFlow),
+    Settings,
+    ReloadConfig,
+    ToggleLockMute,
+    SetLockMute(LockMute),
+    ToggleLockLayout,
+    ToggleEditLayout,
+    ToggleDiagnostics,
+    Place(Preset),
+    ConnectBluetooth(String),
+    BluetoothSettings,
+    Quit,
+}
+
+/// a popup menu whose items map back to `MenuCommand`s
+pub struct Menu {
+    root: HMENU,
+    commands: Vec<MenuCommand>,
+}
+
+impl Menu {
+    pub fn new() -> Result<Self> {
+        let root = unsafe { CreatePopupMenu()? };
+
+        Ok(Self {
+            root,
+            commands: vec![],
+        })
+    }
+
+    pub fn root(&self) -> HMENU {
+        self.root
+    }
+
+    pub fn item(
+        &mut self,
+        parent: HMENU,
+        label: &str,
+        command: MenuCommand,
+        checked: bool,
+    ) -> Result<()> {
+        self.commands.push(command);
+
+        // command ids start at 1, since TrackPopupMenu returns 0 when dismissed
+        let id = self.commands.len();
+        let flags = if checked {
+            MF_STRING | MF_CHECKED
+        } else {
+            MF_STRING
+        };
+
+        unsafe { AppendMenuW(parent, flags, id, &HSTRING::from(label))? };
+
+        Ok(())
+    }
+
+    pub fn submenu(&mut self, parent: HMENU, label: &str) -> Result<HMENU> {
+        unsafe {
+            let menu = CreatePopupMenu()?;
+            AppendMenuW(parent, MF_POPUP, menu.0 as usize, &HSTRING::from(label))?;
+
+            Ok(menu)
+        }
+    }
+
+    pub fn separator(&mut self, parent: HMENU) -> Result<()> {
+        unsafe { AppendMenuW(parent, MF_SEPARATOR, 0, PCWSTR::null())? };
+
+        Ok(())
+    }
+
+    /// shows the menu at the cursor and blocks until an item is picked or the menu is dismissed
+    pub fn track(self, hwnd: HWND) -> Result<Option<MenuCommand>> {
+        unsafe {
+            let mut point = POINT::default();
+            GetCursorPos(&mut point)?;
+
+            // the menu won't close when clicking elsewhere unless our window is in the foreground
+            let _ = SetForegroundWindow(hwnd);
+
+            let id = TrackPopupMenu(
+                self.root,
+                TPM_RETURNCMD | TPM_NONOTIFY | TPM_RIGHTBUTTON,
+                point.x,
+                point.y,
+                None,
+                hwnd,
+                None,
+            );
+
+            let _ = PostMessageA(Some(hwnd), WM_NULL, default(), default());
+
+            let command = match id.0 as usize {
+                0 => None,
+                id => self.commands.get(id - 1).cloned(),
+            };
+
+            Ok(command)
+        }
+    }
+}
+
+impl Drop for Menu {
+    fn drop(&mut self) {
+        // destroys submenus along with the root
+        let _ = unsafe { DestroyMenu(self.root) };
+    }
+}
@@ -0,0 +1,192 @@
+use std::{
+    fmt::Write,
+    sync::{
+        Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+/// a count that only goes up, from any thread
+pub struct Counter(AtomicU64);
+
+impl Counter {
+    const fn new() -> Self {
+        Self(AtomicU64::new(0))
+    }
+
+    pub fn add(&self) {
+        self.0.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn get(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// mutes and unmutes made by the app, whatever asked for them
+pub static MUTE_TOGGLES: Counter = Counter::new();
+/// default device changes, from anywhere
+pub static DEVICE_SWITCHES: Counter = Counter::new();
+/// messages handled by the window, from the system or posted by the app itself
+pub static EVENTS: Counter = Counter::new();
+
+/// errors from the window's handlers, which are otherwise only logged
+static ERRORS: Counter = Counter::new();
+static LAST_ERROR: Mutex<Option<String>> = Mutex::new(None);
+
+static PAINTS: Counter = Counter::new();
+static PAINT_MICROS: AtomicU64 = AtomicU64::new(0);
+static LAST_PAINT_MICROS: AtomicU64 = AtomicU64::new(0);
+/// when the window last answered the watchdog's ping
+static LAST_PONG: AtomicU64 = AtomicU64::new(0);
+static STARTED: AtomicU64 = AtomicU64::new(0);
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|since| since.as_secs())
+        .unwrap_or_default()
+}
+
+pub fn start() {
+    STARTED.store(now(), Ordering::Relaxed);
+}
+
+pub fn record_paint(duration: Duration) {
+    PAINTS.add();
+    PAINT_MICROS.fetch_add(duration.as_micros() as u64, Ordering::Relaxed);
+    LAST_PAINT_MICROS.store(duration.as_micros() as u64, Ordering::Relaxed);
+}
+
+pub fn record_pong() {
+    LAST_PONG.store(now(), Ordering::Relaxed);
+}
+
+pub fn record_error(error: &anyhow::Error) {
+    ERRORS.add();
+
+    if let Ok(mut last) = LAST_ERROR.lock() {
+        *last = Some(format!("{:#}", error));
+    }
+}
+
+/// the counters as they are now, for the diagnostics overlay and the status command
+pub struct Snapshot {
+    pub paints: u64,
+    pub paint_time: Duration,
+    pub last_paint_time: Duration,
+    pub events: u64,
+    pub errors: u64,
+    pub last_error: Option<String>,
+}
+
+pub fn snapshot() -> Snapshot {
+    Snapshot {
+        paints: PAINTS.get(),
+        paint_time: Duration::from_micros(PAINT_MICROS.load(Ordering::Relaxed)),
+        last_paint_time: Duration::from_micros(LAST_PAINT_MICROS.load(Ordering::Relaxed)),
+        events: EVENTS.get(),
+        errors: ERRORS.get(),
+        last_error: LAST_ERROR.lock().ok().and_then(|last| last.clone()),
+    }
+}
+
+/// how fast `EVENTS` is going up, worked out again whenever a second or more has passed
+pub struct EventRate {
+    since: Instant,
+    events: u64,
+    per_second: f64,
+}
+
+impl EventRate {
+    pub fn new() -> Self {
+        Self {
+            since: Instant::now(),
+            events: EVENTS.get(),
+            per_second: 0.0,
+        }
+    }
+
+    pub fn per_second(&mut self) -> f64 {
+        let elapsed = self.since.elapsed();
+
+        if elapsed >= Duration::from_secs(1) {
+            let events = EVENTS.get();
+            self.per_second = (events - self.events) as f64 / elapsed.as_secs_f64();
+            self.since = Instant::now();
+            self.events = events;
+        }
+
+        self.per_second
+    }
+}
+
+/// everything in prometheus's text format. the last pong's time stops moving if the window's
+/// thread gets stuck, which is worth alerting on. paints can't be used for that, since nothing
+/// is repainted while nothing changes.
+pub fn render() -> String {
+    let paint_seconds = PAINT_MICROS.load(Ordering::Relaxed) as f64 / 1e6;
+
+    let metrics = [
+        (
+            "mute_toggles_total",
+            "counter",
+            "mutes and unmutes made by the panel",
+            MUTE_TOGGLES.get() as f64,
+        ),
+        (
+            "device_switches_total",
+            "counter",
+            "default device changes",
+            DEVICE_SWITCHES.get() as f64,
+        ),
+        ("redraws_total", "counter", "paints", PAINTS.get() as f64),
+        (
+            "paint_duration_seconds_total",
+            "counter",
+            "time spent painting",
+            paint_seconds,
+        ),
+        (
+            "last_paint_duration_seconds",
+            "gauge",
+            "how long the last paint took",
+            LAST_PAINT_MICROS.load(Ordering::Relaxed) as f64 / 1e6,
+        ),
+        (
+            "events_total",
+            "counter",
+            "messages handled by the window",
+            EVENTS.get() as f64,
+        ),
+        (
+            "errors_total",
+            "counter",
+            "errors from the window's handlers",
+            ERRORS.get() as f64,
+        ),
+        (
+            "last_pong_timestamp_seconds",
+            "gauge",
+            "when the window last answered the watchdog, every few seconds while it's responsive",
+            LAST_PONG.load(Ordering::Relaxed) as f64,
+        ),
+        (
+            "start_time_seconds",
+            "gauge",
+            "when the panel started",
+            STARTED.load(Ordering::Relaxed) as f64,
+        ),
+    ];
+
+    let mut text = String::new();
+
+    for (name, kind, help, value) in metrics {
+        let _ = writeln!(text, "# HELP control_panel_{} {}", name, help);
+        let _ = writeln!(text, "# TYPE control_panel_{} {}", name, kind);
+        let _ = writeln!(text, "control_panel_{} {}", name, value);
+    }
+
+    text
+}
@@ -0,0 +1,172 @@
+use std::{
+    collections::HashSet,
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use windows::{
+    UI::Notifications::{ToastNotification, ToastNotificationManager, ToastTemplateType},
+    Win32::{
+        Storage::EnhancedStorage::PKEY_AppUserModel_ID,
+        System::{
+            Com::{
+                CLSCTX_INPROC_SERVER, CoCreateInstance, CoTaskMemFree, IPersistFile,
+                StructuredStorage::{PROPVARIANT, PVCHF_DEFAULT, PropVariantChangeType},
+            },
+            Variant::VT_LPWSTR,
+        },
+        UI::Shell::{
+            FOLDERID_Programs, IShellLinkW, KF_FLAG_DEFAULT, PropertiesSystem::IPropertyStore,
+            SHGetKnownFolderPath, SetCurrentProcessExplicitAppUserModelID, ShellLink,
+        },
+    },
+};
+use windows_core::{HSTRING, Interface, h};
+
+/// the app id toasts are shown under. windows only shows them for an id that a start menu
+/// shortcut carries, which `register` takes care of
+const APP_ID: &str = "mfro.ControlPanel";
+
+const SHORTCUT_NAME: &str = "Control Panel.lnk";
+
+/// battery percentage at or below which the headset or a peripheral counts as low
+const BATTERY_LOW: u8 = 20;
+
+/// how long the mic has to stay live before it's announced
+const MIC_HOT_AFTER: Duration = Duration::from_secs(10 * 60);
+
+/// which events raise a toast
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct NotificationConfig {
+    pub default_device_changed: bool,
+    pub battery_low: bool,
+    pub reconnect_failed: bool,
+    pub mic_left_hot: bool,
+    /// hotkeys that couldn't be registered, when starting or reloading the config
+    pub hotkey_conflict: bool,
+}
+
+impl Default for NotificationConfig {
+    fn default() -> Self {
+        Self {
+            // usually the result of something the user just did
+            default_device_changed: false,
+            battery_low: true,
+            reconnect_failed: true,
+            mic_left_hot: true,
+            hotkey_conflict: true,
+        }
+    }
+}
+
+/// points a start menu shortcut carrying `APP_ID` at the running exe, creating it if it's
+/// missing, and takes on `APP_ID` for this process. needs com to be initialized
+pub fn register() -> Result<()> {
+    unsafe {
+        SetCurrentProcessExplicitAppUserModelID(&HSTRING::from(APP_ID))?;
+
+        let exe = std::env::current_exe()?;
+        let link: IShellLinkW = CoCreateInstance(&ShellLink, None, CLSCTX_INPROC_SERVER)?;
+        link.SetPath(&HSTRING::from(exe.as_os_str()))?;
+
+        // the shell link only takes the id as a plain wide string, not the bstr `from` makes
+        let mut id = PROPVARIANT::default();
+        PropVariantChangeType(
+            &mut id,
+            &PROPVARIANT::from(APP_ID),
+            PVCHF_DEFAULT,
+            VT_LPWSTR,
+        )?;
+
+        let store: IPropertyStore = link.cast()?;
+        store.SetValue(&PKEY_AppUserModel_ID, &id)?;
+        store.Commit()?;
+
+        let path = programs_folder()?.join(SHORTCUT_NAME);
+        link.cast::<IPersistFile>()?
+            .Save(&HSTRING::from(path.as_os_str()), true)
+            .with_context(|| format!("saving {}", path.display()))?;
+    }
+
+    Ok(())
+}
+
+/// the current user's start menu programs folder
+fn programs_folder() -> Result<PathBuf> {
+    unsafe {
+        let path = SHGetKnownFolderPath(&FOLDERID_Programs, KF_FLAG_DEFAULT, None)?;
+        let folder = path.to_string();
+        CoTaskMemFree(Some(path.0 as _));
+
+        Ok(PathBuf::from(folder?))
+    }
+}
+
+pub fn show(title: &str, body: &str) -> Result<()> {
+    let xml = ToastNotificationManager::GetTemplateContent(ToastTemplateType::ToastText02)?;
+    let texts = xml.GetElementsByTagName(h!("text"))?;
+
+    texts
+        .Item(0)?
+        .AppendChild(&xml.CreateTextNode(&HSTRING::from(title))?)?;
+    texts
+        .Item(1)?
+        .AppendChild(&xml.CreateTextNode(&HSTRING::from(body))?)?;
+
+    let toast = ToastNotification::CreateToastNotification(&xml)?;
+    ToastNotificationManager::CreateToastNotifierWithId(&HSTRING::from(APP_ID))?.Show(&toast)?;
+
+    Ok(())
+}
+
+/// tracks ongoing conditions so each one is announced once when it starts, not on every check
+#[derive(Default)]
+pub struct Conditions {
+    battery_low: bool,
+    /// the peripherals whose battery is low
+    peripherals_low: HashSet<String>,
+    mic_hot_since: Option<Instant>,
+    mic_hot_announced: bool,
+}
+
+impl Conditions {
+    /// records the headset's battery level, returning true when it has just become low
+    pub fn battery(&mut self, level: Option<u8>) -> bool {
+        let low = level.is_some_and(|level| level <= BATTERY_LOW);
+        let started = low && !self.battery_low;
+        self.battery_low = low;
+
+        started
+    }
+
+    /// records a peripheral's battery level, returning true when it has just become low
+    pub fn peripheral_battery(&mut self, device: &str, level: Option<u8>) -> bool {
+        let low = level.is_some_and(|level| level <= BATTERY_LOW);
+        if !low {
+            self.peripherals_low.remove(device);
+            return false;
+        }
+
+        self.peripherals_low.insert(device.to_string())
+    }
+
+    /// records whether the mic is live, returning true once it's been live for `MIC_HOT_AFTER`
+    pub fn mic(&mut self, hot: bool, now: Instant) -> bool {
+        if !hot {
+            self.mic_hot_since = None;
+            self.mic_hot_announced = false;
+            return false;
+        }
+
+        let since = *self.mic_hot_since.get_or_insert(now);
+        if self.mic_hot_announced || now - since < MIC_HOT_AFTER {
+            return false;
+        }
+
+        self.mic_hot_announced = true;
+        true
+    }
+}
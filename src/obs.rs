@@ -0,0 +1,344 @@
+use std::{
+    sync::{
+        Mutex,
+        mpsc::{self, Receiver, RecvTimeoutError, Sender, TryRecvError},
+    },
+    time::Duration,
+};
+
+use anyhow::{Context, Result, bail};
+use serde::Deserialize;
+use serde_json::{Value, json};
+use windows::{
+    Foundation::{TypedEventHandler, Uri},
+    Networking::Sockets::{
+        IWebSocket, MessageWebSocket, MessageWebSocketMessageReceivedEventArgs, SocketMessageType,
+        WebSocketClosedEventArgs,
+    },
+    Security::Cryptography::{
+        BinaryStringEncoding,
+        Core::{HashAlgorithmNames, HashAlgorithmProvider},
+        CryptographicBuffer,
+    },
+    Storage::Streams::DataWriter,
+};
+use windows_core::{HSTRING, Ref};
+
+use crate::throttle::Throttle;
+
+const OP_HELLO: u64 = 0;
+const OP_IDENTIFY: u64 = 1;
+const OP_IDENTIFIED: u64 = 2;
+const OP_EVENT: u64 = 5;
+const OP_REQUEST: u64 = 6;
+const OP_REQUEST_RESPONSE: u64 = 7;
+
+/// the event subscription for outputs starting and stopping
+const EVENTS_OUTPUTS: u64 = 1 << 6;
+
+/// how long to wait before looking for obs again after it closes or isn't running
+const RETRY: Duration = Duration::from_secs(15);
+
+/// the most failures logged a minute, on top of repeats being collapsed
+const FAILURES_PER_MINUTE: u32 = 2;
+
+/// mutes an audio source in obs along with the input device, over obs-websocket (version 5,
+/// built into obs 28 and later). read at startup only.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct ObsConfig {
+    /// e.g. `ws://localhost:4455`. nothing connects to obs without one
+    pub url: Option<String>,
+    /// the server password from obs's websocket settings, if authentication is on
+    pub password: Option<String>,
+    /// the name of the audio source in obs to mute, e.g. `Mic/Aux`. without one the connection is
+    /// only for the widget.
+    pub source: Option<String>,
+}
+
+/// whether obs is recording or streaming, for the widget
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Outputs {
+    pub connected: bool,
+    pub recording: bool,
+    pub streaming: bool,
+}
+
+static OUTPUTS: Mutex<Outputs> = Mutex::new(Outputs {
+    connected: false,
+    recording: false,
+    streaming: false,
+});
+
+pub fn outputs() -> Outputs {
+    *OUTPUTS.lock().unwrap()
+}
+
+/// why obs turned the last connection down, e.g. a wrong password, until one's accepted
+static REFUSED: Mutex<Option<String>> = Mutex::new(None);
+
+/// for the widget to show, since retrying won't help until the config's fixed
+pub fn refused() -> Option<String> {
+    REFUSED.lock().unwrap().clone()
+}
+
+/// the panel's side of the connection, which lives on its own thread
+pub struct Obs {
+    mute: Sender<bool>,
+    /// the input's mute state as last sent to obs
+    last_muted: Option<bool>,
+}
+
+impl Obs {
+    pub fn spawn(config: &ObsConfig) -> Option<Self> {
+        let url = config.url.clone()?;
+        let password = config.password.clone().unwrap_or_default();
+        let source = config.source.clone();
+        let (mute, commands) = mpsc::channel();
+
+        std::thread::spawn(move || run(&url, &password, source.as_deref(), commands));
+
+        Some(Self {
+            mute,
+            last_muted: None,
+        })
+    }
+
+    /// tells obs the input's mute state, if it's changed since obs last heard about it
+    pub fn sync(&mut self, muted: bool) {
+        if self.last_muted != Some(muted) {
+            self.last_muted = Some(muted);
+            let _ = self.mute.send(muted);
+        }
+    }
+}
+
+/// base64 of the sha256 of `text`, which is what obs-websocket's authentication is made of
+fn hash(text: &str) -> Result<String> {
+    let sha256 = HashAlgorithmProvider::OpenAlgorithm(&HashAlgorithmNames::Sha256()?)?;
+    let data = CryptographicBuffer::ConvertStringToBinary(
+        &HSTRING::from(text),
+        BinaryStringEncoding::Utf8,
+    )?;
+    let hash = sha256.HashData(&data)?;
+
+    Ok(CryptographicBuffer::EncodeToBase64String(&hash)?.to_string())
+}
+
+struct Connection {
+    socket: MessageWebSocket,
+    writer: DataWriter,
+    /// each message as it arrives, or `None` once the socket closes
+    messages: Receiver<Option<Value>>,
+    request_id: u64,
+}
+
+impl Connection {
+    fn open(url: &str) -> Result<Self> {
+        let socket = MessageWebSocket::new()?;
+        socket.Control()?.SetMessageType(SocketMessageType::Utf8)?;
+
+        let (sender, messages) = mpsc::channel();
+
+        let received = sender.clone();
+        socket.MessageReceived(&TypedEventHandler::new(
+            move |_, args: Ref<MessageWebSocketMessageReceivedEventArgs>| {
+                let reader = args.ok()?.GetDataReader()?;
+                let text = reader.ReadString(reader.UnconsumedBufferLength()?)?;
+
+                if let Ok(message) = serde_json::from_str(&text.to_string_lossy()) {
+                    let _ = received.send(Some(message));
+                }
+
+                Ok(())
+            },
+        ))?;
+
+        socket.Closed(&TypedEventHandler::new(
+            move |_: Ref<IWebSocket>, _: Ref<WebSocketClosedEventArgs>| {
+                let _ = sender.send(None);
+                Ok(())
+            },
+        ))?;
+
+        socket
+            .ConnectAsync(&Uri::CreateUri(&HSTRING::from(url))?)?
+            .get()
+            .context("obs isn't running")?;
+
+        let writer = DataWriter::CreateDataWriter(&socket.OutputStream()?)?;
+
+        Ok(Self {
+            socket,
+            writer,
+            messages,
+            request_id: 0,
+        })
+    }
+
+    fn send(&mut self, op: u64, data: Value) -> Result<()> {
+        let message = json!({ "op": op, "d": data }).to_string();
+        self.writer.WriteString(&HSTRING::from(message))?;
+        self.writer.StoreAsync()?.get()?;
+        Ok(())
+    }
+
+    fn request(&mut self, request_type: &str, data: Value) -> Result<()> {
+        self.request_id += 1;
+
+        let request = json!({
+            "requestType": request_type,
+            "requestId": self.request_id.to_string(),
+            "requestData": data,
+        });
+        self.send(OP_REQUEST, request)
+    }
+
+    /// the next message, or `None` if there isn't one within `timeout`
+    fn receive(&self, timeout: Duration) -> Result<Option<Value>> {
+        match self.messages.recv_timeout(timeout) {
+            Ok(Some(message)) => Ok(Some(message)),
+            Ok(None) | Err(RecvTimeoutError::Disconnected) => bail!("obs closed the connection"),
+            Err(RecvTimeoutError::Timeout) => Ok(None),
+        }
+    }
+
+    fn expect(&self, op: u64) -> Result<Value> {
+        let message = self
+            .receive(Duration::from_secs(5))?
+            .context("obs didn't answer")?;
+
+        if message["op"].as_u64() != Some(op) {
+            bail!("unexpected message from obs: {}", message);
+        }
+
+        Ok(message["d"].clone())
+    }
+
+    /// answers the hello, with the password if obs asks for it
+    fn identify(&mut self, password: &str) -> Result<()> {
+        let hello = self.expect(OP_HELLO)?;
+
+        let mut identify = json!({
+            "rpcVersion": 1,
+            "eventSubscriptions": EVENTS_OUTPUTS,
+        });
+
+        let authentication = &hello["authentication"];
+        if let (Some(challenge), Some(salt)) = (
+            authentication["challenge"].as_str(),
+            authentication["salt"].as_str(),
+        ) {
+            let secret = hash(&format!("{}{}", password, salt))?;
+            identify["authentication"] = json!(hash(&format!("{}{}", secret, challenge))?);
+        }
+
+        self.send(OP_IDENTIFY, identify)?;
+
+        // obs closes the connection instead if the password's wrong
+        self.expect(OP_IDENTIFIED)?;
+        Ok(())
+    }
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        let _ = self.socket.Close();
+    }
+}
+
+/// picks up recording and streaming starting or stopping from an event or the answer to asking
+fn update_outputs(message: &Value) {
+    let (kind, data) = match message["op"].as_u64() {
+        Some(OP_EVENT) => (&message["d"]["eventType"], &message["d"]["eventData"]),
+        Some(OP_REQUEST_RESPONSE) => (&message["d"]["requestType"], &message["d"]["responseData"]),
+        _ => return,
+    };
+
+    let Some(active) = data["outputActive"].as_bool() else {
+        return;
+    };
+
+    let mut outputs = OUTPUTS.lock().unwrap();
+
+    match kind.as_str() {
+        Some("RecordStateChanged" | "GetRecordStatus") => outputs.recording = active,
+        Some("StreamStateChanged" | "GetStreamStatus") => outputs.streaming = active,
+        _ => {}
+    }
+}
+
+/// one connection to obs, until either side closes it. `latest` is the last state the panel
+/// asked for, sent again on connecting so obs picks up changes made while it was closed
+fn session(
+    url: &str,
+    password: &str,
+    source: Option<&str>,
+    commands: &Receiver<bool>,
+    latest: &mut Option<bool>,
+) -> Result<()> {
+    let mut connection = Connection::open(url)?;
+
+    let identified = connection
+        .identify(password)
+        .context("obs refused the connection, check the password");
+    *REFUSED.lock().unwrap() = identified.as_ref().err().map(|e| format!("{:#}", e));
+    identified?;
+
+    OUTPUTS.lock().unwrap().connected = true;
+    connection.request("GetRecordStatus", json!({}))?;
+    connection.request("GetStreamStatus", json!({}))?;
+
+    let set_mute = |connection: &mut Connection, muted: bool| match source {
+        Some(source) => connection.request(
+            "SetInputMute",
+            json!({ "inputName": source, "inputMuted": muted }),
+        ),
+        None => Ok(()),
+    };
+
+    if let Some(muted) = *latest {
+        set_mute(&mut connection, muted)?;
+    }
+
+    loop {
+        loop {
+            match commands.try_recv() {
+                Ok(muted) => {
+                    *latest = Some(muted);
+                    set_mute(&mut connection, muted)?;
+                }
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => return Ok(()),
+            }
+        }
+
+        if let Some(message) = connection.receive(Duration::from_millis(100))? {
+            update_outputs(&message);
+        }
+    }
+}
+
+fn run(url: &str, password: &str, source: Option<&str>, commands: Receiver<bool>) {
+    let mut latest = None;
+    let mut failures = Throttle::new(FAILURES_PER_MINUTE);
+
+    loop {
+        if let Err(e) = session(url, password, source, &commands, &mut latest) {
+            failures.pass(&format!("obs: {:#}", e), crate::log_background);
+        }
+
+        *OUTPUTS.lock().unwrap() = Outputs::default();
+
+        std::thread::sleep(RETRY);
+
+        // keeps whatever the panel asked for while obs was closed for when it's back
+        loop {
+            match commands.try_recv() {
+                Ok(muted) => latest = Some(muted),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => return,
+            }
+        }
+    }
+}
@@ -0,0 +1,262 @@
+use anyhow::{Result, bail};
+use serde::Deserialize;
+use windows::Win32::{
+    Foundation::{HWND, LPARAM, LRESULT, POINT, RECT, WPARAM},
+    Graphics::Gdi::{MONITOR_DEFAULTTOPRIMARY, MonitorFromPoint},
+    System::LibraryLoader::GetModuleHandleA,
+    UI::{
+        HiDpi::{GetDpiForMonitor, GetDpiForWindow, MDT_EFFECTIVE_DPI},
+        WindowsAndMessaging::{
+            DefWindowProcA, DestroyWindow, GetSystemMetrics, GetWindowRect, HICON, HWND_DESKTOP,
+            HWND_TOPMOST, RegisterClassA, SM_CXSCREEN, SM_CYSCREEN, SW_HIDE, SW_SHOWNOACTIVATE,
+            SWP_NOACTIVATE, SetWindowPos, ShowWindow, UnregisterClassA, WNDCLASSA,
+            WS_EX_NOACTIVATE, WS_EX_TOOLWINDOW, WS_EX_TOPMOST, WS_EX_TRANSPARENT, WS_POPUP,
+        },
+    },
+};
+use windows_core::{PCSTR, s};
+
+use crate::{
+    default,
+    interop::CreateWindowExA,
+    layout::Rect,
+    render::{Backend, LineCap, Renderer},
+};
+
+const WINDOW_CLASS: PCSTR = s!("mfro osd class");
+
+// in pixels at 96 dpi, like everything drawn on the osd
+const WIDTH: f32 = 240.0;
+const HEIGHT: f32 = 56.0;
+
+/// gap between the osd and the panel
+const MARGIN: f32 = 8.0;
+
+/// where the osd appears
+#[derive(Clone, Copy, Debug, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OsdPosition {
+    #[default]
+    AbovePanel,
+    BelowPanel,
+    /// the middle of the primary screen
+    Center,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct OsdConfig {
+    pub enabled: bool,
+    pub position: OsdPosition,
+    /// how long the osd stays up after the last change
+    pub timeout_ms: u32,
+    /// also show it for the keyboard's volume keys, alongside windows' own flyout
+    pub media_keys: bool,
+}
+
+impl Default for OsdConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            position: OsdPosition::default(),
+            timeout_ms: 1500,
+            media_keys: false,
+        }
+    }
+}
+
+/// a device's state as shown by the osd
+pub struct OsdState {
+    pub icon: HICON,
+    pub volume: f32,
+    pub muted: bool,
+}
+
+/// transient popup showing a device's volume and mute state after a hotkey changes it, since
+/// the panel itself is easy to miss
+pub struct Osd {
+    hwnd: HWND,
+    renderer: Box<dyn Renderer>,
+}
+
+impl Osd {
+    pub fn new(backend: Backend) -> Result<Self> {
+        // the renderer first, so failing to make it doesn't leave the window behind
+        let renderer = backend.create()?;
+        let hwnd = create_popup(WINDOW_CLASS, s!("mfro osd"), backend)?;
+
+        Ok(Self { hwnd, renderer })
+    }
+
+    /// shows `state` next to the panel at `panel`, until `hide` is called. it's scaled for the
+    /// monitor it shows up on.
+    pub fn show(&mut self, panel: HWND, position: OsdPosition, state: &OsdState) -> Result<()> {
+        let mut rect = RECT::default();
+        unsafe { GetWindowRect(panel, &mut rect)? };
+
+        let dpi = match position {
+            OsdPosition::AbovePanel | OsdPosition::BelowPanel => unsafe { GetDpiForWindow(panel) },
+            OsdPosition::Center => primary_dpi()?,
+        };
+
+        let scale = dpi as f32 / 96.0;
+        let px = |value: f32| value * scale;
+        let (width, height) = (px(WIDTH).round() as i32, px(HEIGHT).round() as i32);
+        let margin = px(MARGIN).round() as i32;
+
+        let (x, y) = match position {
+            OsdPosition::AbovePanel => (rect.left, rect.top - height - margin),
+            OsdPosition::BelowPanel => (rect.left, rect.bottom + margin),
+            OsdPosition::Center => unsafe {
+                (
+                    (GetSystemMetrics(SM_CXSCREEN) - width) / 2,
+                    (GetSystemMetrics(SM_CYSCREEN) - height) / 2,
+                )
+            },
+        };
+
+        unsafe {
+            SetWindowPos(
+                self.hwnd,
+                Some(HWND_TOPMOST),
+                x,
+                y,
+                width,
+                height,
+                SWP_NOACTIVATE,
+            )?;
+        }
+
+        // drawn before it's shown so the previous frame doesn't flash up
+        self.renderer.render(self.hwnd, &mut |canvas| {
+            let background = Rect {
+                x: 0.0,
+                y: 0.0,
+                width: width as f32,
+                height: height as f32,
+            };
+            canvas.fill_rect(background, 0xe0202020)?;
+
+            canvas.draw_icon(state.icon, px(12.0), px(12.0), px(32.0))?;
+
+            let (left, right, middle) = (px(56.0), px(WIDTH - 16.0), px(HEIGHT / 2.0));
+            let filled = left + (right - left) * state.volume.clamp(0.0, 1.0);
+
+            let fill = if state.muted { 0xff808080 } else { 0xffffffff };
+
+            canvas.draw_line(
+                (left, middle),
+                (right, middle),
+                px(6.0),
+                LineCap::Square,
+                0xff606060,
+            )?;
+            if filled > left {
+                let (from, to) = ((left, middle), (filled, middle));
+                canvas.draw_line(from, to, px(6.0), LineCap::Square, fill)?;
+            }
+
+            if state.muted {
+                let red = 0xffff0000;
+                let (near, far) = (px(12.0), px(44.0));
+                canvas.draw_line((near, near), (far, far), px(6.0), LineCap::Triangle, red)?;
+                canvas.draw_line((far, near), (near, far), px(6.0), LineCap::Triangle, red)?;
+            }
+
+            Ok(())
+        })?;
+
+        unsafe {
+            let _ = ShowWindow(self.hwnd, SW_SHOWNOACTIVATE);
+        }
+
+        Ok(())
+    }
+
+    pub fn forget_icon(&mut self, icon: HICON) {
+        self.renderer.forget_icon(icon);
+    }
+
+    pub fn hide(&self) {
+        unsafe {
+            let _ = ShowWindow(self.hwnd, SW_HIDE);
+        }
+    }
+}
+
+/// the window and its class, so another `Osd` can be made in the same process
+impl Drop for Osd {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = DestroyWindow(self.hwnd);
+
+            if let Ok(module) = GetModuleHandleA(None) {
+                let _ = UnregisterClassA(WINDOW_CLASS, Some(module.into()));
+            }
+        }
+    }
+}
+
+unsafe extern "system" fn window_proc(
+    hwnd: HWND,
+    event: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    unsafe { DefWindowProcA(hwnd, event, wparam, lparam) }
+}
+
+/// registers `class` and makes a hidden, topmost popup of it that never takes focus, to be sized
+/// when it's shown. the caller destroys the window and unregisters the class.
+pub fn create_popup(class: PCSTR, title: PCSTR, backend: Backend) -> Result<HWND> {
+    unsafe {
+        let hinstance = GetModuleHandleA(None)?.into();
+
+        let window_class_name = class;
+
+        let wc = WNDCLASSA {
+            hInstance: hinstance,
+            lpfnWndProc: Some(window_proc),
+            lpszClassName: window_class_name,
+            ..default()
+        };
+
+        if 0 == RegisterClassA(&wc) {
+            bail!("failed to register popup window class")
+        }
+
+        // clicks go through to whatever is underneath
+        let hwnd = CreateWindowExA(
+            WS_EX_NOACTIVATE
+                | WS_EX_TOPMOST
+                | WS_EX_TOOLWINDOW
+                | WS_EX_TRANSPARENT
+                | backend.window_style(),
+            window_class_name,
+            title,
+            WS_POPUP,
+            0,
+            0,
+            0,
+            0,
+            HWND_DESKTOP,
+            default(),
+            hinstance,
+            default(),
+        );
+
+        Ok(hwnd)
+    }
+}
+
+/// the dpi of the primary monitor, where a centered osd shows up
+fn primary_dpi() -> Result<u32> {
+    unsafe {
+        let monitor = MonitorFromPoint(POINT::default(), MONITOR_DEFAULTTOPRIMARY);
+
+        let (mut dpi, mut dpi_y) = (0, 0);
+        GetDpiForMonitor(monitor, MDT_EFFECTIVE_DPI, &mut dpi, &mut dpi_y)?;
+
+        Ok(dpi)
+    }
+}
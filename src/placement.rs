@@ -0,0 +1,210 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use windows::Win32::{
+    Foundation::{HWND, POINT, RECT},
+    Graphics::Gdi::{GetMonitorInfoW, MONITOR_DEFAULTTONEAREST, MONITORINFO, MonitorFromWindow},
+    UI::{
+        Input::KeyboardAndMouse::{ReleaseCapture, SetCapture},
+        WindowsAndMessaging::{
+            GetCursorPos, GetWindowRect, SWP_NOACTIVATE, SWP_NOSIZE, SWP_NOZORDER, SetWindowPos,
+        },
+    },
+};
+
+use crate::default;
+
+/// how close to a screen or work area edge the panel has to be dragged to stick to it
+const SNAP_DISTANCE: i32 = 12;
+
+/// the panel's top-left corner in screen coordinates
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Position {
+    pub x: i32,
+    pub y: i32,
+}
+
+impl Default for Position {
+    fn default() -> Self {
+        Self {
+            x: -800,
+            y: 1440 - 48,
+        }
+    }
+}
+
+/// a named spot on the monitor the panel is on, as an alternative to a numeric `Position`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Preset {
+    TopLeft,
+    TopCenter,
+    TopRight,
+    BottomLeft,
+    BottomCenter,
+    BottomRight,
+}
+
+impl Preset {
+    pub const ALL: [Preset; 6] = [
+        Preset::TopLeft,
+        Preset::TopCenter,
+        Preset::TopRight,
+        Preset::BottomLeft,
+        Preset::BottomCenter,
+        Preset::BottomRight,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Preset::TopLeft => "Top left",
+            Preset::TopCenter => "Top center",
+            Preset::TopRight => "Top right",
+            Preset::BottomLeft => "Bottom left",
+            Preset::BottomCenter => "Bottom center",
+            Preset::BottomRight => "Bottom right",
+        }
+    }
+
+    /// where the panel goes within the work area of the monitor it's currently on
+    pub fn position(self, hwnd: HWND) -> Result<Position> {
+        let window = window_rect(hwnd)?;
+        let width = window.right - window.left;
+        let height = window.bottom - window.top;
+
+        let (_, work) = monitor_areas(hwnd)?;
+
+        let x = match self {
+            Preset::TopLeft | Preset::BottomLeft => work.left,
+            Preset::TopCenter | Preset::BottomCenter => (work.left + work.right - width) / 2,
+            Preset::TopRight | Preset::BottomRight => work.right - width,
+        };
+
+        let y = match self {
+            Preset::TopLeft | Preset::TopCenter | Preset::TopRight => work.top,
+            _ => work.bottom - height,
+        };
+
+        Ok(Position { x, y })
+    }
+}
+
+/// moving the panel with shift-drag
+pub struct Drag {
+    /// where the cursor grabbed the panel, relative to its top-left corner
+    offset: POINT,
+}
+
+impl Drag {
+    pub fn start(hwnd: HWND) -> Result<Self> {
+        let window = window_rect(hwnd)?;
+        let cursor = cursor()?;
+
+        unsafe { SetCapture(hwnd) };
+
+        Ok(Self {
+            offset: POINT {
+                x: cursor.x - window.left,
+                y: cursor.y - window.top,
+            },
+        })
+    }
+
+    /// moves the panel to follow the cursor, sticking to the edges of the monitor and of its work
+    /// area, since the panel usually sits over the taskbar
+    pub fn update(&self, hwnd: HWND) -> Result<()> {
+        let window = window_rect(hwnd)?;
+        let cursor = cursor()?;
+
+        let x = cursor.x - self.offset.x;
+        let y = cursor.y - self.offset.y;
+        let width = window.right - window.left;
+        let height = window.bottom - window.top;
+
+        let (monitor, work) = monitor_areas(hwnd)?;
+        let x = snap(
+            x,
+            width,
+            &[(work.left, work.right), (monitor.left, monitor.right)],
+        );
+        let y = snap(
+            y,
+            height,
+            &[(work.top, work.bottom), (monitor.top, monitor.bottom)],
+        );
+
+        move_to(hwnd, Position { x, y })
+    }
+
+    /// releases the mouse and returns where the panel ended up
+    pub fn finish(self, hwnd: HWND) -> Result<Position> {
+        unsafe { ReleaseCapture()? };
+
+        let window = window_rect(hwnd)?;
+        Ok(Position {
+            x: window.left,
+            y: window.top,
+        })
+    }
+}
+
+pub fn move_to(hwnd: HWND, position: Position) -> Result<()> {
+    unsafe {
+        SetWindowPos(
+            hwnd,
+            None,
+            position.x,
+            position.y,
+            0,
+            0,
+            SWP_NOSIZE | SWP_NOZORDER | SWP_NOACTIVATE,
+        )?;
+    }
+
+    Ok(())
+}
+
+/// lines a span of `size` starting at `start` up with the first of `edges` it's near
+fn snap(start: i32, size: i32, edges: &[(i32, i32)]) -> i32 {
+    for &(low, high) in edges {
+        if (start - low).abs() <= SNAP_DISTANCE {
+            return low;
+        }
+
+        if (start + size - high).abs() <= SNAP_DISTANCE {
+            return high - size;
+        }
+    }
+
+    start
+}
+
+fn window_rect(hwnd: HWND) -> Result<RECT> {
+    let mut rect = RECT::default();
+    unsafe { GetWindowRect(hwnd, &mut rect)? };
+
+    Ok(rect)
+}
+
+fn cursor() -> Result<POINT> {
+    let mut point = POINT::default();
+    unsafe { GetCursorPos(&mut point)? };
+
+    Ok(point)
+}
+
+/// the bounds and the work area (the bounds minus the taskbar) of the monitor the window is
+/// mostly on
+fn monitor_areas(hwnd: HWND) -> Result<(RECT, RECT)> {
+    unsafe {
+        let monitor = MonitorFromWindow(hwnd, MONITOR_DEFAULTTONEAREST);
+
+        let mut info = MONITORINFO {
+            cbSize: size_of::<MONITORINFO>() as u32,
+            ..default()
+        };
+
+        GetMonitorInfoW(monitor, &mut info).ok()?;
+
+        Ok((info.rcMonitor, info.rcWork))
+    }
+}
@@ -0,0 +1,253 @@
+use windows::Win32::Media::Audio::{EDataFlow, eRender};
+
+use crate::{actions::Action, layout::Widget, webhook::MuteSource};
+
+/// an action waiting in the `ActionQueue`, with what's needed to run it the way its source would
+/// have
+pub struct Queued {
+    pub widget: Option<Widget>,
+    pub action: Action,
+    /// the direction, for actions that have one
+    pub up: bool,
+    /// how many times to run it, more than once when volume steps have been coalesced
+    pub times: u32,
+    /// given to the mute watch before running, so a mute it causes is put down to it
+    pub source: Option<MuteSource>,
+    /// the other sources the same toggle came from, e.g. the hotkey a headset's button also sends
+    echoes: Vec<MuteSource>,
+    /// shows the osd for the action's device once it's run, as hotkeys do
+    pub osd: bool,
+}
+
+impl Queued {
+    pub fn new(widget: Option<Widget>, action: Action, up: bool) -> Self {
+        Self {
+            widget,
+            action,
+            up,
+            times: 1,
+            source: None,
+            echoes: vec![],
+            osd: false,
+        }
+    }
+
+    pub fn from(mut self, source: MuteSource) -> Self {
+        self.source = Some(source);
+        self
+    }
+
+    pub fn with_osd(mut self) -> Self {
+        self.osd = true;
+        self
+    }
+
+    /// the device the action applies to, as `run_action` picks it
+    pub fn flow(&self) -> EDataFlow {
+        self.widget.and_then(Widget::flow).unwrap_or(eRender)
+    }
+
+    /// whether `other` comes from a source this hasn't been seen from yet
+    fn is_echoed_by(&self, other: &Queued) -> bool {
+        match (self.source, other.source) {
+            (Some(source), Some(other)) => source != other && !self.echoes.contains(&other),
+            _ => false,
+        }
+    }
+}
+
+/// actions from clicks, hotkeys, the headset, ipc and the panel itself, run in order on the
+/// window's thread once it gets to `WM_RUN_ACTIONS`. a burst of them is coalesced first, so
+/// several sources reacting to the same thing don't each make their own calls to the audio
+/// service.
+#[derive(Default)]
+pub struct ActionQueue {
+    queued: Vec<Queued>,
+}
+
+impl ActionQueue {
+    /// returns whether the queue was empty, and so needs running
+    pub fn push(&mut self, queued: Queued) -> bool {
+        let Some(last) = self.queued.last_mut() else {
+            self.queued.push(queued);
+            return true;
+        };
+
+        // only folded into the action just before it, since anything in between could change
+        // what it would do, e.g. by switching the default device
+        if last.flow() != queued.flow() {
+            self.queued.push(queued);
+            return false;
+        }
+
+        match (&last.action, &queued.action) {
+            // one press seen from two places, e.g. a headset's button that also sends a hotkey, is
+            // one toggle. toggles from the same place are each asked for, so they all run.
+            (Action::ToggleMute, Action::ToggleMute) if last.is_echoed_by(&queued) => {
+                last.echoes.extend(queued.source);
+                last.osd |= queued.osd;
+            }
+
+            // steps in opposite directions cancel out, but still show the osd if one asked for it
+            (Action::StepVolume, Action::StepVolume) => {
+                last.osd |= queued.osd;
+
+                if last.up == queued.up {
+                    last.times += queued.times;
+                } else if last.times > queued.times {
+                    last.times -= queued.times;
+                } else if last.times < queued.times {
+                    last.up = queued.up;
+                    last.times = queued.times - last.times;
+                } else {
+                    last.times = 0;
+                }
+            }
+
+            _ => self.queued.push(queued),
+        }
+
+        false
+    }
+
+    /// everything queued, in order, leaving the queue empty
+    pub fn take(&mut self) -> Vec<Queued> {
+        std::mem::take(&mut self.queued)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn toggle(source: MuteSource) -> Queued {
+        Queued::new(Some(Widget::Input), Action::ToggleMute, true).from(source)
+    }
+
+    fn step(up: bool) -> Queued {
+        Queued::new(Some(Widget::Volume), Action::StepVolume, up)
+    }
+
+    #[test]
+    fn only_the_first_push_needs_running() {
+        let mut queue = ActionQueue::default();
+
+        assert!(queue.push(step(true)));
+        assert!(!queue.push(toggle(MuteSource::Hotkey)));
+        assert_eq!(queue.take().len(), 2);
+        assert!(queue.push(step(true)));
+    }
+
+    #[test]
+    fn toggles_from_one_source_all_run() {
+        let mut queue = ActionQueue::default();
+        queue.push(toggle(MuteSource::Hotkey));
+        queue.push(toggle(MuteSource::Hotkey));
+
+        assert_eq!(queue.take().len(), 2);
+    }
+
+    #[test]
+    fn toggles_without_a_source_all_run() {
+        let mut queue = ActionQueue::default();
+        queue.push(Queued::new(Some(Widget::Input), Action::ToggleMute, true));
+        queue.push(Queued::new(Some(Widget::Input), Action::ToggleMute, true));
+
+        assert_eq!(queue.take().len(), 2);
+    }
+
+    #[test]
+    fn one_press_from_two_sources_is_one_toggle() {
+        let mut queue = ActionQueue::default();
+        queue.push(toggle(MuteSource::Headset));
+        queue.push(toggle(MuteSource::Hotkey).with_osd());
+
+        let queued = queue.take();
+        assert_eq!(queued.len(), 1);
+        assert_eq!(queued[0].times, 1);
+        assert_eq!(queued[0].source, Some(MuteSource::Headset));
+        assert!(queued[0].osd);
+    }
+
+    #[test]
+    fn a_second_press_after_an_echo_still_runs() {
+        let mut queue = ActionQueue::default();
+        queue.push(toggle(MuteSource::Headset));
+        queue.push(toggle(MuteSource::Hotkey));
+        queue.push(toggle(MuteSource::Hotkey));
+
+        assert_eq!(queue.take().len(), 2);
+    }
+
+    #[test]
+    fn toggles_for_different_devices_all_run() {
+        let mut queue = ActionQueue::default();
+        queue.push(toggle(MuteSource::Headset));
+        queue.push(
+            Queued::new(Some(Widget::Output), Action::ToggleMute, true).from(MuteSource::Hotkey),
+        );
+
+        assert_eq!(queue.take().len(), 2);
+    }
+
+    #[test]
+    fn steps_in_one_direction_add_up() {
+        let mut queue = ActionQueue::default();
+        queue.push(step(true));
+        queue.push(step(true));
+        queue.push(step(true));
+
+        let queued = queue.take();
+        assert_eq!(queued.len(), 1);
+        assert!(queued[0].up);
+        assert_eq!(queued[0].times, 3);
+    }
+
+    #[test]
+    fn opposite_steps_cancel() {
+        let mut queue = ActionQueue::default();
+        queue.push(step(true));
+        queue.push(step(true));
+        queue.push(step(false));
+
+        let queued = queue.take();
+        assert_eq!(queued.len(), 1);
+        assert!(queued[0].up);
+        assert_eq!(queued[0].times, 1);
+    }
+
+    #[test]
+    fn steps_that_cancel_out_keep_the_osd() {
+        let mut queue = ActionQueue::default();
+        queue.push(step(true).with_osd());
+        queue.push(step(false));
+
+        let queued = queue.take();
+        assert_eq!(queued.len(), 1);
+        assert_eq!(queued[0].times, 0);
+        assert!(queued[0].osd);
+    }
+
+    #[test]
+    fn steps_after_cancelling_out_carry_on() {
+        let mut queue = ActionQueue::default();
+        queue.push(step(true));
+        queue.push(step(false));
+        queue.push(step(false));
+
+        let queued = queue.take();
+        assert_eq!(queued.len(), 1);
+        assert!(!queued[0].up);
+        assert_eq!(queued[0].times, 1);
+    }
+
+    #[test]
+    fn nothing_is_folded_across_another_action() {
+        let mut queue = ActionQueue::default();
+        queue.push(step(true));
+        queue.push(Queued::new(Some(Widget::Volume), Action::CycleDevice, true));
+        queue.push(step(true));
+
+        assert_eq!(queue.take().len(), 3);
+    }
+}
@@ -0,0 +1,142 @@
+use anyhow::{Context, Result};
+use windows::Win32::{
+    Foundation::WIN32_ERROR,
+    System::Registry::{
+        HKEY, KEY_READ, REG_BINARY, REG_DWORD, REG_EXPAND_SZ, REG_VALUE_TYPE, RRF_RT_REG_BINARY,
+        RRF_RT_REG_QWORD, RegCloseKey, RegEnumKeyExW, RegGetValueW, RegOpenKeyExW, RegSetKeyValueW,
+    },
+};
+use windows_core::{HSTRING, PWSTR};
+
+/// the names of the keys directly under `root\path`
+pub fn subkeys(root: HKEY, path: &str) -> Result<Vec<String>> {
+    let mut key = HKEY::default();
+
+    unsafe {
+        RegOpenKeyExW(root, &HSTRING::from(path), None, KEY_READ, &mut key)
+            .ok()
+            .with_context(|| format!("failed to open {}", path))?;
+
+        let mut names = vec![];
+        let mut buffer = [0u16; 256];
+
+        for index in 0.. {
+            let mut len = buffer.len() as u32;
+            let result = RegEnumKeyExW(
+                key,
+                index,
+                Some(PWSTR(buffer.as_mut_ptr())),
+                &mut len,
+                None,
+                None,
+                None,
+                None,
+            );
+
+            // ERROR_NO_MORE_ITEMS after the last one
+            if result != WIN32_ERROR(0) {
+                break;
+            }
+
+            names.push(String::from_utf16_lossy(&buffer[..len as usize]));
+        }
+
+        let _ = RegCloseKey(key);
+
+        Ok(names)
+    }
+}
+
+pub fn qword(root: HKEY, path: &str, name: &str) -> Result<u64> {
+    let mut value = 0u64;
+    let mut size = size_of::<u64>() as u32;
+
+    unsafe {
+        RegGetValueW(
+            root,
+            &HSTRING::from(path),
+            &HSTRING::from(name),
+            RRF_RT_REG_QWORD,
+            None,
+            Some(&mut value as *mut u64 as _),
+            Some(&mut size),
+        )
+        .ok()
+        .with_context(|| format!("failed to read {}\\{}", path, name))?;
+    }
+
+    Ok(value)
+}
+
+pub fn binary(root: HKEY, path: &str, name: &str) -> Result<Vec<u8>> {
+    let path = HSTRING::from(path);
+    let name = HSTRING::from(name);
+    let mut size = 0;
+
+    unsafe {
+        // the first call only asks for the size
+        RegGetValueW(
+            root,
+            &path,
+            &name,
+            RRF_RT_REG_BINARY,
+            None,
+            None,
+            Some(&mut size),
+        )
+        .ok()
+        .with_context(|| format!("failed to read {}\\{}", path, name))?;
+
+        let mut value = vec![0u8; size as usize];
+        RegGetValueW(
+            root,
+            &path,
+            &name,
+            RRF_RT_REG_BINARY,
+            None,
+            Some(value.as_mut_ptr() as _),
+            Some(&mut size),
+        )
+        .ok()
+        .with_context(|| format!("failed to read {}\\{}", path, name))?;
+
+        value.truncate(size as usize);
+        Ok(value)
+    }
+}
+
+pub fn set_binary(root: HKEY, path: &str, name: &str, value: &[u8]) -> Result<()> {
+    set(root, path, name, REG_BINARY, value)
+}
+
+pub fn set_dword(root: HKEY, path: &str, name: &str, value: u32) -> Result<()> {
+    set(root, path, name, REG_DWORD, &value.to_le_bytes())
+}
+
+/// a string with environment variables like `%SystemRoot%` in it
+pub fn set_expand_string(root: HKEY, path: &str, name: &str, value: &str) -> Result<()> {
+    let value = HSTRING::from(value);
+    // including the terminating null
+    let bytes =
+        unsafe { std::slice::from_raw_parts(value.as_ptr() as *const u8, (value.len() + 1) * 2) };
+
+    set(root, path, name, REG_EXPAND_SZ, bytes)
+}
+
+/// creates the key if it isn't there yet
+fn set(root: HKEY, path: &str, name: &str, kind: REG_VALUE_TYPE, value: &[u8]) -> Result<()> {
+    unsafe {
+        RegSetKeyValueW(
+            root,
+            &HSTRING::from(path),
+            &HSTRING::from(name),
+            kind.0,
+            Some(value.as_ptr() as _),
+            value.len() as u32,
+        )
+        .ok()
+        .with_context(|| format!("failed to write {}\\{}", path, name))?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,120 @@
+use std::fmt;
+
+use anyhow::Result;
+use serde::Deserialize;
+use windows::Win32::{
+    Foundation::HWND,
+    UI::WindowsAndMessaging::{HICON, WINDOW_EX_STYLE, WS_EX_LAYERED, WS_EX_NOREDIRECTIONBITMAP},
+};
+
+use crate::layout::Rect;
+
+mod direct2d;
+mod gdiplus;
+
+pub use direct2d::Direct2dRenderer;
+pub use gdiplus::GdiPlusRenderer;
+
+/// which renderer draws the panel
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Backend {
+    /// GDI+ into a bitmap pushed with UpdateLayeredWindow, for when Direct2D can't be used
+    GdiPlus,
+    /// Direct2D into a swap chain composited by DirectComposition
+    #[default]
+    Direct2d,
+}
+
+impl Backend {
+    /// the extended window styles the backend needs on the panel window
+    pub fn window_style(self) -> WINDOW_EX_STYLE {
+        match self {
+            Backend::GdiPlus => WS_EX_LAYERED,
+            Backend::Direct2d => WS_EX_NOREDIRECTIONBITMAP,
+        }
+    }
+
+    /// fails if the backend can't be used on this machine, before there's a window for it
+    pub fn check(self) -> Result<()> {
+        if self == Backend::Direct2d {
+            Direct2dRenderer::new()?;
+            direct2d::create_d3d_device()?;
+        }
+
+        Ok(())
+    }
+
+    pub fn create(self) -> Result<Box<dyn Renderer>> {
+        let renderer: Box<dyn Renderer> = match self {
+            Backend::GdiPlus => Box::new(GdiPlusRenderer::new()),
+            Backend::Direct2d => Box::new(Direct2dRenderer::new()?),
+        };
+
+        Ok(renderer)
+    }
+}
+
+/// what `Renderer::render` fails with when the window's surface has gone out from under it, e.g.
+/// while the display driver resets or a remote desktop session starts or ends. whatever the
+/// renderer had made for the window has been let go, so the frame's worth trying again shortly.
+#[derive(Debug)]
+pub struct SurfaceLost;
+
+impl fmt::Display for SurfaceLost {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "the window's surface was lost")
+    }
+}
+
+impl std::error::Error for SurfaceLost {}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LineCap {
+    Square,
+    Triangle,
+}
+
+/// the drawing surface for a single frame. coordinates are in window pixels and colors are
+/// 0xAARRGGBB.
+pub trait Canvas {
+    fn fill_rect(&mut self, rect: Rect, color: u32) -> Result<()>;
+
+    fn draw_line(
+        &mut self,
+        from: (f32, f32),
+        to: (f32, f32),
+        width: f32,
+        cap: LineCap,
+        color: u32,
+    ) -> Result<()>;
+
+    /// outlines the ellipse that fits inside `rect`
+    fn draw_ellipse(&mut self, rect: Rect, width: f32, color: u32) -> Result<()>;
+
+    /// returns the width and height `text` would take up when drawn at `size`
+    fn measure_text(&mut self, text: &str, size: f32) -> Result<(f32, f32)>;
+
+    /// draws `text` with its top-left corner at `x`, `y`
+    fn draw_text(&mut self, text: &str, size: f32, x: f32, y: f32, color: u32) -> Result<()>;
+
+    /// draws `icon` scaled to `size` pixels square with its top-left corner at `x`, `y`
+    fn draw_icon(&mut self, icon: HICON, x: f32, y: f32, size: f32) -> Result<()>;
+
+    /// limits what's drawn to `rect` until `pop_clip`. clips don't nest.
+    fn push_clip(&mut self, rect: Rect) -> Result<()>;
+
+    fn pop_clip(&mut self) -> Result<()>;
+}
+
+pub trait Renderer {
+    /// draws one frame of the window's content, starting from a transparent background
+    fn render(
+        &mut self,
+        hwnd: HWND,
+        paint: &mut dyn FnMut(&mut dyn Canvas) -> Result<()>,
+    ) -> Result<()>;
+
+    /// drops anything cached for `icon`, which is about to be destroyed
+    fn forget_icon(&mut self, _icon: HICON) {}
+}
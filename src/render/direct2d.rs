@@ -0,0 +1,515 @@
+use std::{collections::HashMap, mem::ManuallyDrop};
+
+use anyhow::{Context, Result};
+use windows::Win32::{
+    Foundation::{D2DERR_RECREATE_TARGET, HMODULE, HWND, RECT},
+    Graphics::{
+        Direct2D::{
+            Common::{D2D_RECT_F, D2D1_ALPHA_MODE_PREMULTIPLIED, D2D1_COLOR_F, D2D1_PIXEL_FORMAT},
+            D2D1_ANTIALIAS_MODE_PER_PRIMITIVE, D2D1_BITMAP_OPTIONS_CANNOT_DRAW,
+            D2D1_BITMAP_OPTIONS_TARGET, D2D1_BITMAP_PROPERTIES1, D2D1_CAP_STYLE,
+            D2D1_CAP_STYLE_SQUARE, D2D1_CAP_STYLE_TRIANGLE, D2D1_DEVICE_CONTEXT_OPTIONS_NONE,
+            D2D1_DRAW_TEXT_OPTIONS_NONE, D2D1_ELLIPSE, D2D1_FACTORY_TYPE_SINGLE_THREADED,
+            D2D1_INTERPOLATION_MODE_LINEAR, D2D1_STROKE_STYLE_PROPERTIES1, D2D1CreateFactory,
+            ID2D1Bitmap1, ID2D1DeviceContext, ID2D1Factory1, ID2D1StrokeStyle1,
+        },
+        Direct3D::{D3D_DRIVER_TYPE, D3D_DRIVER_TYPE_HARDWARE, D3D_DRIVER_TYPE_WARP},
+        Direct3D11::{
+            D3D11_CREATE_DEVICE_BGRA_SUPPORT, D3D11_SDK_VERSION, D3D11CreateDevice, ID3D11Device,
+        },
+        DirectComposition::{
+            DCompositionCreateDevice, IDCompositionDevice, IDCompositionTarget, IDCompositionVisual,
+        },
+        DirectWrite::{
+            DWRITE_FACTORY_TYPE_SHARED, DWRITE_FONT_STRETCH_NORMAL, DWRITE_FONT_STYLE_NORMAL,
+            DWRITE_FONT_WEIGHT_REGULAR, DWRITE_TEXT_METRICS, DWriteCreateFactory, IDWriteFactory,
+            IDWriteTextFormat, IDWriteTextLayout,
+        },
+        Dxgi::{
+            Common::{
+                DXGI_ALPHA_MODE_PREMULTIPLIED, DXGI_FORMAT_B8G8R8A8_UNORM, DXGI_FORMAT_UNKNOWN,
+                DXGI_SAMPLE_DESC,
+            },
+            CreateDXGIFactory2, DXGI_CREATE_FACTORY_FLAGS, DXGI_ERROR_DEVICE_REMOVED,
+            DXGI_ERROR_DEVICE_RESET, DXGI_PRESENT, DXGI_SCALING_STRETCH, DXGI_SWAP_CHAIN_DESC1,
+            DXGI_SWAP_CHAIN_FLAG, DXGI_SWAP_EFFECT_FLIP_SEQUENTIAL,
+            DXGI_USAGE_RENDER_TARGET_OUTPUT, IDXGIDevice, IDXGIFactory2, IDXGISurface,
+            IDXGISwapChain1,
+        },
+        Imaging::{
+            CLSID_WICImagingFactory, GUID_WICPixelFormat32bppPBGRA, IWICImagingFactory,
+            WICBitmapDitherTypeNone, WICBitmapPaletteTypeMedianCut,
+        },
+    },
+    System::Com::{CLSCTX_INPROC_SERVER, CoCreateInstance},
+    UI::WindowsAndMessaging::{GetClientRect, HICON},
+};
+use windows_core::{Interface, w};
+use windows_numerics::Vector2;
+
+use super::{Canvas, LineCap, Renderer, SurfaceLost};
+use crate::layout::Rect;
+
+/// the GPU's D3D device, or WARP's software one if the GPU won't make one, e.g. in a virtual
+/// machine or with a broken driver
+pub fn create_d3d_device() -> Result<ID3D11Device> {
+    let create = |driver: D3D_DRIVER_TYPE| unsafe {
+        let mut device = None;
+        D3D11CreateDevice(
+            None,
+            driver,
+            HMODULE::default(),
+            D3D11_CREATE_DEVICE_BGRA_SUPPORT,
+            None,
+            D3D11_SDK_VERSION,
+            Some(&mut device),
+            None,
+            None,
+        )?;
+
+        device.context("no d3d device")
+    };
+
+    create(D3D_DRIVER_TYPE_HARDWARE).or_else(|_| create(D3D_DRIVER_TYPE_WARP))
+}
+
+/// renders with Direct2D into a swap chain that DirectComposition places on the window, so
+/// frames are composited on the GPU instead of being copied through a layered window bitmap
+pub struct Direct2dRenderer {
+    factory: ID2D1Factory1,
+    dwrite: IDWriteFactory,
+    wic: IWICImagingFactory,
+    text_formats: HashMap<u32, IDWriteTextFormat>,
+    device: Option<DeviceResources>,
+}
+
+/// everything tied to the D3D device, which has to be recreated if the device is lost
+struct DeviceResources {
+    context: ID2D1DeviceContext,
+    swap_chain: IDXGISwapChain1,
+    width: u32,
+    height: u32,
+
+    square: ID2D1StrokeStyle1,
+    triangle: ID2D1StrokeStyle1,
+    icons: HashMap<isize, ID2D1Bitmap1>,
+
+    // kept alive for as long as the swap chain is shown
+    _composition: IDCompositionDevice,
+    _target: IDCompositionTarget,
+    _visual: IDCompositionVisual,
+}
+
+impl Direct2dRenderer {
+    pub fn new() -> Result<Self> {
+        unsafe {
+            let factory = D2D1CreateFactory(D2D1_FACTORY_TYPE_SINGLE_THREADED, None)?;
+            let dwrite = DWriteCreateFactory(DWRITE_FACTORY_TYPE_SHARED)?;
+            let wic = CoCreateInstance(&CLSID_WICImagingFactory, None, CLSCTX_INPROC_SERVER)?;
+
+            Ok(Self {
+                factory,
+                dwrite,
+                wic,
+                text_formats: HashMap::new(),
+                device: None,
+            })
+        }
+    }
+
+    fn create_device(&self, hwnd: HWND, width: u32, height: u32) -> Result<DeviceResources> {
+        unsafe {
+            let d3d_device = create_d3d_device()?;
+            let dxgi_device: IDXGIDevice = d3d_device.cast()?;
+
+            let d2d_device = self.factory.CreateDevice(&dxgi_device)?;
+            let context = d2d_device.CreateDeviceContext(D2D1_DEVICE_CONTEXT_OPTIONS_NONE)?;
+
+            let dxgi_factory: IDXGIFactory2 = CreateDXGIFactory2(DXGI_CREATE_FACTORY_FLAGS(0))?;
+            let desc = DXGI_SWAP_CHAIN_DESC1 {
+                Width: width,
+                Height: height,
+                Format: DXGI_FORMAT_B8G8R8A8_UNORM,
+                SampleDesc: DXGI_SAMPLE_DESC {
+                    Count: 1,
+                    Quality: 0,
+                },
+                BufferUsage: DXGI_USAGE_RENDER_TARGET_OUTPUT,
+                BufferCount: 2,
+                Scaling: DXGI_SCALING_STRETCH,
+                SwapEffect: DXGI_SWAP_EFFECT_FLIP_SEQUENTIAL,
+                AlphaMode: DXGI_ALPHA_MODE_PREMULTIPLIED,
+                ..Default::default()
+            };
+            let swap_chain =
+                dxgi_factory.CreateSwapChainForComposition(&dxgi_device, &desc, None)?;
+
+            let composition: IDCompositionDevice = DCompositionCreateDevice(&dxgi_device)?;
+            let target = composition.CreateTargetForHwnd(hwnd, true)?;
+            let visual = composition.CreateVisual()?;
+            visual.SetContent(&swap_chain)?;
+            target.SetRoot(&visual)?;
+            composition.Commit()?;
+
+            Ok(DeviceResources {
+                context,
+                swap_chain,
+                width,
+                height,
+                square: self.stroke_style(D2D1_CAP_STYLE_SQUARE)?,
+                triangle: self.stroke_style(D2D1_CAP_STYLE_TRIANGLE)?,
+                icons: HashMap::new(),
+                _composition: composition,
+                _target: target,
+                _visual: visual,
+            })
+        }
+    }
+
+    fn stroke_style(&self, cap: D2D1_CAP_STYLE) -> Result<ID2D1StrokeStyle1> {
+        let properties = D2D1_STROKE_STYLE_PROPERTIES1 {
+            startCap: cap,
+            endCap: cap,
+            dashCap: cap,
+            miterLimit: 10.0,
+            ..Default::default()
+        };
+
+        let style = unsafe { self.factory.CreateStrokeStyle(&properties, None)? };
+        Ok(style)
+    }
+
+    fn draw(
+        &mut self,
+        hwnd: HWND,
+        paint: &mut dyn FnMut(&mut dyn Canvas) -> Result<()>,
+    ) -> Result<()> {
+        unsafe {
+            let mut client = RECT::default();
+            GetClientRect(hwnd, &mut client)?;
+            let width = (client.right - client.left).max(1) as u32;
+            let height = (client.bottom - client.top).max(1) as u32;
+
+            let device = match self.device.take() {
+                Some(mut device) => {
+                    if device.width != width || device.height != height {
+                        device.context.SetTarget(None);
+                        device.swap_chain.ResizeBuffers(
+                            0,
+                            width,
+                            height,
+                            DXGI_FORMAT_UNKNOWN,
+                            DXGI_SWAP_CHAIN_FLAG(0),
+                        )?;
+                        device.width = width;
+                        device.height = height;
+                    }
+
+                    device
+                }
+                None => self.create_device(hwnd, width, height)?,
+            };
+            let device = self.device.insert(device);
+
+            let surface: IDXGISurface = device.swap_chain.GetBuffer(0)?;
+            let properties = D2D1_BITMAP_PROPERTIES1 {
+                pixelFormat: D2D1_PIXEL_FORMAT {
+                    format: DXGI_FORMAT_B8G8R8A8_UNORM,
+                    alphaMode: D2D1_ALPHA_MODE_PREMULTIPLIED,
+                },
+                dpiX: 96.0,
+                dpiY: 96.0,
+                bitmapOptions: D2D1_BITMAP_OPTIONS_TARGET | D2D1_BITMAP_OPTIONS_CANNOT_DRAW,
+                colorContext: ManuallyDrop::new(None),
+            };
+            let target = device
+                .context
+                .CreateBitmapFromDxgiSurface(&surface, Some(&properties))?;
+
+            device.context.SetTarget(&target);
+            device.context.BeginDraw();
+            device.context.Clear(Some(&color(0)));
+
+            let mut canvas = Direct2dCanvas {
+                device,
+                dwrite: &self.dwrite,
+                wic: &self.wic,
+                text_formats: &mut self.text_formats,
+            };
+            let painted = paint(&mut canvas);
+
+            let ended = device.context.EndDraw(None, None);
+            device.context.SetTarget(None);
+            ended?;
+
+            // presented even if painting failed partway, rather than leaving an old frame up
+            device.swap_chain.Present(1, DXGI_PRESENT(0)).ok()?;
+            painted
+        }
+    }
+}
+
+impl Renderer for Direct2dRenderer {
+    fn render(
+        &mut self,
+        hwnd: HWND,
+        paint: &mut dyn FnMut(&mut dyn Canvas) -> Result<()>,
+    ) -> Result<()> {
+        let result = self.draw(hwnd, paint);
+
+        if let Err(e) = &result
+            && let Some(e) = e.downcast_ref::<windows_core::Error>()
+            && [
+                D2DERR_RECREATE_TARGET,
+                DXGI_ERROR_DEVICE_REMOVED,
+                DXGI_ERROR_DEVICE_RESET,
+            ]
+            .contains(&e.code())
+        {
+            // the device was lost, start over with a new one on the next frame
+            self.device = None;
+            return result.context(SurfaceLost);
+        }
+
+        result
+    }
+
+    fn forget_icon(&mut self, icon: HICON) {
+        // the handle may be reused for a different icon
+        if let Some(device) = &mut self.device {
+            device.icons.remove(&(icon.0 as isize));
+        }
+    }
+}
+
+struct Direct2dCanvas<'a> {
+    device: &'a mut DeviceResources,
+    dwrite: &'a IDWriteFactory,
+    wic: &'a IWICImagingFactory,
+    text_formats: &'a mut HashMap<u32, IDWriteTextFormat>,
+}
+
+fn color(argb: u32) -> D2D1_COLOR_F {
+    let channel = |shift: u32| ((argb >> shift) & 0xff) as f32 / 255.0;
+
+    D2D1_COLOR_F {
+        r: channel(16),
+        g: channel(8),
+        b: channel(0),
+        a: channel(24),
+    }
+}
+
+impl Direct2dCanvas<'_> {
+    fn text_layout(&mut self, text: &str, size: f32) -> Result<IDWriteTextLayout> {
+        let format = match self.text_formats.get(&size.to_bits()) {
+            Some(format) => format.clone(),
+            None => {
+                let format = unsafe {
+                    self.dwrite.CreateTextFormat(
+                        w!("Segoe UI"),
+                        None,
+                        DWRITE_FONT_WEIGHT_REGULAR,
+                        DWRITE_FONT_STYLE_NORMAL,
+                        DWRITE_FONT_STRETCH_NORMAL,
+                        size,
+                        w!("en-us"),
+                    )?
+                };
+
+                self.text_formats.insert(size.to_bits(), format.clone());
+                format
+            }
+        };
+
+        let string: Vec<u16> = text.encode_utf16().collect();
+        let layout = unsafe {
+            self.dwrite
+                .CreateTextLayout(&string, &format, 1000.0, 1000.0)?
+        };
+
+        Ok(layout)
+    }
+
+    fn icon(&mut self, icon: HICON) -> Result<ID2D1Bitmap1> {
+        if let Some(bitmap) = self.device.icons.get(&(icon.0 as isize)) {
+            return Ok(bitmap.clone());
+        }
+
+        let bitmap = unsafe {
+            let source = self.wic.CreateBitmapFromHICON(icon)?;
+            let converter = self.wic.CreateFormatConverter()?;
+            converter.Initialize(
+                &source,
+                &GUID_WICPixelFormat32bppPBGRA,
+                WICBitmapDitherTypeNone,
+                None,
+                0.0,
+                WICBitmapPaletteTypeMedianCut,
+            )?;
+
+            self.device
+                .context
+                .CreateBitmapFromWicBitmap(&converter, None)?
+        };
+
+        self.device.icons.insert(icon.0 as isize, bitmap.clone());
+        Ok(bitmap)
+    }
+}
+
+impl Canvas for Direct2dCanvas<'_> {
+    fn fill_rect(&mut self, rect: Rect, argb: u32) -> Result<()> {
+        unsafe {
+            let brush = self
+                .device
+                .context
+                .CreateSolidColorBrush(&color(argb), None)?;
+
+            let rect = D2D_RECT_F {
+                left: rect.x,
+                top: rect.y,
+                right: rect.right(),
+                bottom: rect.bottom(),
+            };
+
+            self.device.context.FillRectangle(&rect, &brush);
+        }
+
+        Ok(())
+    }
+
+    fn draw_line(
+        &mut self,
+        from: (f32, f32),
+        to: (f32, f32),
+        width: f32,
+        cap: LineCap,
+        argb: u32,
+    ) -> Result<()> {
+        let style = match cap {
+            LineCap::Square => &self.device.square,
+            LineCap::Triangle => &self.device.triangle,
+        };
+
+        unsafe {
+            let brush = self
+                .device
+                .context
+                .CreateSolidColorBrush(&color(argb), None)?;
+
+            self.device.context.DrawLine(
+                Vector2 {
+                    X: from.0,
+                    Y: from.1,
+                },
+                Vector2 { X: to.0, Y: to.1 },
+                &brush,
+                width,
+                style,
+            );
+        }
+
+        Ok(())
+    }
+
+    fn draw_ellipse(&mut self, rect: Rect, width: f32, argb: u32) -> Result<()> {
+        let ellipse = D2D1_ELLIPSE {
+            point: Vector2 {
+                X: rect.x + rect.width / 2.0,
+                Y: rect.y + rect.height / 2.0,
+            },
+            radiusX: rect.width / 2.0,
+            radiusY: rect.height / 2.0,
+        };
+
+        unsafe {
+            let brush = self
+                .device
+                .context
+                .CreateSolidColorBrush(&color(argb), None)?;
+
+            self.device
+                .context
+                .DrawEllipse(&ellipse, &brush, width, None);
+        }
+
+        Ok(())
+    }
+
+    fn measure_text(&mut self, text: &str, size: f32) -> Result<(f32, f32)> {
+        let layout = self.text_layout(text, size)?;
+
+        let mut metrics = DWRITE_TEXT_METRICS::default();
+        unsafe { layout.GetMetrics(&mut metrics)? };
+
+        Ok((metrics.widthIncludingTrailingWhitespace, metrics.height))
+    }
+
+    fn draw_text(&mut self, text: &str, size: f32, x: f32, y: f32, argb: u32) -> Result<()> {
+        let layout = self.text_layout(text, size)?;
+
+        unsafe {
+            let brush = self
+                .device
+                .context
+                .CreateSolidColorBrush(&color(argb), None)?;
+
+            self.device.context.DrawTextLayout(
+                Vector2 { X: x, Y: y },
+                &layout,
+                &brush,
+                D2D1_DRAW_TEXT_OPTIONS_NONE,
+            );
+        }
+
+        Ok(())
+    }
+
+    fn draw_icon(&mut self, icon: HICON, x: f32, y: f32, size: f32) -> Result<()> {
+        let bitmap = self.icon(icon)?;
+
+        let rect = D2D_RECT_F {
+            left: x,
+            top: y,
+            right: x + size,
+            bottom: y + size,
+        };
+
+        unsafe {
+            self.device.context.DrawBitmap(
+                &bitmap,
+                Some(&rect),
+                1.0,
+                D2D1_INTERPOLATION_MODE_LINEAR,
+                None,
+                None,
+            );
+        }
+
+        Ok(())
+    }
+
+    fn push_clip(&mut self, rect: Rect) -> Result<()> {
+        let rect = D2D_RECT_F {
+            left: rect.x,
+            top: rect.y,
+            right: rect.right(),
+            bottom: rect.bottom(),
+        };
+
+        unsafe {
+            self.device
+                .context
+                .PushAxisAlignedClip(&rect, D2D1_ANTIALIAS_MODE_PER_PRIMITIVE);
+        }
+
+        Ok(())
+    }
+
+    fn pop_clip(&mut self) -> Result<()> {
+        unsafe { self.device.context.PopAxisAlignedClip() };
+
+        Ok(())
+    }
+}
@@ -0,0 +1,402 @@
+use std::{collections::HashMap, ffi::OsString, os::windows::ffi::OsStrExt};
+
+use anyhow::{Context, Result, bail};
+use windows::Win32::{
+    Foundation::{HWND, RECT, SIZE},
+    Graphics::{
+        Gdi::{
+            AC_SRC_ALPHA, AC_SRC_OVER, BLENDFUNCTION, CreateCompatibleBitmap, CreateCompatibleDC,
+            DeleteDC, DeleteObject, GetDC, HBITMAP, HDC, ReleaseDC, SelectObject,
+        },
+        GdiPlus::{
+            CombineModeReplace, FontStyleRegular, GdipCreateFont, GdipCreateFontFamilyFromName,
+            GdipCreateFromHDC, GdipCreatePen1, GdipCreateSolidFill, GdipCreateStringFormat,
+            GdipDeleteBrush, GdipDeleteFont, GdipDeleteFontFamily, GdipDeleteGraphics,
+            GdipDeletePen, GdipDeleteStringFormat, GdipDrawEllipse, GdipDrawLine, GdipDrawString,
+            GdipFillRectangle, GdipMeasureString, GdipResetClip, GdipSetClipRect, GdipSetPenEndCap,
+            GdipSetPenStartCap, GdipSetTextRenderingHint, GpFont, GpFontFamily, GpGraphics, GpPen,
+            GpSolidFill, GpStringFormat, LineCapSquare, LineCapTriangle, RectF,
+            TextRenderingHintAntiAlias, UnitPixel,
+        },
+    },
+    UI::WindowsAndMessaging::{
+        DI_NORMAL, DrawIconEx, GetWindowRect, HICON, ULW_ALPHA, UpdateLayeredWindow,
+    },
+};
+use windows_core::{PCWSTR, w};
+
+use super::{Canvas, LineCap, Renderer, SurfaceLost};
+use crate::{default, layout::Rect};
+
+/// renders with GDI+ into a memory DC, which is pushed to the layered window
+pub struct GdiPlusRenderer {
+    font_family: *mut GpFontFamily,
+    format: *mut GpStringFormat,
+    fonts: HashMap<u32, *mut GpFont>,
+}
+
+impl GdiPlusRenderer {
+    pub fn new() -> Self {
+        let mut font_family = default();
+        let mut format = default();
+
+        unsafe {
+            GdipCreateFontFamilyFromName(w!("Segoe UI"), default(), &mut font_family);
+            GdipCreateStringFormat(0, 0, &mut format);
+        }
+
+        Self {
+            font_family,
+            format,
+            fonts: HashMap::new(),
+        }
+    }
+}
+
+impl Drop for GdiPlusRenderer {
+    fn drop(&mut self) {
+        unsafe {
+            for (_, font) in self.fonts.drain() {
+                GdipDeleteFont(font);
+            }
+
+            GdipDeleteStringFormat(self.format);
+            GdipDeleteFontFamily(self.font_family);
+        }
+    }
+}
+
+/// the screen's DC, released when dropped
+struct ScreenDc(HDC);
+
+impl Drop for ScreenDc {
+    fn drop(&mut self) {
+        unsafe { ReleaseDC(None, self.0) };
+    }
+}
+
+/// a memory DC, deleted when dropped
+struct MemoryDc(HDC);
+
+impl Drop for MemoryDc {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = DeleteDC(self.0);
+        }
+    }
+}
+
+/// deleted when dropped, which only works once it's no longer selected into a DC
+struct Bitmap(HBITMAP);
+
+impl Drop for Bitmap {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = DeleteObject(self.0.into());
+        }
+    }
+}
+
+/// deleted when dropped, which has to happen before its DC is
+struct Graphics(*mut GpGraphics);
+
+impl Drop for Graphics {
+    fn drop(&mut self) {
+        unsafe { GdipDeleteGraphics(self.0) };
+    }
+}
+
+struct Pen(*mut GpPen);
+
+impl Pen {
+    fn new(color: u32, width: f32) -> Self {
+        let mut pen = default();
+        unsafe { GdipCreatePen1(color, width, UnitPixel, &mut pen) };
+        Self(pen)
+    }
+}
+
+impl Drop for Pen {
+    fn drop(&mut self) {
+        unsafe { GdipDeletePen(self.0) };
+    }
+}
+
+struct Brush(*mut GpSolidFill);
+
+impl Brush {
+    fn new(color: u32) -> Self {
+        let mut brush = default();
+        unsafe { GdipCreateSolidFill(color, &mut brush) };
+        Self(brush)
+    }
+}
+
+impl Drop for Brush {
+    fn drop(&mut self) {
+        unsafe { GdipDeleteBrush(self.0 as _) };
+    }
+}
+
+impl Renderer for GdiPlusRenderer {
+    fn render(
+        &mut self,
+        hwnd: HWND,
+        paint: &mut dyn FnMut(&mut dyn Canvas) -> Result<()>,
+    ) -> Result<()> {
+        unsafe {
+            let mut window_rect = RECT::default();
+            GetWindowRect(hwnd, &mut window_rect)?;
+
+            let size = SIZE {
+                cx: window_rect.right - window_rect.left,
+                cy: window_rect.bottom - window_rect.top,
+            };
+
+            // declared so they're dropped in the right order, the graphics before the DC and the
+            // DC before the bitmap selected into it, whether or not painting fails. they're made
+            // afresh for every frame, so there's nothing to recreate when the screen goes away.
+            let screen = ScreenDc(GetDC(None));
+            let bitmap = Bitmap(CreateCompatibleBitmap(screen.0, size.cx, size.cy));
+            let dc = MemoryDc(CreateCompatibleDC(Some(screen.0)));
+
+            if screen.0.is_invalid() || bitmap.0.is_invalid() || dc.0.is_invalid() {
+                bail!(SurfaceLost);
+            }
+
+            SelectObject(dc.0, bitmap.0.into());
+
+            let mut graphics = Graphics(default());
+            GdipCreateFromHDC(dc.0, &mut graphics.0);
+            GdipSetTextRenderingHint(graphics.0, TextRenderingHintAntiAlias);
+
+            let mut canvas = GdiPlusCanvas {
+                renderer: self,
+                graphics: graphics.0,
+                dc: dc.0,
+            };
+
+            // a fully transparent pixel would let clicks fall through the layered window
+            let background = Rect {
+                x: 0.0,
+                y: 0.0,
+                width: size.cx as _,
+                height: size.cy as _,
+            };
+            canvas.fill_rect(background, 0x01000000)?;
+
+            // the window is updated with whatever was drawn even if painting failed partway,
+            // rather than being left showing an old frame
+            let painted = paint(&mut canvas);
+
+            let blend = BLENDFUNCTION {
+                BlendOp: AC_SRC_OVER as _,
+                BlendFlags: 0,
+                SourceConstantAlpha: 0xff,
+                AlphaFormat: AC_SRC_ALPHA as _,
+            };
+
+            UpdateLayeredWindow(
+                hwnd,
+                Some(screen.0),
+                None,
+                Some(&size),
+                Some(dc.0),
+                Some(&default()),
+                default(),
+                Some(&blend),
+                ULW_ALPHA,
+            )
+            .context(SurfaceLost)?;
+
+            painted
+        }
+    }
+}
+
+struct GdiPlusCanvas<'a> {
+    renderer: &'a mut GdiPlusRenderer,
+    graphics: *mut GpGraphics,
+    dc: HDC,
+}
+
+impl GdiPlusCanvas<'_> {
+    fn font(&mut self, size: f32) -> *mut GpFont {
+        let family = self.renderer.font_family;
+
+        *self
+            .renderer
+            .fonts
+            .entry(size.to_bits())
+            .or_insert_with(|| {
+                let mut font = default();
+                unsafe { GdipCreateFont(family, size, FontStyleRegular.0, UnitPixel, &mut font) };
+                font
+            })
+    }
+}
+
+fn encode(text: &str) -> Vec<u16> {
+    OsString::from(text).encode_wide().chain([0]).collect()
+}
+
+impl Canvas for GdiPlusCanvas<'_> {
+    fn fill_rect(&mut self, rect: Rect, color: u32) -> Result<()> {
+        let brush = Brush::new(color);
+
+        unsafe {
+            GdipFillRectangle(
+                self.graphics,
+                brush.0 as _,
+                rect.x,
+                rect.y,
+                rect.width,
+                rect.height,
+            );
+        }
+
+        Ok(())
+    }
+
+    fn draw_line(
+        &mut self,
+        from: (f32, f32),
+        to: (f32, f32),
+        width: f32,
+        cap: LineCap,
+        color: u32,
+    ) -> Result<()> {
+        let cap = match cap {
+            LineCap::Square => LineCapSquare,
+            LineCap::Triangle => LineCapTriangle,
+        };
+
+        let pen = Pen::new(color, width);
+
+        unsafe {
+            GdipSetPenEndCap(pen.0, cap);
+            GdipSetPenStartCap(pen.0, cap);
+            GdipDrawLine(self.graphics, pen.0, from.0, from.1, to.0, to.1);
+        }
+
+        Ok(())
+    }
+
+    fn draw_ellipse(&mut self, rect: Rect, width: f32, color: u32) -> Result<()> {
+        let pen = Pen::new(color, width);
+
+        unsafe {
+            GdipDrawEllipse(
+                self.graphics,
+                pen.0,
+                rect.x,
+                rect.y,
+                rect.width,
+                rect.height,
+            );
+        }
+
+        Ok(())
+    }
+
+    fn measure_text(&mut self, text: &str, size: f32) -> Result<(f32, f32)> {
+        let string = encode(text);
+        let font = self.font(size);
+
+        let rect = RectF {
+            X: 0.0,
+            Y: 0.0,
+            Width: 1000.0,
+            Height: 1000.0,
+        };
+
+        let mut bounds = RectF::default();
+        let mut chars = 0;
+        let mut lines = 0;
+
+        unsafe {
+            GdipMeasureString(
+                self.graphics,
+                PCWSTR(string.as_ptr()),
+                text.encode_utf16().count() as _,
+                font,
+                &rect,
+                self.renderer.format,
+                &mut bounds,
+                &mut chars,
+                &mut lines,
+            );
+        }
+
+        Ok((bounds.Width, bounds.Height))
+    }
+
+    fn draw_text(&mut self, text: &str, size: f32, x: f32, y: f32, color: u32) -> Result<()> {
+        let string = encode(text);
+        let font = self.font(size);
+
+        let rect = RectF {
+            X: x,
+            Y: y,
+            Width: 1000.0,
+            Height: 1000.0,
+        };
+
+        let brush = Brush::new(color);
+
+        unsafe {
+            GdipDrawString(
+                self.graphics,
+                PCWSTR(string.as_ptr()),
+                text.encode_utf16().count() as _,
+                font,
+                &rect,
+                self.renderer.format,
+                brush.0 as _,
+            );
+        }
+
+        Ok(())
+    }
+
+    fn draw_icon(&mut self, icon: HICON, x: f32, y: f32, size: f32) -> Result<()> {
+        let size = size.round() as i32;
+
+        unsafe {
+            DrawIconEx(
+                self.dc,
+                x.round() as i32,
+                y.round() as i32,
+                icon,
+                size,
+                size,
+                0,
+                None,
+                DI_NORMAL,
+            )?
+        };
+
+        Ok(())
+    }
+
+    fn push_clip(&mut self, rect: Rect) -> Result<()> {
+        unsafe {
+            GdipSetClipRect(
+                self.graphics,
+                rect.x,
+                rect.y,
+                rect.width,
+                rect.height,
+                CombineModeReplace,
+            );
+        }
+
+        Ok(())
+    }
+
+    fn pop_clip(&mut self) -> Result<()> {
+        unsafe { GdipResetClip(self.graphics) };
+
+        Ok(())
+    }
+}
@@ -0,0 +1,229 @@
+use std::time::Duration;
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use windows::Win32::{
+    Media::Audio::{EDataFlow, eCapture, eRender},
+    System::{
+        RemoteDesktop::{
+            ProcessIdToSessionId, WTS_CURRENT_SERVER_HANDLE, WTSClientProtocolType, WTSFreeMemory,
+            WTSQuerySessionInformationW,
+        },
+        SystemInformation::GetTickCount,
+        Threading::GetCurrentProcessId,
+    },
+    UI::{
+        Input::KeyboardAndMouse::{GetLastInputInfo, LASTINPUTINFO},
+        WindowsAndMessaging::{SPI_GETSCREENSAVERRUNNING, SystemParametersInfoW},
+    },
+};
+use windows_core::{BOOL, PWSTR};
+
+use crate::{default, widgets::focus::FocusMode};
+
+/// which kinds of device locking the session mutes, to be unmuted again on unlock
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LockMute {
+    #[default]
+    Both,
+    Output,
+    Input,
+    None,
+}
+
+impl LockMute {
+    pub const ALL: [LockMute; 4] = [
+        LockMute::Both,
+        LockMute::Input,
+        LockMute::Output,
+        LockMute::None,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            LockMute::Both => "Speakers and microphone",
+            LockMute::Input => "Microphone only",
+            LockMute::Output => "Speakers only",
+            LockMute::None => "Nothing",
+        }
+    }
+
+    pub fn flows(self) -> &'static [EDataFlow] {
+        match self {
+            LockMute::Both => &[eRender, eCapture],
+            LockMute::Output => &[eRender],
+            LockMute::Input => &[eCapture],
+            LockMute::None => &[],
+        }
+    }
+}
+
+/// what happens while the session is locked
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct LockConfig {
+    /// `input` keeps music playing through the speakers while making sure the mic is dead
+    pub mute: LockMute,
+    /// mute every active device of those kinds, rather than just the defaults
+    pub all_devices: bool,
+    /// pause whatever's playing, as an alternative to muting the speakers
+    pub pause_media: bool,
+    /// play again on unlock if it was paused on lock
+    pub resume_media: bool,
+    /// turn the displays off straight away, rather than leaving the lock screen up until they
+    /// time out
+    pub displays_off: bool,
+    /// mute while the screensaver runs too, for when it starts before the session locks
+    pub screensaver: bool,
+    /// substrings of the device names to make the defaults again on unlock, for docks that
+    /// switch them around while locked
+    pub output_device: Option<String>,
+    pub input_device: Option<String>,
+    /// switched to while locked
+    pub focus_assist: Option<FocusMode>,
+    /// react to other sessions locking and unlocking too, e.g. when another user switches in or
+    /// out, rather than only the one the panel runs in
+    pub other_sessions: bool,
+}
+
+/// what's holding focus assist in a mode other than the user's own
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FocusReason {
+    Lock,
+    Call,
+}
+
+/// changes focus assist for as long as a lock or a call lasts, and puts the user's own mode back
+/// once none do
+#[derive(Default)]
+pub struct FocusOverride {
+    /// the overrides in effect, the last of which decides the mode
+    reasons: Vec<(FocusReason, FocusMode)>,
+    /// the mode from before the first override
+    previous: Option<FocusMode>,
+}
+
+impl FocusOverride {
+    pub fn start(&mut self, reason: FocusReason, mode: FocusMode) -> Result<()> {
+        if self.reasons.is_empty() {
+            self.previous = Some(FocusMode::current()?);
+        }
+
+        self.reasons.retain(|(r, _)| *r != reason);
+        self.reasons.push((reason, mode));
+
+        mode.select()
+    }
+
+    pub fn end(&mut self, reason: FocusReason) -> Result<()> {
+        let Some(index) = self.reasons.iter().position(|(r, _)| *r == reason) else {
+            return Ok(());
+        };
+
+        self.reasons.remove(index);
+
+        match self.reasons.last() {
+            Some((_, mode)) => mode.select(),
+            None => match self.previous.take() {
+                Some(previous) => previous.select(),
+                None => Ok(()),
+            },
+        }
+    }
+}
+
+/// muting after a while without any input, for walking away without locking
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct IdleConfig {
+    /// minutes without keyboard or mouse input before muting, or never if unset
+    pub after_minutes: Option<u32>,
+    /// unmuted again as soon as there's input
+    pub mute: LockMute,
+}
+
+impl Default for IdleConfig {
+    fn default() -> Self {
+        Self {
+            after_minutes: None,
+            mute: LockMute::Input,
+        }
+    }
+}
+
+impl IdleConfig {
+    pub fn timeout(&self) -> Option<Duration> {
+        self.after_minutes
+            .map(|minutes| Duration::from_secs(minutes as u64 * 60))
+    }
+}
+
+/// run with `cmd /C` when the session changes, for glue that doesn't need its own option
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct SessionCommands {
+    pub lock: Vec<String>,
+    pub unlock: Vec<String>,
+    pub suspend: Vec<String>,
+    pub resume: Vec<String>,
+}
+
+/// whether `session` is connected over remote desktop rather than at the console
+pub fn is_remote(session: u32) -> Result<bool> {
+    unsafe {
+        let mut buffer = PWSTR::null();
+        let mut size = 0;
+        WTSQuerySessionInformationW(
+            Some(WTS_CURRENT_SERVER_HANDLE),
+            session,
+            WTSClientProtocolType,
+            &mut buffer,
+            &mut size,
+        )?;
+
+        // a USHORT, which is 0 for the console and 2 for rdp
+        let protocol = *(buffer.0 as *const u16);
+        WTSFreeMemory(buffer.0 as _);
+
+        Ok(protocol != 0)
+    }
+}
+
+/// whether `session` is the one the panel is running in
+pub fn is_own(session: u32) -> Result<bool> {
+    let mut own = 0;
+    unsafe { ProcessIdToSessionId(GetCurrentProcessId(), &mut own)? };
+
+    Ok(session == own)
+}
+
+/// how long it's been since the last keyboard or mouse input in this session
+pub fn idle_time() -> Result<Duration> {
+    let mut info = LASTINPUTINFO {
+        cbSize: size_of::<LASTINPUTINFO>() as u32,
+        ..default()
+    };
+
+    unsafe {
+        GetLastInputInfo(&mut info).ok()?;
+
+        // both are tick counts, which wrap around every 49 days
+        let idle = GetTickCount().wrapping_sub(info.dwTime);
+        Ok(Duration::from_millis(idle as u64))
+    }
+}
+
+pub fn is_screensaver_running() -> Result<bool> {
+    let mut running = BOOL::default();
+    unsafe {
+        SystemParametersInfoW(
+            SPI_GETSCREENSAVERRUNNING,
+            0,
+            Some(&mut running as *mut BOOL as _),
+            default(),
+        )?
+    };
+
+    Ok(running.as_bool())
+}
@@ -0,0 +1,187 @@
+use std::{
+    mem::size_of,
+    ptr::addr_of_mut,
+    sync::atomic::{AtomicU32, Ordering, fence},
+};
+
+use anyhow::{Result, bail};
+use windows::Win32::{
+    Foundation::{CloseHandle, HANDLE, INVALID_HANDLE_VALUE},
+    System::{
+        Memory::{
+            CreateFileMappingW, FILE_MAP_WRITE, MEMORY_MAPPED_VIEW_ADDRESS, MapViewOfFile,
+            PAGE_READWRITE, UnmapViewOfFile,
+        },
+        Threading::{CreateEventW, SetEvent},
+    },
+};
+use windows_core::HSTRING;
+
+/// the section holding a `Block`, for `OpenFileMappingW` with `FILE_MAP_READ`. it's in the
+/// session's own namespace, so only tools running in the same session see it.
+pub const SECTION_NAME: &str = r"Local\control-panel-status";
+
+/// a manual-reset event set after every change, for `OpenEventW` with `SYNCHRONIZE` and
+/// `EVENT_MODIFY_STATE`. wait on it, `ResetEvent` it, then read the block. with more than one
+/// reader, another may reset it first, so compare `sequence` too rather than only trusting it.
+pub const EVENT_NAME: &str = r"Local\control-panel-status-changed";
+
+/// bumped whenever a field changes meaning. fields are only ever added at the end without
+/// bumping it, and `size` grows to cover them.
+pub const VERSION: u32 = 1;
+
+/// the longest device name kept, in utf-16 units including the nul at the end
+pub const NAME_LENGTH: usize = 64;
+
+/// `flags` bit set while the session is locked
+pub const FLAG_LOCKED: u32 = 1 << 0;
+
+/// what's in the section, all little-endian `u32`s and utf-16:
+///
+/// | offset | field            |
+/// |--------|------------------|
+/// | 0      | `version`        |
+/// | 4      | `size`           |
+/// | 8      | `sequence`       |
+/// | 12     | `status.flags`   |
+/// | 16     | `status.output`  |
+/// | 152    | `status.input`   |
+///
+/// `sequence` is odd while the panel is writing. to read consistently, read it, copy the rest,
+/// then read it again and start over if it was odd or has changed. it's zero until the panel has
+/// published anything.
+#[repr(C)]
+struct Block {
+    version: u32,
+    /// in bytes, so readers built against an older layout can tell there's more
+    size: u32,
+    sequence: AtomicU32,
+    status: Status,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, PartialEq)]
+pub struct Status {
+    /// `FLAG_LOCKED`
+    pub flags: u32,
+    pub output: Device,
+    pub input: Device,
+}
+
+/// one of the default devices, 136 bytes:
+///
+/// | offset | field                                 |
+/// |--------|---------------------------------------|
+/// | 0      | `volume`, from 0 to 100               |
+/// | 4      | `muted`, 0 or 1                       |
+/// | 8      | `name`, nul-terminated and truncated  |
+///
+/// all zeroes, with an empty name, while there's no default device for the flow.
+#[repr(C)]
+#[derive(Clone, Copy, PartialEq)]
+pub struct Device {
+    pub volume: u32,
+    pub muted: u32,
+    pub name: [u16; NAME_LENGTH],
+}
+
+impl Device {
+    pub fn new(name: &str, volume: f32, muted: bool) -> Self {
+        let mut units = [0; NAME_LENGTH];
+        for (unit, c) in units[..NAME_LENGTH - 1].iter_mut().zip(name.encode_utf16()) {
+            *unit = c;
+        }
+
+        Self {
+            volume: (volume * 100.0).round() as u32,
+            muted: muted as u32,
+            name: units,
+        }
+    }
+}
+
+/// the panel's state published for tools that can't easily talk http or to a pipe, like
+/// rainmeter plugins or autohotkey scripts
+pub struct StatusBlock {
+    mapping: HANDLE,
+    view: MEMORY_MAPPED_VIEW_ADDRESS,
+    changed: HANDLE,
+    /// what's in the section, so it's only written and signalled when something changes
+    last: Option<Status>,
+}
+
+impl StatusBlock {
+    pub fn create() -> Result<Self> {
+        let size = size_of::<Block>() as u32;
+
+        unsafe {
+            let mapping = CreateFileMappingW(
+                INVALID_HANDLE_VALUE,
+                None,
+                PAGE_READWRITE,
+                0,
+                size,
+                &HSTRING::from(SECTION_NAME),
+            )?;
+
+            let view = MapViewOfFile(mapping, FILE_MAP_WRITE, 0, 0, size as usize);
+            if view.Value.is_null() {
+                let _ = CloseHandle(mapping);
+                bail!("failed to map the status block");
+            }
+
+            let changed = match CreateEventW(None, true, false, &HSTRING::from(EVENT_NAME)) {
+                Ok(changed) => changed,
+                Err(e) => {
+                    let _ = UnmapViewOfFile(view);
+                    let _ = CloseHandle(mapping);
+                    return Err(e.into());
+                }
+            };
+
+            let block = view.Value as *mut Block;
+            (*block).version = VERSION;
+            (*block).size = size;
+
+            Ok(Self {
+                mapping,
+                view,
+                changed,
+                last: None,
+            })
+        }
+    }
+
+    /// writes `status` to the section and sets the event, if it's changed
+    pub fn publish(&mut self, status: Status) -> Result<()> {
+        if self.last == Some(status) {
+            return Ok(());
+        }
+
+        self.last = Some(status);
+
+        unsafe {
+            let block = self.view.Value as *mut Block;
+            let sequence = &(*block).sequence;
+
+            sequence.fetch_add(1, Ordering::Relaxed);
+            fence(Ordering::Release);
+            addr_of_mut!((*block).status).write_volatile(status);
+            sequence.fetch_add(1, Ordering::Release);
+
+            SetEvent(self.changed)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Drop for StatusBlock {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = CloseHandle(self.changed);
+            let _ = UnmapViewOfFile(self.view);
+            let _ = CloseHandle(self.mapping);
+        }
+    }
+}
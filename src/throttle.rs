@@ -0,0 +1,66 @@
+use std::time::{Duration, Instant};
+
+/// collapses repeated lines and holds back floods, so something failing in a loop can't fill the
+/// log file or the event log
+pub struct Throttle {
+    /// the most lines passed on a minute
+    per_minute: u32,
+    last: String,
+    /// how many times `last` has come again since it was passed on
+    repeats: u32,
+    /// the start of the current minute and the lines passed on and held back in it
+    minute: Option<Instant>,
+    written: u32,
+    dropped: u32,
+}
+
+impl Throttle {
+    pub const fn new(per_minute: u32) -> Self {
+        Self {
+            per_minute,
+            last: String::new(),
+            repeats: 0,
+            minute: None,
+            written: 0,
+            dropped: 0,
+        }
+    }
+
+    /// passes `line` on to `output` unless it's a repeat or over the limit, with a note first
+    /// about whatever was collapsed or held back before it
+    pub fn pass(&mut self, line: &str, mut output: impl FnMut(&str)) {
+        if line == self.last {
+            self.repeats += 1;
+            return;
+        }
+
+        if self.repeats > 0 {
+            let repeats = std::mem::take(&mut self.repeats);
+            output(&format!("last message repeated {} times", repeats));
+        }
+
+        self.last = line.to_string();
+
+        let now = Instant::now();
+        if self
+            .minute
+            .is_none_or(|minute| now - minute >= Duration::from_secs(60))
+        {
+            self.minute = Some(now);
+            self.written = 0;
+
+            if self.dropped > 0 {
+                let dropped = std::mem::take(&mut self.dropped);
+                output(&format!("{} messages dropped", dropped));
+            }
+        }
+
+        if self.written >= self.per_minute {
+            self.dropped += 1;
+            return;
+        }
+
+        self.written += 1;
+        output(line);
+    }
+}
@@ -0,0 +1,121 @@
+use anyhow::Result;
+use windows::Win32::{
+    Foundation::{HWND, LPARAM, RECT},
+    System::LibraryLoader::GetModuleHandleW,
+    UI::{
+        Controls::{
+            NMTTDISPINFOW, TOOLTIPS_CLASSW, TTF_SUBCLASS, TTM_ADDTOOLW, TTM_DELTOOLW,
+            TTM_SETMAXTIPWIDTH, TTS_ALWAYSTIP, TTS_NOPREFIX, TTTOOLINFOW,
+        },
+        WindowsAndMessaging::{
+            CW_USEDEFAULT, CreateWindowExW, SendMessageW, WINDOW_STYLE, WS_EX_TOPMOST, WS_POPUP,
+        },
+    },
+};
+use windows_core::PWSTR;
+
+use crate::{default, layout::Layout};
+
+/// tells the tooltip to ask for the text with TTN_GETDISPINFO when it's about to show
+const LPSTR_TEXTCALLBACKW: PWSTR = PWSTR(-1isize as *mut u16);
+
+const MAX_WIDTH: isize = 300;
+
+/// a tooltip for each widget, showing the same text screen readers announce
+pub struct Tooltips {
+    hwnd: HWND,
+    /// the ids of the tools added for the current layout
+    tools: Vec<usize>,
+    /// the text of the tooltip being shown, which has to outlive the notification asking for it
+    text: Vec<u16>,
+}
+
+impl Tooltips {
+    pub fn new(owner: HWND) -> Result<Self> {
+        let hwnd = unsafe {
+            CreateWindowExW(
+                WS_EX_TOPMOST,
+                TOOLTIPS_CLASSW,
+                None,
+                WS_POPUP | WINDOW_STYLE(TTS_ALWAYSTIP | TTS_NOPREFIX),
+                CW_USEDEFAULT,
+                CW_USEDEFAULT,
+                CW_USEDEFAULT,
+                CW_USEDEFAULT,
+                Some(owner),
+                None,
+                Some(GetModuleHandleW(None)?.into()),
+                None,
+            )?
+        };
+
+        unsafe { SendMessageW(hwnd, TTM_SETMAXTIPWIDTH, None, Some(LPARAM(MAX_WIDTH))) };
+
+        Ok(Self {
+            hwnd,
+            tools: vec![],
+            text: vec![],
+        })
+    }
+
+    /// replaces the tools with one over each widget of `layout`. a tool's id is its widget's
+    /// `Widget::index`.
+    pub fn update(&mut self, owner: HWND, layout: &Layout) {
+        for id in self.tools.drain(..) {
+            let info = tool_info(owner, id, RECT::default());
+            unsafe {
+                SendMessageW(
+                    self.hwnd,
+                    TTM_DELTOOLW,
+                    None,
+                    Some(LPARAM(&info as *const _ as _)),
+                )
+            };
+        }
+
+        for widget in layout.widgets() {
+            let Some(rect) = layout.rect(widget) else {
+                continue;
+            };
+
+            let rect = RECT {
+                left: rect.x as i32,
+                top: rect.y as i32,
+                right: rect.right() as i32,
+                bottom: rect.bottom() as i32,
+            };
+
+            let id = widget.index();
+            let info = tool_info(owner, id, rect);
+            unsafe {
+                SendMessageW(
+                    self.hwnd,
+                    TTM_ADDTOOLW,
+                    None,
+                    Some(LPARAM(&info as *const _ as _)),
+                )
+            };
+
+            self.tools.push(id);
+        }
+    }
+
+    /// answers a TTN_GETDISPINFO notification with `text`
+    pub fn fill(&mut self, info: &mut NMTTDISPINFOW, text: &str) {
+        self.text = text.encode_utf16().chain([0]).collect();
+        info.lpszText = PWSTR(self.text.as_mut_ptr());
+    }
+}
+
+fn tool_info(owner: HWND, id: usize, rect: RECT) -> TTTOOLINFOW {
+    TTTOOLINFOW {
+        cbSize: size_of::<TTTOOLINFOW>() as u32,
+        // the tooltip watches the panel's mouse messages itself
+        uFlags: TTF_SUBCLASS,
+        hwnd: owner,
+        uId: id,
+        rect,
+        lpszText: LPSTR_TEXTCALLBACKW,
+        ..default()
+    }
+}
@@ -0,0 +1,288 @@
+use std::{mem::size_of, ptr::null_mut};
+
+use anyhow::{Result, bail};
+use serde::Deserialize;
+use windows::Win32::{
+    Foundation::HWND,
+    Graphics::Gdi::{
+        BI_RGB, BITMAPINFO, BITMAPINFOHEADER, CreateBitmap, CreateCompatibleDC, CreateDIBSection,
+        DIB_RGB_COLORS, DeleteDC, DeleteObject, SelectObject,
+    },
+    Media::Audio::{EDataFlow, eCapture, eRender},
+    UI::{
+        Shell::{
+            NIF_ICON, NIF_MESSAGE, NIF_TIP, NIM_ADD, NIM_DELETE, NIM_MODIFY, NIM_SETVERSION,
+            NOTIFYICON_VERSION_4, NOTIFYICONDATAW, NOTIFYICONDATAW_0, Shell_NotifyIconW,
+        },
+        WindowsAndMessaging::{
+            CreateIconIndirect, DI_NORMAL, DestroyIcon, DrawIconEx, GetSystemMetrics, HICON,
+            ICONINFO, SM_CXSMICON,
+        },
+    },
+};
+
+use crate::{default, layout::Widget};
+
+/// which default device a tray icon follows
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TrayDevice {
+    Output,
+    Input,
+}
+
+impl TrayDevice {
+    /// the panel widget showing the same device, whose bindings and menu the tray icon shares
+    pub fn widget(self) -> Widget {
+        match self {
+            TrayDevice::Output => Widget::Output,
+            TrayDevice::Input => Widget::Input,
+        }
+    }
+
+    pub fn flow(self) -> EDataFlow {
+        match self {
+            TrayDevice::Output => eRender,
+            TrayDevice::Input => eCapture,
+        }
+    }
+}
+
+/// notification area icon showing a default device's icon, crossed out while it's muted, so its
+/// state is visible even when a fullscreen app covers the panel. it uses the version 4 callbacks,
+/// which pack the event and icon id into `lparam` and the anchor point into `wparam`. without
+/// `NIF_SHOWTIP` the shell sends `NIN_POPUPOPEN` and `NIN_POPUPCLOSE` instead of showing the
+/// tooltip, which is then only read out by screen readers.
+pub struct TrayIcon {
+    hwnd: HWND,
+    device: TrayDevice,
+    callback_message: u32,
+    added: bool,
+    /// the device icon and mute state the badge was made from
+    shown: Option<(HICON, bool)>,
+    tip: String,
+    badge: Option<HICON>,
+}
+
+impl TrayIcon {
+    pub fn new(hwnd: HWND, device: TrayDevice, callback_message: u32) -> Self {
+        Self {
+            hwnd,
+            device,
+            callback_message,
+            added: false,
+            shown: None,
+            tip: String::new(),
+            badge: None,
+        }
+    }
+
+    pub fn device(&self) -> TrayDevice {
+        self.device
+    }
+
+    /// the id the icon is registered with, passed in the high word of its callback's `lparam`
+    pub fn id(&self) -> u32 {
+        self.device as u32
+    }
+
+    pub fn update(&mut self, device_icon: HICON, muted: bool) -> Result<()> {
+        let state = (device_icon, muted);
+        if self.added && self.shown == Some(state) {
+            return Ok(());
+        }
+
+        let size = unsafe { GetSystemMetrics(SM_CXSMICON) };
+        let badge = badge(device_icon, size, muted)?;
+
+        let mut data = NOTIFYICONDATAW {
+            cbSize: size_of::<NOTIFYICONDATAW>() as u32,
+            hWnd: self.hwnd,
+            uID: self.id(),
+            uFlags: NIF_ICON | NIF_TIP | NIF_MESSAGE,
+            uCallbackMessage: self.callback_message,
+            hIcon: badge,
+            ..default()
+        };
+
+        copy_tip(&mut data, &self.tip);
+
+        let message = if self.added { NIM_MODIFY } else { NIM_ADD };
+
+        unsafe {
+            if !Shell_NotifyIconW(message, &data).as_bool() {
+                DestroyIcon(badge)?;
+                bail!("failed to update tray icon");
+            }
+
+            if let Some(old) = self.badge.replace(badge) {
+                DestroyIcon(old)?;
+            }
+
+            if !self.added {
+                let data = NOTIFYICONDATAW {
+                    cbSize: size_of::<NOTIFYICONDATAW>() as u32,
+                    hWnd: self.hwnd,
+                    uID: self.id(),
+                    Anonymous: NOTIFYICONDATAW_0 {
+                        uVersion: NOTIFYICON_VERSION_4,
+                    },
+                    ..default()
+                };
+
+                if !Shell_NotifyIconW(NIM_SETVERSION, &data).as_bool() {
+                    bail!("failed to set tray icon version");
+                }
+            }
+        }
+
+        self.added = true;
+        self.shown = Some(state);
+
+        Ok(())
+    }
+
+    /// sets the tooltip, which is filled in lazily when the icon is hovered. it isn't shown, see
+    /// `TrayIcon`
+    pub fn set_tip(&mut self, tip: &str) -> Result<()> {
+        if !self.added || self.tip == tip {
+            return Ok(());
+        }
+
+        let mut data = NOTIFYICONDATAW {
+            cbSize: size_of::<NOTIFYICONDATAW>() as u32,
+            hWnd: self.hwnd,
+            uID: self.id(),
+            uFlags: NIF_TIP,
+            ..default()
+        };
+
+        copy_tip(&mut data, tip);
+
+        unsafe {
+            if !Shell_NotifyIconW(NIM_MODIFY, &data).as_bool() {
+                bail!("failed to update tray tooltip");
+            }
+        }
+
+        self.tip = tip.to_string();
+
+        Ok(())
+    }
+
+    /// rebuilds the badge on the next update, for when the device icon was replaced by one that
+    /// may have the same handle
+    pub fn invalidate(&mut self) {
+        self.shown = None;
+    }
+
+    /// re-adds the icon on the next update, after explorer has restarted and lost it
+    pub fn reset(&mut self) {
+        self.added = false;
+    }
+}
+
+/// copies as much of `tip` as fits, leaving room for the terminator
+fn copy_tip(data: &mut NOTIFYICONDATAW, tip: &str) {
+    let capacity = data.szTip.len() - 1;
+    for (dst, src) in data.szTip[..capacity].iter_mut().zip(tip.encode_utf16()) {
+        *dst = src;
+    }
+}
+
+impl Drop for TrayIcon {
+    fn drop(&mut self) {
+        unsafe {
+            if self.added {
+                let data = NOTIFYICONDATAW {
+                    cbSize: size_of::<NOTIFYICONDATAW>() as u32,
+                    hWnd: self.hwnd,
+                    uID: self.id(),
+                    ..default()
+                };
+
+                let _ = Shell_NotifyIconW(NIM_DELETE, &data);
+            }
+
+            if let Some(badge) = self.badge {
+                let _ = DestroyIcon(badge);
+            }
+        }
+    }
+}
+
+/// copies `icon` at `size` pixels, with a red cross over it if `muted`
+fn badge(icon: HICON, size: i32, muted: bool) -> Result<HICON> {
+    let info = BITMAPINFO {
+        bmiHeader: BITMAPINFOHEADER {
+            biSize: size_of::<BITMAPINFOHEADER>() as u32,
+            biWidth: size,
+            // negative for a top-down bitmap
+            biHeight: -size,
+            biPlanes: 1,
+            biBitCount: 32,
+            biCompression: BI_RGB.0,
+            ..default()
+        },
+        ..default()
+    };
+
+    unsafe {
+        let dc = CreateCompatibleDC(None);
+        let mut bits = null_mut();
+        let color = CreateDIBSection(Some(dc), &info, DIB_RGB_COLORS, &mut bits, None, 0)?;
+
+        let old = SelectObject(dc, color.into());
+        let drawn = DrawIconEx(dc, 0, 0, icon, size, size, 0, None, DI_NORMAL);
+        SelectObject(dc, old);
+        let _ = DeleteDC(dc);
+
+        if let Err(e) = drawn {
+            let _ = DeleteObject(color.into());
+            return Err(e.into());
+        }
+
+        if muted {
+            let count = (size * size) as usize;
+            let pixels = std::slice::from_raw_parts_mut(bits as *mut u32, count);
+            draw_cross(pixels, size);
+        }
+
+        // the mask is ignored for 32bpp color bitmaps, but has to be there
+        let mask = CreateBitmap(size, size, 1, 1, None);
+
+        let info = ICONINFO {
+            fIcon: true.into(),
+            hbmMask: mask,
+            hbmColor: color,
+            ..default()
+        };
+
+        let badge = CreateIconIndirect(&info);
+
+        let _ = DeleteObject(color.into());
+        let _ = DeleteObject(mask.into());
+
+        Ok(badge?)
+    }
+}
+
+/// paints an opaque red cross corner to corner over a square 0xAARRGGBB bitmap
+fn draw_cross(pixels: &mut [u32], size: i32) {
+    let half_width = size as f32 / 10.0;
+    let last = (size - 1) as f32;
+
+    for y in 0..size {
+        for x in 0..size {
+            let (fx, fy) = (x as f32, y as f32);
+
+            // distance to each diagonal
+            let down = (fx - fy).abs() / 2f32.sqrt();
+            let up = (fx + fy - last).abs() / 2f32.sqrt();
+
+            if down.min(up) <= half_width {
+                pixels[(y * size + x) as usize] = 0xffff0000;
+            }
+        }
+    }
+}
@@ -0,0 +1,276 @@
+use std::{
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        mpsc::{self, Receiver, RecvTimeoutError, Sender},
+    },
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+use windows::Win32::{
+    Foundation::{CloseHandle, HANDLE, HMODULE, LPARAM, WPARAM},
+    System::{
+        LibraryLoader::{
+            GET_MODULE_HANDLE_EX_FLAG_FROM_ADDRESS, GET_MODULE_HANDLE_EX_FLAG_UNCHANGED_REFCOUNT,
+            GetModuleFileNameW, GetModuleHandleExW,
+        },
+        Threading::{
+            GetCurrentThreadId, OpenProcess, OpenThread, PROCESS_SYNCHRONIZE, THREAD_GET_CONTEXT,
+            THREAD_QUERY_INFORMATION, THREAD_SUSPEND_RESUME, WaitForSingleObject,
+        },
+    },
+    UI::WindowsAndMessaging::PostMessageA,
+};
+use windows_core::PCWSTR;
+
+use crate::{WM_WATCHDOG_PING, ipc::X, metrics};
+
+/// how often the window is pinged
+const INTERVAL: Duration = Duration::from_secs(5);
+
+/// how long the window can go without answering before it's taken to be stuck. generous, since
+/// a slow device or a busy machine can hold it up for a while without anything being wrong
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// the longest stack reported
+const MAX_FRAMES: usize = 64;
+
+/// the argument a replacement for a hung process is started with, followed by the old one's id
+pub const AFTER_HANG: &str = "--after-hang";
+
+/// how long a replacement waits for the hung process to be gone before starting anyway
+const EXIT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// the last ping the window answered
+static PONG: AtomicU32 = AtomicU32::new(0);
+
+/// answers `WM_WATCHDOG_PING`, from the window's thread
+pub fn pong(ping: u32) {
+    PONG.store(ping, Ordering::Relaxed);
+    metrics::record_pong();
+}
+
+/// waits for the hung process `pid` to exit, so its window, hotkeys and pipe are free to take
+pub fn wait_for_exit(pid: u32) {
+    unsafe {
+        // already gone if it can't be opened
+        if let Ok(process) = OpenProcess(PROCESS_SYNCHRONIZE, false, pid) {
+            WaitForSingleObject(process, EXIT_TIMEOUT.as_millis() as u32);
+            let _ = CloseHandle(process);
+        }
+    }
+}
+
+/// the window's side of the watchdog, which stops it when dropped
+pub struct Watchdog {
+    _stop: Sender<()>,
+}
+
+/// starts watching the calling thread's message loop by pinging `hwnd`. if it stops answering,
+/// `hung` is called on the watchdog's thread with the stuck thread's stack.
+pub fn spawn(hwnd: X, hung: impl FnOnce(String) + Send + 'static) -> Result<Watchdog> {
+    let thread = unsafe {
+        OpenThread(
+            THREAD_SUSPEND_RESUME | THREAD_GET_CONTEXT | THREAD_QUERY_INFORMATION,
+            false,
+            GetCurrentThreadId(),
+        )?
+    };
+
+    // handles are only numbers, and this one is only used by the watchdog
+    let thread = thread.0 as isize;
+    let (stop, stopped) = mpsc::channel();
+
+    std::thread::spawn(move || {
+        let thread = HANDLE(thread as _);
+        run(hwnd, thread, stopped, hung);
+        unsafe {
+            let _ = CloseHandle(thread);
+        }
+    });
+
+    Ok(Watchdog { _stop: stop })
+}
+
+/// waits for `duration`, returning whether the watchdog's been stopped meanwhile
+fn wait(stopped: &Receiver<()>, duration: Duration) -> bool {
+    !matches!(
+        stopped.recv_timeout(duration),
+        Err(RecvTimeoutError::Timeout)
+    )
+}
+
+fn run(hwnd: X, thread: HANDLE, stopped: Receiver<()>, hung: impl FnOnce(String)) {
+    let mut ping = PONG.load(Ordering::Relaxed);
+
+    loop {
+        ping = ping.wrapping_add(1);
+
+        let sent = unsafe {
+            PostMessageA(
+                Some(hwnd.0),
+                WM_WATCHDOG_PING,
+                WPARAM(ping as usize),
+                LPARAM(0),
+            )
+        };
+
+        if sent.is_err() {
+            return;
+        }
+
+        let since = Instant::now();
+
+        while PONG.load(Ordering::Relaxed) != ping {
+            if since.elapsed() >= TIMEOUT {
+                let stack =
+                    stack(thread).unwrap_or_else(|e| format!("couldn't read the stack: {:?}", e));
+
+                hung(format!(
+                    "the window hasn't answered for {}s, its thread is at:\n{}",
+                    TIMEOUT.as_secs(),
+                    stack
+                ));
+                return;
+            }
+
+            if wait(
+                &stopped,
+                INTERVAL.min(TIMEOUT.saturating_sub(since.elapsed())),
+            ) {
+                return;
+            }
+        }
+
+        if wait(&stopped, INTERVAL) {
+            return;
+        }
+    }
+}
+
+/// where `thread` is, one frame per line as the module and offset into it
+fn stack(thread: HANDLE) -> Result<String> {
+    let mut addresses = [0; MAX_FRAMES];
+    let count = unwind::return_addresses(thread, &mut addresses)?;
+
+    let frames: Vec<String> = addresses[..count]
+        .iter()
+        .map(|&address| describe(address))
+        .collect();
+
+    Ok(frames.join("\n"))
+}
+
+fn describe(address: u64) -> String {
+    unsafe {
+        let mut module = HMODULE::default();
+        let found = GetModuleHandleExW(
+            GET_MODULE_HANDLE_EX_FLAG_FROM_ADDRESS | GET_MODULE_HANDLE_EX_FLAG_UNCHANGED_REFCOUNT,
+            PCWSTR(address as _),
+            &mut module,
+        );
+
+        if found.is_err() {
+            return format!("{:#x}", address);
+        }
+
+        let mut path = [0; 260];
+        let length = GetModuleFileNameW(Some(module), &mut path);
+        let path = String::from_utf16_lossy(&path[..length as usize]);
+        let name = path.rsplit('\\').next().unwrap_or(&path);
+
+        format!("{}+{:#x}", name, address - module.0 as u64)
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+mod unwind {
+    use std::ptr::null_mut;
+
+    use anyhow::{Result, bail};
+    use windows::Win32::{
+        Foundation::HANDLE,
+        System::{
+            Diagnostics::Debug::{
+                CONTEXT, CONTEXT_CONTROL_AMD64, CONTEXT_INTEGER_AMD64, GetThreadContext,
+                RtlLookupFunctionEntry, RtlVirtualUnwind, UNW_FLAG_NHANDLER,
+            },
+            Threading::{ResumeThread, SuspendThread},
+        },
+    };
+
+    use crate::default;
+
+    /// `GetThreadContext` wants it 16-byte aligned, which the binding doesn't promise
+    #[repr(C, align(16))]
+    struct Context(CONTEXT);
+
+    /// fills `addresses` with `thread`'s return addresses, innermost first, and says how many
+    /// there were
+    pub fn return_addresses(thread: HANDLE, addresses: &mut [u64]) -> Result<usize> {
+        unsafe {
+            if SuspendThread(thread) == u32::MAX {
+                bail!("failed to suspend the thread");
+            }
+
+            let count = walk(thread, addresses);
+            ResumeThread(thread);
+
+            match count {
+                Some(count) => Ok(count),
+                None => bail!("failed to read the thread's registers"),
+            }
+        }
+    }
+
+    /// nothing here allocates, since the suspended thread could be holding the heap's lock
+    unsafe fn walk(thread: HANDLE, addresses: &mut [u64]) -> Option<usize> {
+        unsafe {
+            let mut context = Context(default());
+            context.0.ContextFlags = CONTEXT_CONTROL_AMD64 | CONTEXT_INTEGER_AMD64;
+            GetThreadContext(thread, &mut context.0).ok()?;
+
+            let context = &mut context.0;
+            let mut count = 0;
+
+            while count < addresses.len() && context.Rip != 0 {
+                addresses[count] = context.Rip;
+                count += 1;
+
+                let mut image_base = 0;
+                let entry = RtlLookupFunctionEntry(context.Rip, &mut image_base, None);
+
+                if entry.is_null() {
+                    // a leaf function, which leaves the return address on top of the stack
+                    context.Rip = *(context.Rsp as *const u64);
+                    context.Rsp += 8;
+                } else {
+                    let mut handler_data = null_mut();
+                    let mut establisher_frame = 0;
+                    RtlVirtualUnwind(
+                        UNW_FLAG_NHANDLER,
+                        image_base,
+                        context.Rip,
+                        entry,
+                        context,
+                        &mut handler_data,
+                        &mut establisher_frame,
+                        None,
+                    );
+                }
+            }
+
+            Some(count)
+        }
+    }
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+mod unwind {
+    use anyhow::{Result, bail};
+    use windows::Win32::Foundation::HANDLE;
+
+    pub fn return_addresses(_thread: HANDLE, _addresses: &mut [u64]) -> Result<usize> {
+        bail!("stacks are only walked on x86-64")
+    }
+}
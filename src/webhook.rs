@@ -0,0 +1,174 @@
+use std::{
+    collections::HashMap,
+    sync::mpsc::{self, RecvTimeoutError},
+    time::Duration,
+};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use windows::{
+    Foundation::Uri,
+    Storage::Streams::UnicodeEncoding,
+    Web::Http::{HttpClient, HttpStringContent},
+    Win32::Media::Audio::{EDataFlow, eRender},
+};
+use windows_core::{HSTRING, h};
+
+/// urls posted to when something happens, e.g. for home automation to react to
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct WebhookConfig {
+    pub lock: Vec<String>,
+    pub unlock: Vec<String>,
+    /// posted when either default device is muted or unmuted
+    pub mute: Vec<String>,
+    /// requests still going after this long are cancelled
+    pub timeout_ms: u32,
+}
+
+impl Default for WebhookConfig {
+    fn default() -> Self {
+        Self {
+            lock: vec![],
+            unlock: vec![],
+            mute: vec![],
+            timeout_ms: 5000,
+        }
+    }
+}
+
+/// what changed a mute state
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MuteSource {
+    /// a click on the panel, its menu or a tray icon
+    Panel,
+    Hotkey,
+    /// the mute button on a headset, see `headset`
+    Headset,
+    /// the pipe, the http api or `WM_COPYDATA`
+    Command,
+    /// the mute button in discord, see `discord`
+    Discord,
+    Lock,
+    Idle,
+    Screensaver,
+    /// anything other than this app
+    External,
+}
+
+/// posted as json, e.g.
+/// `{ "event": "mute", "device": "input", "muted": true, "was_muted": false, "source": "hotkey" }`
+pub enum Event {
+    Lock,
+    Unlock,
+    Mute {
+        flow: EDataFlow,
+        muted: bool,
+        source: MuteSource,
+    },
+}
+
+impl Event {
+    fn urls(&self, config: &WebhookConfig) -> Vec<String> {
+        match self {
+            Event::Lock => config.lock.clone(),
+            Event::Unlock => config.unlock.clone(),
+            Event::Mute { .. } => config.mute.clone(),
+        }
+    }
+
+    fn body(&self) -> String {
+        let body = match self {
+            Event::Lock => json!({ "event": "lock" }),
+            Event::Unlock => json!({ "event": "unlock" }),
+            Event::Mute {
+                flow,
+                muted,
+                source,
+            } => json!({
+                "event": "mute",
+                "device": if *flow == eRender { "output" } else { "input" },
+                "muted": muted,
+                "was_muted": !muted,
+                "source": source,
+            }),
+        };
+
+        body.to_string()
+    }
+}
+
+/// posts `event` to its urls in the background, without waiting for the responses. failures are
+/// logged and reported to the event log.
+pub fn fire(config: &WebhookConfig, event: Event) {
+    let body = event.body();
+    let timeout = Duration::from_millis(config.timeout_ms as u64);
+
+    for url in event.urls(config) {
+        let body = body.clone();
+        std::thread::spawn(move || {
+            if let Err(e) = post(&url, &body, timeout) {
+                crate::log_background(&format!("webhook {}: {:#}", url, e));
+            }
+        });
+    }
+}
+
+fn post(url: &str, body: &str, timeout: Duration) -> Result<()> {
+    let client = HttpClient::new()?;
+    let uri = Uri::CreateUri(&HSTRING::from(url))?;
+    let content = HttpStringContent::CreateFromStringWithEncodingAndMediaType(
+        &HSTRING::from(body),
+        UnicodeEncoding::Utf8,
+        h!("application/json"),
+    )?;
+
+    let request = client.PostAsync(&uri, &content)?;
+
+    // cancels the request unless it's finished first, which drops `finished`
+    let (finished, wait) = mpsc::channel::<()>();
+    let watched = request.clone();
+    std::thread::spawn(move || {
+        if wait.recv_timeout(timeout) == Err(RecvTimeoutError::Timeout) {
+            let _ = watched.Cancel();
+        }
+    });
+
+    let response = request.get();
+    drop(finished);
+    response?.EnsureSuccessStatusCode()?;
+
+    Ok(())
+}
+
+/// the last seen mute state of each default device, so only changes are posted
+#[derive(Default)]
+pub struct MuteWatch {
+    muted: HashMap<i32, bool>,
+    /// what the app itself was doing when it last changed a mute state, see `expect`
+    source: Option<MuteSource>,
+}
+
+impl MuteWatch {
+    /// attributes the changes seen next to `source`, unless something further up already
+    /// claimed them, e.g. a hotkey running the toggle mute action
+    pub fn expect(&mut self, source: MuteSource) {
+        self.source.get_or_insert(source);
+    }
+
+    /// what caused the changes since the last call. the app's own changes always show up by the
+    /// next check, which their volume notification leads to, so anything else is external.
+    pub fn take_source(&mut self) -> MuteSource {
+        self.source.take().unwrap_or(MuteSource::External)
+    }
+
+    /// records whether `flow`'s default device is muted, returning true if that's a change.
+    /// the first state seen isn't one.
+    pub fn update(&mut self, flow: EDataFlow, muted: bool) -> bool {
+        self.muted
+            .insert(flow.0, muted)
+            .is_some_and(|previous| previous != muted)
+    }
+}
@@ -0,0 +1,392 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+use serde::Deserialize;
+use windows::Win32::UI::WindowsAndMessaging::HICON;
+
+use crate::{
+    config::Config,
+    layout::{Layout, Rect, Widget},
+    render::{Canvas, LineCap},
+};
+
+pub mod bluetooth;
+pub mod brightness;
+pub mod button;
+pub mod camera;
+pub mod capture;
+pub mod clock;
+pub mod command;
+pub mod consent;
+pub mod controllers;
+pub mod cpu;
+pub mod date;
+pub mod disk;
+pub mod focus;
+pub mod locks;
+pub mod media;
+pub mod meeting;
+pub mod memory;
+pub mod network;
+pub mod nightlight;
+pub mod obs;
+pub mod peripherals;
+pub mod poll;
+pub mod pomodoro;
+pub mod refresh;
+pub mod source;
+pub mod uptime;
+pub mod vpn;
+pub mod weather;
+pub mod wifi;
+
+/// color of text drawn on the panel
+pub const TEXT_COLOR: u32 = 0xff202020;
+
+const WARNING_COLOR: u32 = 0xffc07000;
+pub const CRITICAL_COLOR: u32 = 0xffd02020;
+
+/// the value after `current` in `values`, wrapping around, or the first one if `current` isn't
+/// among them. for widgets that step through a configured list when clicked
+pub fn cycle<T: Copy + PartialEq>(values: &[T], current: Option<T>) -> Option<T> {
+    let position = values.iter().position(|&value| Some(value) == current);
+    let next = position.map_or(0, |position| (position + 1) % values.len());
+
+    values.get(next).copied()
+}
+
+/// draws `text` centered in `rect`
+pub fn draw_centered_text(
+    canvas: &mut dyn Canvas,
+    rect: Rect,
+    text: &str,
+    size: f32,
+    color: u32,
+) -> Result<()> {
+    let (width, height) = canvas.measure_text(text, size)?;
+
+    let x = rect.x + (rect.width - width) / 2.0;
+    let y = rect.y + (rect.height - height) / 2.0;
+
+    canvas.draw_text(text, size, x, y, color)
+}
+
+/// fills a circle of `radius` around `(x, y)`, since `Canvas` only outlines ellipses
+pub fn fill_circle(
+    canvas: &mut dyn Canvas,
+    (x, y): (f32, f32),
+    radius: f32,
+    color: u32,
+) -> Result<()> {
+    // a circle of half the radius, outlined as thick as the radius, comes out filled
+    let rect = Rect {
+        x: x - radius / 2.0,
+        y: y - radius / 2.0,
+        width: radius,
+        height: radius,
+    };
+
+    canvas.draw_ellipse(rect, radius, color)
+}
+
+/// a horizontal battery centered on `(x, y)`, filled up to `level` (0 to 1) and red when nearly
+/// empty. an unknown level leaves it empty.
+pub fn draw_battery(
+    canvas: &mut dyn Canvas,
+    layout: &Layout,
+    (x, y): (f32, f32),
+    level: Option<f32>,
+) -> Result<()> {
+    let (width, height) = (layout.px(18.0), layout.px(10.0));
+    let body = Rect {
+        x: x - width / 2.0,
+        y: y - height / 2.0,
+        width,
+        height,
+    };
+
+    let line = layout.px(1.5);
+    let corners = [
+        (body.x, body.y),
+        (body.right(), body.y),
+        (body.right(), body.bottom()),
+        (body.x, body.bottom()),
+    ];
+    for i in 0..corners.len() {
+        let next = corners[(i + 1) % corners.len()];
+        canvas.draw_line(corners[i], next, line, LineCap::Square, TEXT_COLOR)?;
+    }
+
+    let nub = Rect {
+        x: body.right() + line / 2.0,
+        y: y - layout.px(2.5),
+        width: layout.px(2.0),
+        height: layout.px(5.0),
+    };
+    canvas.fill_rect(nub, TEXT_COLOR)?;
+
+    if let Some(level) = level {
+        let color = if level <= 0.2 {
+            CRITICAL_COLOR
+        } else {
+            TEXT_COLOR
+        };
+
+        let inset = layout.px(2.5);
+        let fill = Rect {
+            x: body.x + inset,
+            y: body.y + inset,
+            width: (body.width - inset * 2.0) * level.clamp(0.0, 1.0),
+            height: body.height - inset * 2.0,
+        };
+        canvas.fill_rect(fill, color)?;
+    }
+
+    Ok(())
+}
+
+/// the last `capacity` readings of something, oldest first
+pub struct History {
+    samples: VecDeque<f32>,
+    capacity: usize,
+}
+
+impl History {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            samples: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    pub fn push(&mut self, sample: f32) {
+        if self.samples.len() == self.capacity {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(sample);
+    }
+
+    pub fn latest(&self) -> Option<f32> {
+        self.samples.back().copied()
+    }
+
+    pub fn max(&self) -> f32 {
+        self.samples.iter().copied().fold(0.0, f32::max)
+    }
+}
+
+/// draws `history` as a line across `area`, with `max` at the top and the newest sample at the
+/// right edge
+pub fn draw_sparkline(
+    canvas: &mut dyn Canvas,
+    area: Rect,
+    history: &History,
+    max: f32,
+    width: f32,
+    color: u32,
+) -> Result<()> {
+    let step = area.width / (history.capacity - 1).max(1) as f32;
+    let start = area.right() - step * (history.samples.len() as f32 - 1.0);
+
+    let points: Vec<_> = history
+        .samples
+        .iter()
+        .enumerate()
+        .map(|(i, sample)| {
+            let fraction = if max > 0.0 { sample / max } else { 0.0 };
+            let y = area.bottom() - area.height * fraction.clamp(0.0, 1.0);
+            (start + step * i as f32, y)
+        })
+        .collect();
+
+    for pair in points.windows(2) {
+        canvas.draw_line(pair[0], pair[1], width, LineCap::Triangle, color)?;
+    }
+
+    Ok(())
+}
+
+/// percentages at which a reading is drawn in the warning and critical colors
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(default)]
+pub struct Thresholds {
+    pub warning: f32,
+    pub critical: f32,
+}
+
+impl Default for Thresholds {
+    fn default() -> Self {
+        Self {
+            warning: 70.0,
+            critical: 90.0,
+        }
+    }
+}
+
+impl Thresholds {
+    /// the color to draw a reading of `fraction` (0 to 1) in
+    pub fn color(&self, fraction: f32) -> u32 {
+        let percent = fraction * 100.0;
+
+        if percent >= self.critical {
+            CRITICAL_COLOR
+        } else if percent >= self.warning {
+            WARNING_COLOR
+        } else {
+            TEXT_COLOR
+        }
+    }
+}
+
+const MIN_INTERVAL_MS: u32 = 100;
+
+/// the readings behind the widgets that poll for them
+#[derive(Default)]
+pub struct Monitors {
+    scheduler: poll::Scheduler,
+    pub cpu: cpu::CpuMonitor,
+    pub memory: memory::MemoryMonitor,
+    pub network: network::NetworkMonitor,
+    pub wifi: wifi::WifiMonitor,
+    pub vpn: vpn::VpnMonitor,
+    pub disk: disk::DiskMonitor,
+    pub locks: locks::LockKeysMonitor,
+    pub media: media::NowPlayingMonitor,
+    pub camera: consent::Usage,
+    pub capture: consent::Usage,
+    pub focus: focus::FocusMonitor,
+    pub night_light: nightlight::NightLightMonitor,
+    pub brightness: brightness::BrightnessMonitor,
+    pub input_source: source::InputSourceMonitor,
+    pub refresh_rate: refresh::RefreshRateMonitor,
+    pub bluetooth: bluetooth::BluetoothMonitor,
+    pub controllers: controllers::ControllersMonitor,
+    pub peripherals: peripherals::PeripheralsMonitor,
+    pub meeting: meeting::MeetingMonitor,
+    pub command: command::CommandMonitor,
+    pub weather: weather::WeatherMonitor,
+    pub obs: obs::ObsMonitor,
+    /// counts down on the clock timer rather than being polled
+    pub pomodoro: pomodoro::Pomodoro,
+    /// icons of the configured buttons, loaded along with the config rather than polled
+    pub buttons: Vec<Option<HICON>>,
+    /// why each widget's last reading failed, for it to show a warning
+    pub errors: HashMap<Widget, String>,
+}
+
+impl Monitors {
+    /// how often `widget` is polled, if it is
+    fn interval(widget: Widget, config: &Config) -> Option<Duration> {
+        let ms = match widget {
+            Widget::Cpu | Widget::Network => 1000,
+            Widget::Memory => config.memory.interval_ms,
+            Widget::Wifi => 5000,
+            Widget::Vpn => 2000,
+            Widget::Disk => config.disk.interval_ms,
+            Widget::LockKeys => 250,
+            Widget::NowPlaying => 1000,
+            Widget::Camera | Widget::ScreenCapture => 1000,
+            Widget::Bluetooth | Widget::Meeting => 2000,
+            Widget::Controllers => 5000,
+            // the command's own interval is kept by the monitor, this just picks up its output
+            Widget::Command | Widget::Weather => 1000,
+            Widget::Obs => 1000,
+            Widget::Peripherals => 60 * 1000,
+            Widget::FocusAssist | Widget::NightLight | Widget::RefreshRate => 1000,
+            Widget::Brightness | Widget::InputSource => 5000,
+            _ => return None,
+        };
+
+        // configured intervals could otherwise spin the timer
+        Some(Duration::from_millis(ms.max(MIN_INTERVAL_MS) as u64))
+    }
+
+    /// the widgets with nothing to show, which are left out of the layout
+    pub fn hidden(&self) -> Vec<Widget> {
+        let mut hidden = vec![];
+        if self.controllers.controllers.is_empty() {
+            hidden.push(Widget::Controllers);
+        }
+
+        hidden
+    }
+
+    /// takes a reading for each of `widgets` that's due. one that fails doesn't stop the rest,
+    /// and its widget shows a warning until a reading works again
+    pub fn poll(&mut self, widgets: impl Iterator<Item = Widget>, config: &Config) -> Polled {
+        let now = Instant::now();
+        let polled = widgets.filter_map(|widget| Some((widget, Self::interval(widget, config)?)));
+
+        let due = self.scheduler.due(polled, now);
+        let next = self.scheduler.next_poll(now);
+
+        let mut changed = false;
+        let mut failed = vec![];
+
+        for &widget in &due {
+            // whether the reading may have changed. the ones polled often only repaint when
+            // something actually did
+            let sampled = match widget {
+                Widget::Cpu => self.cpu.sample().map(|()| true),
+                Widget::Memory => self.memory.sample().map(|()| true),
+                Widget::Network => self.network.sample(&config.network).map(|()| true),
+                Widget::Wifi => self.wifi.sample().map(|()| true),
+                Widget::Vpn => self.vpn.sample(&config.vpn).map(|()| true),
+                Widget::Disk => self.disk.sample(&config.disk).map(|()| true),
+                Widget::Brightness => self.brightness.sample(),
+                Widget::InputSource => self.input_source.sample(&config.input_source),
+                Widget::Peripherals => self.peripherals.sample(&config.peripherals).map(|()| true),
+                Widget::LockKeys => Ok(self.locks.sample(&config.lock_keys)),
+                Widget::NowPlaying => self.media.sample(),
+                Widget::Camera => self.camera.sample(camera::CAPABILITIES),
+                Widget::ScreenCapture => self.capture.sample(capture::CAPABILITIES),
+                Widget::FocusAssist => self.focus.sample(),
+                Widget::NightLight => self.night_light.sample(),
+                Widget::RefreshRate => self.refresh_rate.sample(&config.refresh_rate),
+                Widget::Bluetooth => self.bluetooth.sample(&config.bluetooth_devices()),
+                Widget::Controllers => self.controllers.sample(),
+                Widget::Meeting => self.meeting.sample(&config.meeting),
+                Widget::Command => Ok(self.command.sample(&config.command)),
+                Widget::Weather => Ok(self.weather.sample(&config.weather)),
+                Widget::Obs => self.obs.sample(),
+                _ => Ok(false),
+            };
+
+            match sampled {
+                Ok(sampled) => {
+                    changed |= sampled;
+                    changed |= self.errors.remove(&widget).is_some();
+                }
+                Err(e) => {
+                    let error = format!("{:#}", e);
+
+                    // passed on once rather than on every poll while it keeps failing
+                    if self.errors.get(&widget) != Some(&error) {
+                        self.errors.insert(widget, error);
+                        failed.push((widget, e));
+                        changed = true;
+                    }
+                }
+            }
+        }
+
+        Polled {
+            changed,
+            next,
+            failed,
+        }
+    }
+}
+
+/// what a `Monitors::poll` came to
+pub struct Polled {
+    /// whether any of the readings may have changed
+    pub changed: bool,
+    /// how long until the next one is due
+    pub next: Option<Duration>,
+    /// the widgets whose reading has started failing, or failing differently, since the last
+    pub failed: Vec<(Widget, anyhow::Error)>,
+}
@@ -0,0 +1,205 @@
+use std::time::Duration;
+
+use anyhow::Result;
+use serde::Deserialize;
+use windows::{
+    Devices::Radios::{Radio, RadioKind, RadioState},
+    Win32::{
+        Devices::FunctionDiscovery::PKEY_Device_FriendlyName,
+        Media::Audio::{DEVICE_STATE_ACTIVE, IMMDeviceEnumerator, MMDeviceEnumerator, eRender},
+        System::Com::{CLSCTX_ALL, CoCreateInstance, STGM_READ},
+    },
+};
+
+use super::{TEXT_COLOR, draw_centered_text, poll::Background};
+use crate::{
+    layout::{Layout, Rect},
+    render::{Canvas, LineCap},
+};
+
+/// opens the bluetooth page of the settings app
+pub const BLUETOOTH_SETTINGS: &str = "ms-settings:bluetooth";
+
+const ON_COLOR: u32 = 0xff0060c0;
+const OFF_COLOR: u32 = 0xffa0a0a0;
+
+/// how long to wait before looking for the radio again after the lookup failed
+const LOOKUP_RETRY: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct BluetoothConfig {
+    /// substrings of the names of the audio devices to count and list in the widget's menu.
+    /// `bluetooth_device` is used if this is empty.
+    pub devices: Vec<String>,
+    /// reconnect any of the devices that didn't come back on their own after sleep
+    pub reconnect_on_resume: bool,
+}
+
+#[derive(Default)]
+pub struct BluetoothMonitor {
+    /// the bluetooth radio, or `None` inside if there's no adapter. `None` until it's been
+    /// looked up.
+    radio: Option<Option<Radio>>,
+    lookup: Background<Result<Option<Radio>>>,
+    enumerator: Option<IMMDeviceEnumerator>,
+    /// whether the radio is on, or `None` if there's no bluetooth adapter or it's still being
+    /// looked for
+    pub on: Option<bool>,
+    /// the configured devices that currently have an active audio endpoint
+    pub connected: Vec<String>,
+}
+
+impl BluetoothMonitor {
+    /// returns whether the radio or the connected devices changed
+    pub fn sample(&mut self, devices: &[String]) -> Result<bool> {
+        let on = match self.radio()? {
+            Some(radio) => Some(radio.State()? == RadioState::On),
+            None => None,
+        };
+
+        let connected = self.connected(devices)?;
+
+        let changed = on != self.on || connected != self.connected;
+        self.on = on;
+        self.connected = connected;
+
+        Ok(changed)
+    }
+
+    /// looking up the radio can take a while, so it happens on another thread and the radio
+    /// shows as missing until it's done. once it's found there's no adapter, that sticks.
+    fn radio(&mut self) -> Result<Option<&Radio>> {
+        if self.radio.is_none() {
+            match self.lookup.finished() {
+                Some(radio) => self.radio = Some(radio?),
+                None => self.lookup.start(LOOKUP_RETRY, find_radio),
+            }
+        }
+
+        Ok(self.radio.as_ref().and_then(Option::as_ref))
+    }
+
+    /// which of `devices` have an active playback endpoint, which bluetooth headsets only do
+    /// while connected
+    fn connected(&mut self, devices: &[String]) -> Result<Vec<String>> {
+        let enumerator = match &self.enumerator {
+            Some(enumerator) => enumerator,
+            None => self
+                .enumerator
+                .insert(unsafe { CoCreateInstance(&MMDeviceEnumerator, None, CLSCTX_ALL)? }),
+        };
+
+        let mut names = vec![];
+        unsafe {
+            let endpoints = enumerator.EnumAudioEndpoints(eRender, DEVICE_STATE_ACTIVE)?;
+
+            for i in 0..endpoints.GetCount()? {
+                let props = endpoints.Item(i)?.OpenPropertyStore(STGM_READ)?;
+                names.push(
+                    props
+                        .GetValue(&PKEY_Device_FriendlyName)?
+                        .to_string()
+                        .to_lowercase(),
+                );
+            }
+        }
+
+        let connected = devices
+            .iter()
+            .filter(|device| {
+                let device = device.to_lowercase();
+                names.iter().any(|name| name.contains(&device))
+            })
+            .cloned()
+            .collect();
+
+        Ok(connected)
+    }
+}
+
+fn find_radio() -> Result<Option<Radio>> {
+    let radios = Radio::GetRadiosAsync()?.get()?;
+    let radio = radios
+        .into_iter()
+        .find(|radio| radio.Kind().is_ok_and(|kind| kind == RadioKind::Bluetooth));
+
+    Ok(radio)
+}
+
+/// the bluetooth rune, colored while the radio is on, with the number of connected devices next
+/// to it
+pub fn draw(
+    canvas: &mut dyn Canvas,
+    layout: &Layout,
+    rect: Rect,
+    bluetooth: &BluetoothMonitor,
+) -> Result<()> {
+    let color = match bluetooth.on {
+        Some(true) => ON_COLOR,
+        _ => OFF_COLOR,
+    };
+
+    let count = bluetooth.connected.len();
+    let height = layout.px(20.0);
+    let center = match count {
+        0 => rect.x + rect.width / 2.0,
+        _ => rect.x + rect.width / 2.0 - layout.px(8.0),
+    };
+
+    draw_rune(
+        canvas,
+        layout,
+        center,
+        rect.y + (rect.height - height) / 2.0,
+        height,
+        color,
+    )?;
+
+    if count > 0 {
+        let area = Rect {
+            x: center + layout.px(8.0),
+            width: layout.px(16.0),
+            ..rect
+        };
+        draw_centered_text(
+            canvas,
+            area,
+            &count.to_string(),
+            layout.px(13.0),
+            TEXT_COLOR,
+        )?;
+    }
+
+    Ok(())
+}
+
+/// a stem with two arrowheads on its right, crossing over to the left at the quarter points
+fn draw_rune(
+    canvas: &mut dyn Canvas,
+    layout: &Layout,
+    x: f32,
+    top: f32,
+    height: f32,
+    color: u32,
+) -> Result<()> {
+    let bottom = top + height;
+    let reach = height / 4.0;
+    let (upper, lower) = (top + height / 4.0, bottom - height / 4.0);
+
+    let points = [
+        (x - reach, upper),
+        (x + reach, lower),
+        (x, bottom),
+        (x, top),
+        (x + reach, upper),
+        (x - reach, lower),
+    ];
+
+    let width = layout.px(2.0);
+    for pair in points.windows(2) {
+        canvas.draw_line(pair[0], pair[1], width, LineCap::Square, color)?;
+    }
+
+    Ok(())
+}
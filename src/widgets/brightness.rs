@@ -0,0 +1,193 @@
+use std::time::Duration;
+
+use anyhow::{Result, bail};
+use serde::Deserialize;
+use windows::Win32::Devices::Display::{GetMonitorBrightness, SetMonitorBrightness};
+
+use super::{
+    TEXT_COLOR, draw_centered_text,
+    poll::{Background, Failures},
+};
+use crate::{
+    ddc::{self, PhysicalMonitor},
+    layout::{Layout, Rect},
+    render::Canvas,
+};
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct BrightnessConfig {
+    /// how many percent one notch of the wheel changes the brightness by
+    pub step: u32,
+}
+
+impl Default for BrightnessConfig {
+    fn default() -> Self {
+        Self { step: 10 }
+    }
+}
+
+/// a monitor's brightness, along with the range it reports
+struct Level {
+    min: u32,
+    current: u32,
+    max: u32,
+}
+
+impl Level {
+    fn read(monitor: &PhysicalMonitor) -> Result<Self> {
+        let (mut min, mut current, mut max) = (0, 0, 0);
+
+        let ok =
+            unsafe { GetMonitorBrightness(monitor.handle(), &mut min, &mut current, &mut max) };
+        if ok == 0 || max <= min {
+            bail!("{} doesn't report its brightness", monitor.name());
+        }
+
+        Ok(Self { min, current, max })
+    }
+
+    fn percent(&self) -> u32 {
+        // some monitors report a current level outside their own range
+        self.current
+            .saturating_sub(self.min)
+            .min(self.max - self.min)
+            * 100
+            / (self.max - self.min)
+    }
+
+    fn value(&self, percent: u32) -> u32 {
+        self.min + percent.min(100) * (self.max - self.min) / 100
+    }
+}
+
+/// the brightness of each monitor that supports DDC/CI. it's read and set on the DDC/CI worker,
+/// see `ddc::queue`.
+#[derive(Default)]
+pub struct BrightnessMonitor {
+    pub displays: Vec<(String, u32)>,
+    reads: Background<Result<Vec<(String, u32)>>>,
+    failures: Failures,
+}
+
+impl BrightnessMonitor {
+    /// picks up the levels read since the last call and queues the next read. fails if a step
+    /// has since, see `step`. returns whether any level changed.
+    pub fn sample(&mut self) -> Result<bool> {
+        let read = self.reads.finished();
+        self.reads.start_on(Duration::ZERO, ddc::queue, read_all);
+
+        self.failures.take()?;
+
+        let Some(displays) = read else {
+            return Ok(false);
+        };
+
+        let displays = displays?;
+        let changed = displays != self.displays;
+        self.displays = displays;
+
+        Ok(changed)
+    }
+
+    /// steps the brightness of the `index`th monitor shown, or of all of them, up or down by
+    /// `config.step` percent. the levels shown change straight away, and the next sample
+    /// catches up with what the monitors actually did.
+    pub fn step(&mut self, index: Option<usize>, up: bool, config: &BrightnessConfig) {
+        for (i, display) in self.displays.iter_mut().enumerate() {
+            if index.is_none_or(|index| index == i) {
+                display.1 = step(display.1, up, config.step);
+            }
+        }
+
+        let amount = config.step;
+        let failures = self.failures.sender();
+
+        ddc::queue(move || {
+            if let Err(e) = step_all(index, up, amount) {
+                let _ = failures.send(e);
+            }
+        });
+    }
+}
+
+fn step(percent: u32, up: bool, amount: u32) -> u32 {
+    match up {
+        true => (percent + amount).min(100),
+        false => percent.saturating_sub(amount),
+    }
+}
+
+fn read_all() -> Result<Vec<(String, u32)>> {
+    let displays = ddc::monitors()?
+        .iter()
+        .filter_map(|monitor| Some((monitor.name(), Level::read(monitor).ok()?.percent())))
+        .collect();
+
+    Ok(displays)
+}
+
+/// one monitor that can't be set doesn't stop the rest
+fn step_all(index: Option<usize>, up: bool, amount: u32) -> Result<()> {
+    let monitors = ddc::monitors()?;
+    let controllable = monitors
+        .iter()
+        .filter_map(|monitor| Some((monitor, Level::read(monitor).ok()?)));
+
+    let mut failed = vec![];
+
+    for (i, (monitor, level)) in controllable.enumerate() {
+        if index.is_some_and(|index| index != i) {
+            continue;
+        }
+
+        let percent = step(level.percent(), up, amount);
+        let ok = unsafe { SetMonitorBrightness(monitor.handle(), level.value(percent)) };
+        if ok == 0 {
+            failed.push(monitor.name());
+        }
+    }
+
+    if !failed.is_empty() {
+        bail!("failed to set the brightness of {}", failed.join(", "));
+    }
+
+    Ok(())
+}
+
+/// which of `count` equal parts of `rect` the point `x` falls in
+pub fn segment(rect: Rect, count: usize, x: f32) -> Option<usize> {
+    if count == 0 || !(rect.x..rect.right()).contains(&x) {
+        return None;
+    }
+
+    let index = ((x - rect.x) / (rect.width / count as f32)) as usize;
+    Some(index.min(count - 1))
+}
+
+/// each monitor's brightness, side by side
+pub fn draw(
+    canvas: &mut dyn Canvas,
+    layout: &Layout,
+    rect: Rect,
+    brightness: &BrightnessMonitor,
+) -> Result<()> {
+    let count = brightness.displays.len();
+    if count == 0 {
+        return draw_centered_text(canvas, rect, "-", layout.px(13.0), TEXT_COLOR);
+    }
+
+    let width = rect.width / count as f32;
+    for (i, (_, percent)) in brightness.displays.iter().enumerate() {
+        let part = Rect {
+            x: rect.x + width * i as f32,
+            width,
+            ..rect
+        };
+
+        let text = format!("{}%", percent);
+        draw_centered_text(canvas, part, &text, layout.px(13.0), TEXT_COLOR)?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,54 @@
+use anyhow::Result;
+use serde::Deserialize;
+use windows::Win32::UI::WindowsAndMessaging::HICON;
+
+use super::{TEXT_COLOR, draw_centered_text};
+use crate::{
+    layout::{Layout, Rect},
+    render::Canvas,
+};
+
+/// a widget defined entirely in the config, placed with `{ "button": index }` in `widgets`
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct ButtonConfig {
+    /// an icon file, or a dll or exe with the icon's index after a comma, e.g. "shell32.dll,4"
+    pub icon: Option<String>,
+    /// shown on hover, and drawn in place of the icon if there isn't one
+    pub tooltip: String,
+    /// run with `cmd /C` when the button is clicked
+    pub command: String,
+}
+
+impl ButtonConfig {
+    /// the tooltip, or the command if there's no tooltip
+    pub fn name(&self) -> &str {
+        match self.tooltip.is_empty() {
+            true => &self.command,
+            false => &self.tooltip,
+        }
+    }
+}
+
+/// the icon, or the first letter of the tooltip without one
+pub fn draw(
+    canvas: &mut dyn Canvas,
+    layout: &Layout,
+    rect: Rect,
+    config: &ButtonConfig,
+    icon: Option<HICON>,
+) -> Result<()> {
+    match icon {
+        Some(icon) => {
+            let size = layout.px(24.0);
+            let x = rect.x + (rect.width - size) / 2.0;
+            let y = rect.y + (rect.height - size) / 2.0;
+
+            canvas.draw_icon(icon, x, y, size)
+        }
+        None => {
+            let letter: String = config.name().chars().take(1).collect();
+            draw_centered_text(canvas, rect, &letter, layout.px(18.0), TEXT_COLOR)
+        }
+    }
+}
@@ -0,0 +1,63 @@
+use anyhow::Result;
+
+use super::consent::Usage;
+use crate::{
+    layout::{Layout, Rect},
+    render::{Canvas, LineCap},
+};
+
+const IN_USE_COLOR: u32 = 0xffd02020;
+const IDLE_COLOR: u32 = 0xffa0a0a0;
+
+/// the capability the camera is recorded under in the consent store
+pub const CAPABILITIES: &[&str] = &["webcam"];
+
+/// a camera glyph, lit red while any app is using the camera
+pub fn draw(canvas: &mut dyn Canvas, layout: &Layout, rect: Rect, camera: &Usage) -> Result<()> {
+    let color = if camera.in_use() {
+        IN_USE_COLOR
+    } else {
+        IDLE_COLOR
+    };
+
+    draw_camera(canvas, layout, rect, color)
+}
+
+/// the body of a video camera with its lens cone on the right, centered in `rect`
+pub fn draw_camera(canvas: &mut dyn Canvas, layout: &Layout, rect: Rect, color: u32) -> Result<()> {
+    let x = rect.x + (rect.width - layout.px(26.0)) / 2.0;
+    let middle = rect.y + rect.height / 2.0;
+
+    let body = Rect {
+        x,
+        y: middle - layout.px(7.0),
+        width: layout.px(18.0),
+        height: layout.px(14.0),
+    };
+    canvas.fill_rect(body, color)?;
+
+    let lens = body.right() + layout.px(1.0);
+    let end = lens + layout.px(7.0);
+    let width = layout.px(2.0);
+    canvas.draw_line(
+        (lens, middle),
+        (end, middle - layout.px(6.0)),
+        width,
+        LineCap::Square,
+        color,
+    )?;
+    canvas.draw_line(
+        (lens, middle),
+        (end, middle + layout.px(6.0)),
+        width,
+        LineCap::Square,
+        color,
+    )?;
+    canvas.draw_line(
+        (end, middle - layout.px(6.0)),
+        (end, middle + layout.px(6.0)),
+        width,
+        LineCap::Square,
+        color,
+    )
+}
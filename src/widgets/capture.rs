@@ -0,0 +1,53 @@
+use anyhow::Result;
+
+use super::{consent::Usage, fill_circle};
+use crate::{
+    layout::{Layout, Rect},
+    render::{Canvas, LineCap},
+};
+
+const RECORDING_COLOR: u32 = 0xffd02020;
+const IDLE_COLOR: u32 = 0xffa0a0a0;
+
+/// what the consent store records screen capture under: through the graphics capture api, and
+/// without the yellow border it normally draws around what's being captured
+pub const CAPABILITIES: &[&str] = &[
+    "graphicsCaptureProgrammatic",
+    "graphicsCaptureWithoutBorder",
+];
+
+/// a screen with a dot in the middle, which turns red while anything is capturing the screen
+pub fn draw(canvas: &mut dyn Canvas, layout: &Layout, rect: Rect, capture: &Usage) -> Result<()> {
+    let color = if capture.in_use() {
+        RECORDING_COLOR
+    } else {
+        IDLE_COLOR
+    };
+
+    let width = layout.px(24.0);
+    let height = layout.px(16.0);
+    let screen = Rect {
+        x: rect.x + (rect.width - width) / 2.0,
+        y: rect.y + (rect.height - height) / 2.0,
+        width,
+        height,
+    };
+
+    let line = layout.px(2.0);
+    let corners = [
+        (screen.x, screen.y),
+        (screen.right(), screen.y),
+        (screen.right(), screen.bottom()),
+        (screen.x, screen.bottom()),
+    ];
+    for i in 0..corners.len() {
+        let next = corners[(i + 1) % corners.len()];
+        canvas.draw_line(corners[i], next, line, LineCap::Square, color)?;
+    }
+
+    let center = (
+        screen.x + screen.width / 2.0,
+        screen.y + screen.height / 2.0,
+    );
+    fill_circle(canvas, center, layout.px(3.0), color)
+}
@@ -0,0 +1,129 @@
+use std::fmt::Write;
+
+use anyhow::Result;
+use serde::Deserialize;
+use windows::Win32::{Foundation::SYSTEMTIME, System::SystemInformation::GetLocalTime};
+
+use super::{TEXT_COLOR, date::iso_week, draw_centered_text};
+use crate::{
+    layout::{Layout, Rect},
+    render::Canvas,
+};
+
+const DAYS: [&str; 7] = [
+    "Sunday",
+    "Monday",
+    "Tuesday",
+    "Wednesday",
+    "Thursday",
+    "Friday",
+    "Saturday",
+];
+
+const MONTHS: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct ClockConfig {
+    /// strftime-style format, see `format`. defaults to hours and minutes.
+    pub format: Option<String>,
+    /// shows a 12 hour clock when there's no format
+    pub twelve_hour: bool,
+}
+
+impl ClockConfig {
+    fn format(&self) -> &str {
+        match &self.format {
+            Some(format) => format,
+            None if self.twelve_hour => "%I:%M %p",
+            None => "%H:%M",
+        }
+    }
+
+    /// milliseconds until the clock's text next changes
+    pub fn next_tick(&self) -> u32 {
+        let now = now();
+        let ms = now.wMilliseconds as u32;
+
+        if self.format().contains("%S") {
+            1000 - ms
+        } else {
+            (60 - now.wSecond as u32) * 1000 - ms
+        }
+    }
+
+    pub fn text(&self) -> String {
+        format(self.format(), &now())
+    }
+}
+
+pub fn now() -> SYSTEMTIME {
+    unsafe { GetLocalTime() }
+}
+
+pub fn draw(
+    canvas: &mut dyn Canvas,
+    layout: &Layout,
+    rect: Rect,
+    config: &ClockConfig,
+) -> Result<()> {
+    draw_centered_text(canvas, rect, &config.text(), layout.px(18.0), TEXT_COLOR)
+}
+
+/// formats `time` like strftime. supports %H %I %M %S %p %a %A %b %B %d %e %m %y %Y %V and %%;
+/// anything else is copied as is.
+pub fn format(format: &str, time: &SYSTEMTIME) -> String {
+    let mut text = String::new();
+    let mut chars = format.chars();
+
+    let hour12 = match time.wHour % 12 {
+        0 => 12,
+        hour => hour,
+    };
+
+    let day = DAYS[time.wDayOfWeek as usize % 7];
+    let month = MONTHS[(time.wMonth as usize).saturating_sub(1) % 12];
+
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            text.push(c);
+            continue;
+        }
+
+        let _ = match chars.next() {
+            Some('H') => write!(text, "{:02}", time.wHour),
+            Some('I') => write!(text, "{:02}", hour12),
+            Some('M') => write!(text, "{:02}", time.wMinute),
+            Some('S') => write!(text, "{:02}", time.wSecond),
+            Some('p') => write!(text, "{}", if time.wHour < 12 { "AM" } else { "PM" }),
+            Some('a') => write!(text, "{}", &day[..3]),
+            Some('A') => write!(text, "{}", day),
+            Some('b') => write!(text, "{}", &month[..3]),
+            Some('B') => write!(text, "{}", month),
+            Some('d') => write!(text, "{:02}", time.wDay),
+            Some('e') => write!(text, "{}", time.wDay),
+            Some('m') => write!(text, "{:02}", time.wMonth),
+            Some('y') => write!(text, "{:02}", time.wYear % 100),
+            Some('Y') => write!(text, "{}", time.wYear),
+            Some('V') => write!(text, "{:02}", iso_week(time).1),
+            Some('%') => write!(text, "%"),
+            Some(other) => write!(text, "%{}", other),
+            None => write!(text, "%"),
+        };
+    }
+
+    text
+}
@@ -0,0 +1,187 @@
+use std::{
+    io::Read,
+    os::windows::process::CommandExt,
+    process::{Command, Stdio},
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+use serde::Deserialize;
+use windows::Win32::System::Threading::CREATE_NO_WINDOW;
+
+use super::{CRITICAL_COLOR, TEXT_COLOR, draw_centered_text, poll::Background};
+use crate::{
+    layout::{Layout, Rect},
+    render::Canvas,
+};
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct CommandConfig {
+    /// run with `cmd /C`, its trimmed output is shown
+    pub command: String,
+    pub interval_ms: u32,
+    /// in characters, longer output is cut off with an ellipsis
+    pub max_width: usize,
+    /// "#rrggbb" to draw failures in
+    pub error_color: Option<String>,
+    /// shown instead of the error when the command fails
+    pub error_text: Option<String>,
+    /// a run still going after this long is killed, and shown as failed
+    pub timeout_ms: u32,
+}
+
+impl Default for CommandConfig {
+    fn default() -> Self {
+        Self {
+            command: String::new(),
+            interval_ms: 60 * 1000,
+            max_width: 24,
+            error_color: None,
+            error_text: None,
+            timeout_ms: 30 * 1000,
+        }
+    }
+}
+
+impl CommandConfig {
+    fn error_color(&self) -> u32 {
+        let rgb = self
+            .error_color
+            .as_deref()
+            .and_then(|color| color.strip_prefix('#'))
+            .and_then(|hex| u32::from_str_radix(hex, 16).ok());
+
+        match rgb {
+            Some(rgb) => 0xff000000 | rgb,
+            None => CRITICAL_COLOR,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct CommandMonitor {
+    /// the last run's output, or why it failed
+    pub output: Option<Result<String, String>>,
+    runs: Background<Result<String, String>>,
+}
+
+impl CommandMonitor {
+    /// picks up the result of the last run, and starts the next one once the interval is up.
+    /// returns whether the output changed.
+    pub fn sample(&mut self, config: &CommandConfig) -> bool {
+        let mut changed = false;
+
+        if let Some(output) = self.runs.finished() {
+            changed = self.output.as_ref() != Some(&output);
+            self.output = Some(output);
+        }
+
+        if !config.command.is_empty() {
+            let command = config.command.clone();
+            let timeout = Duration::from_millis(config.timeout_ms as u64);
+            let interval = Duration::from_millis(config.interval_ms as u64);
+            self.runs.start(interval, move || run(&command, timeout));
+        }
+
+        changed
+    }
+
+    /// the full output, or the error
+    pub fn text(&self, config: &CommandConfig) -> String {
+        match &self.output {
+            Some(Ok(output)) => output.clone(),
+            Some(Err(e)) => config.error_text.clone().unwrap_or_else(|| e.clone()),
+            None => String::new(),
+        }
+    }
+}
+
+/// how often a run is checked on while waiting for it to exit
+const WAIT_INTERVAL: Duration = Duration::from_millis(50);
+
+fn run(command: &str, timeout: Duration) -> Result<String, String> {
+    let mut child = Command::new("cmd")
+        .arg("/C")
+        .raw_arg(command)
+        .creation_flags(CREATE_NO_WINDOW.0)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| e.to_string())?;
+
+    // read as it comes, so a command with a lot to say doesn't block on a full pipe
+    let stdout = read_all(child.stdout.take());
+    let stderr = read_all(child.stderr.take());
+
+    let started = Instant::now();
+    let status = loop {
+        if let Some(status) = child.try_wait().map_err(|e| e.to_string())? {
+            break status;
+        }
+
+        if started.elapsed() >= timeout {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(format!("timed out after {}s", timeout.as_secs()));
+        }
+
+        std::thread::sleep(WAIT_INTERVAL);
+    };
+
+    let stdout = stdout.join().unwrap_or_default();
+    let stderr = stderr.join().unwrap_or_default();
+
+    if !status.success() {
+        return Err(match stderr.trim() {
+            "" => status.to_string(),
+            stderr => stderr.lines().next().unwrap_or_default().to_string(),
+        });
+    }
+
+    Ok(stdout.trim().to_string())
+}
+
+/// reads `pipe` to the end on its own thread
+fn read_all(pipe: Option<impl Read + Send + 'static>) -> std::thread::JoinHandle<String> {
+    std::thread::spawn(move || {
+        let mut bytes = vec![];
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_end(&mut bytes);
+        }
+
+        String::from_utf8_lossy(&bytes).into_owned()
+    })
+}
+
+/// the output on one line, cut down to `max_width`
+pub fn draw(
+    canvas: &mut dyn Canvas,
+    layout: &Layout,
+    rect: Rect,
+    config: &CommandConfig,
+    command: &CommandMonitor,
+) -> Result<()> {
+    let color = match command.output {
+        Some(Err(_)) => config.error_color(),
+        _ => TEXT_COLOR,
+    };
+
+    let text = command.text(config);
+    let line = text.lines().next().unwrap_or_default();
+
+    let text = match line.chars().count() > config.max_width {
+        true => {
+            let kept: String = line
+                .chars()
+                .take(config.max_width.saturating_sub(1))
+                .collect();
+            kept + "…"
+        }
+        false => line.to_string(),
+    };
+
+    canvas.push_clip(rect)?;
+    draw_centered_text(canvas, rect, &text, layout.px(14.0), color)?;
+    canvas.pop_clip()
+}
@@ -0,0 +1,94 @@
+use std::path::Path;
+
+use anyhow::Result;
+use windows::Win32::System::Registry::{HKEY_CURRENT_USER, HKEY_LOCAL_MACHINE};
+
+use crate::registry;
+
+/// where windows records which apps have used each privacy-sensitive capability, and when
+const CONSENT_STORE: &str =
+    r"Software\Microsoft\Windows\CurrentVersion\CapabilityAccessManager\ConsentStore";
+
+/// which apps are using a capability
+#[derive(Default)]
+pub struct Usage {
+    pub apps: Vec<String>,
+}
+
+impl Usage {
+    /// looks up the apps using any of `capabilities`. returns whether the set of apps changed.
+    pub fn sample(&mut self, capabilities: &[&str]) -> Result<bool> {
+        let mut apps = vec![];
+        for capability in capabilities {
+            for app in apps_using(capability)? {
+                if !apps.contains(&app) {
+                    apps.push(app);
+                }
+            }
+        }
+
+        let changed = apps != self.apps;
+        self.apps = apps;
+
+        Ok(changed)
+    }
+
+    pub fn in_use(&self) -> bool {
+        !self.apps.is_empty()
+    }
+}
+
+/// the names of the apps currently using `capability` (e.g. "webcam"), according to the
+/// capability access manager. an app is using it while its last use has a start time but no stop
+/// time.
+fn apps_using(capability: &str) -> Result<Vec<String>> {
+    let mut apps = vec![];
+
+    for root in [HKEY_CURRENT_USER, HKEY_LOCAL_MACHINE] {
+        let path = format!(r"{}\{}", CONSENT_STORE, capability);
+
+        // the capability isn't there until something has asked for it
+        let Ok(keys) = registry::subkeys(root, &path) else {
+            continue;
+        };
+
+        for key in keys {
+            // desktop apps are grouped under one key, packaged apps each have their own
+            let entries = match key.as_str() {
+                "NonPackaged" => {
+                    let path = format!(r"{}\NonPackaged", path);
+                    let keys = registry::subkeys(root, &path).unwrap_or_default();
+                    keys.into_iter().map(|key| (path.clone(), key)).collect()
+                }
+                _ => vec![(path.clone(), key)],
+            };
+
+            for (parent, key) in entries {
+                let entry = format!(r"{}\{}", parent, key);
+                let start = registry::qword(root, &entry, "LastUsedTimeStart").unwrap_or(0);
+                let stop = registry::qword(root, &entry, "LastUsedTimeStop").unwrap_or(0);
+
+                let name = app_name(&key);
+                if start != 0 && stop == 0 && !apps.contains(&name) {
+                    apps.push(name);
+                }
+            }
+        }
+    }
+
+    Ok(apps)
+}
+
+/// a readable name from a consent store key, which is either a package family name like
+/// "Microsoft.WindowsCamera_8wekyb3d8bbwe" or an executable path with '#' for '\'
+fn app_name(key: &str) -> String {
+    if key.contains('#') {
+        let path = key.replace('#', r"\");
+        if let Some(stem) = Path::new(&path).file_stem() {
+            return stem.to_string_lossy().into_owned();
+        }
+    }
+
+    let name = key.split('_').next().unwrap_or(key);
+    name.rsplit('.').next().unwrap_or(name).to_string()
+}
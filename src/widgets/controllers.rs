@@ -0,0 +1,103 @@
+use anyhow::Result;
+use windows::Win32::UI::Input::XboxController::{
+    BATTERY_DEVTYPE_GAMEPAD, BATTERY_LEVEL_FULL, BATTERY_TYPE_DISCONNECTED, BATTERY_TYPE_UNKNOWN,
+    BATTERY_TYPE_WIRED, XINPUT_BATTERY_INFORMATION, XINPUT_STATE, XInputGetBatteryInformation,
+    XInputGetState,
+};
+
+use super::draw_battery;
+use crate::{
+    layout::{Layout, Rect},
+    render::Canvas,
+};
+
+/// how many controllers xinput supports
+const SLOTS: u32 = 4;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Battery {
+    Wired,
+    /// from 0 (empty) to 3 (full), which is as precise as xinput gets
+    Level(u8),
+    Unknown,
+}
+
+impl Battery {
+    fn fraction(self) -> Option<f32> {
+        match self {
+            Battery::Level(level) => Some(level as f32 / BATTERY_LEVEL_FULL.0 as f32),
+            Battery::Wired | Battery::Unknown => None,
+        }
+    }
+
+    pub fn describe(self) -> &'static str {
+        match self {
+            Battery::Wired => "wired",
+            Battery::Level(0) => "battery empty",
+            Battery::Level(1) => "battery low",
+            Battery::Level(2) => "battery medium",
+            Battery::Level(_) => "battery full",
+            Battery::Unknown => "battery unknown",
+        }
+    }
+}
+
+/// the connected xinput controllers, by their player number starting at 1
+#[derive(Default)]
+pub struct ControllersMonitor {
+    pub controllers: Vec<(u32, Battery)>,
+}
+
+impl ControllersMonitor {
+    /// returns whether a controller was connected, disconnected, or its battery changed
+    pub fn sample(&mut self) -> Result<bool> {
+        let controllers: Vec<_> = (0..SLOTS)
+            .filter_map(|slot| Some((slot + 1, battery(slot)?)))
+            .collect();
+
+        let changed = controllers != self.controllers;
+        self.controllers = controllers;
+
+        Ok(changed)
+    }
+}
+
+/// the battery of the controller in `slot`, or `None` if there isn't one
+fn battery(slot: u32) -> Option<Battery> {
+    unsafe {
+        let mut state = XINPUT_STATE::default();
+        if XInputGetState(slot, &mut state) != 0 {
+            return None;
+        }
+
+        let mut info = XINPUT_BATTERY_INFORMATION::default();
+        if XInputGetBatteryInformation(slot, BATTERY_DEVTYPE_GAMEPAD, &mut info) != 0 {
+            return Some(Battery::Unknown);
+        }
+
+        Some(match info.BatteryType {
+            BATTERY_TYPE_WIRED => Battery::Wired,
+            BATTERY_TYPE_DISCONNECTED | BATTERY_TYPE_UNKNOWN => Battery::Unknown,
+            _ => Battery::Level(info.BatteryLevel.0),
+        })
+    }
+}
+
+/// a battery for each controller, side by side
+pub fn draw(
+    canvas: &mut dyn Canvas,
+    layout: &Layout,
+    rect: Rect,
+    controllers: &ControllersMonitor,
+) -> Result<()> {
+    let width = rect.width / controllers.controllers.len().max(1) as f32;
+
+    for (i, (_, battery)) in controllers.controllers.iter().enumerate() {
+        let x = rect.x + width * (i as f32 + 0.5);
+        let y = rect.y + rect.height / 2.0;
+
+        draw_battery(canvas, layout, (x, y), battery.fraction())?;
+    }
+
+    Ok(())
+}
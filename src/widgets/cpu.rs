@@ -0,0 +1,106 @@
+use anyhow::Result;
+use serde::Deserialize;
+use windows::Win32::{Foundation::FILETIME, System::Threading::GetSystemTimes};
+
+use super::{History, TEXT_COLOR, Thresholds, draw_sparkline};
+use crate::{
+    layout::{Layout, Rect},
+    render::{Canvas, LineCap},
+};
+
+/// how many samples the sparkline shows
+const HISTORY: usize = 30;
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct CpuConfig {
+    pub thresholds: Thresholds,
+}
+
+/// total cpu use, from the system-wide idle and busy times between polls
+pub struct CpuMonitor {
+    /// idle and total time at the last poll, in 100ns units
+    last: Option<(u64, u64)>,
+    /// fractions of time spent busy
+    history: History,
+}
+
+impl Default for CpuMonitor {
+    fn default() -> Self {
+        Self {
+            last: None,
+            history: History::new(HISTORY),
+        }
+    }
+}
+
+impl CpuMonitor {
+    pub fn sample(&mut self) -> Result<()> {
+        let (mut idle, mut kernel, mut user) = Default::default();
+        unsafe { GetSystemTimes(Some(&mut idle), Some(&mut kernel), Some(&mut user))? };
+
+        // kernel time includes the idle time
+        let idle = ticks(idle);
+        let total = ticks(kernel) + ticks(user);
+
+        if let Some((last_idle, last_total)) = self.last.replace((idle, total)) {
+            let elapsed = total.saturating_sub(last_total);
+            if elapsed > 0 {
+                let busy = elapsed.saturating_sub(idle.saturating_sub(last_idle));
+                self.history.push(busy as f32 / elapsed as f32);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// the latest reading, once there have been two polls
+    pub fn usage(&self) -> Option<f32> {
+        self.history.latest()
+    }
+}
+
+fn ticks(time: FILETIME) -> u64 {
+    (time.dwHighDateTime as u64) << 32 | time.dwLowDateTime as u64
+}
+
+/// the percentage on top, and a sparkline of recent use below it
+pub fn draw(
+    canvas: &mut dyn Canvas,
+    layout: &Layout,
+    rect: Rect,
+    config: &CpuConfig,
+    cpu: &CpuMonitor,
+) -> Result<()> {
+    let text = match cpu.usage() {
+        Some(usage) => format!("CPU {:.0}%", usage * 100.0),
+        None => "CPU".to_string(),
+    };
+
+    let color = cpu
+        .usage()
+        .map_or(TEXT_COLOR, |usage| config.thresholds.color(usage));
+
+    let size = layout.px(13.0);
+    let (width, _) = canvas.measure_text(&text, size)?;
+    let x = rect.x + (rect.width - width) / 2.0;
+    canvas.draw_text(&text, size, x, rect.y + layout.px(6.0), color)?;
+
+    // the sparkline runs from 26 to 40 of the 48 high widget
+    let area = Rect {
+        x: rect.x + layout.px(6.0),
+        y: rect.y + layout.px(26.0),
+        width: rect.width - layout.px(12.0),
+        height: layout.px(14.0),
+    };
+
+    canvas.draw_line(
+        (area.x, area.bottom()),
+        (area.right(), area.bottom()),
+        layout.px(1.0),
+        LineCap::Square,
+        0xffc0c0c0,
+    )?;
+
+    draw_sparkline(canvas, area, &cpu.history, 1.0, layout.px(1.5), color)
+}
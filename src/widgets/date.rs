@@ -0,0 +1,129 @@
+use anyhow::Result;
+use serde::Deserialize;
+use windows::Win32::Foundation::SYSTEMTIME;
+
+use super::{
+    TEXT_COLOR,
+    clock::{format, now},
+};
+use crate::{
+    layout::{Layout, Rect},
+    render::Canvas,
+};
+
+/// opens the notification center, which holds the calendar on windows 11
+const CALENDAR_FLYOUT: &str = "ms-actioncenter:";
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct DateConfig {
+    /// strftime-style format for the date line, see `clock::format`
+    pub format: Option<String>,
+    /// opened instead of the calendar flyout when the widget is clicked
+    pub url: Option<String>,
+}
+
+impl DateConfig {
+    fn format(&self) -> &str {
+        self.format.as_deref().unwrap_or("%a %e %b")
+    }
+
+    /// milliseconds until the date next changes, at midnight
+    pub fn next_tick(&self) -> u32 {
+        let now = now();
+
+        let seconds = (23 - now.wHour as u32) * 3600
+            + (59 - now.wMinute as u32) * 60
+            + (60 - now.wSecond as u32);
+
+        seconds * 1000 - now.wMilliseconds as u32
+    }
+
+    pub fn text(&self) -> String {
+        format(self.format(), &now())
+    }
+
+    /// what a click on the widget opens
+    pub fn target(&self) -> &str {
+        self.url.as_deref().unwrap_or(CALENDAR_FLYOUT)
+    }
+}
+
+/// the date on top, and the week number below it
+pub fn draw(
+    canvas: &mut dyn Canvas,
+    layout: &Layout,
+    rect: Rect,
+    config: &DateConfig,
+) -> Result<()> {
+    let (_, week) = iso_week(&now());
+    let lines = [
+        (config.text(), layout.px(15.0)),
+        (format!("Week {}", week), layout.px(11.0)),
+    ];
+
+    let mut sizes = vec![];
+    for (text, size) in &lines {
+        sizes.push(canvas.measure_text(text, *size)?);
+    }
+
+    let total: f32 = sizes.iter().map(|(_, height)| height).sum();
+    let mut y = rect.y + (rect.height - total) / 2.0;
+
+    for ((text, size), (width, height)) in lines.iter().zip(sizes) {
+        let x = rect.x + (rect.width - width) / 2.0;
+        canvas.draw_text(text, *size, x, y, TEXT_COLOR)?;
+        y += height;
+    }
+
+    Ok(())
+}
+
+/// the ISO 8601 week-numbering year and week of `time`. weeks start on monday, and week 1 is
+/// the one with the year's first thursday in it.
+pub fn iso_week(time: &SYSTEMTIME) -> (i32, u32) {
+    let year = time.wYear as i32;
+
+    // monday is 1 and sunday is 7
+    let weekday = match time.wDayOfWeek {
+        0 => 7,
+        day => day as i32,
+    };
+
+    let week = (day_of_year(year, time.wMonth, time.wDay) - weekday + 10) / 7;
+
+    if week < 1 {
+        (year - 1, weeks_in_year(year - 1))
+    } else if week as u32 > weeks_in_year(year) {
+        (year + 1, 1)
+    } else {
+        (year, week as u32)
+    }
+}
+
+fn is_leap_year(year: i32) -> bool {
+    year % 4 == 0 && (year % 100 != 0 || year % 400 == 0)
+}
+
+fn day_of_year(year: i32, month: u16, day: u16) -> i32 {
+    const DAYS_BEFORE: [i32; 12] = [0, 31, 59, 90, 120, 151, 181, 212, 243, 273, 304, 334];
+
+    let mut days = DAYS_BEFORE[(month as usize).saturating_sub(1) % 12] + day as i32;
+    if month > 2 && is_leap_year(year) {
+        days += 1;
+    }
+
+    days
+}
+
+/// years that start on a thursday, or leap years that start on a wednesday, have 53 weeks
+fn weeks_in_year(year: i32) -> u32 {
+    // the weekday of december 31st, with sunday as 0
+    let last_day = |year: i32| (year + year / 4 - year / 100 + year / 400) % 7;
+
+    if last_day(year) == 4 || last_day(year - 1) == 3 {
+        53
+    } else {
+        52
+    }
+}
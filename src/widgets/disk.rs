@@ -0,0 +1,101 @@
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use windows::Win32::Storage::FileSystem::GetDiskFreeSpaceExW;
+use windows_core::HSTRING;
+
+use super::TEXT_COLOR;
+use crate::{
+    layout::{Layout, Rect},
+    render::Canvas,
+};
+
+const GB: f64 = 1e9;
+
+const LOW_COLOR: u32 = 0xffd02020;
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct DiskConfig {
+    /// e.g. "C:"
+    pub drives: Vec<String>,
+    /// free space in GB below which a drive is drawn in red
+    pub low_gb: f64,
+    pub interval_ms: u32,
+}
+
+impl Default for DiskConfig {
+    fn default() -> Self {
+        Self {
+            drives: vec!["C:".to_string()],
+            low_gb: 10.0,
+            interval_ms: 5 * 60 * 1000,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct DiskMonitor {
+    /// each configured drive and its free bytes, or `None` if it couldn't be read
+    pub drives: Vec<(String, Option<u64>)>,
+}
+
+impl DiskMonitor {
+    pub fn sample(&mut self, config: &DiskConfig) -> Result<()> {
+        self.drives = config
+            .drives
+            .iter()
+            .map(|drive| (drive.clone(), free_space(drive).ok()))
+            .collect();
+
+        Ok(())
+    }
+}
+
+fn free_space(drive: &str) -> Result<u64> {
+    // the api wants a directory, and "C:" on its own means the current one on that drive
+    let root = format!("{}\\", drive.trim_end_matches('\\'));
+
+    let mut free = 0;
+    unsafe { GetDiskFreeSpaceExW(&HSTRING::from(&root), Some(&mut free), None, None) }
+        .with_context(|| format!("failed to read free space on {}", root))?;
+
+    Ok(free)
+}
+
+/// "C: 123 GB" for each drive, one per line
+pub fn draw(
+    canvas: &mut dyn Canvas,
+    layout: &Layout,
+    rect: Rect,
+    config: &DiskConfig,
+    disk: &DiskMonitor,
+) -> Result<()> {
+    let count = disk.drives.len().max(1) as f32;
+    let line_height = (rect.height - layout.px(8.0)) / count;
+    let size = (line_height * 0.8).min(layout.px(14.0));
+
+    let mut y = rect.y + (rect.height - line_height * count) / 2.0;
+
+    for (drive, free) in &disk.drives {
+        let (text, color) = match free {
+            Some(free) => {
+                let gb = *free as f64 / GB;
+                let color = if gb < config.low_gb {
+                    LOW_COLOR
+                } else {
+                    TEXT_COLOR
+                };
+                (format!("{} {:.0} GB", drive, gb), color)
+            }
+            None => (format!("{} ?", drive), 0xff808080),
+        };
+
+        let (width, height) = canvas.measure_text(&text, size)?;
+        let x = rect.x + (rect.width - width) / 2.0;
+        canvas.draw_text(&text, size, x, y + (line_height - height) / 2.0, color)?;
+
+        y += line_height;
+    }
+
+    Ok(())
+}
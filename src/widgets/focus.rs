@@ -0,0 +1,128 @@
+use anyhow::{Result, bail};
+use serde::{Deserialize, Serialize};
+use windows::Win32::System::Com::{CLSCTX_ALL, CoCreateInstance};
+use windows_core::{HSTRING, PCWSTR};
+
+use super::{TEXT_COLOR, draw_centered_text};
+use crate::{
+    interop::{CLSID_QuietHoursSettings, IQuietHoursSettings, NtQueryWnfStateData},
+    layout::{Layout, Rect},
+    render::Canvas,
+};
+
+/// published by the shell whenever the active focus assist profile changes, holding its index
+const WNF_SHEL_QUIETHOURS_ACTIVE_PROFILE_CHANGED: u64 = 0x0d83063ea3bf1c75;
+
+const OFF_COLOR: u32 = 0xffc0c0c0;
+
+/// what focus assist lets through
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FocusMode {
+    #[default]
+    Off,
+    PriorityOnly,
+    AlarmsOnly,
+}
+
+impl FocusMode {
+    pub fn label(self) -> &'static str {
+        match self {
+            FocusMode::Off => "Off",
+            FocusMode::PriorityOnly => "Priority only",
+            FocusMode::AlarmsOnly => "Alarms only",
+        }
+    }
+
+    /// the mode a click switches to
+    pub fn next(self) -> Self {
+        match self {
+            FocusMode::Off => FocusMode::PriorityOnly,
+            FocusMode::PriorityOnly => FocusMode::AlarmsOnly,
+            FocusMode::AlarmsOnly => FocusMode::Off,
+        }
+    }
+
+    fn profile(self) -> &'static str {
+        match self {
+            FocusMode::Off => "Microsoft.QuietHoursProfile.Unrestricted",
+            FocusMode::PriorityOnly => "Microsoft.QuietHoursProfile.PriorityOnly",
+            FocusMode::AlarmsOnly => "Microsoft.QuietHoursProfile.AlarmsOnly",
+        }
+    }
+
+    /// the active mode, including ones turned on automatically, e.g. while gaming
+    pub fn current() -> Result<Self> {
+        let mut index = 0u32;
+        let mut size = size_of::<u32>() as u32;
+        let mut stamp = 0;
+
+        let status = unsafe {
+            NtQueryWnfStateData(
+                &WNF_SHEL_QUIETHOURS_ACTIVE_PROFILE_CHANGED,
+                std::ptr::null(),
+                std::ptr::null(),
+                &mut stamp,
+                &mut index as *mut u32 as _,
+                &mut size,
+            )
+        };
+
+        if status.is_err() {
+            bail!("failed to query focus assist state: {:?}", status);
+        }
+
+        Ok(match index {
+            1 => FocusMode::PriorityOnly,
+            2 => FocusMode::AlarmsOnly,
+            _ => FocusMode::Off,
+        })
+    }
+
+    /// makes this the mode the user has chosen, as the quick settings button would
+    pub fn select(self) -> Result<()> {
+        unsafe {
+            let settings: IQuietHoursSettings =
+                CoCreateInstance(&CLSID_QuietHoursSettings, None, CLSCTX_ALL)?;
+
+            let profile = HSTRING::from(self.profile());
+            settings
+                .put_UserSelectedProfile(PCWSTR(profile.as_ptr()))
+                .ok()?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+pub struct FocusMonitor {
+    pub mode: FocusMode,
+}
+
+impl FocusMonitor {
+    /// returns whether the mode changed
+    pub fn sample(&mut self) -> Result<bool> {
+        let mode = FocusMode::current()?;
+
+        let changed = mode != self.mode;
+        self.mode = mode;
+
+        Ok(changed)
+    }
+}
+
+/// the mode's name, faded while focus assist is off
+pub fn draw(
+    canvas: &mut dyn Canvas,
+    layout: &Layout,
+    rect: Rect,
+    focus: &FocusMonitor,
+) -> Result<()> {
+    let color = match focus.mode {
+        FocusMode::Off => OFF_COLOR,
+        _ => TEXT_COLOR,
+    };
+
+    draw_centered_text(canvas, rect, focus.mode.label(), layout.px(13.0), color)
+}
@@ -0,0 +1,107 @@
+use anyhow::Result;
+use serde::Deserialize;
+use windows::Win32::UI::Input::KeyboardAndMouse::{
+    GetKeyState, VIRTUAL_KEY, VK_CAPITAL, VK_NUMLOCK, VK_SCROLL,
+};
+
+use super::TEXT_COLOR;
+use crate::{
+    layout::{Layout, Rect},
+    render::Canvas,
+};
+
+const OFF_COLOR: u32 = 0xffc0c0c0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LockKey {
+    Caps,
+    Num,
+    Scroll,
+}
+
+impl LockKey {
+    fn virtual_key(self) -> VIRTUAL_KEY {
+        match self {
+            LockKey::Caps => VK_CAPITAL,
+            LockKey::Num => VK_NUMLOCK,
+            LockKey::Scroll => VK_SCROLL,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            LockKey::Caps => "CAPS",
+            LockKey::Num => "NUM",
+            LockKey::Scroll => "SCRL",
+        }
+    }
+
+    /// whether the key is toggled on, which is the low bit of its state
+    fn is_on(self) -> bool {
+        unsafe { GetKeyState(self.virtual_key().0 as i32) & 1 != 0 }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct LockKeysConfig {
+    /// the keys to show, left to right
+    pub keys: Vec<LockKey>,
+}
+
+impl Default for LockKeysConfig {
+    fn default() -> Self {
+        Self {
+            keys: vec![LockKey::Caps, LockKey::Num],
+        }
+    }
+}
+
+/// lock key states, polled since the panel doesn't see key presses aimed at other windows
+#[derive(Default)]
+pub struct LockKeysMonitor {
+    pub states: Vec<(LockKey, bool)>,
+}
+
+impl LockKeysMonitor {
+    /// returns whether any key changed
+    pub fn sample(&mut self, config: &LockKeysConfig) -> bool {
+        let states: Vec<_> = config.keys.iter().map(|&key| (key, key.is_on())).collect();
+
+        let changed = states != self.states;
+        self.states = states;
+
+        changed
+    }
+}
+
+/// each key's label, dark while it's on and faded while it's off
+pub fn draw(
+    canvas: &mut dyn Canvas,
+    layout: &Layout,
+    rect: Rect,
+    locks: &LockKeysMonitor,
+) -> Result<()> {
+    let size = layout.px(11.0);
+    let gap = layout.px(6.0);
+
+    let mut widths = vec![];
+    for (key, _) in &locks.states {
+        widths.push(canvas.measure_text(key.label(), size)?);
+    }
+
+    let total: f32 = widths.iter().map(|(width, _)| width).sum::<f32>()
+        + gap * widths.len().saturating_sub(1) as f32;
+    let mut x = rect.x + (rect.width - total) / 2.0;
+
+    for ((key, on), (width, height)) in locks.states.iter().zip(widths) {
+        let color = if *on { TEXT_COLOR } else { OFF_COLOR };
+        let y = rect.y + (rect.height - height) / 2.0;
+        canvas.draw_text(key.label(), size, x, y, color)?;
+
+        x += width + gap;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,394 @@
+use std::{
+    cell::Cell,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+use windows::{
+    Media::Control::{
+        GlobalSystemMediaTransportControlsSession as Session,
+        GlobalSystemMediaTransportControlsSessionManager as SessionManager,
+        GlobalSystemMediaTransportControlsSessionPlaybackStatus as PlaybackStatus,
+    },
+    Win32::{
+        Foundation::{HWND, LPARAM, WPARAM},
+        System::SystemServices::{
+            APPCOMMAND_ID, APPCOMMAND_MEDIA_NEXTTRACK, APPCOMMAND_MEDIA_PAUSE,
+            APPCOMMAND_MEDIA_PLAY, APPCOMMAND_MEDIA_PLAY_PAUSE, APPCOMMAND_MEDIA_PREVIOUSTRACK,
+        },
+        UI::WindowsAndMessaging::{PostMessageW, WM_APPCOMMAND},
+    },
+};
+
+use super::{
+    TEXT_COLOR, draw_centered_text,
+    poll::{self, Background},
+};
+use crate::{
+    layout::{Layout, Rect},
+    render::{Canvas, LineCap},
+};
+
+/// how fast titles too long for the widget scroll, in device-independent pixels per second
+const SCROLL_SPEED: f32 = 30.0;
+
+/// space between the end of a scrolling title and its next repeat
+const SCROLL_GAP: f32 = 32.0;
+
+/// how often a scrolling title moves along. slower than animations, since it's on for as long
+/// as the track plays
+pub const SCROLL_INTERVAL_MS: u32 = 50;
+
+/// a transport button
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MediaCommand {
+    PlayPause,
+    Play,
+    Pause,
+    Next,
+    Previous,
+}
+
+impl MediaCommand {
+    fn app_command(self) -> APPCOMMAND_ID {
+        match self {
+            MediaCommand::PlayPause => APPCOMMAND_MEDIA_PLAY_PAUSE,
+            MediaCommand::Play => APPCOMMAND_MEDIA_PLAY,
+            MediaCommand::Pause => APPCOMMAND_MEDIA_PAUSE,
+            MediaCommand::Next => APPCOMMAND_MEDIA_NEXTTRACK,
+            MediaCommand::Previous => APPCOMMAND_MEDIA_PREVIOUSTRACK,
+        }
+    }
+}
+
+/// what the current media session is playing
+#[derive(Clone, Debug, PartialEq)]
+pub struct Track {
+    pub title: String,
+    pub artist: String,
+    pub playing: bool,
+}
+
+/// the media session manager, shared with the threads that talk to it. any call on a session
+/// can wait on the app that owns it, so none happen on the ui thread.
+#[derive(Clone, Default)]
+struct Sessions(Arc<Mutex<Option<SessionManager>>>);
+
+impl Sessions {
+    /// the session windows considers current, if anything has one open
+    fn current(&self) -> Result<Option<Session>> {
+        let mut manager = self.0.lock().unwrap();
+        let manager = match &*manager {
+            Some(manager) => manager,
+            None => manager.insert(SessionManager::RequestAsync()?.get()?),
+        };
+
+        // fails when there's no session
+        Ok(manager.GetCurrentSession().ok())
+    }
+
+    fn current_track(&self) -> Result<Option<Track>> {
+        let Some(session) = self.current()? else {
+            return Ok(None);
+        };
+
+        let properties = session.TryGetMediaPropertiesAsync()?.get()?;
+        let status = session.GetPlaybackInfo()?.PlaybackStatus()?;
+
+        Ok(Some(Track {
+            title: properties.Title()?.to_string(),
+            artist: properties.Artist()?.to_string(),
+            playing: status == PlaybackStatus::Playing,
+        }))
+    }
+
+    /// returns whether there was a session and it handled `command`
+    fn send(&self, command: MediaCommand) -> Result<bool> {
+        let Some(session) = self.current()? else {
+            return Ok(false);
+        };
+
+        let handled = match command {
+            MediaCommand::PlayPause => session.TryTogglePlayPauseAsync()?,
+            MediaCommand::Play => session.TryPlayAsync()?,
+            MediaCommand::Pause => session.TryPauseAsync()?,
+            MediaCommand::Next => session.TrySkipNextAsync()?,
+            MediaCommand::Previous => session.TrySkipPreviousAsync()?,
+        }
+        .get()?;
+
+        Ok(handled)
+    }
+}
+
+/// the track of the session windows considers current, from whichever app is playing
+pub struct NowPlayingMonitor {
+    sessions: Sessions,
+    fetches: Background<Result<Option<Track>>>,
+    pub track: Option<Track>,
+    /// when the current title started scrolling
+    scroll_start: Instant,
+    /// set while painting when a line didn't fit, so the panel keeps repainting to scroll it
+    overflowing: Cell<bool>,
+}
+
+impl Default for NowPlayingMonitor {
+    fn default() -> Self {
+        Self {
+            sessions: Sessions::default(),
+            fetches: Background::default(),
+            track: None,
+            scroll_start: Instant::now(),
+            overflowing: Cell::new(false),
+        }
+    }
+}
+
+impl NowPlayingMonitor {
+    /// picks up the track fetched since the last call, and starts fetching it again. returns
+    /// whether the track or its playback state changed.
+    pub fn sample(&mut self) -> Result<bool> {
+        let fetched = self.fetches.finished();
+
+        let sessions = self.sessions.clone();
+        self.fetches
+            .start(Duration::ZERO, move || sessions.current_track());
+
+        let Some(track) = fetched else {
+            return Ok(false);
+        };
+
+        let track = track?;
+        if track == self.track {
+            return Ok(false);
+        }
+
+        let title = |track: &Option<Track>| track.as_ref().map(|track| track.title.clone());
+        if title(&track) != title(&self.track) {
+            self.scroll_start = Instant::now();
+        }
+
+        self.track = track;
+        Ok(true)
+    }
+
+    /// whether the current session was playing as of the last fetch. samples as well, so this
+    /// keeps up even when the widget isn't shown.
+    pub fn is_playing(&mut self) -> Result<bool> {
+        self.sample()?;
+        Ok(self.track.as_ref().is_some_and(|track| track.playing))
+    }
+
+    /// sends `command` to the current session from another thread. players that don't publish
+    /// one, or don't handle it, may still pick it up as an app command, which `hwnd` passes on
+    /// to the shell.
+    pub fn send(&self, hwnd: HWND, command: MediaCommand) {
+        let sessions = self.sessions.clone();
+        let hwnd = hwnd.0 as isize;
+
+        poll::spawn(move || {
+            if matches!(sessions.send(command), Ok(true)) {
+                return;
+            }
+
+            // the command goes in the high word, and the window proc leaves it to DefWindowProc
+            let hwnd = HWND(hwnd as _);
+            let lparam = LPARAM((command.app_command().0 << 16) as isize);
+            let _ =
+                unsafe { PostMessageW(Some(hwnd), WM_APPCOMMAND, WPARAM(hwnd.0 as usize), lparam) };
+        });
+    }
+
+    /// pauses the current session if it's playing, from another thread like `send`, and sets
+    /// `paused` once it has
+    pub fn pause_if_playing(&self, paused: &Arc<AtomicBool>) {
+        let sessions = self.sessions.clone();
+        let paused = paused.clone();
+
+        poll::spawn(move || {
+            let playing = sessions
+                .current_track()
+                .is_ok_and(|track| track.is_some_and(|track| track.playing));
+
+            if playing && matches!(sessions.send(MediaCommand::Pause), Ok(true)) {
+                paused.store(true, Ordering::Release);
+            }
+        });
+    }
+
+    /// whether a line was cut off the last time the widget was drawn
+    pub fn is_scrolling(&self) -> bool {
+        self.overflowing.get()
+    }
+}
+
+/// a play or pause glyph, then the title over the artist
+pub fn draw(
+    canvas: &mut dyn Canvas,
+    layout: &Layout,
+    rect: Rect,
+    media: &NowPlayingMonitor,
+) -> Result<()> {
+    media.overflowing.set(false);
+
+    let Some(track) = &media.track else {
+        return draw_centered_text(canvas, rect, "Nothing playing", layout.px(12.0), 0xff808080);
+    };
+
+    let middle = rect.y + rect.height / 2.0;
+    draw_play_pause(
+        canvas,
+        layout,
+        (rect.x + layout.px(16.0), middle),
+        track.playing,
+    )?;
+
+    let text = Rect {
+        x: rect.x + layout.px(28.0),
+        width: rect.width - layout.px(34.0),
+        ..rect
+    };
+
+    let scrolled = media.scroll_start.elapsed().as_secs_f32() * layout.px(SCROLL_SPEED);
+
+    let title = Rect {
+        y: rect.y + layout.px(7.0),
+        height: layout.px(18.0),
+        ..text
+    };
+    let artist = Rect {
+        y: rect.y + layout.px(25.0),
+        height: layout.px(16.0),
+        ..text
+    };
+
+    let lines = [
+        (&track.title, title, layout.px(13.0), TEXT_COLOR),
+        (&track.artist, artist, layout.px(11.0), 0xff606060),
+    ];
+
+    for (line, area, size, color) in lines {
+        let (width, _) = canvas.measure_text(line, size)?;
+
+        if width <= area.width {
+            canvas.draw_text(line, size, area.x, area.y, color)?;
+            continue;
+        }
+
+        media.overflowing.set(true);
+
+        // drawn twice so the start follows the end around
+        let period = width + layout.px(SCROLL_GAP);
+        let x = area.x - scrolled % period;
+
+        canvas.push_clip(area)?;
+        canvas.draw_text(line, size, x, area.y, color)?;
+        canvas.draw_text(line, size, x + period, area.y, color)?;
+        canvas.pop_clip()?;
+    }
+
+    Ok(())
+}
+
+/// draws a transport button's glyph centered in `rect`. play/pause shows pause while playing.
+pub fn draw_button(
+    canvas: &mut dyn Canvas,
+    layout: &Layout,
+    rect: Rect,
+    command: MediaCommand,
+    media: &NowPlayingMonitor,
+) -> Result<()> {
+    let center = (rect.x + rect.width / 2.0, rect.y + rect.height / 2.0);
+    let (x, y) = center;
+    let half = layout.px(6.0);
+    let width = layout.px(2.0);
+
+    match command {
+        MediaCommand::PlayPause => {
+            let playing = media.track.as_ref().is_some_and(|track| track.playing);
+            draw_play_pause(canvas, layout, center, playing)
+        }
+        MediaCommand::Play => draw_play_pause(canvas, layout, center, false),
+        MediaCommand::Pause => draw_play_pause(canvas, layout, center, true),
+
+        // a bar against the side the triangle points to
+        MediaCommand::Next => {
+            draw_triangle(canvas, (x - half, y), half * 1.5, 1.0, width)?;
+            let bar = x + half;
+            canvas.draw_line(
+                (bar, y - half),
+                (bar, y + half),
+                width,
+                LineCap::Square,
+                TEXT_COLOR,
+            )
+        }
+        MediaCommand::Previous => {
+            draw_triangle(canvas, (x + half, y), half * 1.5, -1.0, width)?;
+            let bar = x - half;
+            canvas.draw_line(
+                (bar, y - half),
+                (bar, y + half),
+                width,
+                LineCap::Square,
+                TEXT_COLOR,
+            )
+        }
+    }
+}
+
+/// pause bars while playing, a play triangle otherwise, centered on `center`
+fn draw_play_pause(
+    canvas: &mut dyn Canvas,
+    layout: &Layout,
+    center: (f32, f32),
+    playing: bool,
+) -> Result<()> {
+    let (x, y) = center;
+    let half = layout.px(7.0);
+    let width = layout.px(3.0);
+
+    if playing {
+        for x in [x - layout.px(3.5), x + layout.px(3.5)] {
+            canvas.draw_line(
+                (x, y - half),
+                (x, y + half),
+                width,
+                LineCap::Square,
+                TEXT_COLOR,
+            )?;
+        }
+
+        Ok(())
+    } else {
+        draw_triangle(canvas, (x - half / 2.0, y), half * 1.5, 1.0, width)
+    }
+}
+
+/// an outlined triangle with its flat side at `base`, pointing right for a `direction` of 1 and
+/// left for -1
+fn draw_triangle(
+    canvas: &mut dyn Canvas,
+    base: (f32, f32),
+    length: f32,
+    direction: f32,
+    width: f32,
+) -> Result<()> {
+    let (x, y) = base;
+    let half = length * 2.0 / 3.0;
+    let tip = (x + length * direction, y);
+
+    canvas.draw_line(
+        (x, y - half),
+        (x, y + half),
+        width,
+        LineCap::Square,
+        TEXT_COLOR,
+    )?;
+    canvas.draw_line((x, y - half), tip, width, LineCap::Square, TEXT_COLOR)?;
+    canvas.draw_line((x, y + half), tip, width, LineCap::Square, TEXT_COLOR)
+}
@@ -0,0 +1,186 @@
+use std::path::Path;
+
+use anyhow::Result;
+use serde::Deserialize;
+use windows::Win32::{
+    Foundation::CloseHandle,
+    System::Threading::{
+        OpenProcess, PROCESS_NAME_WIN32, PROCESS_QUERY_LIMITED_INFORMATION,
+        QueryFullProcessImageNameW,
+    },
+    UI::WindowsAndMessaging::{GetForegroundWindow, GetWindowThreadProcessId},
+};
+use windows_core::PWSTR;
+
+use super::focus::FocusMode;
+use super::{TEXT_COLOR, consent::Usage, draw_centered_text, fill_circle};
+use crate::{
+    actions::Action,
+    keyboard::Hotkey,
+    layout::{Layout, Rect},
+    render::Canvas,
+};
+
+const IN_CALL_COLOR: u32 = 0xffd02020;
+const IDLE_COLOR: u32 = 0xffa0a0a0;
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct MeetingConfig {
+    /// substrings (case-insensitive) of the names of the conferencing apps, as the privacy
+    /// settings list them
+    pub apps: Vec<String>,
+    /// run when one of the apps starts using the mic or camera
+    pub on_start: Vec<Action>,
+    /// run when none of them are using either anymore
+    pub on_end: Vec<Action>,
+    /// switched to for the length of the call
+    pub focus_assist: Option<FocusMode>,
+    /// for apps whose own mute doesn't follow the device's, see `MuteKeys`
+    pub mute_keys: Vec<MuteKeys>,
+}
+
+/// an app's own mute hotkey, pressed when the panel mutes or unmutes the mic while the app is in
+/// front or using the mic, so its mute button doesn't disagree with the device, e.g.
+/// `{ "app": "zoom", "keys": "alt+a" }`. apps that aren't in front only get it if the hotkey is
+/// global in their settings.
+#[derive(Debug, Deserialize)]
+pub struct MuteKeys {
+    /// substring (case-insensitive) of the app's executable name
+    pub app: String,
+    pub keys: Hotkey,
+}
+
+impl Default for MeetingConfig {
+    fn default() -> Self {
+        Self {
+            apps: ["teams", "zoom", "webex", "slack", "discord", "skype"]
+                .map(String::from)
+                .to_vec(),
+            on_start: vec![],
+            on_end: vec![],
+            focus_assist: None,
+            mute_keys: vec![],
+        }
+    }
+}
+
+/// whether a conferencing app has the mic or camera open
+#[derive(Default)]
+pub struct MeetingMonitor {
+    microphone: Usage,
+    camera: Usage,
+    /// the conferencing apps using either
+    pub apps: Vec<String>,
+    /// set when a call starts (true) or ends (false), until it's been acted on
+    pub transition: Option<bool>,
+}
+
+impl MeetingMonitor {
+    /// returns whether the apps in the call changed
+    pub fn sample(&mut self, config: &MeetingConfig) -> Result<bool> {
+        self.microphone.sample(&["microphone"])?;
+        self.camera.sample(super::camera::CAPABILITIES)?;
+
+        let mut apps = vec![];
+        for app in self.microphone.apps.iter().chain(&self.camera.apps) {
+            let name = app.to_lowercase();
+            let conferencing = config
+                .apps
+                .iter()
+                .any(|app| name.contains(&app.to_lowercase()));
+
+            if conferencing && !apps.contains(app) {
+                apps.push(app.clone());
+            }
+        }
+
+        if apps.is_empty() != self.apps.is_empty() {
+            self.transition = Some(!apps.is_empty());
+        }
+
+        let changed = apps != self.apps;
+        self.apps = apps;
+
+        Ok(changed)
+    }
+
+    pub fn in_call(&self) -> bool {
+        !self.apps.is_empty()
+    }
+}
+
+/// the executable name of the app in front, without `.exe`
+fn foreground_app() -> Option<String> {
+    unsafe {
+        let mut pid = 0;
+        GetWindowThreadProcessId(GetForegroundWindow(), Some(&mut pid));
+
+        let process = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, pid).ok()?;
+
+        let mut path = [0u16; 260];
+        let mut size = path.len() as u32;
+        let result = QueryFullProcessImageNameW(
+            process,
+            PROCESS_NAME_WIN32,
+            PWSTR(path.as_mut_ptr()),
+            &mut size,
+        );
+        let _ = CloseHandle(process);
+        result.ok()?;
+
+        let path = String::from_utf16_lossy(&path[..size as usize]);
+        let stem = Path::new(&path).file_stem()?;
+        Some(stem.to_string_lossy().into_owned())
+    }
+}
+
+/// presses the mute hotkeys of the apps in front or using the mic, after the panel's muted or
+/// unmuted it
+pub fn press_mute_keys(config: &MeetingConfig) -> Result<()> {
+    if config.mute_keys.is_empty() {
+        return Ok(());
+    }
+
+    let mut microphone = Usage::default();
+    microphone.sample(&["microphone"])?;
+
+    let apps: Vec<_> = foreground_app()
+        .into_iter()
+        .chain(microphone.apps)
+        .map(|app| app.to_lowercase())
+        .collect();
+
+    for rule in &config.mute_keys {
+        let app = rule.app.to_lowercase();
+        if apps.iter().any(|name| name.contains(&app)) {
+            rule.keys.press()?;
+        }
+    }
+
+    Ok(())
+}
+
+/// a dot and a label, both red during a call
+pub fn draw(
+    canvas: &mut dyn Canvas,
+    layout: &Layout,
+    rect: Rect,
+    meeting: &MeetingMonitor,
+) -> Result<()> {
+    let (label, dot, text) = match meeting.in_call() {
+        true => ("In a call", IN_CALL_COLOR, IN_CALL_COLOR),
+        false => ("Free", IDLE_COLOR, TEXT_COLOR),
+    };
+
+    let radius = layout.px(5.0);
+    let center = (rect.x + layout.px(14.0), rect.y + rect.height / 2.0);
+    fill_circle(canvas, center, radius, dot)?;
+
+    let area = Rect {
+        x: rect.x + layout.px(24.0),
+        width: rect.width - layout.px(24.0),
+        ..rect
+    };
+    draw_centered_text(canvas, area, label, layout.px(13.0), text)
+}
@@ -0,0 +1,113 @@
+use anyhow::Result;
+use serde::Deserialize;
+use windows::Win32::System::SystemInformation::{GlobalMemoryStatusEx, MEMORYSTATUSEX};
+
+use super::{TEXT_COLOR, Thresholds, draw_centered_text};
+use crate::{
+    default,
+    layout::{Layout, Rect},
+    render::{Canvas, LineCap},
+};
+
+const GIB: f32 = (1u64 << 30) as f32;
+
+#[derive(Clone, Copy, Debug, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MemoryStyle {
+    /// used and total memory above a bar
+    #[default]
+    Bar,
+    /// just the percentage in use
+    Percent,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct MemoryConfig {
+    pub style: MemoryStyle,
+    pub interval_ms: u32,
+    pub thresholds: Thresholds,
+}
+
+impl Default for MemoryConfig {
+    fn default() -> Self {
+        Self {
+            style: MemoryStyle::default(),
+            interval_ms: 2000,
+            thresholds: Thresholds::default(),
+        }
+    }
+}
+
+/// physical memory in use, as of the last poll
+#[derive(Default)]
+pub struct MemoryMonitor {
+    /// used and total bytes
+    reading: Option<(u64, u64)>,
+}
+
+impl MemoryMonitor {
+    pub fn sample(&mut self) -> Result<()> {
+        let mut status = MEMORYSTATUSEX {
+            dwLength: size_of::<MEMORYSTATUSEX>() as u32,
+            ..default()
+        };
+
+        unsafe { GlobalMemoryStatusEx(&mut status)? };
+
+        let used = status.ullTotalPhys.saturating_sub(status.ullAvailPhys);
+        self.reading = Some((used, status.ullTotalPhys));
+
+        Ok(())
+    }
+
+    /// the fraction of memory in use, once it's been polled
+    pub fn usage(&self) -> Option<f32> {
+        let (used, total) = self.reading?;
+        (total > 0).then(|| used as f32 / total as f32)
+    }
+}
+
+pub fn draw(
+    canvas: &mut dyn Canvas,
+    layout: &Layout,
+    rect: Rect,
+    config: &MemoryConfig,
+    memory: &MemoryMonitor,
+) -> Result<()> {
+    let (Some((used, total)), Some(usage)) = (memory.reading, memory.usage()) else {
+        return draw_centered_text(canvas, rect, "RAM", layout.px(13.0), TEXT_COLOR);
+    };
+
+    let color = config.thresholds.color(usage);
+
+    if let MemoryStyle::Percent = config.style {
+        let text = format!("RAM {:.0}%", usage * 100.0);
+        return draw_centered_text(canvas, rect, &text, layout.px(15.0), color);
+    }
+
+    let text = format!("{:.1} / {:.0} GB", used as f32 / GIB, total as f32 / GIB);
+    let size = layout.px(13.0);
+    let (width, _) = canvas.measure_text(&text, size)?;
+    let x = rect.x + (rect.width - width) / 2.0;
+    canvas.draw_text(&text, size, x, rect.y + layout.px(8.0), color)?;
+
+    // the bar runs along 33 of the 48 high widget
+    let left = rect.x + layout.px(8.0);
+    let right = rect.right() - layout.px(8.0);
+    let y = rect.y + layout.px(33.0);
+    let filled = left + (right - left) * usage.clamp(0.0, 1.0);
+    let width = layout.px(6.0);
+
+    canvas.draw_line((left, y), (right, y), width, LineCap::Square, 0xffc0c0c0)?;
+    if filled > left {
+        let fill = if color == TEXT_COLOR {
+            0xff404040
+        } else {
+            color
+        };
+        canvas.draw_line((left, y), (filled, y), width, LineCap::Square, fill)?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,215 @@
+use std::time::Instant;
+
+use anyhow::{Result, bail};
+use serde::Deserialize;
+use windows::Win32::NetworkManagement::{
+    IpHelper::{FreeMibTable, GetIfTable2, MIB_IF_ROW2, MIB_IF_TABLE2},
+    Ndis::IfOperStatusUp,
+};
+
+use super::{History, TEXT_COLOR, draw_sparkline};
+use crate::{
+    layout::{Layout, Rect},
+    render::Canvas,
+};
+
+/// how many samples each sparkline shows
+const HISTORY: usize = 30;
+
+const DOWN_COLOR: u32 = 0xff2070d0;
+const UP_COLOR: u32 = 0xff30a050;
+
+#[derive(Clone, Copy, Debug, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NetworkStyle {
+    /// download and upload rates on two lines
+    #[default]
+    Text,
+    /// the rates above overlapping sparklines of each
+    Sparklines,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct NetworkConfig {
+    /// substring matched (case-insensitively) against adapter names and descriptions. defaults to
+    /// the first connected hardware adapter.
+    pub adapter: Option<String>,
+    pub style: NetworkStyle,
+}
+
+/// a network interface as listed by `GetIfTable2`
+pub struct Interface {
+    pub alias: String,
+    pub description: String,
+    pub up: bool,
+    /// whether it's a physical adapter rather than a tunnel, loopback or filter
+    pub hardware: bool,
+    pub received: u64,
+    pub sent: u64,
+}
+
+impl Interface {
+    /// whether `name` (lowercase) is part of the interface's name or description
+    pub fn matches(&self, name: &str) -> bool {
+        self.alias.to_lowercase().contains(name) || self.description.to_lowercase().contains(name)
+    }
+}
+
+pub fn interfaces() -> Result<Vec<Interface>> {
+    unsafe {
+        let mut table: *mut MIB_IF_TABLE2 = std::ptr::null_mut();
+        let result = GetIfTable2(&mut table);
+        if result.is_err() {
+            bail!("failed to list network interfaces: {:?}", result);
+        }
+
+        let rows =
+            std::slice::from_raw_parts((*table).Table.as_ptr(), (*table).NumEntries as usize);
+        let interfaces = rows.iter().map(interface).collect();
+
+        FreeMibTable(table as _);
+
+        Ok(interfaces)
+    }
+}
+
+fn interface(row: &MIB_IF_ROW2) -> Interface {
+    Interface {
+        alias: wide_string(&row.Alias),
+        description: wide_string(&row.Description),
+        up: row.OperStatus == IfOperStatusUp,
+        hardware: row.InterfaceAndOperStatusFlags._bitfield & 1 != 0,
+        received: row.InOctets,
+        sent: row.OutOctets,
+    }
+}
+
+fn wide_string(chars: &[u16]) -> String {
+    let len = chars.iter().position(|&c| c == 0).unwrap_or(chars.len());
+    String::from_utf16_lossy(&chars[..len])
+}
+
+/// download and upload rates of the configured adapter, from the change in its byte counters
+/// between polls
+pub struct NetworkMonitor {
+    /// bytes received and sent by the adapter as of the last poll
+    last: Option<(String, u64, u64, Instant)>,
+    /// bytes per second
+    down: History,
+    up: History,
+}
+
+impl Default for NetworkMonitor {
+    fn default() -> Self {
+        Self {
+            last: None,
+            down: History::new(HISTORY),
+            up: History::new(HISTORY),
+        }
+    }
+}
+
+impl NetworkMonitor {
+    pub fn sample(&mut self, config: &NetworkConfig) -> Result<()> {
+        let interfaces = interfaces()?;
+        let adapter = config.adapter.as_ref().map(|name| name.to_lowercase());
+
+        let interface = interfaces.iter().find(|interface| match &adapter {
+            Some(name) => interface.matches(name),
+            None => interface.up && interface.hardware,
+        });
+
+        let Some(interface) = interface else {
+            self.last = None;
+            return Ok(());
+        };
+
+        let now = Instant::now();
+        let last = self.last.replace((
+            interface.alias.clone(),
+            interface.received,
+            interface.sent,
+            now,
+        ));
+
+        // the counters of a different adapter aren't comparable
+        if let Some((alias, received, sent, at)) = last
+            && alias == interface.alias
+        {
+            let seconds = now.duration_since(at).as_secs_f32();
+            if seconds > 0.0 {
+                let down = interface.received.saturating_sub(received) as f32 / seconds;
+                let up = interface.sent.saturating_sub(sent) as f32 / seconds;
+                self.down.push(down);
+                self.up.push(up);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// bytes per second down and up, once there have been two polls
+    pub fn rates(&self) -> Option<(f32, f32)> {
+        self.last.as_ref()?;
+        Some((self.down.latest()?, self.up.latest()?))
+    }
+}
+
+/// e.g. "1.2 MB/s"
+fn rate(bytes: f32) -> String {
+    match bytes {
+        b if b >= 1e9 => format!("{:.1} GB/s", b / 1e9),
+        b if b >= 1e6 => format!("{:.1} MB/s", b / 1e6),
+        b if b >= 1e3 => format!("{:.0} KB/s", b / 1e3),
+        b => format!("{:.0} B/s", b),
+    }
+}
+
+pub fn draw(
+    canvas: &mut dyn Canvas,
+    layout: &Layout,
+    rect: Rect,
+    config: &NetworkConfig,
+    network: &NetworkMonitor,
+) -> Result<()> {
+    let (down, up) = network.rates().unwrap_or_default();
+    let lines = [format!("↓ {}", rate(down)), format!("↑ {}", rate(up))];
+
+    match config.style {
+        NetworkStyle::Text => {
+            let size = layout.px(12.0);
+            let x = rect.x + layout.px(8.0);
+
+            for (i, line) in lines.iter().enumerate() {
+                let y = rect.y + layout.px(8.0 + 16.0 * i as f32);
+                canvas.draw_text(line, size, x, y, TEXT_COLOR)?;
+            }
+        }
+
+        NetworkStyle::Sparklines => {
+            let size = layout.px(10.0);
+            let y = rect.y + layout.px(4.0);
+            canvas.draw_text(&lines[0], size, rect.x + layout.px(6.0), y, DOWN_COLOR)?;
+
+            let (width, _) = canvas.measure_text(&lines[1], size)?;
+            let x = rect.right() - layout.px(6.0) - width;
+            canvas.draw_text(&lines[1], size, x, y, UP_COLOR)?;
+
+            // both share a scale so they can be compared
+            let area = Rect {
+                x: rect.x + layout.px(6.0),
+                y: rect.y + layout.px(20.0),
+                width: rect.width - layout.px(12.0),
+                height: layout.px(22.0),
+            };
+            let max = network.down.max().max(network.up.max());
+            let width = layout.px(1.5);
+
+            draw_sparkline(canvas, area, &network.down, max, width, DOWN_COLOR)?;
+            draw_sparkline(canvas, area, &network.up, max, width, UP_COLOR)?;
+        }
+    }
+
+    Ok(())
+}
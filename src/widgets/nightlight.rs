@@ -0,0 +1,109 @@
+use anyhow::{Result, bail};
+use windows::Win32::System::Registry::HKEY_CURRENT_USER;
+
+use super::fill_circle;
+use crate::{
+    layout::{Layout, Rect},
+    registry,
+    render::Canvas,
+};
+
+/// where windows keeps whether night light is on, in a serialized blob that the settings app and
+/// the quick settings button both watch
+const STATE_KEY: &str = concat!(
+    r"Software\Microsoft\Windows\CurrentVersion\CloudStore\Store\DefaultAccount\Current",
+    r"\default$windows.data.bluelightreduction.bluelightreductionstate",
+    r"\windows.data.bluelightreduction.bluelightreductionstate",
+);
+
+/// the byte of the blob that holds the state, and its values
+const STATE_OFFSET: usize = 18;
+const STATE_ON: u8 = 0x15;
+const STATE_OFF: u8 = 0x13;
+
+/// while on, the blob has an extra field at this offset
+const ON_FIELD_OFFSET: usize = 23;
+const ON_FIELD: [u8; 2] = [0x10, 0x00];
+
+/// the bytes of the blob's last-modified timestamp, which has to move forward for windows to
+/// pick up a change
+const TIMESTAMP: std::ops::Range<usize> = 10..15;
+
+const ON_COLOR: u32 = 0xffe0a030;
+const OFF_COLOR: u32 = 0xffa0a0a0;
+
+fn read() -> Result<Vec<u8>> {
+    let data = registry::binary(HKEY_CURRENT_USER, STATE_KEY, "Data")?;
+    if data.len() <= ON_FIELD_OFFSET {
+        bail!("unexpected night light state of {} bytes", data.len());
+    }
+
+    Ok(data)
+}
+
+pub fn is_on() -> Result<bool> {
+    Ok(read()?[STATE_OFFSET] == STATE_ON)
+}
+
+pub fn set(on: bool) -> Result<()> {
+    let mut data = read()?;
+
+    match (data[STATE_OFFSET], on) {
+        (STATE_OFF, true) => {
+            data[STATE_OFFSET] = STATE_ON;
+            data.splice(ON_FIELD_OFFSET..ON_FIELD_OFFSET, ON_FIELD);
+        }
+        (STATE_ON, false) => {
+            data[STATE_OFFSET] = STATE_OFF;
+            data.drain(ON_FIELD_OFFSET..ON_FIELD_OFFSET + ON_FIELD.len());
+        }
+        _ => return Ok(()),
+    }
+
+    if let Some(byte) = data[TIMESTAMP].iter_mut().find(|byte| **byte != 0xff) {
+        *byte += 1;
+    }
+
+    registry::set_binary(HKEY_CURRENT_USER, STATE_KEY, "Data", &data)
+}
+
+#[derive(Default)]
+pub struct NightLightMonitor {
+    pub on: bool,
+}
+
+impl NightLightMonitor {
+    /// returns whether the state changed. night light counts as off until it's been set up once,
+    /// since the blob isn't there before that.
+    pub fn sample(&mut self) -> Result<bool> {
+        let on = is_on().unwrap_or(false);
+
+        let changed = on != self.on;
+        self.on = on;
+
+        Ok(changed)
+    }
+}
+
+/// a circle, filled in and warm while night light is on
+pub fn draw(
+    canvas: &mut dyn Canvas,
+    layout: &Layout,
+    rect: Rect,
+    night_light: &NightLightMonitor,
+) -> Result<()> {
+    let radius = layout.px(7.0);
+    let (x, y) = (rect.x + rect.width / 2.0, rect.y + rect.height / 2.0);
+
+    if night_light.on {
+        fill_circle(canvas, (x, y), radius, ON_COLOR)
+    } else {
+        let ring = Rect {
+            x: x - radius,
+            y: y - radius,
+            width: radius * 2.0,
+            height: radius * 2.0,
+        };
+        canvas.draw_ellipse(ring, layout.px(2.0), OFF_COLOR)
+    }
+}
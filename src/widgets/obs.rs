@@ -0,0 +1,84 @@
+use anyhow::{Result, bail};
+
+use super::{TEXT_COLOR, fill_circle};
+use crate::{
+    layout::{Layout, Rect},
+    obs::{self, Outputs},
+    render::Canvas,
+};
+
+const LIVE_COLOR: u32 = 0xffd02020;
+const IDLE_COLOR: u32 = 0xffa0a0a0;
+
+#[derive(Default)]
+pub struct ObsMonitor {
+    pub outputs: Outputs,
+}
+
+impl ObsMonitor {
+    /// picks up what the obs connection last heard, returning whether it changed. fails while
+    /// obs is refusing the connection.
+    pub fn sample(&mut self) -> Result<bool> {
+        if let Some(refused) = obs::refused() {
+            bail!(refused);
+        }
+
+        let outputs = obs::outputs();
+        let changed = outputs != self.outputs;
+        self.outputs = outputs;
+        Ok(changed)
+    }
+
+    /// e.g. "OBS, recording and streaming"
+    pub fn describe(&self) -> String {
+        let state = match self.outputs {
+            Outputs {
+                connected: false, ..
+            } => "not running",
+            Outputs {
+                recording: true,
+                streaming: true,
+                ..
+            } => "recording and streaming",
+            Outputs {
+                recording: true, ..
+            } => "recording",
+            Outputs {
+                streaming: true, ..
+            } => "streaming",
+            _ => "idle",
+        };
+
+        format!("OBS, {}", state)
+    }
+}
+
+/// a dot that's red while recording or streaming, next to "LIVE", "REC" or just "OBS"
+pub fn draw(canvas: &mut dyn Canvas, layout: &Layout, rect: Rect, obs: &ObsMonitor) -> Result<()> {
+    let Outputs {
+        connected,
+        recording,
+        streaming,
+    } = obs.outputs;
+
+    let (label, color) = match (streaming, recording) {
+        (true, _) => ("LIVE", LIVE_COLOR),
+        (false, true) => ("REC", LIVE_COLOR),
+        (false, false) => ("OBS", IDLE_COLOR),
+    };
+
+    let text_color = match connected {
+        true => TEXT_COLOR,
+        false => IDLE_COLOR,
+    };
+
+    let middle = rect.y + rect.height / 2.0;
+    let radius = layout.px(5.0);
+    let center = rect.x + layout.px(10.0) + radius;
+    fill_circle(canvas, (center, middle), radius, color)?;
+
+    let text_size = layout.px(14.0);
+    let (_, height) = canvas.measure_text(label, text_size)?;
+    let x = center + radius + layout.px(6.0);
+    canvas.draw_text(label, text_size, x, middle - height / 2.0, text_color)
+}
@@ -0,0 +1,55 @@
+use anyhow::Result;
+use serde::Deserialize;
+
+use super::draw_battery;
+use crate::{
+    battery,
+    layout::{Layout, Rect},
+    render::Canvas,
+};
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct PeripheralsConfig {
+    /// substrings (case-insensitive) of the names of the devices to show, e.g. "mx master"
+    pub devices: Vec<String>,
+}
+
+/// the battery of each configured device, as its driver reports it
+#[derive(Default)]
+pub struct PeripheralsMonitor {
+    pub levels: Vec<(String, Option<u8>)>,
+}
+
+impl PeripheralsMonitor {
+    pub fn sample(&mut self, config: &PeripheralsConfig) -> Result<()> {
+        let mut levels = vec![];
+        for device in &config.devices {
+            levels.push((device.clone(), battery::battery_level(device)?));
+        }
+
+        self.levels = levels;
+
+        Ok(())
+    }
+}
+
+/// a battery for each device, side by side
+pub fn draw(
+    canvas: &mut dyn Canvas,
+    layout: &Layout,
+    rect: Rect,
+    peripherals: &PeripheralsMonitor,
+) -> Result<()> {
+    let width = rect.width / peripherals.levels.len().max(1) as f32;
+
+    for (i, (_, level)) in peripherals.levels.iter().enumerate() {
+        let x = rect.x + width * (i as f32 + 0.5);
+        let y = rect.y + rect.height / 2.0;
+        let level = level.map(|level| level as f32 / 100.0);
+
+        draw_battery(canvas, layout, (x, y), level)?;
+    }
+
+    Ok(())
+}
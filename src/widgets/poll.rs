@@ -0,0 +1,149 @@
+use std::{
+    collections::HashMap,
+    sync::mpsc::{self, Receiver, Sender, TryRecvError},
+    time::{Duration, Instant},
+};
+
+use windows::Win32::System::Com::{COINIT_MULTITHREADED, CoInitializeEx, CoUninitialize};
+
+use crate::layout::Widget;
+
+/// runs the polling of every shown widget off one timer, each at its own interval
+#[derive(Default)]
+pub struct Scheduler {
+    next: HashMap<Widget, Instant>,
+}
+
+impl Scheduler {
+    /// the widgets whose poll is due at `now`, scheduling their next one. widgets that aren't in
+    /// `widgets` any more are dropped, and new ones are due straight away.
+    pub fn due(
+        &mut self,
+        widgets: impl Iterator<Item = (Widget, Duration)>,
+        now: Instant,
+    ) -> Vec<Widget> {
+        let mut due = vec![];
+        let mut next = HashMap::new();
+
+        for (widget, interval) in widgets {
+            let at = self.next.get(&widget).copied().unwrap_or(now);
+
+            if at <= now {
+                due.push(widget);
+                next.insert(widget, now + interval);
+            } else {
+                next.insert(widget, at);
+            }
+        }
+
+        self.next = next;
+        due
+    }
+
+    /// how long until the next widget is due, if any are scheduled
+    pub fn next_poll(&self, now: Instant) -> Option<Duration> {
+        let at = self.next.values().min()?;
+        Some(at.saturating_duration_since(now))
+    }
+}
+
+/// something slow a monitor runs on its own thread, e.g. a command or a download, so it can't
+/// hold up the panel. the result is picked up by a later poll.
+pub struct Background<T> {
+    /// the run in progress, which sends its result when it's done
+    running: Option<Receiver<T>>,
+    last_run: Option<Instant>,
+}
+
+impl<T> Default for Background<T> {
+    fn default() -> Self {
+        Self {
+            running: None,
+            last_run: None,
+        }
+    }
+}
+
+impl<T: Send + 'static> Background<T> {
+    /// the result of the run that's finished since the last call, if one has
+    pub fn finished(&mut self) -> Option<T> {
+        let result = match self.running.as_ref()?.try_recv() {
+            Ok(result) => Some(result),
+            Err(TryRecvError::Empty) => return None,
+            Err(TryRecvError::Disconnected) => None,
+        };
+
+        self.running = None;
+        result
+    }
+
+    /// starts `run` on its own thread, unless it's already running or last started less than
+    /// `interval` ago
+    pub fn start(&mut self, interval: Duration, run: impl FnOnce() -> T + Send + 'static) {
+        self.start_on(interval, spawn, run);
+    }
+
+    /// like `start`, but hands `run` to `spawn`, e.g. to queue it behind other work on a worker
+    pub fn start_on(
+        &mut self,
+        interval: Duration,
+        spawn: impl FnOnce(Box<dyn FnOnce() + Send>),
+        run: impl FnOnce() -> T + Send + 'static,
+    ) {
+        let due = self.last_run.is_none_or(|at| at.elapsed() >= interval);
+
+        if self.running.is_some() || !due {
+            return;
+        }
+
+        let (sender, receiver) = mpsc::channel();
+
+        spawn(Box::new(move || {
+            let _ = sender.send(run());
+        }));
+
+        self.running = Some(receiver);
+        self.last_run = Some(Instant::now());
+    }
+}
+
+/// failures of work done in the background for something the user did, e.g. a click. nothing
+/// waits for that work, so they're passed on by the next poll instead.
+pub struct Failures {
+    sender: Sender<anyhow::Error>,
+    receiver: Receiver<anyhow::Error>,
+}
+
+impl Default for Failures {
+    fn default() -> Self {
+        let (sender, receiver) = mpsc::channel();
+        Self { sender, receiver }
+    }
+}
+
+impl Failures {
+    /// for the work to report a failure through
+    pub fn sender(&self) -> Sender<anyhow::Error> {
+        self.sender.clone()
+    }
+
+    /// the latest failure since the last call, if there was one
+    pub fn take(&self) -> anyhow::Result<()> {
+        match self.receiver.try_iter().last() {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+}
+
+/// runs `run` on its own thread, in the multithreaded apartment for anything that uses com
+pub fn spawn(run: impl FnOnce() + Send + 'static) {
+    std::thread::spawn(move || unsafe {
+        let com = CoInitializeEx(None, COINIT_MULTITHREADED).is_ok();
+        run();
+
+        if com {
+            CoUninitialize();
+        }
+    });
+}
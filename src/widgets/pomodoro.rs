@@ -0,0 +1,206 @@
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use serde::Deserialize;
+use windows::Win32::Media::Audio::{PlaySoundW, SND_ASYNC, SND_FILENAME, SND_NODEFAULT};
+use windows_core::HSTRING;
+
+use super::TEXT_COLOR;
+use crate::{
+    layout::{Layout, Rect},
+    render::Canvas,
+};
+
+const PAUSED_COLOR: u32 = 0xffa0a0a0;
+const BREAK_COLOR: u32 = 0xff208040;
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct PomodoroConfig {
+    pub work_minutes: u32,
+    pub break_minutes: u32,
+    /// a .wav file played when a phase ends, along with the toast
+    pub sound: Option<String>,
+}
+
+impl Default for PomodoroConfig {
+    fn default() -> Self {
+        Self {
+            work_minutes: 25,
+            break_minutes: 5,
+            sound: None,
+        }
+    }
+}
+
+impl PomodoroConfig {
+    fn duration(&self, phase: Phase) -> Duration {
+        let minutes = match phase {
+            Phase::Work => self.work_minutes,
+            Phase::Break => self.break_minutes,
+        };
+
+        Duration::from_secs(minutes as u64 * 60)
+    }
+
+    pub fn play_sound(&self) {
+        if let Some(sound) = &self.sound {
+            let flags = SND_FILENAME | SND_ASYNC | SND_NODEFAULT;
+            let _ = unsafe { PlaySoundW(&HSTRING::from(sound), None, flags) };
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Phase {
+    #[default]
+    Work,
+    Break,
+}
+
+impl Phase {
+    pub fn label(self) -> &'static str {
+        match self {
+            Phase::Work => "Work",
+            Phase::Break => "Break",
+        }
+    }
+
+    fn next(self) -> Self {
+        match self {
+            Phase::Work => Phase::Break,
+            Phase::Break => Phase::Work,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+enum State {
+    /// at the start of the phase
+    #[default]
+    Stopped,
+    Paused {
+        left: Duration,
+    },
+    Running {
+        ends: Instant,
+    },
+}
+
+/// a work/break countdown. phases follow each other until the timer is paused or reset.
+#[derive(Default)]
+pub struct Pomodoro {
+    pub phase: Phase,
+    state: State,
+}
+
+impl Pomodoro {
+    pub fn remaining(&self, config: &PomodoroConfig, now: Instant) -> Duration {
+        match self.state {
+            State::Stopped => config.duration(self.phase),
+            State::Paused { left } => left,
+            State::Running { ends } => ends.saturating_duration_since(now),
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        matches!(self.state, State::Running { .. })
+    }
+
+    /// starts or resumes the countdown, or pauses it
+    pub fn toggle(&mut self, config: &PomodoroConfig, now: Instant) {
+        let left = self.remaining(config, now);
+
+        self.state = match self.state {
+            State::Running { .. } => State::Paused { left },
+            _ => State::Running { ends: now + left },
+        };
+    }
+
+    /// stops the timer and goes back to the start of a work phase
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    /// moves on to the next phase if the current one is over, returning the one that ended
+    pub fn advance(&mut self, config: &PomodoroConfig, now: Instant) -> Option<Phase> {
+        let State::Running { ends } = self.state else {
+            return None;
+        };
+
+        if now < ends {
+            return None;
+        }
+
+        let ended = self.phase;
+        self.phase = ended.next();
+        self.state = State::Running {
+            ends: now + config.duration(self.phase),
+        };
+
+        Some(ended)
+    }
+
+    /// milliseconds until the seconds shown next change, while running
+    pub fn next_tick(&self, config: &PomodoroConfig, now: Instant) -> Option<u32> {
+        if !self.is_running() {
+            return None;
+        }
+
+        // the display rounds up, so it changes as the time left passes a whole second
+        let ms = self.remaining(config, now).as_millis() as u32;
+        Some(match ms % 1000 {
+            0 if ms > 0 => 1000,
+            0 => 1,
+            ms => ms,
+        })
+    }
+}
+
+/// whole seconds left, rounded up so the timer reads 0:00 only once it's done
+fn seconds_left(remaining: Duration) -> u64 {
+    remaining.as_millis().div_ceil(1000) as u64
+}
+
+pub fn format_remaining(remaining: Duration) -> String {
+    let seconds = seconds_left(remaining);
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+/// the time left on top, and the phase below it, faded while paused
+pub fn draw(
+    canvas: &mut dyn Canvas,
+    layout: &Layout,
+    rect: Rect,
+    config: &PomodoroConfig,
+    pomodoro: &Pomodoro,
+) -> Result<()> {
+    let remaining = pomodoro.remaining(config, Instant::now());
+
+    let color = match (pomodoro.is_running(), pomodoro.phase) {
+        (false, _) => PAUSED_COLOR,
+        (true, Phase::Work) => TEXT_COLOR,
+        (true, Phase::Break) => BREAK_COLOR,
+    };
+
+    let lines = [
+        (format_remaining(remaining), layout.px(15.0)),
+        (pomodoro.phase.label().to_string(), layout.px(11.0)),
+    ];
+
+    let mut sizes = vec![];
+    for (text, size) in &lines {
+        sizes.push(canvas.measure_text(text, *size)?);
+    }
+
+    let total: f32 = sizes.iter().map(|(_, height)| height).sum();
+    let mut y = rect.y + (rect.height - total) / 2.0;
+
+    for ((text, size), (width, height)) in lines.iter().zip(sizes) {
+        let x = rect.x + (rect.width - width) / 2.0;
+        canvas.draw_text(text, *size, x, y, color)?;
+        y += height;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,107 @@
+use anyhow::{Result, bail};
+use serde::Deserialize;
+use windows::Win32::Graphics::Gdi::{
+    CDS_UPDATEREGISTRY, ChangeDisplaySettingsExW, DEVMODEW, DISP_CHANGE_SUCCESSFUL,
+    DM_DISPLAYFREQUENCY, ENUM_CURRENT_SETTINGS, EnumDisplaySettingsW,
+};
+use windows_core::{HSTRING, PCWSTR};
+
+use super::{TEXT_COLOR, cycle, draw_centered_text};
+use crate::{
+    default,
+    layout::{Layout, Rect},
+    render::Canvas,
+};
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct RefreshRateConfig {
+    /// the display's device name, like "\\.\DISPLAY1", or the primary display if unset
+    pub display: Option<String>,
+    /// the rates in Hz a click cycles through
+    pub rates: Vec<u32>,
+}
+
+impl Default for RefreshRateConfig {
+    fn default() -> Self {
+        Self {
+            display: None,
+            rates: vec![60, 144],
+        }
+    }
+}
+
+impl RefreshRateConfig {
+    fn device(&self) -> Option<HSTRING> {
+        self.display.as_deref().map(HSTRING::from)
+    }
+
+    /// the rate after `current` in `rates`, or the first one
+    pub fn next(&self, current: Option<u32>) -> Option<u32> {
+        cycle(&self.rates, current)
+    }
+}
+
+fn current_mode(device: &Option<HSTRING>) -> Result<DEVMODEW> {
+    let mut mode = DEVMODEW {
+        dmSize: size_of::<DEVMODEW>() as u16,
+        ..default()
+    };
+
+    let name = device
+        .as_ref()
+        .map_or(PCWSTR::null(), |d| PCWSTR(d.as_ptr()));
+    unsafe { EnumDisplaySettingsW(name, ENUM_CURRENT_SETTINGS, &mut mode).ok()? };
+
+    Ok(mode)
+}
+
+/// switches the display to `rate` at its current resolution, and keeps it that way
+pub fn set(config: &RefreshRateConfig, rate: u32) -> Result<()> {
+    let device = config.device();
+
+    let mut mode = current_mode(&device)?;
+    mode.dmDisplayFrequency = rate;
+    mode.dmFields = DM_DISPLAYFREQUENCY;
+
+    let name = device
+        .as_ref()
+        .map_or(PCWSTR::null(), |d| PCWSTR(d.as_ptr()));
+    let result =
+        unsafe { ChangeDisplaySettingsExW(name, Some(&mode), None, CDS_UPDATEREGISTRY, None) };
+
+    if result != DISP_CHANGE_SUCCESSFUL {
+        bail!("failed to switch to {} Hz: {:?}", rate, result);
+    }
+
+    Ok(())
+}
+
+#[derive(Default)]
+pub struct RefreshRateMonitor {
+    pub rate: Option<u32>,
+}
+
+impl RefreshRateMonitor {
+    /// returns whether the rate changed
+    pub fn sample(&mut self, config: &RefreshRateConfig) -> Result<bool> {
+        let rate = Some(current_mode(&config.device())?.dmDisplayFrequency);
+
+        let changed = rate != self.rate;
+        self.rate = rate;
+
+        Ok(changed)
+    }
+}
+
+pub fn draw(
+    canvas: &mut dyn Canvas,
+    layout: &Layout,
+    rect: Rect,
+    refresh: &RefreshRateMonitor,
+) -> Result<()> {
+    let text = refresh
+        .rate
+        .map_or("-".to_string(), |rate| format!("{} Hz", rate));
+    draw_centered_text(canvas, rect, &text, layout.px(13.0), TEXT_COLOR)
+}
@@ -0,0 +1,130 @@
+use std::time::Duration;
+
+use anyhow::Result;
+use serde::Deserialize;
+
+use super::{
+    TEXT_COLOR, cycle, draw_centered_text,
+    poll::{Background, Failures},
+};
+use crate::{
+    ddc,
+    layout::{Layout, Rect},
+    render::Canvas,
+};
+
+/// the MCCS feature selecting a monitor's input
+const INPUT_SOURCE: u8 = 0x60;
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct InputSourceConfig {
+    /// part of the name of the monitor to switch, or the first one if unset
+    pub monitor: Option<String>,
+    /// the input source values a click cycles through, e.g. 15 for DisplayPort 1 and 17 for
+    /// HDMI 1, see `label`
+    pub inputs: Vec<u32>,
+}
+
+impl Default for InputSourceConfig {
+    fn default() -> Self {
+        Self {
+            monitor: None,
+            inputs: vec![0x0f, 0x11],
+        }
+    }
+}
+
+impl InputSourceConfig {
+    fn monitor(&self) -> Option<String> {
+        self.monitor.as_deref().map(str::to_lowercase)
+    }
+
+    /// the input after `current` in `inputs`, or the first one
+    pub fn next(&self, current: Option<u32>) -> Option<u32> {
+        cycle(&self.inputs, current)
+    }
+}
+
+/// the short name of an input source value, for the common ones
+pub fn label(input: u32) -> String {
+    match input {
+        0x01 => "VGA".to_string(),
+        0x03 => "DVI".to_string(),
+        0x0f => "DP".to_string(),
+        0x10 => "DP 2".to_string(),
+        0x11 => "HDMI".to_string(),
+        0x12 => "HDMI 2".to_string(),
+        0x1b => "USB-C".to_string(),
+        input => format!("{:#04x}", input),
+    }
+}
+
+/// the configured monitor's input, which can't be read once it's been switched away from this
+/// computer on monitors that stop answering then. it's read and switched on the DDC/CI worker,
+/// see `ddc::queue`.
+#[derive(Default)]
+pub struct InputSourceMonitor {
+    pub input: Option<u32>,
+    reads: Background<Result<Option<u32>>>,
+    failures: Failures,
+}
+
+impl InputSourceMonitor {
+    /// picks up the input read since the last call and queues the next read. fails if a switch
+    /// has since. returns whether the input changed.
+    pub fn sample(&mut self, config: &InputSourceConfig) -> Result<bool> {
+        let read = self.reads.finished();
+
+        let name = config.monitor();
+        self.reads.start_on(Duration::ZERO, ddc::queue, move || {
+            read_input(name.as_deref())
+        });
+
+        self.failures.take()?;
+
+        let Some(input) = read else {
+            return Ok(false);
+        };
+
+        let input = input?;
+        let changed = input != self.input;
+        self.input = input;
+
+        Ok(changed)
+    }
+
+    /// shows `input` straight away, and the next sample catches up if switching failed
+    pub fn switch(&mut self, config: &InputSourceConfig, input: u32) {
+        self.input = Some(input);
+
+        let name = config.monitor();
+        let failures = self.failures.sender();
+
+        ddc::queue(move || {
+            let switched =
+                ddc::find(name.as_deref()).and_then(|monitor| monitor.set_vcp(INPUT_SOURCE, input));
+
+            if let Err(e) = switched {
+                let _ = failures.send(e);
+            }
+        });
+    }
+}
+
+fn read_input(name: Option<&str>) -> Result<Option<u32>> {
+    let monitor = ddc::find(name)?;
+
+    // some monitors keep other bits in the high byte
+    Ok(monitor.vcp(INPUT_SOURCE).ok().map(|input| input & 0xff))
+}
+
+pub fn draw(
+    canvas: &mut dyn Canvas,
+    layout: &Layout,
+    rect: Rect,
+    source: &InputSourceMonitor,
+) -> Result<()> {
+    let text = source.input.map_or("-".to_string(), label);
+    draw_centered_text(canvas, rect, &text, layout.px(13.0), TEXT_COLOR)
+}
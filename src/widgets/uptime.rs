@@ -0,0 +1,38 @@
+use anyhow::Result;
+use windows::Win32::System::SystemInformation::GetTickCount64;
+
+use super::{TEXT_COLOR, draw_centered_text};
+use crate::{
+    layout::{Layout, Rect},
+    render::Canvas,
+};
+
+const MINUTE_MS: u64 = 60 * 1000;
+
+/// milliseconds since the system booted
+fn uptime_ms() -> u64 {
+    unsafe { GetTickCount64() }
+}
+
+/// milliseconds until the minutes shown next change
+pub fn next_tick() -> u32 {
+    (MINUTE_MS - uptime_ms() % MINUTE_MS) as u32
+}
+
+/// e.g. "3d 4h", "5h 12m", or "12m", keeping only the two largest units
+pub fn text() -> String {
+    let minutes = uptime_ms() / MINUTE_MS;
+    let (days, hours, minutes) = (minutes / (24 * 60), minutes / 60 % 24, minutes % 60);
+
+    if days > 0 {
+        format!("{}d {}h", days, hours)
+    } else if hours > 0 {
+        format!("{}h {}m", hours, minutes)
+    } else {
+        format!("{}m", minutes)
+    }
+}
+
+pub fn draw(canvas: &mut dyn Canvas, layout: &Layout, rect: Rect) -> Result<()> {
+    draw_centered_text(canvas, rect, &text(), layout.px(13.0), TEXT_COLOR)
+}
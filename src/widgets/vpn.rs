@@ -0,0 +1,78 @@
+use anyhow::Result;
+use serde::Deserialize;
+
+use super::{TEXT_COLOR, network::interfaces};
+use crate::{
+    layout::{Layout, Rect},
+    render::Canvas,
+};
+
+const CONNECTED_COLOR: u32 = 0xff30a050;
+const DISCONNECTED_COLOR: u32 = 0xffa0a0a0;
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct VpnConfig {
+    /// substring matched (case-insensitively) against interface names and descriptions to find
+    /// the vpn's adapter
+    pub adapter: String,
+    /// run with `cmd /C` when the widget is clicked while disconnected
+    pub connect: Option<String>,
+    /// run with `cmd /C` when the widget is clicked while connected
+    pub disconnect: Option<String>,
+}
+
+impl VpnConfig {
+    /// the command that switches the vpn to the other state
+    pub fn toggle_command(&self, connected: bool) -> Option<&str> {
+        match connected {
+            true => self.disconnect.as_deref(),
+            false => self.connect.as_deref(),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct VpnMonitor {
+    pub connected: bool,
+}
+
+impl VpnMonitor {
+    pub fn sample(&mut self, config: &VpnConfig) -> Result<()> {
+        let adapter = config.adapter.to_lowercase();
+
+        // with no adapter configured there's nothing to watch
+        self.connected = !adapter.is_empty()
+            && interfaces()?
+                .iter()
+                .any(|interface| interface.up && interface.matches(&adapter));
+
+        Ok(())
+    }
+}
+
+/// a dot that's lit while connected, next to the label
+pub fn draw(canvas: &mut dyn Canvas, layout: &Layout, rect: Rect, vpn: &VpnMonitor) -> Result<()> {
+    let (color, text_color) = match vpn.connected {
+        true => (CONNECTED_COLOR, TEXT_COLOR),
+        false => (DISCONNECTED_COLOR, DISCONNECTED_COLOR),
+    };
+
+    // there's only an outline to draw with, so the dot is a circle half its size with a
+    // stroke that covers the rest
+    let radius = layout.px(5.0);
+    let middle = rect.y + rect.height / 2.0;
+    let center = rect.x + layout.px(10.0) + radius;
+    let dot = Rect {
+        x: center - radius / 2.0,
+        y: middle - radius / 2.0,
+        width: radius,
+        height: radius,
+    };
+    canvas.draw_ellipse(dot, radius, color)?;
+
+    let text_size = layout.px(14.0);
+    let (_, height) = canvas.measure_text("VPN", text_size)?;
+    let x = center + radius + layout.px(6.0);
+    canvas.draw_text("VPN", text_size, x, middle - height / 2.0, text_color)
+}
@@ -0,0 +1,272 @@
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use windows::{Foundation::Uri, Web::Http::HttpClient};
+use windows_core::HSTRING;
+
+use super::{TEXT_COLOR, fill_circle, poll::Background};
+use crate::{
+    layout::{Layout, Rect},
+    render::{Canvas, LineCap},
+};
+
+const SUN_COLOR: u32 = 0xffe0a020;
+const CLOUD_COLOR: u32 = 0xff909090;
+const RAIN_COLOR: u32 = 0xff3070d0;
+
+/// drawn instead of the text color while showing a reading from before the last failed fetch
+const STALE_COLOR: u32 = 0xffa0a0a0;
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct WeatherConfig {
+    /// an Open-Meteo forecast url, or anything else answering with the same json. it has to ask
+    /// for `current=temperature_2m,weather_code`.
+    pub url: String,
+    pub interval_ms: u32,
+}
+
+impl Default for WeatherConfig {
+    fn default() -> Self {
+        Self {
+            url: String::new(),
+            interval_ms: 15 * 60 * 1000,
+        }
+    }
+}
+
+/// the WMO weather codes Open-Meteo uses, grouped by what's drawn for them
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Condition {
+    Clear,
+    PartlyCloudy,
+    Cloudy,
+    Fog,
+    Rain,
+    Snow,
+    Thunder,
+}
+
+impl Condition {
+    fn from_code(code: u64) -> Self {
+        match code {
+            0 => Condition::Clear,
+            1 | 2 => Condition::PartlyCloudy,
+            45 | 48 => Condition::Fog,
+            51..=67 | 80..=82 => Condition::Rain,
+            71..=77 | 85 | 86 => Condition::Snow,
+            95..=99 => Condition::Thunder,
+            _ => Condition::Cloudy,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Condition::Clear => "Clear",
+            Condition::PartlyCloudy => "Partly cloudy",
+            Condition::Cloudy => "Cloudy",
+            Condition::Fog => "Fog",
+            Condition::Rain => "Rain",
+            Condition::Snow => "Snow",
+            Condition::Thunder => "Thunderstorm",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Reading {
+    pub temperature: f64,
+    /// e.g. "°C", as the endpoint reports it
+    pub unit: String,
+    pub condition: Condition,
+}
+
+#[derive(Default)]
+pub struct WeatherMonitor {
+    /// the last successful reading, kept while offline
+    pub reading: Option<Reading>,
+    /// whether the last fetch failed, so `reading` may be out of date
+    pub stale: bool,
+    fetches: Background<Result<Reading>>,
+}
+
+impl WeatherMonitor {
+    /// picks up the result of the last fetch, and starts the next one once the interval is up.
+    /// returns whether anything shown changed.
+    pub fn sample(&mut self, config: &WeatherConfig) -> bool {
+        let mut changed = false;
+
+        match self.fetches.finished() {
+            Some(Ok(reading)) => {
+                changed = self.stale || self.reading.as_ref() != Some(&reading);
+                self.reading = Some(reading);
+                self.stale = false;
+            }
+            Some(Err(_)) => {
+                changed = !self.stale;
+                self.stale = true;
+            }
+            None => {}
+        }
+
+        if !config.url.is_empty() {
+            let url = config.url.clone();
+            let interval = Duration::from_millis(config.interval_ms as u64);
+            self.fetches.start(interval, move || fetch(&url));
+        }
+
+        changed
+    }
+
+    /// e.g. "Rain, 12°C", or with "offline" after it while it's stale
+    pub fn describe(&self) -> String {
+        match &self.reading {
+            Some(reading) => format!(
+                "{}, {:.0}{}{}",
+                reading.condition.label(),
+                reading.temperature,
+                reading.unit,
+                if self.stale { ", offline" } else { "" }
+            ),
+            None => "No weather yet".to_string(),
+        }
+    }
+}
+
+fn fetch(url: &str) -> Result<Reading> {
+    let client = HttpClient::new()?;
+    let uri = Uri::CreateUri(&HSTRING::from(url))?;
+    let text = client.GetStringAsync(&uri)?.get()?;
+
+    let json: serde_json::Value = serde_json::from_str(&text.to_string_lossy())?;
+    let current = &json["current"];
+
+    let temperature = current["temperature_2m"]
+        .as_f64()
+        .context("no current temperature_2m")?;
+    let code = current["weather_code"]
+        .as_u64()
+        .context("no current weather_code")?;
+    let unit = json["current_units"]["temperature_2m"]
+        .as_str()
+        .unwrap_or("°")
+        .to_string();
+
+    Ok(Reading {
+        temperature,
+        unit,
+        condition: Condition::from_code(code),
+    })
+}
+
+/// the condition's glyph on the left, and the temperature next to it
+pub fn draw(
+    canvas: &mut dyn Canvas,
+    layout: &Layout,
+    rect: Rect,
+    weather: &WeatherMonitor,
+) -> Result<()> {
+    let Some(reading) = &weather.reading else {
+        return Ok(());
+    };
+
+    let middle = rect.y + rect.height / 2.0;
+    let center = (rect.x + layout.px(20.0), middle);
+    draw_condition(canvas, layout, center, reading.condition)?;
+
+    let color = match weather.stale {
+        true => STALE_COLOR,
+        false => TEXT_COLOR,
+    };
+
+    let text = format!("{:.0}{}", reading.temperature, reading.unit);
+    let size = layout.px(15.0);
+    let (_, height) = canvas.measure_text(&text, size)?;
+    let x = rect.x + layout.px(36.0);
+    canvas.draw_text(&text, size, x, middle - height / 2.0, color)
+}
+
+/// a glyph about 20 pixels across centered on `(x, y)`
+fn draw_condition(
+    canvas: &mut dyn Canvas,
+    layout: &Layout,
+    (x, y): (f32, f32),
+    condition: Condition,
+) -> Result<()> {
+    let px = |dip: f32| layout.px(dip);
+
+    if condition == Condition::Clear {
+        return fill_circle(canvas, (x, y), px(7.0), SUN_COLOR);
+    }
+
+    if condition == Condition::Fog {
+        for dy in [-5.0, 0.0, 5.0] {
+            canvas.draw_line(
+                (x - px(9.0), y + px(dy)),
+                (x + px(9.0), y + px(dy)),
+                px(2.0),
+                LineCap::Square,
+                CLOUD_COLOR,
+            )?;
+        }
+
+        return Ok(());
+    }
+
+    if condition == Condition::PartlyCloudy {
+        fill_circle(canvas, (x + px(3.0), y - px(4.0)), px(6.0), SUN_COLOR)?;
+    }
+
+    // the cloud sits higher up when there's something falling out of it
+    let top = match condition {
+        Condition::PartlyCloudy | Condition::Cloudy => y,
+        _ => y - px(4.0),
+    };
+
+    fill_circle(canvas, (x - px(4.0), top + px(1.0)), px(5.0), CLOUD_COLOR)?;
+    fill_circle(canvas, (x + px(2.0), top - px(2.0)), px(6.0), CLOUD_COLOR)?;
+    let base = Rect {
+        x: x - px(9.0),
+        y: top + px(1.0),
+        width: px(17.0),
+        height: px(5.0),
+    };
+    canvas.fill_rect(base, CLOUD_COLOR)?;
+
+    let below = top + px(9.0);
+
+    match condition {
+        Condition::Rain => {
+            for dx in [-5.0, 0.0, 5.0] {
+                canvas.draw_line(
+                    (x + px(dx), below),
+                    (x + px(dx - 2.0), below + px(5.0)),
+                    px(1.5),
+                    LineCap::Square,
+                    RAIN_COLOR,
+                )?;
+            }
+        }
+        Condition::Snow => {
+            for dx in [-5.0, 0.0, 5.0] {
+                fill_circle(canvas, (x + px(dx), below + px(2.0)), px(1.5), CLOUD_COLOR)?;
+            }
+        }
+        Condition::Thunder => {
+            let points = [
+                (x + px(1.0), below - px(1.0)),
+                (x - px(2.0), below + px(3.0)),
+                (x + px(2.0), below + px(3.0)),
+                (x - px(1.0), below + px(7.0)),
+            ];
+
+            for pair in points.windows(2) {
+                canvas.draw_line(pair[0], pair[1], px(1.5), LineCap::Square, SUN_COLOR)?;
+            }
+        }
+        _ => {}
+    }
+
+    Ok(())
+}
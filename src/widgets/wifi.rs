@@ -0,0 +1,167 @@
+use anyhow::Result;
+use windows::Win32::{
+    Foundation::{HANDLE, WIN32_ERROR},
+    NetworkManagement::WiFi::{
+        WLAN_API_VERSION_2_0, WLAN_CONNECTION_ATTRIBUTES, WLAN_INTERFACE_INFO_LIST,
+        WlanCloseHandle, WlanEnumInterfaces, WlanFreeMemory, WlanOpenHandle, WlanQueryInterface,
+        wlan_interface_state_connected, wlan_intf_opcode_current_connection,
+    },
+};
+
+use super::{TEXT_COLOR, draw_centered_text};
+use crate::{
+    layout::{Layout, Rect},
+    render::Canvas,
+};
+
+/// opens the flyout listing the available networks
+pub const NETWORK_FLYOUT: &str = "ms-availablenetworks:";
+
+const BARS: u32 = 4;
+
+/// the wireless network the first connected wlan interface is on
+pub struct Connection {
+    pub ssid: String,
+    /// 0 to 100
+    pub signal: u32,
+}
+
+impl Connection {
+    /// how many of the signal bars are lit
+    pub fn bars(&self) -> u32 {
+        (self.signal * BARS).div_ceil(100).min(BARS)
+    }
+}
+
+#[derive(Default)]
+pub struct WifiMonitor {
+    /// `None` while disconnected or before the first poll
+    pub connection: Option<Connection>,
+}
+
+impl WifiMonitor {
+    pub fn sample(&mut self) -> Result<()> {
+        let mut version = 0;
+        let mut client = HANDLE::default();
+
+        unsafe {
+            WIN32_ERROR(WlanOpenHandle(
+                WLAN_API_VERSION_2_0,
+                None,
+                &mut version,
+                &mut client,
+            ))
+            .ok()?;
+
+            let connection = current_connection(client);
+            WlanCloseHandle(client, None);
+
+            self.connection = connection?;
+        }
+
+        Ok(())
+    }
+}
+
+unsafe fn current_connection(client: HANDLE) -> Result<Option<Connection>> {
+    unsafe {
+        let mut list: *mut WLAN_INTERFACE_INFO_LIST = std::ptr::null_mut();
+        WIN32_ERROR(WlanEnumInterfaces(client, None, &mut list)).ok()?;
+
+        let interfaces = std::slice::from_raw_parts(
+            (*list).InterfaceInfo.as_ptr(),
+            (*list).dwNumberOfItems as usize,
+        );
+
+        let mut connection = None;
+
+        for interface in interfaces {
+            if interface.isState != wlan_interface_state_connected {
+                continue;
+            }
+
+            let mut size = 0;
+            let mut data = std::ptr::null_mut();
+            let result = WlanQueryInterface(
+                client,
+                &interface.InterfaceGuid,
+                wlan_intf_opcode_current_connection,
+                None,
+                &mut size,
+                &mut data,
+                None,
+            );
+
+            if WIN32_ERROR(result).is_err() {
+                continue;
+            }
+
+            let attributes = &*(data as *const WLAN_CONNECTION_ATTRIBUTES);
+            let association = &attributes.wlanAssociationAttributes;
+            let ssid = &association.dot11Ssid;
+            let len = (ssid.uSSIDLength as usize).min(ssid.ucSSID.len());
+
+            connection = Some(Connection {
+                ssid: String::from_utf8_lossy(&ssid.ucSSID[..len]).into_owned(),
+                signal: association.wlanSignalQuality,
+            });
+
+            WlanFreeMemory(data);
+            break;
+        }
+
+        WlanFreeMemory(list as _);
+
+        Ok(connection)
+    }
+}
+
+/// signal bars followed by the network name
+pub fn draw(
+    canvas: &mut dyn Canvas,
+    layout: &Layout,
+    rect: Rect,
+    wifi: &WifiMonitor,
+) -> Result<()> {
+    let Some(connection) = &wifi.connection else {
+        return draw_centered_text(canvas, rect, "No Wi-Fi", layout.px(13.0), 0xff808080);
+    };
+
+    // bars grow from 4 to 16 high, sitting on the same baseline
+    let bottom = rect.y + layout.px(32.0);
+    let bar_width = layout.px(4.0);
+
+    for i in 0..BARS {
+        let x = rect.x + layout.px(8.0 + 6.0 * i as f32);
+        let height = layout.px(4.0 + 4.0 * i as f32);
+        let color = if i < connection.bars() {
+            TEXT_COLOR
+        } else {
+            0xffc0c0c0
+        };
+
+        canvas.fill_rect(
+            Rect {
+                x,
+                y: bottom - height,
+                width: bar_width,
+                height,
+            },
+            color,
+        )?;
+    }
+
+    let text_rect = Rect {
+        x: rect.x + layout.px(36.0),
+        width: rect.width - layout.px(40.0),
+        ..rect
+    };
+
+    draw_centered_text(
+        canvas,
+        text_rect,
+        &connection.ssid,
+        layout.px(13.0),
+        TEXT_COLOR,
+    )
+}
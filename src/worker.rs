@@ -0,0 +1,202 @@
+use std::sync::mpsc::{self, Receiver, Sender};
+
+use anyhow::{Context, Result};
+use windows::Win32::{
+    Devices::FunctionDiscovery::{PKEY_Device_FriendlyName, PKEY_DeviceClass_IconPath},
+    Foundation::{HWND, LPARAM, WPARAM},
+    Media::Audio::{
+        DEVICE_STATE_ACTIVE, EDataFlow, Endpoints::IAudioEndpointVolume, IMMDevice, IMMEndpoint,
+        eRender,
+    },
+    System::Com::{COINIT_MULTITHREADED, CoInitializeEx, CoUninitialize, STGM_READ},
+    UI::WindowsAndMessaging::PostMessageA,
+};
+
+use windows_core::{AgileReference, Interface};
+
+use crate::{WM_WORKER_DONE, ipc::X};
+use control_panel::audio::{self, Endpoints, Reconnect, Volume};
+
+/// the audio work that can block for a while on a slow or flaky device, which happens on the
+/// worker so the window keeps painting and answering in the meantime
+pub enum Job {
+    /// gets a device ready to be tracked
+    Prepare(String),
+    /// asks the first bluetooth output whose name contains `name` to reconnect. `report` is
+    /// whether to tell the user if nothing took the request.
+    Reconnect { name: String, report: bool },
+    /// looks for an output whose name contains the given text, connected or not
+    FindHeadset(String),
+    /// reads a device's icon path and loads the icon from it, which can mean loading a driver's
+    /// dll
+    LoadIcon(String),
+    /// reads a device's friendly name again, after it's been renamed
+    ReadName(String),
+}
+
+pub enum Done {
+    Prepared {
+        id: String,
+        device: Result<Prepared>,
+    },
+    Reconnected {
+        name: String,
+        report: bool,
+        result: Result<Reconnect>,
+    },
+    Headset(Result<Option<Headset>>),
+    /// the icon's path and handle. icons belong to the process rather than the thread that
+    /// loaded them, so the handle is passed as a plain number
+    Icon {
+        id: String,
+        icon: Result<(String, isize)>,
+    },
+    Name {
+        id: String,
+        name: Result<String>,
+    },
+}
+
+/// what tracking a device needs, which is the slow part of it. the controls are activated in the
+/// worker's apartment, so the window's thread gets at them through an agile reference.
+pub struct Prepared {
+    pub controls: AgileReference<IAudioEndpointVolume>,
+    pub name: String,
+    /// for picking the placeholder icon
+    pub flow: EDataFlow,
+}
+
+pub struct Headset {
+    pub id: String,
+    pub name: String,
+    pub connected: bool,
+}
+
+/// activates the device's volume controls and reads its properties
+pub fn prepare(device: &IMMDevice) -> Result<Prepared> {
+    unsafe {
+        let props = device.OpenPropertyStore(STGM_READ)?;
+
+        Ok(Prepared {
+            name: props.GetValue(&PKEY_Device_FriendlyName)?.to_string(),
+            flow: device.cast::<IMMEndpoint>()?.GetDataFlow()?,
+            controls: AgileReference::new(Volume::new(device)?.controls())?,
+        })
+    }
+}
+
+/// the window's side of the worker. results are picked up with `finished` once the worker posts
+/// `WM_WORKER_DONE`.
+pub struct Worker {
+    jobs: Sender<Job>,
+    done: Receiver<Done>,
+}
+
+impl Worker {
+    pub fn spawn(hwnd: HWND) -> Self {
+        let hwnd = X(hwnd);
+        let (jobs, receiver) = mpsc::channel();
+        let (sender, done) = mpsc::channel();
+
+        std::thread::spawn(move || run(hwnd, receiver, sender));
+
+        Self { jobs, done }
+    }
+
+    pub fn send(&self, job: Job) {
+        let _ = self.jobs.send(job);
+    }
+
+    /// whatever's finished since the last call
+    pub fn finished(&self) -> Vec<Done> {
+        self.done.try_iter().collect()
+    }
+}
+
+fn find_headset(endpoints: &Endpoints, name: &str) -> Result<Option<Headset>> {
+    let name = name.to_lowercase();
+
+    for device in endpoints.all_devices(eRender)? {
+        let Ok(device_name) = audio::name(&device) else {
+            continue;
+        };
+
+        if device_name.to_lowercase().contains(&name) {
+            return Ok(Some(Headset {
+                id: audio::id(&device)?,
+                name: device_name,
+                connected: unsafe { device.GetState()? } == DEVICE_STATE_ACTIVE,
+            }));
+        }
+    }
+
+    Ok(None)
+}
+
+fn load_icon(endpoints: &Endpoints, id: &str) -> Result<(String, isize)> {
+    let icon_path = unsafe {
+        let props = endpoints.device(id)?.OpenPropertyStore(STGM_READ)?;
+        props.GetValue(&PKEY_DeviceClass_IconPath)?.to_string()
+    };
+
+    let icon = crate::load_icon(&icon_path)?;
+    Ok((icon_path, icon.0 as isize))
+}
+
+fn work(endpoints: &Endpoints, job: Job) -> Done {
+    match job {
+        Job::Prepare(id) => {
+            let device = endpoints
+                .device(&id)
+                .and_then(|device| prepare(&device))
+                .with_context(|| format!("failed to prepare {}", id));
+
+            Done::Prepared { id, device }
+        }
+        Job::Reconnect { name, report } => Done::Reconnected {
+            result: endpoints.reconnect_bluetooth(&name),
+            name,
+            report,
+        },
+        Job::FindHeadset(name) => Done::Headset(find_headset(endpoints, &name)),
+        Job::LoadIcon(id) => Done::Icon {
+            icon: load_icon(endpoints, &id),
+            id,
+        },
+        Job::ReadName(id) => Done::Name {
+            name: endpoints
+                .device(&id)
+                .and_then(|device| audio::name(&device)),
+            id,
+        },
+    }
+}
+
+fn run(hwnd: X, jobs: Receiver<Job>, done: Sender<Done>) {
+    unsafe {
+        if CoInitializeEx(None, COINIT_MULTITHREADED).is_err() {
+            return;
+        }
+    }
+
+    // everything com has to be gone before the apartment is
+    serve(hwnd, jobs, done);
+
+    unsafe { CoUninitialize() };
+}
+
+fn serve(hwnd: X, jobs: Receiver<Job>, done: Sender<Done>) {
+    let Ok(endpoints) = Endpoints::new() else {
+        return;
+    };
+
+    for job in jobs {
+        if done.send(work(&endpoints, job)).is_err() {
+            return;
+        }
+
+        unsafe {
+            let _ = PostMessageA(Some(hwnd.0), WM_WORKER_DONE, WPARAM(0), LPARAM(0));
+        }
+    }
+}
@@ -0,0 +1,59 @@
+use anyhow::Result;
+use serde::Deserialize;
+use windows::Win32::{
+    Foundation::HWND,
+    UI::WindowsAndMessaging::{
+        HWND_BOTTOM, HWND_NOTOPMOST, HWND_TOPMOST, SWP_NOACTIVATE, SWP_NOMOVE, SWP_NOSIZE,
+        SetWindowPos, WINDOW_EX_STYLE, WS_EX_TOPMOST,
+    },
+};
+
+/// where the panel sits relative to other windows
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ZOrder {
+    /// above everything, re-asserted whenever another window may have covered it
+    #[default]
+    AlwaysOnTop,
+    /// below everything, like a desktop widget
+    Desktop,
+    /// stacked like any other window
+    Normal,
+}
+
+impl ZOrder {
+    /// the extended window styles to create the panel with
+    pub fn window_style(self) -> WINDOW_EX_STYLE {
+        match self {
+            ZOrder::AlwaysOnTop => WS_EX_TOPMOST,
+            ZOrder::Desktop | ZOrder::Normal => WINDOW_EX_STYLE(0),
+        }
+    }
+
+    /// whether other windows can move the panel out of place, so it has to be re-applied
+    pub fn needs_reassert(self) -> bool {
+        self != ZOrder::Normal
+    }
+
+    pub fn apply(self, hwnd: HWND) -> Result<()> {
+        let insert_after = match self {
+            ZOrder::AlwaysOnTop => HWND_TOPMOST,
+            ZOrder::Desktop => HWND_BOTTOM,
+            ZOrder::Normal => HWND_NOTOPMOST,
+        };
+
+        unsafe {
+            SetWindowPos(
+                hwnd,
+                Some(insert_after),
+                0,
+                0,
+                0,
+                0,
+                SWP_NOMOVE | SWP_NOSIZE | SWP_NOACTIVATE,
+            )?;
+        }
+
+        Ok(())
+    }
+}